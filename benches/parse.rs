@@ -0,0 +1,33 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use smashlang::{Lexer, SmashParser};
+
+const SOURCE: &str = r#"
+let total = 0;
+for (let i = 0; i < 100; i++) {
+    if (i % 3 == 0) {
+        total += i * 2;
+    } else {
+        total -= 1;
+    }
+}
+const greet = (name) => `Hello, ${name}!`;
+greet("world");
+"#;
+
+// `execute`/`compile` used to tokenize the source and discard the tokens
+// before parsing it with pest. They now only pay for the pest parse.
+fn bench_parse(c: &mut Criterion) {
+    c.bench_function("pest parse", |b| {
+        b.iter(|| SmashParser::parse(black_box(SOURCE)).unwrap())
+    });
+
+    c.bench_function("pest parse + discarded tokenize", |b| {
+        b.iter(|| {
+            let _tokens = Lexer::new(black_box(SOURCE)).tokenize();
+            SmashParser::parse(black_box(SOURCE)).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
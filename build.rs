@@ -16,6 +16,8 @@ fn main() {
     // Set up platform-specific configurations
     let target = env::var("TARGET").unwrap();
     
+    // `native` isn't a Cargo feature, so declare it for cfg checking
+    println!("cargo:rustc-check-cfg=cfg(feature, values(\"native\"))");
     if target.contains("wasm32") {
         println!("cargo:rustc-cfg=feature=\"wasm\"");
     } else {
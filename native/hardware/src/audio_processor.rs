@@ -0,0 +1,290 @@
+//! Audio processors applied to captured microphone samples
+//!
+//! Processors run in the capture callback, in the order they were applied,
+//! before samples reach the recording buffer and audio-level metering.
+
+use serde::Deserialize;
+
+use crate::error::HardwareError;
+use crate::Result;
+
+/// Processor names accepted by `AudioProcessor::from_name`
+pub(crate) const AUDIO_PROCESSORS: &[&str] = &["noise_gate", "normalize", "highpass", "lowpass"];
+
+/// A processor transforming `f32` samples in the -1.0 to 1.0 range
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AudioProcessor {
+    /// Zero samples quieter than `threshold`
+    NoiseGate { threshold: f32 },
+    /// Scale each chunk so its peak reaches `target`
+    Normalize { target: f32 },
+    /// High-pass or low-pass biquad filter
+    Filter(Biquad),
+}
+
+/// Second-order IIR filter (RBJ audio EQ cookbook coefficients)
+///
+/// Filter state is kept per channel, so consecutive chunks of an interleaved
+/// stream are filtered without discontinuities at the chunk boundaries.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    /// Previous two inputs and outputs for each channel: [x1, x2, y1, y2]
+    state: Vec<[f32; 4]>,
+}
+
+impl Biquad {
+    fn new(high_pass: bool, cutoff: f32, q: f32, sample_rate: u32, channels: u16) -> Self {
+        let omega = 2.0 * std::f32::consts::PI * cutoff / sample_rate as f32;
+        let (sin, cos) = omega.sin_cos();
+        let alpha = sin / (2.0 * q);
+        
+        let (b0, b1, b2) = if high_pass {
+            ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0)
+        } else {
+            ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0)
+        };
+        let a0 = 1.0 + alpha;
+        
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            state: vec![[0.0; 4]; channels.max(1) as usize],
+        }
+    }
+    
+    fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.state.len();
+        
+        for (index, sample) in samples.iter_mut().enumerate() {
+            let [x1, x2, y1, y2] = self.state[index % channels];
+            let x0 = *sample;
+            let y0 = self.b0 * x0 + self.b1 * x1 + self.b2 * x2 - self.a1 * y1 - self.a2 * y2;
+            
+            self.state[index % channels] = [x0, x1, y0, y1];
+            *sample = y0;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct NoiseGateOptions {
+    #[serde(default = "default_gate_threshold")]
+    threshold: f32,
+}
+
+#[derive(Deserialize)]
+struct NormalizeOptions {
+    #[serde(default = "default_normalize_target")]
+    target: f32,
+}
+
+#[derive(Deserialize)]
+struct FilterOptions {
+    /// Cutoff frequency in Hz
+    cutoff: f32,
+    #[serde(default = "default_filter_q")]
+    q: f32,
+}
+
+fn default_gate_threshold() -> f32 { 0.01 }
+fn default_normalize_target() -> f32 { 1.0 }
+fn default_filter_q() -> f32 { std::f32::consts::FRAC_1_SQRT_2 }
+
+/// Peaks below this are treated as silence and never amplified
+const SILENCE_PEAK: f32 = 1e-6;
+
+impl AudioProcessor {
+    /// Build a processor from its name and JSON options for a stream with the
+    /// given sample rate and (interleaved) channel count
+    pub(crate) fn from_name(name: &str, options: &serde_json::Value, sample_rate: u32, channels: u16) -> Result<Self> {
+        match name {
+            "noise_gate" => {
+                let options: NoiseGateOptions = parse_options(name, options)?;
+                check_level(name, "threshold", options.threshold, 0.0)?;
+                Ok(AudioProcessor::NoiseGate { threshold: options.threshold })
+            }
+            "normalize" => {
+                let options: NormalizeOptions = parse_options(name, options)?;
+                check_level(name, "target", options.target, f32::MIN_POSITIVE)?;
+                Ok(AudioProcessor::Normalize { target: options.target })
+            }
+            "highpass" | "lowpass" => {
+                let options: FilterOptions = parse_options(name, options)?;
+                
+                let nyquist = sample_rate as f32 / 2.0;
+                if options.cutoff.is_nan() || options.cutoff <= 0.0 || options.cutoff >= nyquist {
+                    return Err(HardwareError::InvalidParameter(format!(
+                        "Processor '{}' cutoff must be between 0 and {} Hz, got {}",
+                        name, nyquist, options.cutoff
+                    )));
+                }
+                if options.q.is_nan() || options.q <= 0.0 {
+                    return Err(HardwareError::InvalidParameter(format!("Processor '{}' q must be positive", name)));
+                }
+                
+                let high_pass = name == "highpass";
+                Ok(AudioProcessor::Filter(Biquad::new(high_pass, options.cutoff, options.q, sample_rate, channels)))
+            }
+            _ => Err(HardwareError::UnsupportedOperation(format!(
+                "Unsupported microphone processor '{}' (expected one of: {})",
+                name,
+                AUDIO_PROCESSORS.join(", ")
+            ))),
+        }
+    }
+    
+    /// Process a chunk of samples in place
+    pub(crate) fn process(&mut self, samples: &mut [f32]) {
+        match *self {
+            AudioProcessor::NoiseGate { threshold } => {
+                for sample in samples.iter_mut().filter(|sample| sample.abs() < threshold) {
+                    *sample = 0.0;
+                }
+            }
+            AudioProcessor::Normalize { target } => {
+                let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+                if peak > SILENCE_PEAK {
+                    let gain = target / peak;
+                    samples.iter_mut().for_each(|sample| *sample *= gain);
+                }
+            }
+            AudioProcessor::Filter(ref mut biquad) => biquad.process(samples),
+        }
+    }
+}
+
+fn parse_options<T: for<'de> Deserialize<'de>>(name: &str, options: &serde_json::Value) -> Result<T> {
+    let options = if options.is_null() { serde_json::json!({}) } else { options.clone() };
+    
+    serde_json::from_value(options)
+        .map_err(|e| HardwareError::InvalidParameter(format!("Invalid options for processor '{}': {}", name, e)))
+}
+
+fn check_level(name: &str, option: &str, value: f32, min: f32) -> Result<()> {
+    if !(min..=1.0).contains(&value) {
+        return Err(HardwareError::InvalidParameter(format!(
+            "Processor '{}' option '{}' must be between {} and 1.0, got {}",
+            name, option, min, value
+        )));
+    }
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    
+    const SAMPLE_RATE: u32 = 44100;
+    
+    fn processor(name: &str, options: serde_json::Value) -> Result<AudioProcessor> {
+        AudioProcessor::from_name(name, &options, SAMPLE_RATE, 1)
+    }
+    
+    fn sine(frequency: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+    
+    /// RMS of the samples after the filter has settled
+    fn settled_rms(samples: &[f32]) -> f32 {
+        let tail = &samples[samples.len() / 2..];
+        (tail.iter().map(|sample| sample * sample).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+    
+    #[test]
+    fn test_noise_gate_silences_quiet_buffer() {
+        let mut gate = processor("noise_gate", json!({ "threshold": 0.05 })).unwrap();
+        
+        let mut samples: Vec<f32> = (0..256).map(|i| 0.02 * (i as f32 * 0.1).sin()).collect();
+        gate.process(&mut samples);
+        assert!(samples.iter().all(|&sample| sample == 0.0));
+        
+        // Louder samples pass through untouched
+        let mut samples = vec![0.5, -0.01, -0.5];
+        gate.process(&mut samples);
+        assert_eq!(samples, vec![0.5, 0.0, -0.5]);
+    }
+    
+    #[test]
+    fn test_normalize_amplifies_quiet_buffer() {
+        let mut normalize = processor("normalize", json!({ "target": 0.8 })).unwrap();
+        
+        let mut samples = vec![0.1, -0.2, 0.05];
+        normalize.process(&mut samples);
+        
+        let peak = samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.8).abs() < 1e-6);
+        assert!((samples[0] - 0.4).abs() < 1e-6);
+        
+        // Silence stays silent rather than being blown up
+        let mut silence = vec![0.0; 16];
+        normalize.process(&mut silence);
+        assert!(silence.iter().all(|&sample| sample == 0.0));
+    }
+    
+    #[test]
+    fn test_lowpass_attenuates_high_frequencies() {
+        let mut low = sine(100.0, 8192);
+        let mut high = sine(8000.0, 8192);
+        
+        processor("lowpass", json!({ "cutoff": 1000.0 })).unwrap().process(&mut low);
+        processor("lowpass", json!({ "cutoff": 1000.0 })).unwrap().process(&mut high);
+        
+        // A full-scale sine has an RMS of 1/sqrt(2)
+        assert!(settled_rms(&low) > 0.65);
+        assert!(settled_rms(&high) < 0.05);
+        
+        let mut low = sine(100.0, 8192);
+        processor("highpass", json!({ "cutoff": 1000.0 })).unwrap().process(&mut low);
+        assert!(settled_rms(&low) < 0.05);
+    }
+    
+    #[test]
+    fn test_filter_state_carries_across_chunks() {
+        let input = sine(440.0, 4096);
+        
+        let mut whole = input.clone();
+        processor("lowpass", json!({ "cutoff": 800.0 })).unwrap().process(&mut whole);
+        
+        let mut chunked = input;
+        let mut filter = processor("lowpass", json!({ "cutoff": 800.0 })).unwrap();
+        for chunk in chunked.chunks_mut(333) {
+            filter.process(chunk);
+        }
+        
+        assert_eq!(whole, chunked);
+    }
+    
+    #[test]
+    fn test_processor_options_and_names() {
+        // Options default when omitted
+        assert_eq!(
+            processor("normalize", serde_json::Value::Null).unwrap(),
+            AudioProcessor::Normalize { target: 1.0 }
+        );
+        
+        assert!(matches!(processor("reverb", json!({})), Err(HardwareError::UnsupportedOperation(_))));
+        assert!(matches!(
+            processor("noise_gate", json!({ "threshold": 2.0 })),
+            Err(HardwareError::InvalidParameter(_))
+        ));
+        
+        // Filters need a cutoff below the Nyquist frequency
+        assert!(matches!(processor("lowpass", json!({})), Err(HardwareError::InvalidParameter(_))));
+        assert!(matches!(
+            processor("highpass", json!({ "cutoff": 30000.0 })),
+            Err(HardwareError::InvalidParameter(_))
+        ));
+    }
+}
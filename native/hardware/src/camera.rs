@@ -3,18 +3,45 @@
 //! Provides access to camera devices for capturing photos and recording videos.
 //! Uses platform-specific APIs through the nokhwa crate.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
 use nokhwa::pixel_format::RgbFormat;
 use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
-use nokhwa::{Camera as NokhwaCamera, CameraFormat};
+#[cfg(not(feature = "test-devices"))]
+use nokhwa::utils::{CameraControl, ControlValueDescription, ControlValueSetter, FrameFormat, KnownCameraControl};
+use nokhwa::Camera as NokhwaCamera;
 use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
+use crate::focus::{FocusMode, FocusSetting, FocusSupport};
+use crate::imaging::{
+    data_uri, digital_zoom, encode_image, resize_image, strip_data_uri, transcode, write_thumbnail, ImageFormat,
+    ResizeOptions,
+};
+use crate::microphone::{AudioRecordingOptions, Microphone, MicrophoneOptions};
+#[cfg(target_os = "windows")]
+use crate::permission::consent_store_status;
+use crate::permission::PermissionStatus;
+#[cfg(target_os = "linux")]
+use crate::permission::warn_group_hint;
+use crate::stream::{validate_effect, MediaStream, RecordingClock};
+#[cfg(feature = "test-devices")]
+use crate::virtual_camera::VirtualCamera;
 use crate::Result;
+use crate::utils::MutexExt;
+
+/// V4L2 switch for continuous autofocus (V4L2_CID_FOCUS_AUTO)
+#[cfg(not(feature = "test-devices"))]
+const V4L2_CID_FOCUS_AUTO: u128 = 0x009a_090c;
+/// V4L2 button starting a single autofocus pass (V4L2_CID_AUTO_FOCUS_START)
+#[cfg(not(feature = "test-devices"))]
+const V4L2_CID_AUTO_FOCUS_START: u128 = 0x009a_091c;
+
+/// Largest zoom factor accepted by `Camera::set_zoom`
+pub(crate) const MAX_ZOOM: f32 = 4.0;
 
 /// Camera device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +67,24 @@ pub struct CameraStream {
     pub height: u32,
     /// Frame rate of the video stream
     pub frame_rate: u32,
+    /// Pixel format negotiated with the camera (e.g. 'MJPEG', 'YUYV')
+    pub pixel_format: String,
+}
+
+/// A resolution, frame rate and pixel format supported by a camera
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraFormat {
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Frame rate in frames per second
+    pub frame_rate: u32,
+    /// Pixel format reported by the device (e.g. 'MJPEG', 'YUYV')
+    pub format: String,
+    /// Largest factor `Camera::set_zoom` applies at this format
+    #[serde(default = "default_max_zoom")]
+    pub max_zoom: f32,
 }
 
 /// Camera configuration options
@@ -63,17 +108,49 @@ pub struct CameraOptions {
     /// Whether to include audio from the camera's microphone
     #[serde(default)]
     pub audio: bool,
+    /// Keep the last this many seconds of video in memory for `save_replay`
+    #[serde(default)]
+    pub replay_buffer_seconds: Option<u32>,
+    /// Pixel formats to stream in, most preferred first
+    ///
+    /// MJPEG needs far less USB bandwidth than raw formats at high
+    /// resolutions. If the camera supports none of these, any supported
+    /// format is used.
+    #[serde(default = "default_pixel_formats")]
+    pub pixel_formats: Vec<String>,
 }
 
 /// Photo capture options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PhotoOptions {
-    /// Image format ('jpeg', 'png', 'webp')
+    /// Image format ('jpeg', 'png', 'webp', 'avif'); WebP and AVIF need the
+    /// `webp` / `avif` features
     #[serde(default = "default_photo_format")]
     pub format: String,
     /// Image quality (0.0 to 1.0)
     #[serde(default = "default_quality")]
     pub quality: f32,
+    /// Longest side, in pixels, of a thumbnail to write alongside the saved photo
+    #[serde(default)]
+    pub thumbnail: Option<u32>,
+    /// Return the photo data as a `data:image/...;base64,` URI instead of raw base64
+    #[serde(default)]
+    pub as_data_uri: bool,
+    /// Resize the captured frame to these dimensions
+    #[serde(default)]
+    pub resize: Option<ResizeOptions>,
+}
+
+impl Default for PhotoOptions {
+    fn default() -> Self {
+        Self {
+            format: default_photo_format(),
+            quality: default_quality(),
+            thumbnail: None,
+            as_data_uri: false,
+            resize: None,
+        }
+    }
 }
 
 /// Video recording options
@@ -88,12 +165,20 @@ pub struct RecordingOptions {
     /// Whether to include audio
     #[serde(default)]
     pub include_audio: bool,
+    /// Microphone device to record audio from alongside the video, saved
+    /// as a separate WAV file (see `RecordingResult::audio_path`)
+    #[serde(default)]
+    pub audio_device_id: Option<String>,
+    /// Maximum number of frames waiting to be encoded; further frames are
+    /// dropped instead of letting the encoder fall behind without bound
+    #[serde(default)]
+    pub max_buffered_frames: Option<usize>,
 }
 
 /// Photo data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Photo {
-    /// Base64-encoded image data
+    /// Base64-encoded image data, or a data URI if requested
     pub data: String,
     /// Width of the image
     pub width: u32,
@@ -110,6 +195,22 @@ pub struct Photo {
 pub struct SaveResult {
     /// Path where the file was saved
     pub path: String,
+    /// Path of the thumbnail, if one was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_path: Option<String>,
+}
+
+/// Instant replay result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayResult {
+    /// Path where the replay was saved
+    pub path: String,
+    /// Duration of the replay in seconds
+    pub duration: f64,
+    /// Number of frames in the replay
+    pub frame_count: usize,
+    /// Size of the replay in bytes
+    pub size: u64,
 }
 
 /// Video recording result
@@ -127,6 +228,15 @@ pub struct RecordingResult {
     pub width: u32,
     /// Height of the recording
     pub height: u32,
+    /// Frames missed relative to the stream's frame rate
+    pub dropped_frames: u64,
+    /// Frames actually recorded per second
+    pub average_fps: f64,
+    /// Path of the audio recorded alongside the video, if an audio device
+    /// was given. Audio is not muxed into the video; it is saved next to it
+    /// as a 32-bit float WAV file with the video's name and a `.wav` extension.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_path: Option<String>,
 }
 
 // Default values for camera options
@@ -137,6 +247,11 @@ fn default_facing_mode() -> String { "user".to_string() }
 fn default_photo_format() -> String { "jpeg".to_string() }
 fn default_video_format() -> String { "mp4".to_string() }
 fn default_quality() -> f32 { 0.9 }
+fn default_max_zoom() -> f32 { MAX_ZOOM }
+fn default_pixel_formats() -> Vec<String> { vec!["mjpeg".to_string(), "yuyv".to_string()] }
+
+/// Filters understood by `apply_filters`
+const CAMERA_FILTERS: &[&str] = &["grayscale", "sepia", "invert"];
 
 // Global camera state
 lazy_static! {
@@ -146,21 +261,274 @@ lazy_static! {
 
 /// Camera instance with the underlying camera and settings
 struct CameraInstance {
-    camera: NokhwaCamera,
+    source: CameraSource,
     options: CameraOptions,
     filters: Vec<String>,
+    /// Digital zoom applied to captured frames; 1.0 when unzoomed or zoomed optically
+    digital_zoom: f32,
+    /// Most recent encoded frames, if instant replay is enabled
+    replay: Option<ReplayBuffer>,
+}
+
+impl CameraInstance {
+    /// Capture the next frame with digital zoom and filters applied
+    fn capture_frame(&mut self) -> Result<image::RgbImage> {
+        let img = digital_zoom(&self.source.capture()?, self.digital_zoom)?;
+        apply_filters(&img, &self.filters)
+    }
+    
+    /// Capture the next frame for replay and recording, as MJPEG
+    fn capture_encoded(&mut self) -> Result<Vec<u8>> {
+        if self.digital_zoom > 1.0 {
+            // Zooming needs the decoded frame, so re-encode it afterwards
+            let img = digital_zoom(&self.source.capture()?, self.digital_zoom)?;
+            encode_image(&img, ImageFormat::Jpeg, 0.8)
+        } else {
+            self.source.capture_encoded()
+        }
+    }
+}
+
+/// Where a camera stream's frames come from
+///
+/// The `test-devices` feature swaps real hardware for the virtual camera.
+enum CameraSource {
+    #[cfg(not(feature = "test-devices"))]
+    Device(NokhwaCamera),
+    #[cfg(feature = "test-devices")]
+    Virtual(VirtualCamera),
+}
+
+impl CameraSource {
+    /// Start delivering frames
+    fn open(&mut self) -> Result<()> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => camera.open_stream()
+                .map_err(|e| HardwareError::DeviceError(format!("Failed to start camera stream: {}", e))),
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(_) => Ok(()),
+        }
+    }
+    
+    /// Capture the next frame as an RGB image
+    fn capture(&mut self) -> Result<image::RgbImage> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => {
+                let frame = camera.frame()
+                    .map_err(|e| HardwareError::DeviceError(format!("Failed to capture frame: {}", e)))?;
+                
+                // Decodes MJPEG and converts raw formats alike
+                frame.decode_image::<RgbFormat>()
+                    .map_err(|e| HardwareError::ProcessingError(format!("Failed to decode frame: {}", e)))
+            }
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(camera) => Ok(camera.next_frame()),
+        }
+    }
+    
+    /// Capture the next frame in the stream's encoded (MJPEG) form
+    fn capture_encoded(&mut self) -> Result<Vec<u8>> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => {
+                let frame = camera.frame()
+                    .map_err(|e| HardwareError::DeviceError(format!("Failed to capture frame: {}", e)))?;
+                
+                if frame.source_frame_format() == FrameFormat::MJPEG {
+                    return Ok(frame.buffer().to_vec());
+                }
+                
+                // Raw formats are compressed here instead
+                let img = frame.decode_image::<RgbFormat>()
+                    .map_err(|e| HardwareError::ProcessingError(format!("Failed to decode frame: {}", e)))?;
+                encode_image(&img, ImageFormat::Jpeg, 0.8)
+            }
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(camera) => encode_image(&camera.next_frame(), ImageFormat::Jpeg, 0.8),
+        }
+    }
+    
+    /// Raw range of the source's optical zoom control, if it has one
+    fn zoom_range(&self) -> Option<(i64, i64)> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => camera.camera_controls().unwrap_or_default()
+                .into_iter()
+                .find(|control| control.control() == KnownCameraControl::Zoom)
+                .and_then(|control| match control.description() {
+                    ControlValueDescription::IntegerRange { min, max, .. } if max > min => Some((*min, *max)),
+                    _ => None,
+                }),
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(_) => None,
+        }
+    }
+    
+    /// Set the source's optical zoom control to a raw value
+    fn set_optical_zoom(&mut self, value: i64) -> Result<()> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => camera
+                .set_camera_control(KnownCameraControl::Zoom, ControlValueSetter::Integer(value))
+                .map_err(|e| HardwareError::DeviceError(format!("Failed to set camera zoom: {}", e))),
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(_) => Err(HardwareError::UnsupportedOperation(format!(
+                "The virtual camera has no optical zoom, cannot set {}",
+                value
+            ))),
+        }
+    }
+    
+    /// Focus controls the source exposes
+    fn focus_support(&self) -> FocusSupport {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => focus_support(&camera.camera_controls().unwrap_or_default()),
+            // The virtual camera has a fixed focus
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(_) => FocusSupport::default(),
+        }
+    }
+    
+    /// Apply a single focus control change
+    fn apply_focus(&mut self, setting: FocusSetting) -> Result<()> {
+        match self {
+            #[cfg(not(feature = "test-devices"))]
+            CameraSource::Device(camera) => {
+                let (control, value) = match setting {
+                    FocusSetting::ContinuousAutofocus(enabled) => {
+                        (KnownCameraControl::Other(V4L2_CID_FOCUS_AUTO), ControlValueSetter::Boolean(enabled))
+                    }
+                    FocusSetting::Distance(value) => (KnownCameraControl::Focus, ControlValueSetter::Integer(value)),
+                    FocusSetting::TriggerAutofocus => {
+                        (KnownCameraControl::Other(V4L2_CID_AUTO_FOCUS_START), ControlValueSetter::Integer(1))
+                    }
+                };
+                
+                camera.set_camera_control(control, value)
+                    .map_err(|e| HardwareError::DeviceError(format!("Failed to set camera focus: {}", e)))
+            }
+            #[cfg(feature = "test-devices")]
+            CameraSource::Virtual(_) => {
+                Err(HardwareError::UnsupportedOperation(format!("The virtual camera has a fixed focus, cannot apply {:?}", setting)))
+            }
+        }
+    }
+}
+
+/// Fixed-capacity ring buffer of the most recent encoded frames
+#[derive(Debug)]
+struct ReplayBuffer {
+    frames: VecDeque<Vec<u8>>,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    /// Buffer enough frames for `seconds` of video at `frame_rate`
+    fn new(seconds: u32, frame_rate: u32) -> Self {
+        let capacity = (seconds as usize * frame_rate as usize).max(1);
+        
+        ReplayBuffer {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+    
+    /// Add an encoded frame, dropping the oldest one once the buffer is full
+    fn push(&mut self, frame: Vec<u8>) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
 }
 
 /// Recording instance with metadata
 struct RecordingInstance {
-    start_time: SystemTime,
+    clock: RecordingClock,
     format: String,
     quality: f32,
     width: u32,
     height: u32,
     include_audio: bool,
     frames: Vec<Vec<u8>>,
-    audio_data: Option<Vec<u8>>,
+    /// Microphone stream capturing audio for this recording
+    audio_stream_id: Option<String>,
+    /// Paces captured frames to the stream's frame rate
+    limiter: FrameLimiter,
+    max_buffered_frames: Option<usize>,
+}
+
+impl RecordingInstance {
+    /// Add a frame captured at `timestamp` on the recording clock
+    fn record_frame(&mut self, timestamp: Duration, frame: Vec<u8>) {
+        if self.clock.is_paused() || !self.limiter.offer(timestamp) {
+            return;
+        }
+        
+        if self.max_buffered_frames.map_or(false, |max| self.frames.len() >= max) {
+            self.limiter.drop_frame();
+            return;
+        }
+        
+        self.frames.push(frame);
+    }
+}
+
+/// Paces recorded frames to a frame rate and counts the ones that were missed
+///
+/// Each frame is assigned to the nearest slot of the frame-rate grid. Frames
+/// landing in an already filled slot arrived faster than the frame rate and
+/// are skipped; empty slots between two frames count as dropped.
+#[derive(Debug, Clone)]
+struct FrameLimiter {
+    interval: Duration,
+    last_slot: Option<u64>,
+    captured: u64,
+    dropped: u64,
+}
+
+impl FrameLimiter {
+    fn new(frame_rate: u32) -> Self {
+        FrameLimiter {
+            interval: Duration::from_secs(1) / frame_rate.max(1),
+            last_slot: None,
+            captured: 0,
+            dropped: 0,
+        }
+    }
+    
+    /// Offer a frame captured at `timestamp`, returning whether to keep it
+    fn offer(&mut self, timestamp: Duration) -> bool {
+        let slot = (timestamp.as_secs_f64() / self.interval.as_secs_f64()).round() as u64;
+        
+        match self.last_slot {
+            Some(last_slot) if slot <= last_slot => return false,
+            Some(last_slot) => self.dropped += slot - last_slot - 1,
+            None => {}
+        }
+        
+        self.last_slot = Some(slot);
+        self.captured += 1;
+        true
+    }
+    
+    /// Count a frame accepted by `offer` as dropped after all
+    fn drop_frame(&mut self) {
+        self.captured -= 1;
+        self.dropped += 1;
+    }
+    
+    /// Average rate of kept frames over `duration`
+    fn average_fps(&self, duration: Duration) -> f64 {
+        if duration.is_zero() {
+            0.0
+        } else {
+            self.captured as f64 / duration.as_secs_f64()
+        }
+    }
 }
 
 /// Camera API for SmashLang
@@ -169,6 +537,10 @@ pub struct Camera;
 impl Camera {
     /// Check if camera access is available on this device
     pub fn is_available() -> bool {
+        if cfg!(feature = "test-devices") {
+            return true;
+        }
+        
         // Try platform-specific implementations first
         #[cfg(target_os = "linux")]
         {
@@ -228,9 +600,7 @@ impl Camera {
                         Err(e) => {
                             if e.kind() == std::io::ErrorKind::PermissionDenied {
                                 // Provide helpful message about adding user to video group
-                                eprintln!("Permission denied accessing camera. You may need to add your user to the 'video' group.");
-                                eprintln!("Try running: sudo usermod -a -G video $USER");
-                                eprintln!("Then log out and log back in.");
+                                warn_group_hint("Camera", "video", "$USER");
                                 return Err(HardwareError::PermissionDenied("Camera access permission denied".to_string()));
                             }
                         }
@@ -257,8 +627,7 @@ impl Camera {
             };
             
             if !mf_result {
-                eprintln!("Camera access may be disabled in Windows privacy settings.");
-                eprintln!("Please check Settings > Privacy > Camera and ensure camera access is enabled for this application.");
+                log::warn!("Camera access may be disabled in Windows privacy settings. Please check Settings > Privacy > Camera and ensure camera access is enabled for this application.");
                 return Err(HardwareError::PermissionDenied("Camera access denied in Windows privacy settings".to_string()));
             }
             
@@ -274,9 +643,7 @@ impl Camera {
             match NokhwaCamera::new(CameraIndex::Index(0), None) {
                 Ok(_) => Ok(true),
                 Err(_) => {
-                    eprintln!("Camera access may be disabled in macOS privacy settings.");
-                    eprintln!("Please check System Preferences > Security & Privacy > Privacy > Camera");
-                    eprintln!("and ensure this application has camera access.");
+                    log::warn!("Camera access may be disabled in macOS privacy settings. Please check System Preferences > Security & Privacy > Privacy > Camera and ensure this application has camera access.");
                     Err(HardwareError::PermissionDenied("Camera access denied in macOS privacy settings".to_string()))
                 }
             }
@@ -292,8 +659,49 @@ impl Camera {
         }
     }
     
+    /// Read the current camera permission without prompting the user
+    pub fn permission_status() -> PermissionStatus {
+        #[cfg(target_os = "linux")]
+        {
+            // Device nodes are opened directly, so their file permissions decide access
+            let mut status = PermissionStatus::Unknown;
+            for i in 0..10 {
+                match std::fs::File::open(format!("/dev/video{}", i)) {
+                    Ok(_) => return PermissionStatus::Granted,
+                    Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => status = PermissionStatus::Denied,
+                    Err(_) => {}
+                }
+            }
+            status
+        }
+        
+        #[cfg(target_os = "windows")]
+        {
+            consent_store_status("webcam")
+        }
+        
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            // Reading the status on macOS and mobile requires the platform permission APIs
+            PermissionStatus::Unknown
+        }
+    }
+    
     /// Get a list of available camera devices
+    ///
+    /// With the `test-devices` feature this is just the virtual camera.
     pub async fn get_devices() -> Result<Vec<CameraDevice>> {
+        #[cfg(feature = "test-devices")]
+        let devices = Ok(vec![VirtualCamera::device()]);
+        #[cfg(not(feature = "test-devices"))]
+        let devices = Self::get_hardware_devices().await;
+        
+        devices
+    }
+    
+    /// Enumerate the camera devices attached to the system
+    #[cfg(not(feature = "test-devices"))]
+    async fn get_hardware_devices() -> Result<Vec<CameraDevice>> {
         let mut camera_devices = Vec::new();
         
         // Platform-specific device enumeration
@@ -324,6 +732,10 @@ impl Camera {
                     // Try to open the device to check if it's accessible
                     if std::fs::File::open(&path).is_ok() {
                         capabilities.push("accessible".to_string());
+                        
+                        if camera_index_from_id(&dev_path).map_or(false, device_has_focus) {
+                            capabilities.push("focus".to_string());
+                        }
                     }
                     
                     camera_devices.push(CameraDevice {
@@ -345,11 +757,16 @@ impl Camera {
             };
             
             for (index, info) in devices.into_iter().enumerate() {
+                let mut capabilities = vec!["photo".to_string(), "video".to_string()];
+                if device_has_focus(info.index().clone()) {
+                    capabilities.push("focus".to_string());
+                }
+                
                 let device = CameraDevice {
                     id: info.index().to_string(),
                     label: info.human_name(),
                     index,
-                    capabilities: vec!["photo".to_string(), "video".to_string()],
+                    capabilities,
                 };
                 camera_devices.push(device);
             }
@@ -374,8 +791,95 @@ impl Camera {
         Ok(camera_devices)
     }
     
+    /// Get the formats supported by a camera, or by the default camera if no id is given
+    pub async fn get_supported_formats(device_id: Option<&str>) -> Result<Vec<CameraFormat>> {
+        let camera_index = match device_id {
+            Some(device_id) => camera_index_from_id(device_id)?,
+            None => CameraIndex::Index(0),
+        };
+        
+        let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::None);
+        let mut camera = NokhwaCamera::new(camera_index, requested_format)
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to open camera: {}", e)))?;
+        
+        let formats = camera.compatible_camera_formats()
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to query camera formats: {}", e)))?;
+        
+        let mut supported: Vec<CameraFormat> = formats.iter().map(describe_format).collect();
+        
+        // Largest resolutions first, then highest frame rate
+        supported.sort_by(|a, b| {
+            (b.width * b.height, b.frame_rate).cmp(&(a.width * a.height, a.frame_rate))
+        });
+        supported.dedup();
+        
+        Ok(supported)
+    }
+    
     /// Start a camera stream
     pub async fn start(options: CameraOptions) -> Result<CameraStream> {
+        if options.replay_buffer_seconds == Some(0) {
+            return Err(HardwareError::InvalidParameter("replay_buffer_seconds must be greater than 0".to_string()));
+        }
+        
+        #[cfg(feature = "test-devices")]
+        let (source, format) = (
+            CameraSource::Virtual(VirtualCamera::new(options.width, options.height)),
+            VirtualCamera::format(options.width, options.height, options.frame_rate),
+        );
+        #[cfg(not(feature = "test-devices"))]
+        let (source, format) = {
+            let (camera, format) = Self::open_device(&options).await?;
+            (CameraSource::Device(camera), format)
+        };
+        
+        // Keep the stream's settings in line with what was negotiated
+        let options = CameraOptions {
+            width: format.width,
+            height: format.height,
+            frame_rate: format.frame_rate,
+            ..options
+        };
+        
+        // Generate a unique ID for this camera stream
+        let stream_id = format!("camera_{}", uuid::Uuid::new_v4().to_string());
+        
+        // Store the camera instance
+        let camera_instance = CameraInstance {
+            source,
+            options: options.clone(),
+            filters: Vec::new(),
+            digital_zoom: 1.0,
+            replay: options.replay_buffer_seconds.map(|seconds| ReplayBuffer::new(seconds, options.frame_rate)),
+        };
+        
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
+        instances.insert(stream_id.clone(), camera_instance);
+        
+        // Start the camera stream
+        if let Err(e) = instances.get_mut(&stream_id).unwrap().source.open() {
+            instances.remove(&stream_id);
+            return Err(e);
+        }
+        
+        if options.replay_buffer_seconds.is_some() {
+            spawn_replay_capture(stream_id.clone(), options.frame_rate);
+        }
+        
+        // Return the stream information
+        Ok(CameraStream {
+            id: stream_id,
+            width: format.width,
+            height: format.height,
+            frame_rate: format.frame_rate,
+            pixel_format: format.format,
+        })
+    }
+    
+    /// Open the hardware camera selected by the stream options in the
+    /// negotiated format
+    #[cfg(not(feature = "test-devices"))]
+    async fn open_device(options: &CameraOptions) -> Result<(NokhwaCamera, CameraFormat)> {
         // Determine which camera to use
         let camera_index = if let Some(device_id) = &options.device_id {
             match device_id.parse::<usize>() {
@@ -397,58 +901,37 @@ impl Camera {
             }
         };
         
-        // Set up the camera format
-        let requested_format = RequestedFormat::new::<RgbFormat>(
-            RequestedFormatType::Closest(CameraFormat::new(
-                options.width,
-                options.height,
-                nokhwa::utils::FrameFormat::MJPEG,
-                options.frame_rate,
-            ))
-        );
+        // Find out which formats the camera supports
+        let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::None);
+        let mut camera = NokhwaCamera::new(camera_index.clone(), requested_format)
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to initialize camera: {}", e)))?;
         
-        // Create the camera
-        let camera = match NokhwaCamera::new(camera_index, Some(requested_format)) {
-            Ok(camera) => camera,
-            Err(e) => return Err(HardwareError::DeviceError(format!("Failed to initialize camera: {}", e))),
-        };
+        let formats = camera.compatible_camera_formats()
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to query camera formats: {}", e)))?;
+        let described: Vec<CameraFormat> = formats.iter().map(describe_format).collect();
         
-        // Generate a unique ID for this camera stream
-        let stream_id = format!("camera_{}", uuid::Uuid::new_v4().to_string());
-        
-        // Store the camera instance
-        let camera_instance = CameraInstance {
-            camera,
-            options: options.clone(),
-            filters: Vec::new(),
-        };
+        let chosen = negotiate_format(&described, options)
+            .ok_or_else(|| HardwareError::DeviceError("Camera reports no supported formats".to_string()))?;
         
-        let mut instances = CAMERA_INSTANCES.lock().unwrap();
-        instances.insert(stream_id.clone(), camera_instance);
+        // Reopen the camera in the negotiated format
+        drop(camera);
+        let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::Exact(formats[chosen]));
+        let camera = NokhwaCamera::new(camera_index, requested_format)
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to initialize camera: {}", e)))?;
         
-        // Start the camera stream
-        if let Err(e) = instances.get_mut(&stream_id).unwrap().camera.open_stream() {
-            instances.remove(&stream_id);
-            return Err(HardwareError::DeviceError(format!("Failed to start camera stream: {}", e)));
-        }
-        
-        // Return the stream information
-        Ok(CameraStream {
-            id: stream_id,
-            width: options.width,
-            height: options.height,
-            frame_rate: options.frame_rate,
-        })
+        Ok((camera, described[chosen].clone()))
     }
     
     /// Stop a camera stream
     pub fn stop(stream_id: &str) -> Result<()> {
-        let mut instances = CAMERA_INSTANCES.lock().unwrap();
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
         
         if let Some(instance) = instances.remove(stream_id) {
             // Stop any active recording
-            let mut recordings = RECORDING_INSTANCES.lock().unwrap();
-            recordings.remove(stream_id);
+            let mut recordings = RECORDING_INSTANCES.lock_or_recover();
+            if let Some(recording) = recordings.remove(stream_id) {
+                stop_audio_capture(&recording);
+            }
             
             // Close the camera stream
             drop(instance);
@@ -458,41 +941,103 @@ impl Camera {
         }
     }
     
-    /// Take a photo from a camera stream
-    pub async fn take_photo(stream_id: &str, options: PhotoOptions) -> Result<Photo> {
-        let mut instances = CAMERA_INSTANCES.lock().unwrap();
+    /// Set how a camera stream focuses
+    ///
+    /// Returns `UnsupportedOperation` if the camera lacks the controls the
+    /// mode needs.
+    pub fn set_focus_mode(stream_id: &str, mode: FocusMode) -> Result<()> {
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
         
         let instance = instances.get_mut(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
         })?;
         
-        // Capture a frame from the camera
-        let frame = match instance.camera.frame() {
-            Ok(frame) => frame,
-            Err(e) => return Err(HardwareError::DeviceError(format!("Failed to capture frame: {}", e))),
-        };
+        for setting in instance.source.focus_support().settings(mode)? {
+            instance.source.apply_focus(setting)?;
+        }
         
-        // Convert to the requested format
-        let img = image::RgbImage::from_raw(
-            frame.width() as u32,
-            frame.height() as u32,
-            frame.buffer().to_vec(),
-        ).ok_or_else(|| HardwareError::ProcessingError("Failed to create image from frame".to_string()))?;
+        Ok(())
+    }
+    
+    /// Run a single autofocus pass on a camera stream
+    pub fn trigger_autofocus(stream_id: &str) -> Result<()> {
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
+        
+        let instance = instances.get_mut(stream_id).ok_or_else(|| {
+            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
+        })?;
+        
+        let setting = instance.source.focus_support().trigger()?;
+        instance.source.apply_focus(setting)
+    }
+    
+    /// Zoom a camera stream by `factor`, returning the factor applied
+    ///
+    /// Cameras with an optical zoom control use it; others fall back to a
+    /// centered digital crop of every captured frame. Factors above the
+    /// `max_zoom` reported by `get_supported_formats` are clamped, and factors
+    /// below 1.0 are rejected.
+    pub fn set_zoom(stream_id: &str, factor: f32) -> Result<f32> {
+        if factor.is_nan() || factor < 1.0 {
+            return Err(HardwareError::InvalidParameter(format!("Zoom factor must be at least 1.0, got {}", factor)));
+        }
+        let factor = factor.min(MAX_ZOOM);
+        
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
+        
+        let instance = instances.get_mut(stream_id).ok_or_else(|| {
+            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
+        })?;
         
-        // Apply any filters
-        let img = apply_filters(&img, &instance.filters)?;
+        match instance.source.zoom_range() {
+            Some((min, max)) => {
+                // Spread 1.0..=MAX_ZOOM over the control's range
+                let position = (factor - 1.0) as f64 / (MAX_ZOOM - 1.0) as f64;
+                instance.source.set_optical_zoom(min + ((max - min) as f64 * position).round() as i64)?;
+                instance.digital_zoom = 1.0;
+            }
+            None => instance.digital_zoom = factor,
+        }
         
-        // Encode the image to the requested format
-        let (data, format) = encode_image(&img, &options.format, options.quality)?;
+        Ok(factor)
+    }
+    
+    /// Grab the current frame of a camera stream with the stream's zoom and filters applied
+    pub fn grab_frame(stream_id: &str) -> Result<image::RgbImage> {
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
+        
+        let instance = instances.get_mut(stream_id).ok_or_else(|| {
+            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
+        })?;
+        
+        instance.capture_frame()
+    }
+    
+    /// Take a photo from a camera stream
+    pub async fn take_photo(stream_id: &str, options: PhotoOptions) -> Result<Photo> {
+        let img = Self::grab_frame(stream_id)?;
+        
+        // Resize to the requested dimensions
+        let img = match &options.resize {
+            Some(resize) => resize_image(&img, resize)?,
+            None => img,
+        };
+        
+        // Encode the image to the requested format, defaulting to JPEG for unknown formats
+        let format = ImageFormat::from_name(&options.format).unwrap_or(ImageFormat::Jpeg);
+        let data = encode_image(&img, format, options.quality)?;
         
         // Convert to base64
-        let base64_data = base64::encode(&data);
+        let mut base64_data = base64::encode(&data);
+        if options.as_data_uri {
+            base64_data = data_uri(format.name(), &base64_data);
+        }
         
         Ok(Photo {
             data: base64_data,
             width: img.width(),
             height: img.height(),
-            format,
+            format: format.name().to_string(),
             timestamp: SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap_or_else(|_| Duration::from_secs(0))
@@ -501,52 +1046,92 @@ impl Camera {
     }
     
     /// Save a photo to a file
+    ///
+    /// `photo_data` may be raw base64 or a data URI. The photo is converted to
+    /// the format named by the file extension (e.g. `.webp`) if it was taken in
+    /// a different one.
     pub async fn save_photo(photo_data: &str, file_path: &str) -> Result<SaveResult> {
+        Self::save_photo_with_options(photo_data, file_path, &PhotoOptions::default()).await
+    }
+    
+    /// Save a photo to a file, writing a `*_thumb.*` thumbnail next to it if
+    /// `options.thumbnail` is set
+    pub async fn save_photo_with_options(photo_data: &str, file_path: &str, options: &PhotoOptions) -> Result<SaveResult> {
         // Decode the base64 data
-        let data = match base64::decode(photo_data) {
+        let data = match base64::decode(strip_data_uri(photo_data)) {
             Ok(data) => data,
             Err(e) => return Err(HardwareError::ProcessingError(format!("Failed to decode photo data: {}", e))),
         };
         
+        let data = match ImageFormat::from_path(file_path) {
+            Some(format) => transcode(data, format, options.quality)?,
+            None => data,
+        };
+        
         // Save to file
         if let Err(e) = std::fs::write(file_path, &data) {
             return Err(HardwareError::IoError(format!("Failed to save photo: {}", e)));
         }
         
+        let thumbnail_path = match options.thumbnail {
+            Some(max_dimension) => Some(write_thumbnail(file_path, max_dimension, options.quality)?),
+            None => None,
+        };
+        
         Ok(SaveResult {
             path: file_path.to_string(),
+            thumbnail_path,
         })
     }
     
     /// Start recording video from a camera stream
     pub async fn start_recording(stream_id: &str, options: RecordingOptions) -> Result<()> {
-        let instances = CAMERA_INSTANCES.lock().unwrap();
-        
-        let instance = instances.get(stream_id).ok_or_else(|| {
-            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
-        })?;
+        if !Self::is_active(stream_id) {
+            return Err(HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id)));
+        }
         
-        // Check if already recording
-        let mut recordings = RECORDING_INSTANCES.lock().unwrap();
-        if recordings.contains_key(stream_id) {
+        if RECORDING_INSTANCES.lock_or_recover().contains_key(stream_id) {
             return Err(HardwareError::AlreadyInUse("Camera is already recording".to_string()));
         }
         
+        // Open the audio source first so a bad device fails the whole recording
+        let audio_stream_id = match &options.audio_device_id {
+            Some(audio_device_id) => Some(start_audio_capture(audio_device_id, options.quality).await?),
+            None => None,
+        };
+        
+        let instances = CAMERA_INSTANCES.lock_or_recover();
+        let mut recordings = RECORDING_INSTANCES.lock_or_recover();
+        
+        // The stream may have been stopped or started recording while the audio was opening
+        let instance = match instances.get(stream_id) {
+            Some(instance) if !recordings.contains_key(stream_id) => instance,
+            _ => {
+                if let Some(audio_stream_id) = &audio_stream_id {
+                    let _ = Microphone::stop(audio_stream_id);
+                }
+                return Err(HardwareError::InvalidOperation(format!("Camera stream is no longer available for recording: {}", stream_id)));
+            }
+        };
+        
         // Create a new recording instance
         let recording = RecordingInstance {
-            start_time: SystemTime::now(),
+            clock: RecordingClock::start(),
             format: options.format.clone(),
             quality: options.quality,
             width: instance.options.width,
             height: instance.options.height,
-            include_audio: options.include_audio,
+            include_audio: options.include_audio || audio_stream_id.is_some(),
             frames: Vec::new(),
-            audio_data: None,
+            audio_stream_id,
+            limiter: FrameLimiter::new(instance.options.frame_rate),
+            max_buffered_frames: options.max_buffered_frames,
         };
         
         recordings.insert(stream_id.to_string(), recording);
         
         // In a real implementation, we would start a background thread to capture frames
+        // with `CameraInstance::capture_encoded` and pass each one to `RecordingInstance::record_frame`
         // For simplicity, we'll just store the recording configuration
         
         Ok(())
@@ -554,22 +1139,36 @@ impl Camera {
     
     /// Stop recording video and save to a file
     pub async fn stop_recording(stream_id: &str, file_path: &str) -> Result<RecordingResult> {
-        let instances = CAMERA_INSTANCES.lock().unwrap();
-        
-        let instance = instances.get(stream_id).ok_or_else(|| {
-            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
-        })?;
+        if !Self::is_active(stream_id) {
+            return Err(HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id)));
+        }
         
         // Get the recording instance
-        let mut recordings = RECORDING_INSTANCES.lock().unwrap();
-        let recording = recordings.remove(stream_id).ok_or_else(|| {
+        let mut recording = RECORDING_INSTANCES.lock_or_recover().remove(stream_id).ok_or_else(|| {
             HardwareError::InvalidOperation("Camera is not recording".to_string())
         })?;
         
-        // Calculate duration
-        let duration = recording.start_time.elapsed()
-            .unwrap_or_else(|_| Duration::from_secs(0))
-            .as_secs_f64();
+        // Collect the captured audio, release the microphone and save the
+        // audio next to the video
+        let mut audio_path = None;
+        if let Some(audio_stream_id) = recording.audio_stream_id.take() {
+            let audio = Microphone::stop_recording(&audio_stream_id, None).await;
+            let _ = Microphone::stop(&audio_stream_id);
+            
+            let audio = audio.map_err(|e| HardwareError::DeviceError(format!("Failed to finish audio recording: {}", e)))?;
+            let samples = audio.data.and_then(|data| base64::decode(data).ok()).unwrap_or_default();
+            
+            // The microphone was opened with the default options
+            let options = MicrophoneOptions::default();
+            let path = Path::new(file_path).with_extension("wav");
+            std::fs::write(&path, float_wav(&samples, options.sample_rate, options.channels))
+                .map_err(|e| HardwareError::IoError(format!("Failed to save audio: {}", e)))?;
+            audio_path = Some(path.display().to_string());
+        }
+        
+        // Calculate duration, excluding time spent paused
+        let elapsed = recording.clock.elapsed();
+        let duration = elapsed.as_secs_f64();
         
         // In a real implementation, we would encode the frames to a video file
         // For simplicity, we'll just create a dummy file
@@ -586,12 +1185,122 @@ impl Camera {
             size: 1024, // Dummy size
             width: recording.width,
             height: recording.height,
+            dropped_frames: recording.limiter.dropped,
+            average_fps: recording.limiter.average_fps(elapsed),
+            audio_path,
+        })
+    }
+    
+    /// Pause recording video, along with any audio recorded alongside it
+    pub async fn pause_recording(stream_id: &str) -> Result<()> {
+        let audio_stream_id = Self::with_recording(stream_id, |recording| {
+            recording.clock.pause()?;
+            Ok(recording.audio_stream_id.clone())
+        })?;
+        
+        if let Some(audio_stream_id) = audio_stream_id {
+            Microphone::pause_recording(&audio_stream_id).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Resume a paused video recording
+    pub async fn resume_recording(stream_id: &str) -> Result<()> {
+        let audio_stream_id = Self::with_recording(stream_id, |recording| {
+            recording.clock.resume()?;
+            Ok(recording.audio_stream_id.clone())
+        })?;
+        
+        if let Some(audio_stream_id) = audio_stream_id {
+            Microphone::resume_recording(&audio_stream_id).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Run `f` on the active recording of a camera stream
+    fn with_recording<T>(stream_id: &str, f: impl FnOnce(&mut RecordingInstance) -> Result<T>) -> Result<T> {
+        if !Self::is_active(stream_id) {
+            return Err(HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id)));
+        }
+        
+        let mut recordings = RECORDING_INSTANCES.lock_or_recover();
+        let recording = recordings.get_mut(stream_id).ok_or_else(|| {
+            HardwareError::InvalidOperation("Camera is not recording".to_string())
+        })?;
+        
+        f(recording)
+    }
+    
+    /// Write the frames held in a stream's instant replay buffer to a file
+    ///
+    /// The frames are written back to back as an MJPEG stream. Requires the
+    /// stream to have been started with `replay_buffer_seconds`.
+    pub async fn save_replay(stream_id: &str, file_path: &str) -> Result<ReplayResult> {
+        let instances = CAMERA_INSTANCES.lock_or_recover();
+        
+        let instance = instances.get(stream_id).ok_or_else(|| {
+            HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
+        })?;
+        
+        let replay = instance.replay.as_ref().ok_or_else(|| {
+            HardwareError::InvalidOperation("Instant replay is not enabled; start the camera with replay_buffer_seconds".to_string())
+        })?;
+        
+        if replay.frames.is_empty() {
+            return Err(HardwareError::InvalidOperation("Replay buffer is empty".to_string()));
+        }
+        
+        let data: Vec<u8> = replay.frames.iter().flatten().copied().collect();
+        if let Err(e) = std::fs::write(file_path, &data) {
+            return Err(HardwareError::IoError(format!("Failed to save replay: {}", e)));
+        }
+        
+        Ok(ReplayResult {
+            path: file_path.to_string(),
+            duration: replay.frames.len() as f64 / instance.options.frame_rate.max(1) as f64,
+            frame_count: replay.frames.len(),
+            size: data.len() as u64,
         })
     }
     
     /// Apply a filter to the camera stream
-    pub async fn apply_filter(stream_id: &str, filter_name: &str, _options: serde_json::Value) -> Result<()> {
-        let mut instances = CAMERA_INSTANCES.lock().unwrap();
+    pub async fn apply_filter(stream_id: &str, filter_name: &str, options: serde_json::Value) -> Result<()> {
+        Self::add_filter(stream_id, filter_name, options)
+    }
+    
+    /// Remove all filters from the camera stream
+    pub async fn remove_filters(stream_id: &str) -> Result<()> {
+        Self::clear_filters(stream_id)
+    }
+    
+    /// Check whether a camera stream is open
+    pub fn is_active(stream_id: &str) -> bool {
+        CAMERA_INSTANCES.lock_or_recover().contains_key(stream_id)
+    }
+    
+    /// Stop every open camera stream and discard any active recordings
+    pub(crate) fn stop_all() {
+        // Dropping the instances releases the underlying devices
+        CAMERA_INSTANCES.lock_or_recover().clear();
+        
+        for (_, recording) in RECORDING_INSTANCES.lock_or_recover().drain() {
+            stop_audio_capture(&recording);
+        }
+    }
+    
+    /// Get the ids of all open camera streams, sorted
+    pub fn active_streams() -> Vec<String> {
+        let mut ids: Vec<String> = CAMERA_INSTANCES.lock_or_recover().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+    
+    fn add_filter(stream_id: &str, filter_name: &str, _options: serde_json::Value) -> Result<()> {
+        validate_effect("camera", filter_name, CAMERA_FILTERS)?;
+        
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
         
         let instance = instances.get_mut(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
@@ -603,9 +1312,8 @@ impl Camera {
         Ok(())
     }
     
-    /// Remove all filters from the camera stream
-    pub async fn remove_filters(stream_id: &str) -> Result<()> {
-        let mut instances = CAMERA_INSTANCES.lock().unwrap();
+    fn clear_filters(stream_id: &str) -> Result<()> {
+        let mut instances = CAMERA_INSTANCES.lock_or_recover();
         
         let instance = instances.get_mut(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Camera stream not found: {}", stream_id))
@@ -618,6 +1326,185 @@ impl Camera {
     }
 }
 
+impl MediaStream for CameraStream {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    
+    fn stop(&self) -> Result<()> {
+        Camera::stop(&self.id)
+    }
+    
+    fn is_active(&self) -> bool {
+        Camera::is_active(&self.id)
+    }
+    
+    fn apply_effect(&self, name: &str, options: serde_json::Value) -> Result<()> {
+        Camera::add_filter(&self.id, name, options)
+    }
+    
+    fn clear_effects(&self) -> Result<()> {
+        Camera::clear_filters(&self.id)
+    }
+}
+
+/// Start capturing audio from a microphone to save alongside a camera recording
+async fn start_audio_capture(audio_device_id: &str, quality: f32) -> Result<String> {
+    let open_error = |e: HardwareError| {
+        HardwareError::DeviceError(format!("Failed to open audio device '{}' for recording: {}", audio_device_id, e))
+    };
+    
+    let stream = Microphone::start_device(audio_device_id, MicrophoneOptions::default())
+        .await
+        .map_err(open_error)?;
+    
+    let recording_options = AudioRecordingOptions {
+        format: "wav".to_string(),
+        quality,
+    };
+    
+    if let Err(e) = Microphone::start_recording(&stream.id, recording_options).await {
+        let _ = Microphone::stop(&stream.id);
+        return Err(open_error(e));
+    }
+    
+    Ok(stream.id)
+}
+
+/// Wrap little-endian 32-bit float samples in a WAV file
+fn float_wav(samples: &[u8], sample_rate: u32, channels: u16) -> Vec<u8> {
+    const FORMAT_IEEE_FLOAT: u16 = 3;
+    let block_align = channels * 4;
+    
+    let mut wav = Vec::with_capacity(44 + samples.len());
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&FORMAT_IEEE_FLOAT.to_le_bytes());
+    wav.extend_from_slice(&channels.to_le_bytes());
+    wav.extend_from_slice(&sample_rate.to_le_bytes());
+    wav.extend_from_slice(&(sample_rate * block_align as u32).to_le_bytes());
+    wav.extend_from_slice(&block_align.to_le_bytes());
+    wav.extend_from_slice(&32u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(samples.len() as u32).to_le_bytes());
+    wav.extend_from_slice(samples);
+    wav
+}
+
+/// Stop the microphone stream attached to a recording, if any
+fn stop_audio_capture(recording: &RecordingInstance) {
+    if let Some(audio_stream_id) = &recording.audio_stream_id {
+        // The microphone may already have been stopped independently
+        let _ = Microphone::stop(audio_stream_id);
+    }
+}
+
+/// Keep filling a stream's replay buffer with its MJPEG frames until the
+/// stream is stopped
+fn spawn_replay_capture(stream_id: String, frame_rate: u32) {
+    let interval = Duration::from_secs(1) / frame_rate.max(1);
+    
+    std::thread::spawn(move || loop {
+        {
+            let mut instances = CAMERA_INSTANCES.lock_or_recover();
+            let instance = match instances.get_mut(&stream_id) {
+                Some(instance) => instance,
+                None => break,
+            };
+            
+            // A missed frame just leaves a gap in the replay
+            if let Ok(frame) = instance.capture_encoded() {
+                if let Some(replay) = instance.replay.as_mut() {
+                    replay.push(frame);
+                }
+            }
+        }
+        
+        std::thread::sleep(interval);
+    });
+}
+
+/// Resolve a camera device id ("2" or "/dev/video2") to a camera index
+fn camera_index_from_id(device_id: &str) -> Result<CameraIndex> {
+    let index = device_id.trim_start_matches("/dev/video");
+    
+    index.parse::<u32>()
+        .map(CameraIndex::Index)
+        .map_err(|_| HardwareError::InvalidId(format!("Camera device not found: {}", device_id)))
+}
+
+/// Describe a format reported by nokhwa
+#[cfg(not(feature = "test-devices"))]
+fn describe_format(format: &nokhwa::CameraFormat) -> CameraFormat {
+    CameraFormat {
+        width: format.width(),
+        height: format.height(),
+        frame_rate: format.frame_rate(),
+        format: format.format().to_string(),
+        max_zoom: MAX_ZOOM,
+    }
+}
+
+/// Pick the index of the supported format a stream should use
+///
+/// Pixel formats are tried in the order of `options.pixel_formats`, taking
+/// the format closest to the requested size and then frame rate. When none
+/// of the preferred pixel formats is supported, the closest format of any
+/// kind is used.
+#[cfg(any(not(feature = "test-devices"), test))]
+fn negotiate_format(supported: &[CameraFormat], options: &CameraOptions) -> Option<usize> {
+    let distance = |format: &CameraFormat| {
+        let size = (format.width as i64 - options.width as i64).abs() + (format.height as i64 - options.height as i64).abs();
+        (size, (format.frame_rate as i64 - options.frame_rate as i64).abs())
+    };
+    
+    // Closest format with the given pixel format, or of any pixel format
+    let closest = |pixel_format: Option<&str>| {
+        supported.iter()
+            .enumerate()
+            .filter(|(_, format)| pixel_format.map_or(true, |name| format.format.eq_ignore_ascii_case(name)))
+            .min_by_key(|(_, format)| distance(format))
+            .map(|(index, _)| index)
+    };
+    
+    options.pixel_formats.iter()
+        .find_map(|name| closest(Some(name)))
+        .or_else(|| closest(None))
+}
+
+/// Focus controls among a camera's controls
+#[cfg(not(feature = "test-devices"))]
+fn focus_support(controls: &[CameraControl]) -> FocusSupport {
+    let mut support = FocusSupport::default();
+    
+    for control in controls {
+        match control.control() {
+            KnownCameraControl::Focus => {
+                if let ControlValueDescription::IntegerRange { min, max, .. } = control.description() {
+                    support.distance_range = Some((*min, *max));
+                }
+            }
+            KnownCameraControl::Other(V4L2_CID_FOCUS_AUTO) => support.continuous = true,
+            KnownCameraControl::Other(V4L2_CID_AUTO_FOCUS_START) => support.trigger = true,
+            _ => {}
+        }
+    }
+    
+    support
+}
+
+/// Whether a camera exposes any focus control
+#[cfg(not(feature = "test-devices"))]
+fn device_has_focus(camera_index: CameraIndex) -> bool {
+    let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::None);
+    
+    NokhwaCamera::new(camera_index, requested_format)
+        .and_then(|camera| camera.camera_controls())
+        .map_or(false, |controls| focus_support(&controls).is_supported())
+}
+
 /// Apply filters to an image
 fn apply_filters(img: &image::RgbImage, filters: &[String]) -> Result<image::RgbImage> {
     let mut result = img.clone();
@@ -666,42 +1553,264 @@ fn apply_filters(img: &image::RgbImage, filters: &[String]) -> Result<image::Rgb
     Ok(result)
 }
 
-/// Encode an image to the specified format
-fn encode_image(img: &image::RgbImage, format: &str, quality: f32) -> Result<(Vec<u8>, String)> {
-    let mut buffer = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
     
-    match format.to_lowercase().as_str() {
-        "jpeg" | "jpg" => {
-            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, (quality * 100.0) as u8);
-            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
-                return Err(HardwareError::ProcessingError(format!("Failed to encode JPEG: {}", e)));
-            }
-            Ok((buffer, "jpeg".to_string()))
-        },
-        "png" => {
-            let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
-                return Err(HardwareError::ProcessingError(format!("Failed to encode PNG: {}", e)));
-            }
-            Ok((buffer, "png".to_string()))
-        },
-        "webp" => {
-            // WebP encoding is not directly supported in the image crate
-            // In a real implementation, we would use a WebP encoder
-            // For now, fall back to PNG
-            let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
-                return Err(HardwareError::ProcessingError(format!("Failed to encode PNG: {}", e)));
-            }
-            Ok((buffer, "png".to_string()))
-        },
-        _ => {
-            // Default to JPEG for unknown formats
-            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, (quality * 100.0) as u8);
-            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
-                return Err(HardwareError::ProcessingError(format!("Failed to encode JPEG: {}", e)));
-            }
-            Ok((buffer, "jpeg".to_string()))
+    #[cfg(feature = "test-devices")]
+    #[test]
+    fn test_virtual_camera_frames() {
+        let devices = futures::executor::block_on(Camera::get_devices()).unwrap();
+        assert_eq!(devices.len(), 1);
+        assert!(devices[0].capabilities.contains(&"virtual".to_string()));
+        
+        let options: CameraOptions = serde_json::from_value(serde_json::json!({
+            "width": 320,
+            "height": 240
+        })).unwrap();
+        let stream = futures::executor::block_on(Camera::start(options)).unwrap();
+        
+        let first = Camera::grab_frame(&stream.id).unwrap();
+        let second = Camera::grab_frame(&stream.id).unwrap();
+        assert_eq!(first.dimensions(), (320, 240));
+        assert_eq!(second.dimensions(), (320, 240));
+        assert_ne!(first, second);
+        
+        let photo = futures::executor::block_on(Camera::take_photo(&stream.id, PhotoOptions::default())).unwrap();
+        assert_eq!((photo.width, photo.height), (320, 240));
+        
+        Camera::stop(&stream.id).unwrap();
+    }
+    
+    #[cfg(feature = "test-devices")]
+    #[test]
+    fn test_virtual_camera_focus_is_unsupported() {
+        let options: CameraOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        let stream = futures::executor::block_on(Camera::start(options)).unwrap();
+        
+        assert!(matches!(
+            Camera::set_focus_mode(&stream.id, FocusMode::Continuous),
+            Err(HardwareError::UnsupportedOperation(_))
+        ));
+        assert!(matches!(Camera::trigger_autofocus(&stream.id), Err(HardwareError::UnsupportedOperation(_))));
+        assert!(matches!(Camera::trigger_autofocus("camera_missing"), Err(HardwareError::InvalidId(_))));
+        
+        Camera::stop(&stream.id).unwrap();
+    }
+    
+    #[cfg(feature = "test-devices")]
+    #[test]
+    fn test_virtual_camera_digital_zoom() {
+        let options: CameraOptions = serde_json::from_value(serde_json::json!({
+            "width": 320,
+            "height": 240
+        })).unwrap();
+        let stream = futures::executor::block_on(Camera::start(options)).unwrap();
+        
+        assert_eq!(Camera::set_zoom(&stream.id, 2.0).unwrap(), 2.0);
+        assert_eq!(Camera::grab_frame(&stream.id).unwrap().dimensions(), (320, 240));
+        
+        // Zoom is clamped to the maximum, and zooming out is rejected
+        assert_eq!(Camera::set_zoom(&stream.id, 100.0).unwrap(), MAX_ZOOM);
+        assert!(matches!(Camera::set_zoom(&stream.id, 0.5), Err(HardwareError::InvalidParameter(_))));
+        
+        Camera::stop(&stream.id).unwrap();
+    }
+    
+    #[test]
+    fn test_negotiate_format_prefers_listed_pixel_formats() {
+        let format = |width, height, frame_rate, name: &str| CameraFormat {
+            width,
+            height,
+            frame_rate,
+            format: name.to_string(),
+            max_zoom: MAX_ZOOM,
+        };
+        let supported = vec![
+            format(1920, 1080, 5, "YUYV"),
+            format(1280, 720, 10, "YUYV"),
+            format(1920, 1080, 30, "MJPEG"),
+            format(1280, 720, 30, "MJPEG"),
+            format(640, 480, 30, "NV12"),
+        ];
+        let options = |json: serde_json::Value| serde_json::from_value::<CameraOptions>(json).unwrap();
+        
+        // MJPEG is preferred by default, at the closest size
+        assert_eq!(negotiate_format(&supported, &options(serde_json::json!({}))), Some(3));
+        assert_eq!(negotiate_format(&supported, &options(serde_json::json!({ "width": 1920, "height": 1080 }))), Some(2));
+        
+        // An explicit preference order is honored
+        let yuyv_first = options(serde_json::json!({ "width": 1920, "height": 1080, "pixel_formats": ["yuyv", "mjpeg"] }));
+        assert_eq!(negotiate_format(&supported, &yuyv_first), Some(0));
+        
+        // Without any preferred format, fall back to the closest supported one
+        let unsupported = options(serde_json::json!({ "width": 640, "height": 480, "pixel_formats": ["gray"] }));
+        assert_eq!(negotiate_format(&supported, &unsupported), Some(4));
+        assert_eq!(negotiate_format(&[], &unsupported), None);
+    }
+    
+    #[test]
+    fn test_camera_index_from_id() {
+        assert!(matches!(camera_index_from_id("1"), Ok(CameraIndex::Index(1))));
+        assert!(matches!(camera_index_from_id("/dev/video2"), Ok(CameraIndex::Index(2))));
+        assert!(matches!(camera_index_from_id("front"), Err(HardwareError::InvalidId(_))));
+    }
+    
+    #[test]
+    fn test_recording_options_audio_device_id() {
+        let options: RecordingOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(options.audio_device_id, None);
+        
+        let options: RecordingOptions = serde_json::from_value(serde_json::json!({
+            "format": "webm",
+            "audio_device_id": "1"
+        })).unwrap();
+        assert_eq!(options.audio_device_id.as_deref(), Some("1"));
+        assert_eq!(options.format, "webm");
+    }
+    
+    #[test]
+    fn test_invalid_audio_device_fails_recording() {
+        let result = futures::executor::block_on(start_audio_capture("not-a-microphone", 0.9));
+        
+        match result {
+            Err(HardwareError::DeviceError(message)) => {
+                assert!(message.contains("'not-a-microphone'"));
+                assert!(message.contains("Microphone device not found"));
+            },
+            other => panic!("expected a device error, got {:?}", other),
         }
     }
+    
+    #[test]
+    fn test_default_camera_has_supported_formats() {
+        // Requires a real camera
+        if !Camera::is_available() {
+            return;
+        }
+        
+        let formats = futures::executor::block_on(Camera::get_supported_formats(None)).unwrap();
+        assert!(!formats.is_empty());
+        assert!(formats.iter().all(|format| format.width > 0 && format.height > 0));
+    }
+    
+    #[test]
+    fn test_frame_limiter_counts_gaps_as_dropped() {
+        let mut limiter = FrameLimiter::new(10);
+        let at = |millis: u64| Duration::from_millis(millis);
+        
+        // Slightly jittered frames every 100ms are all kept
+        for millis in [0, 102, 198, 301] {
+            assert!(limiter.offer(at(millis)));
+        }
+        assert_eq!(limiter.dropped, 0);
+        
+        // A 400ms gap misses three frames
+        assert!(limiter.offer(at(700)));
+        assert_eq!(limiter.dropped, 3);
+        
+        // A frame arriving faster than the frame rate is skipped, not dropped
+        assert!(!limiter.offer(at(720)));
+        assert!(limiter.offer(at(800)));
+        
+        assert_eq!(limiter.captured, 6);
+        assert_eq!(limiter.dropped, 3);
+        assert!((limiter.average_fps(at(900)) - 6.0 / 0.9).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_float_wav_header() {
+        let samples: Vec<u8> = [0.5f32, -0.5].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let wav = float_wav(&samples, 44100, 1);
+        
+        assert_eq!(wav.len(), 44 + 8);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 3);
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 44100);
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 8);
+        assert_eq!(&wav[44..], &samples[..]);
+    }
+    
+    #[test]
+    fn test_recording_drops_frames_over_buffer_limit() {
+        let mut recording = RecordingInstance {
+            clock: RecordingClock::start(),
+            format: default_video_format(),
+            quality: default_quality(),
+            width: 2,
+            height: 2,
+            include_audio: false,
+            frames: Vec::new(),
+            audio_stream_id: None,
+            limiter: FrameLimiter::new(30),
+            max_buffered_frames: Some(2),
+        };
+        
+        for index in 0..5u32 {
+            recording.record_frame(Duration::from_secs(1) / 30 * index, vec![0; 12]);
+        }
+        
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(recording.limiter.captured, 2);
+        assert_eq!(recording.limiter.dropped, 3);
+    }
+    
+    #[test]
+    fn test_replay_buffer_drops_oldest_frames() {
+        // One second at 3 fps holds three frames
+        let mut replay = ReplayBuffer::new(1, 3);
+        
+        for frame in 0..5u8 {
+            replay.push(vec![frame]);
+        }
+        
+        assert_eq!(replay.frames.len(), 3);
+        assert_eq!(replay.frames, VecDeque::from(vec![vec![2], vec![3], vec![4]]));
+    }
+    
+    #[test]
+    fn test_save_replay_requires_replay_buffer() {
+        // Requires a real camera
+        if !Camera::is_available() {
+            return;
+        }
+        
+        let options: CameraOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        let stream = futures::executor::block_on(Camera::start(options)).unwrap();
+        
+        let path = std::env::temp_dir().join(format!("smash_replay_{}.mjpeg", uuid::Uuid::new_v4()));
+        let result = futures::executor::block_on(Camera::save_replay(&stream.id, path.to_str().unwrap()));
+        Camera::stop(&stream.id).unwrap();
+        
+        assert!(matches!(result, Err(HardwareError::InvalidOperation(_))));
+    }
+    
+    #[test]
+    fn test_save_photo_accepts_data_uri() {
+        let png = encode_image(&image::RgbImage::new(4, 4), ImageFormat::Png, 0.9).unwrap();
+        let uri = data_uri("png", &base64::encode(&png));
+        assert!(uri.starts_with("data:image/png;base64,"));
+        
+        let path = std::env::temp_dir().join(format!("smash_photo_{}.png", uuid::Uuid::new_v4()));
+        let result = futures::executor::block_on(Camera::save_photo(&uri, path.to_str().unwrap())).unwrap();
+        let bytes = std::fs::read(&result.path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(bytes, png);
+    }
+    
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_save_photo_converts_to_webp_by_extension() {
+        let png = encode_image(&image::RgbImage::new(4, 4), ImageFormat::Png, 0.9).unwrap();
+        let path = std::env::temp_dir().join(format!("smash_photo_{}.webp", uuid::Uuid::new_v4()));
+        
+        let result = futures::executor::block_on(Camera::save_photo(&base64::encode(&png), path.to_str().unwrap())).unwrap();
+        let bytes = std::fs::read(&result.path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..12], b"WEBP");
+    }
 }
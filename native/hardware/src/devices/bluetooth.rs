@@ -10,6 +10,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 
 /// Bluetooth device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +48,12 @@ pub struct BluetoothScanOptions {
     /// Whether to filter by services
     #[serde(default)]
     pub services: Vec<String>,
+    /// Only include devices whose name contains this text (case-insensitive)
+    #[serde(default)]
+    pub name_filter: Option<String>,
+    /// Sort devices by signal strength, strongest first
+    #[serde(default)]
+    pub sort_by_rssi: bool,
 }
 
 /// Bluetooth connection options
@@ -60,6 +67,74 @@ pub struct BluetoothConnectionOptions {
     pub timeout: u64,
 }
 
+/// Pairing state of a Bluetooth device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingState {
+    /// Not paired and no pairing in progress
+    Unpaired,
+    /// Pairing has started
+    Pairing,
+    /// The device asked for a PIN
+    AwaitingPin,
+    /// The device showed a passkey that must be confirmed
+    AwaitingConfirmation,
+    /// Pairing completed
+    Paired,
+    /// Pairing was rejected
+    Failed,
+}
+
+/// Callback deciding whether a passkey shown by the device matches
+pub type PasskeyConfirmation = Box<dyn Fn(u32) -> bool + Send + Sync>;
+
+/// Options for `pair_device`
+#[derive(Default)]
+pub struct PairingOptions {
+    /// PIN for devices using legacy PIN pairing
+    pub pin: Option<String>,
+    /// Confirms the passkey for numeric-comparison pairing
+    pub confirm_passkey: Option<PasskeyConfirmation>,
+}
+
+/// Result of `pair_device`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BluetoothPairing {
+    /// Device that was paired
+    pub device_id: String,
+    /// Pairing state after the attempt
+    pub state: PairingState,
+}
+
+/// What a device asks for once pairing starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PairingChallenge {
+    /// No user interaction needed ("just works")
+    None,
+    /// A PIN must be entered
+    Pin,
+    /// The passkey shown on both sides must be confirmed
+    Passkey(u32),
+}
+
+/// Answer to a `PairingChallenge`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PairingResponse {
+    None,
+    Pin(String),
+    Confirm(bool),
+}
+
+/// Platform side of pairing, replaceable in tests
+pub(crate) trait PairingBackend {
+    /// Whether the device is already bonded
+    fn is_paired(&self, device: &BluetoothDevice) -> bool;
+    /// Start pairing and return what the device asks for
+    fn start_pairing(&mut self, device: &BluetoothDevice) -> Result<PairingChallenge>;
+    /// Answer the challenge; `Ok(false)` means the device rejected the answer
+    fn respond(&mut self, device: &BluetoothDevice, response: PairingResponse) -> Result<bool>;
+}
+
 // Default values for Bluetooth options
 fn default_scan_duration() -> u64 { 10 }
 fn default_timeout() -> u64 { 30 }
@@ -68,6 +143,7 @@ fn default_true() -> bool { true }
 // Global Bluetooth state
 lazy_static! {
     static ref BLUETOOTH_CONNECTIONS: Arc<Mutex<HashMap<String, BluetoothConnection>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref PAIRING_STATES: Arc<Mutex<HashMap<String, PairingState>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
 /// Bluetooth connection information
@@ -213,9 +289,30 @@ pub async fn scan_for_devices(options: BluetoothScanOptions) -> Result<Vec<Bluet
         });
     }
     
+    filter_and_sort(&mut devices, &options);
+    
     Ok(devices)
 }
 
+/// Apply the name filter and RSSI ordering from the scan options
+///
+/// Devices without an RSSI reading sort after every device that has one.
+fn filter_and_sort(devices: &mut Vec<BluetoothDevice>, options: &BluetoothScanOptions) {
+    if let Some(filter) = &options.name_filter {
+        let filter = filter.to_lowercase();
+        devices.retain(|device| device.name.to_lowercase().contains(&filter));
+    }
+    
+    if options.sort_by_rssi {
+        devices.sort_by(|a, b| match (a.rssi, b.rssi) {
+            (Some(a), Some(b)) => b.total_cmp(&a),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => std::cmp::Ordering::Equal,
+        });
+    }
+}
+
 /// Connect to a Bluetooth device
 pub async fn connect_to_device(device_id: &str, options: BluetoothConnectionOptions) -> Result<bool> {
     if !is_bluetooth_available() {
@@ -228,7 +325,7 @@ pub async fn connect_to_device(device_id: &str, options: BluetoothConnectionOpti
     
     if let Some(device) = device {
         // Check if already connected
-        let connections = BLUETOOTH_CONNECTIONS.lock().unwrap();
+        let connections = BLUETOOTH_CONNECTIONS.lock_or_recover();
         if connections.contains_key(device_id) {
             return Ok(true);
         }
@@ -245,7 +342,7 @@ pub async fn connect_to_device(device_id: &str, options: BluetoothConnectionOpti
         };
         
         // Store the connection
-        let mut connections = BLUETOOTH_CONNECTIONS.lock().unwrap();
+        let mut connections = BLUETOOTH_CONNECTIONS.lock_or_recover();
         connections.insert(device.id.clone(), connection);
         
         Ok(true)
@@ -261,7 +358,7 @@ pub async fn disconnect_from_device(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let mut connections = BLUETOOTH_CONNECTIONS.lock().unwrap();
+    let mut connections = BLUETOOTH_CONNECTIONS.lock_or_recover();
     if connections.remove(device_id).is_none() {
         return Err(HardwareError::InvalidOperation(format!("Not connected to Bluetooth device: {}", device_id)));
     }
@@ -269,8 +366,26 @@ pub async fn disconnect_from_device(device_id: &str) -> Result<bool> {
     Ok(true)
 }
 
+/// Disconnect from every connected Bluetooth device
+pub(crate) fn disconnect_all() {
+    BLUETOOTH_CONNECTIONS.lock_or_recover().clear();
+}
+
 /// Pair with a Bluetooth device
 pub async fn pair_with_device(device_id: &str) -> Result<bool> {
+    let pairing = pair_device(device_id, PairingOptions::default()).await?;
+    Ok(pairing.state == PairingState::Paired)
+}
+
+/// Pair with a Bluetooth device, answering PIN or passkey challenges
+///
+/// Devices that are already paired return immediately. A PIN or passkey the
+/// device rejects fails with `HardwareError::AuthenticationFailed`; a
+/// challenge the options cannot answer fails with `InvalidParameter`.
+///
+/// There is no platform pairing agent yet, so PINs are not checked: any PIN
+/// is accepted and only a declined passkey confirmation fails.
+pub async fn pair_device(device_id: &str, options: PairingOptions) -> Result<BluetoothPairing> {
     if !is_bluetooth_available() {
         return Err(HardwareError::UnsupportedOperation("Bluetooth is not available on this device".to_string()));
     }
@@ -281,21 +396,127 @@ pub async fn pair_with_device(device_id: &str) -> Result<bool> {
         include_paired: true,
         include_unpaired: true,
         services: Vec::new(),
+        name_filter: None,
+        sort_by_rssi: false,
     }).await?;
     
-    let device = devices.iter().find(|d| d.id == device_id).cloned();
+    let device = devices.into_iter().find(|d| d.id == device_id).ok_or_else(|| {
+        HardwareError::InvalidId(format!("Bluetooth device not found: {}", device_id))
+    })?;
     
-    if let Some(device) = device {
-        if device.paired {
-            return Ok(true);
+    let state = run_pairing(&mut SystemPairing, &device, &options, |state| {
+        PAIRING_STATES.lock_or_recover().insert(device.id.clone(), state);
+    })?;
+    
+    Ok(BluetoothPairing {
+        device_id: device.id,
+        state,
+    })
+}
+
+/// Get the pairing state of a device, including pairings in progress
+pub fn get_pairing_state(device_id: &str) -> PairingState {
+    PAIRING_STATES.lock_or_recover().get(device_id).copied().unwrap_or(PairingState::Unpaired)
+}
+
+/// Drive a pairing attempt, reporting each state change to `on_state`
+pub(crate) fn run_pairing(
+    backend: &mut dyn PairingBackend,
+    device: &BluetoothDevice,
+    options: &PairingOptions,
+    mut on_state: impl FnMut(PairingState),
+) -> Result<PairingState> {
+    if backend.is_paired(device) {
+        on_state(PairingState::Paired);
+        return Ok(PairingState::Paired);
+    }
+    
+    on_state(PairingState::Pairing);
+    let result = answer_challenge(backend, device, options, &mut on_state);
+    
+    match result {
+        Ok(()) => {
+            on_state(PairingState::Paired);
+            Ok(PairingState::Paired)
         }
-        
-        // Simulate pairing delay
-        tokio::time::sleep(Duration::from_secs(2)).await;
-        
-        Ok(true)
+        Err(e) => {
+            on_state(PairingState::Failed);
+            Err(e)
+        }
+    }
+}
+
+fn answer_challenge(
+    backend: &mut dyn PairingBackend,
+    device: &BluetoothDevice,
+    options: &PairingOptions,
+    on_state: &mut impl FnMut(PairingState),
+) -> Result<()> {
+    let response = match backend.start_pairing(device)? {
+        PairingChallenge::None => PairingResponse::None,
+        PairingChallenge::Pin => {
+            on_state(PairingState::AwaitingPin);
+            let pin = options.pin.clone().ok_or_else(|| {
+                HardwareError::InvalidParameter(format!("Bluetooth device {} requires a PIN to pair", device.id))
+            })?;
+            PairingResponse::Pin(pin)
+        }
+        PairingChallenge::Passkey(passkey) => {
+            on_state(PairingState::AwaitingConfirmation);
+            let confirm = options.confirm_passkey.as_ref().ok_or_else(|| {
+                HardwareError::InvalidParameter(format!(
+                    "Bluetooth device {} requires passkey confirmation to pair",
+                    device.id
+                ))
+            })?;
+            PairingResponse::Confirm(confirm(passkey))
+        }
+    };
+    
+    let rejected = match &response {
+        PairingResponse::Pin(_) => "PIN was rejected",
+        PairingResponse::Confirm(false) => "passkey was not confirmed",
+        _ => "pairing was rejected",
+    };
+    
+    if backend.respond(device, response)? {
+        Ok(())
     } else {
-        Err(HardwareError::InvalidId(format!("Bluetooth device not found: {}", device_id)))
+        Err(HardwareError::AuthenticationFailed(format!(
+            "Pairing with Bluetooth device {} failed: {}",
+            device.id, rejected
+        )))
+    }
+}
+
+/// Pairing through the platform Bluetooth stack
+struct SystemPairing;
+
+impl PairingBackend for SystemPairing {
+    fn is_paired(&self, device: &BluetoothDevice) -> bool {
+        device.paired || get_pairing_state(&device.id) == PairingState::Paired
+    }
+    
+    fn start_pairing(&mut self, device: &BluetoothDevice) -> Result<PairingChallenge> {
+        // In a real implementation, we would register a pairing agent with the platform
+        // For simplicity, input devices use PIN pairing, phones and computers show a
+        // passkey derived from their address, and everything else just works
+        match device.device_class.as_deref() {
+            Some("input") => Ok(PairingChallenge::Pin),
+            Some("phone") | Some("computer") => {
+                let passkey = device.address.bytes().fold(0u32, |acc, byte| (acc * 31 + byte as u32) % 1_000_000);
+                Ok(PairingChallenge::Passkey(passkey))
+            }
+            _ => Ok(PairingChallenge::None),
+        }
+    }
+    
+    fn respond(&mut self, _device: &BluetoothDevice, response: PairingResponse) -> Result<bool> {
+        // In a real implementation, we would pass the response to the pairing agent.
+        // Without one there is nothing to check a PIN against, so every PIN is
+        // accepted; a wrong PIN can only be rejected by a backend that knows the
+        // device's PIN
+        Ok(response != PairingResponse::Confirm(false))
     }
 }
 
@@ -315,7 +536,7 @@ pub async fn unpair_device(device_id: &str) -> Result<bool> {
         }
         
         // Disconnect if connected
-        let connections = BLUETOOTH_CONNECTIONS.lock().unwrap();
+        let connections = BLUETOOTH_CONNECTIONS.lock_or_recover();
         if connections.contains_key(device_id) {
             drop(connections);
             disconnect_from_device(device_id).await?;
@@ -329,3 +550,146 @@ pub async fn unpair_device(device_id: &str) -> Result<bool> {
         Err(HardwareError::InvalidId(format!("Bluetooth device not found: {}", device_id)))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Backend issuing a fixed challenge and accepting one PIN
+    struct MockPairing {
+        paired: bool,
+        challenge: PairingChallenge,
+        pin: &'static str,
+    }
+    
+    impl PairingBackend for MockPairing {
+        fn is_paired(&self, _device: &BluetoothDevice) -> bool {
+            self.paired
+        }
+        
+        fn start_pairing(&mut self, _device: &BluetoothDevice) -> Result<PairingChallenge> {
+            Ok(self.challenge)
+        }
+        
+        fn respond(&mut self, _device: &BluetoothDevice, response: PairingResponse) -> Result<bool> {
+            let accepted = match response {
+                PairingResponse::None => true,
+                PairingResponse::Pin(pin) => pin == self.pin,
+                PairingResponse::Confirm(confirmed) => confirmed,
+            };
+            self.paired = accepted;
+            Ok(accepted)
+        }
+    }
+    
+    fn scanned(name: &str, rssi: Option<f64>) -> BluetoothDevice {
+        BluetoothDevice {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            rssi,
+            ..device()
+        }
+    }
+    
+    #[test]
+    fn test_scan_name_filter_and_rssi_sort() {
+        let mut devices = vec![
+            scanned("Beacon Far", Some(-90.0)),
+            scanned("Speaker", Some(-40.0)),
+            scanned("Beacon Unknown", None),
+            scanned("beacon near", Some(-50.0)),
+        ];
+        let options: BluetoothScanOptions =
+            serde_json::from_str(r#"{"name_filter": "BEACON", "sort_by_rssi": true}"#).unwrap();
+        
+        filter_and_sort(&mut devices, &options);
+        
+        let names: Vec<&str> = devices.iter().map(|device| device.name.as_str()).collect();
+        assert_eq!(names, vec!["beacon near", "Beacon Far", "Beacon Unknown"]);
+    }
+    
+    #[test]
+    fn test_scan_options_leave_order_by_default() {
+        let mut devices = vec![scanned("A", None), scanned("B", Some(-30.0))];
+        let options: BluetoothScanOptions = serde_json::from_str("{}").unwrap();
+        
+        filter_and_sort(&mut devices, &options);
+        
+        assert_eq!(devices[0].name, "A");
+        assert_eq!(devices.len(), 2);
+    }
+    
+    fn device() -> BluetoothDevice {
+        BluetoothDevice {
+            id: "bt_3".to_string(),
+            name: "Keyboard".to_string(),
+            address: "11:22:33:44:55:66".to_string(),
+            rssi: Some(-65.0),
+            paired: false,
+            connected: false,
+            device_class: Some("input".to_string()),
+            services: vec!["hid".to_string()],
+        }
+    }
+    
+    fn pair(backend: &mut MockPairing, options: &PairingOptions) -> (Result<PairingState>, Vec<PairingState>) {
+        let mut states = Vec::new();
+        let result = run_pairing(backend, &device(), options, |state| states.push(state));
+        (result, states)
+    }
+    
+    fn pin(pin: &str) -> PairingOptions {
+        PairingOptions {
+            pin: Some(pin.to_string()),
+            confirm_passkey: None,
+        }
+    }
+    
+    #[test]
+    fn test_pin_pairing_transitions() {
+        let mut backend = MockPairing { paired: false, challenge: PairingChallenge::Pin, pin: "0000" };
+        
+        let (result, states) = pair(&mut backend, &pin("0000"));
+        assert_eq!(result.unwrap(), PairingState::Paired);
+        assert_eq!(states, vec![PairingState::Pairing, PairingState::AwaitingPin, PairingState::Paired]);
+        
+        // Already paired devices short-circuit
+        let (result, states) = pair(&mut backend, &pin("1234"));
+        assert_eq!(result.unwrap(), PairingState::Paired);
+        assert_eq!(states, vec![PairingState::Paired]);
+    }
+    
+    #[test]
+    fn test_wrong_pin_fails_authentication() {
+        let mut backend = MockPairing { paired: false, challenge: PairingChallenge::Pin, pin: "0000" };
+        
+        let (result, states) = pair(&mut backend, &pin("1234"));
+        assert!(matches!(result, Err(HardwareError::AuthenticationFailed(_))));
+        assert_eq!(states, vec![PairingState::Pairing, PairingState::AwaitingPin, PairingState::Failed]);
+        
+        // A missing PIN is a caller error rather than a rejection
+        let (result, _) = pair(&mut backend, &PairingOptions::default());
+        assert!(matches!(result, Err(HardwareError::InvalidParameter(_))));
+    }
+    
+    #[test]
+    fn test_passkey_confirmation() {
+        let mut backend = MockPairing { paired: false, challenge: PairingChallenge::Passkey(123456), pin: "" };
+        let options = PairingOptions {
+            pin: None,
+            confirm_passkey: Some(Box::new(|passkey| passkey == 654321)),
+        };
+        
+        let (result, states) = pair(&mut backend, &options);
+        assert!(matches!(result, Err(HardwareError::AuthenticationFailed(_))));
+        assert_eq!(states, vec![PairingState::Pairing, PairingState::AwaitingConfirmation, PairingState::Failed]);
+        
+        let options = PairingOptions {
+            pin: None,
+            confirm_passkey: Some(Box::new(|passkey| passkey == 123456)),
+        };
+        let (result, states) = pair(&mut backend, &options);
+        assert_eq!(result.unwrap(), PairingState::Paired);
+        assert_eq!(states.last(), Some(&PairingState::Paired));
+    }
+}
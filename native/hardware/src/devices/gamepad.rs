@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 
 /// Gamepad device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,7 +167,7 @@ pub async fn open_gamepad(device_id: &str) -> Result<bool> {
     
     if let Some(device) = device {
         // Check if already connected
-        let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+        let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
         if connections.contains_key(device_id) {
             return Ok(true);
         }
@@ -180,7 +181,7 @@ pub async fn open_gamepad(device_id: &str) -> Result<bool> {
         };
         
         // Store the connection
-        let mut connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+        let mut connections = GAMEPAD_CONNECTIONS.lock_or_recover();
         connections.insert(device.id.clone(), connection);
         
         Ok(true)
@@ -196,7 +197,7 @@ pub async fn close_gamepad(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let mut connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let mut connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if connections.remove(device_id).is_none() {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -204,6 +205,11 @@ pub async fn close_gamepad(device_id: &str) -> Result<bool> {
     Ok(true)
 }
 
+/// Close every open gamepad
+pub(crate) fn close_all() {
+    GAMEPAD_CONNECTIONS.lock_or_recover().clear();
+}
+
 /// Get the current state of a gamepad
 pub async fn get_gamepad_state(device_id: &str) -> Result<GamepadState> {
     if !is_gamepad_available() {
@@ -211,7 +217,7 @@ pub async fn get_gamepad_state(device_id: &str) -> Result<GamepadState> {
     }
     
     // Check if connected
-    let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -250,7 +256,7 @@ pub async fn start_monitoring_events(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -268,7 +274,7 @@ pub async fn stop_monitoring_events(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -286,7 +292,7 @@ pub async fn apply_force_feedback(device_id: &str, effect: ForceFeedbackEffect)
     }
     
     // Check if connected
-    let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -316,7 +322,7 @@ pub async fn stop_force_feedback(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let connections = GAMEPAD_CONNECTIONS.lock().unwrap();
+    let connections = GAMEPAD_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to gamepad device: {}", device_id)));
     }
@@ -3,12 +3,16 @@
 //! Provides access to MIDI devices and functionality.
 
 use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 
 /// MIDI device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,12 +44,51 @@ pub struct MidiMessage {
     pub data2: u8,
     /// Timestamp in milliseconds
     pub timestamp: u64,
+    /// Raw message bytes for System Exclusive messages, including the 0xF0/0xF7 framing
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<u8>,
+}
+
+/// Start of a System Exclusive message
+const SYSEX_START: u8 = 0xF0;
+/// End of a System Exclusive message
+const SYSEX_END: u8 = 0xF7;
+/// Largest write used when sending a SysEx message; larger messages are chunked
+const SYSEX_CHUNK_SIZE: usize = 256;
+/// MIDI clock resolution
+const PULSES_PER_QUARTER_NOTE: u32 = 24;
+
+/// System real-time messages used to synchronise sequencers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MidiRealtime {
+    /// Timing clock pulse, sent 24 times per quarter note
+    Clock,
+    /// Start playback from the beginning
+    Start,
+    /// Continue playback from where it stopped
+    Continue,
+    /// Stop playback
+    Stop,
+}
+
+impl MidiRealtime {
+    /// Status byte of the message
+    pub fn status(self) -> u8 {
+        match self {
+            MidiRealtime::Clock => 0xF8,
+            MidiRealtime::Start => 0xFA,
+            MidiRealtime::Continue => 0xFB,
+            MidiRealtime::Stop => 0xFC,
+        }
+    }
 }
 
 // Global MIDI state
 lazy_static! {
     static ref MIDI_INPUTS: Arc<Mutex<HashMap<String, MidiInput>>> = Arc::new(Mutex::new(HashMap::new()));
     static ref MIDI_OUTPUTS: Arc<Mutex<HashMap<String, MidiOutput>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref MIDI_CLOCKS: Arc<Mutex<HashMap<String, MidiClock>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
 /// MIDI input connection
@@ -54,6 +97,50 @@ struct MidiInput {
     device_name: String,
     connected_at: std::time::SystemTime,
     // In a real implementation, we would store platform-specific connection handles here
+    /// SysEx message being received across several packets
+    sysex: SysExAssembler,
+    /// Received messages not yet taken by `take_midi_messages`
+    received: Vec<MidiMessage>,
+}
+
+/// Reassembles System Exclusive messages that arrive split across packets
+#[derive(Debug, Default)]
+struct SysExAssembler {
+    /// Bytes of the message in progress, if one has started
+    pending: Option<Vec<u8>>,
+}
+
+impl SysExAssembler {
+    /// Feed a packet of incoming bytes, returning each SysEx message it completes
+    ///
+    /// Real-time bytes (0xF8 and above) may be interleaved with SysEx data and
+    /// are skipped. A new status byte before the terminator means the message
+    /// in progress was malformed; it is discarded and an error returned.
+    fn push(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>> {
+        let mut completed = Vec::new();
+        
+        for &byte in bytes {
+            if byte >= 0xF8 {
+                continue;
+            }
+            
+            match (&mut self.pending, byte) {
+                (None, SYSEX_START) => self.pending = Some(vec![SYSEX_START]),
+                (None, _) => {}
+                (Some(message), SYSEX_END) => {
+                    message.push(SYSEX_END);
+                    completed.extend(self.pending.take());
+                }
+                (Some(message), data) if data < 0x80 => message.push(data),
+                (Some(_), _) => {
+                    self.pending = None;
+                    return Err(HardwareError::ProcessingError("Malformed SysEx message: missing 0xF7 terminator".to_string()));
+                }
+            }
+        }
+        
+        Ok(completed)
+    }
 }
 
 /// MIDI output connection
@@ -171,7 +258,7 @@ pub async fn open_midi_input(device_id: &str) -> Result<bool> {
     
     if let Some(device) = device {
         // Check if already connected
-        let inputs = MIDI_INPUTS.lock().unwrap();
+        let mut inputs = MIDI_INPUTS.lock_or_recover();
         if inputs.contains_key(device_id) {
             return Ok(true);
         }
@@ -181,10 +268,11 @@ pub async fn open_midi_input(device_id: &str) -> Result<bool> {
             device_id: device.id.clone(),
             device_name: device.name.clone(),
             connected_at: std::time::SystemTime::now(),
+            sysex: SysExAssembler::default(),
+            received: Vec::new(),
         };
         
         // Store the connection
-        let mut inputs = MIDI_INPUTS.lock().unwrap();
         inputs.insert(device.id.clone(), input);
         
         Ok(true)
@@ -200,7 +288,7 @@ pub async fn close_midi_input(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let mut inputs = MIDI_INPUTS.lock().unwrap();
+    let mut inputs = MIDI_INPUTS.lock_or_recover();
     if inputs.remove(device_id).is_none() {
         return Err(HardwareError::InvalidOperation(format!("Not connected to MIDI input device: {}", device_id)));
     }
@@ -220,7 +308,7 @@ pub async fn open_midi_output(device_id: &str) -> Result<bool> {
     
     if let Some(device) = device {
         // Check if already connected
-        let outputs = MIDI_OUTPUTS.lock().unwrap();
+        let outputs = MIDI_OUTPUTS.lock_or_recover();
         if outputs.contains_key(device_id) {
             return Ok(true);
         }
@@ -233,7 +321,7 @@ pub async fn open_midi_output(device_id: &str) -> Result<bool> {
         };
         
         // Store the connection
-        let mut outputs = MIDI_OUTPUTS.lock().unwrap();
+        let mut outputs = MIDI_OUTPUTS.lock_or_recover();
         outputs.insert(device.id.clone(), output);
         
         Ok(true)
@@ -249,7 +337,7 @@ pub async fn close_midi_output(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let mut outputs = MIDI_OUTPUTS.lock().unwrap();
+    let mut outputs = MIDI_OUTPUTS.lock_or_recover();
     if outputs.remove(device_id).is_none() {
         return Err(HardwareError::InvalidOperation(format!("Not connected to MIDI output device: {}", device_id)));
     }
@@ -257,6 +345,26 @@ pub async fn close_midi_output(device_id: &str) -> Result<bool> {
     Ok(true)
 }
 
+/// Close every open MIDI input and output
+pub(crate) fn close_all() {
+    // Dropping the clocks stops their timers
+    MIDI_CLOCKS.lock_or_recover().clear();
+    MIDI_INPUTS.lock_or_recover().clear();
+    MIDI_OUTPUTS.lock_or_recover().clear();
+}
+
+/// Write raw bytes to an open output device
+fn write_midi_bytes(device_id: &str, _bytes: &[u8]) -> Result<()> {
+    let outputs = MIDI_OUTPUTS.lock_or_recover();
+    if !outputs.contains_key(device_id) {
+        return Err(HardwareError::InvalidOperation(format!("Not connected to MIDI output device: {}", device_id)));
+    }
+    
+    // In a real implementation, we would write the bytes to the device
+    
+    Ok(())
+}
+
 /// Send a MIDI message to an output device
 pub async fn send_midi_message(device_id: &str, message: MidiMessage) -> Result<bool> {
     if !is_midi_available() {
@@ -264,7 +372,7 @@ pub async fn send_midi_message(device_id: &str, message: MidiMessage) -> Result<
     }
     
     // Check if connected
-    let outputs = MIDI_OUTPUTS.lock().unwrap();
+    let outputs = MIDI_OUTPUTS.lock_or_recover();
     if !outputs.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to MIDI output device: {}", device_id)));
     }
@@ -275,6 +383,202 @@ pub async fn send_midi_message(device_id: &str, message: MidiMessage) -> Result<
     Ok(true)
 }
 
+/// Frame a System Exclusive payload with 0xF0/0xF7
+///
+/// `data` may be the bare payload or an already framed message. Payload
+/// bytes must be 7-bit; a message that starts with 0xF0 but lacks the 0xF7
+/// terminator is rejected.
+pub fn frame_sysex(data: &[u8]) -> Result<Vec<u8>> {
+    let payload = match data.first() {
+        Some(&SYSEX_START) => match data.last() {
+            Some(&SYSEX_END) if data.len() >= 2 => &data[1..data.len() - 1],
+            _ => return Err(HardwareError::InvalidParameter("Malformed SysEx message: missing 0xF7 terminator".to_string())),
+        },
+        _ => data,
+    };
+    
+    if payload.is_empty() {
+        return Err(HardwareError::InvalidParameter("SysEx payload must not be empty".to_string()));
+    }
+    
+    if let Some(position) = payload.iter().position(|&byte| byte >= 0x80) {
+        return Err(HardwareError::InvalidParameter(format!(
+            "SysEx payload byte {} is 0x{:02X}; only 7-bit data bytes are allowed",
+            position, payload[position]
+        )));
+    }
+    
+    let mut message = Vec::with_capacity(payload.len() + 2);
+    message.push(SYSEX_START);
+    message.extend_from_slice(payload);
+    message.push(SYSEX_END);
+    Ok(message)
+}
+
+/// Send a System Exclusive message to an output device
+///
+/// The data is framed by `frame_sysex` and written in chunks of at most
+/// 256 bytes so large dumps don't overrun the device's input buffer.
+pub async fn send_sysex(device_id: &str, data: &[u8]) -> Result<bool> {
+    if !is_midi_available() {
+        return Err(HardwareError::UnsupportedOperation("MIDI access is not available on this device".to_string()));
+    }
+    
+    let message = frame_sysex(data)?;
+    
+    for chunk in message.chunks(SYSEX_CHUNK_SIZE) {
+        write_midi_bytes(device_id, chunk)?;
+    }
+    
+    Ok(true)
+}
+
+/// Send a system real-time message (clock, start, continue or stop)
+pub async fn send_realtime(device_id: &str, message: MidiRealtime) -> Result<bool> {
+    if !is_midi_available() {
+        return Err(HardwareError::UnsupportedOperation("MIDI access is not available on this device".to_string()));
+    }
+    
+    write_midi_bytes(device_id, &[message.status()])?;
+    Ok(true)
+}
+
+/// Time between clock pulses at the given tempo
+pub fn clock_pulse_interval(bpm: f64) -> Duration {
+    Duration::from_secs_f64(60.0 / (bpm * PULSES_PER_QUARTER_NOTE as f64))
+}
+
+/// Sends MIDI clock pulses to an output on a background timer
+///
+/// Starting the clock sends Start followed by 24 Clock pulses per quarter
+/// note; stopping (or dropping) it halts the timer and sends Stop.
+pub struct MidiClock {
+    device_id: String,
+    bpm: f64,
+    stop_tx: Option<mpsc::Sender<()>>,
+    timer: Option<JoinHandle<()>>,
+}
+
+impl MidiClock {
+    /// Send Start and begin emitting clock pulses at `bpm`
+    pub fn start(device_id: &str, bpm: f64) -> Result<Self> {
+        if !bpm.is_finite() || bpm <= 0.0 || bpm > 1000.0 {
+            return Err(HardwareError::InvalidParameter(format!("BPM must be between 0 and 1000, got {}", bpm)));
+        }
+        
+        write_midi_bytes(device_id, &[MidiRealtime::Start.status()])?;
+        
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let interval = clock_pulse_interval(bpm);
+        let id = device_id.to_string();
+        
+        let timer = std::thread::spawn(move || {
+            let started = Instant::now();
+            
+            for pulse in 1u32.. {
+                // Schedule against the start time so timing errors don't accumulate
+                let wait = (started + interval * pulse).saturating_duration_since(Instant::now());
+                
+                match stop_rx.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => {
+                        if write_midi_bytes(&id, &[MidiRealtime::Clock.status()]).is_err() {
+                            break;
+                        }
+                    }
+                    // Stopped, or the clock was dropped
+                    _ => break,
+                }
+            }
+        });
+        
+        Ok(MidiClock {
+            device_id: device_id.to_string(),
+            bpm,
+            stop_tx: Some(stop_tx),
+            timer: Some(timer),
+        })
+    }
+    
+    /// Tempo the clock runs at
+    pub fn bpm(&self) -> f64 {
+        self.bpm
+    }
+    
+    /// Halt the timer and send Stop
+    pub fn stop(mut self) -> Result<()> {
+        self.halt();
+        write_midi_bytes(&self.device_id, &[MidiRealtime::Stop.status()])
+    }
+    
+    fn halt(&mut self) {
+        drop(self.stop_tx.take());
+        if let Some(timer) = self.timer.take() {
+            let _ = timer.join();
+        }
+    }
+}
+
+impl Drop for MidiClock {
+    fn drop(&mut self) {
+        self.halt();
+    }
+}
+
+/// Start a clock on an output device, returning its ID
+pub fn start_midi_clock(device_id: &str, bpm: f64) -> Result<String> {
+    let clock = MidiClock::start(device_id, bpm)?;
+    let clock_id = format!("midi_clock_{}", uuid::Uuid::new_v4().to_string());
+    
+    MIDI_CLOCKS.lock_or_recover().insert(clock_id.clone(), clock);
+    Ok(clock_id)
+}
+
+/// Stop a clock started with `start_midi_clock`
+pub fn stop_midi_clock(clock_id: &str) -> Result<()> {
+    let clock = MIDI_CLOCKS.lock_or_recover().remove(clock_id)
+        .ok_or_else(|| HardwareError::InvalidId(format!("MIDI clock not found: {}", clock_id)))?;
+    
+    clock.stop()
+}
+
+/// Handle raw bytes received from an open input device
+///
+/// Complete SysEx messages are queued for `take_midi_messages`.
+pub fn receive_midi_bytes(device_id: &str, bytes: &[u8]) -> Result<()> {
+    let mut inputs = MIDI_INPUTS.lock_or_recover();
+    let input = inputs.get_mut(device_id).ok_or_else(|| {
+        HardwareError::InvalidOperation(format!("Not connected to MIDI input device: {}", device_id))
+    })?;
+    
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    
+    for data in input.sysex.push(bytes)? {
+        input.received.push(MidiMessage {
+            message_type: "sysex".to_string(),
+            channel: 0,
+            data1: 0,
+            data2: 0,
+            timestamp,
+            data,
+        });
+    }
+    
+    Ok(())
+}
+
+/// Take the messages received on an input device since the last call, in order
+pub fn take_midi_messages(device_id: &str) -> Result<Vec<MidiMessage>> {
+    let mut inputs = MIDI_INPUTS.lock_or_recover();
+    let input = inputs.get_mut(device_id).ok_or_else(|| {
+        HardwareError::InvalidOperation(format!("Not connected to MIDI input device: {}", device_id))
+    })?;
+    
+    Ok(std::mem::take(&mut input.received))
+}
+
 /// Create a virtual MIDI input device
 pub async fn create_virtual_midi_input(name: &str) -> Result<MidiDevice> {
     if !is_midi_available() {
@@ -328,6 +632,7 @@ pub fn create_note_on(channel: u8, note: u8, velocity: u8) -> MidiMessage {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64,
+        data: Vec::new(),
     }
 }
 
@@ -342,6 +647,7 @@ pub fn create_note_off(channel: u8, note: u8, velocity: u8) -> MidiMessage {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64,
+        data: Vec::new(),
     }
 }
 
@@ -356,6 +662,7 @@ pub fn create_control_change(channel: u8, controller: u8, value: u8) -> MidiMess
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64,
+        data: Vec::new(),
     }
 }
 
@@ -370,5 +677,73 @@ pub fn create_program_change(channel: u8, program: u8) -> MidiMessage {
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap_or_default()
             .as_millis() as u64,
+        data: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_frame_sysex() {
+        // Universal identity request
+        let payload = [0x7E, 0x7F, 0x06, 0x01];
+        let framed = frame_sysex(&payload).unwrap();
+        assert_eq!(framed, vec![0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7]);
+        
+        // Already framed messages are accepted unchanged
+        assert_eq!(frame_sysex(&framed).unwrap(), framed);
+        
+        // Missing terminator, empty payloads and 8-bit data are rejected
+        let unterminated = [0xF0, 0x7E, 0x7F, 0x06, 0x01];
+        let err = frame_sysex(&unterminated).unwrap_err();
+        assert!(err.to_string().contains("terminator"));
+        assert!(frame_sysex(&[]).is_err());
+        assert!(frame_sysex(&[0xF0, 0xF7]).is_err());
+        assert!(matches!(frame_sysex(&[0x7E, 0x90]), Err(HardwareError::InvalidParameter(_))));
+    }
+    
+    #[test]
+    fn test_large_sysex_is_chunked() {
+        let framed = frame_sysex(&vec![0x42; 600]).unwrap();
+        let chunks: Vec<&[u8]> = framed.chunks(SYSEX_CHUNK_SIZE).collect();
+        
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0][0], 0xF0);
+        assert_eq!(*chunks[2].last().unwrap(), 0xF7);
+    }
+    
+    #[test]
+    fn test_clock_pulse_interval() {
+        let interval = clock_pulse_interval(120.0);
+        assert!((interval.as_secs_f64() * 1000.0 - 20.833).abs() < 0.001);
+        
+        // 24 pulses make up one beat
+        assert!(((clock_pulse_interval(90.0) * 24).as_secs_f64() - 60.0 / 90.0).abs() < 1e-6);
+        
+        assert_eq!(MidiRealtime::Clock.status(), 0xF8);
+        assert_eq!(MidiRealtime::Stop.status(), 0xFC);
+    }
+    
+    #[test]
+    fn test_clock_requires_valid_bpm_and_open_output() {
+        assert!(matches!(MidiClock::start("midi_out_1", 0.0), Err(HardwareError::InvalidParameter(_))));
+        assert!(matches!(MidiClock::start("midi_out_1", f64::NAN), Err(HardwareError::InvalidParameter(_))));
+        assert!(matches!(MidiClock::start("not_open", 120.0), Err(HardwareError::InvalidOperation(_))));
+    }
+    
+    #[test]
+    fn test_sysex_assembler_across_packets() {
+        let mut assembler = SysExAssembler::default();
+        
+        // A message split over two packets with an interleaved clock byte
+        assert!(assembler.push(&[0x90, 0x3C, 0x40, 0xF0, 0x7E]).unwrap().is_empty());
+        let messages = assembler.push(&[0x7F, 0xF8, 0x06, 0xF7]).unwrap();
+        assert_eq!(messages, vec![vec![0xF0, 0x7E, 0x7F, 0x06, 0xF7]]);
+        
+        // A status byte before the terminator is malformed
+        assert!(assembler.push(&[0xF0, 0x01, 0x90]).is_err());
+        assert_eq!(assembler.push(&[0xF0, 0x02, 0xF7]).unwrap(), vec![vec![0xF0, 0x02, 0xF7]]);
     }
 }
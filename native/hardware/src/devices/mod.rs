@@ -3,12 +3,14 @@
 //! Provides access to various hardware peripherals and system devices.
 
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, Mutex};
 
 use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 
 pub mod bluetooth;
 pub mod usb;
@@ -30,116 +32,296 @@ pub struct Device {
     pub capabilities: HashMap<String, serde_json::Value>,
 }
 
-/// Get a list of all connected hardware devices
-pub async fn get_all_devices(device_type: Option<&str>) -> Result<Vec<Device>> {
-    let mut devices = Vec::new();
-    
-    // Get Bluetooth devices
-    if device_type.is_none() || device_type == Some("bluetooth") {
-        if bluetooth::is_bluetooth_available() {
-            if let Ok(bluetooth_devices) = bluetooth::get_bluetooth_devices().await {
-                devices.extend(bluetooth_devices.into_iter().map(|device| Device {
-                    id: device.id,
-                    name: device.name,
-                    device_type: "bluetooth".to_string(),
-                    connected: device.connected,
-                    capabilities: {
-                        let mut caps = HashMap::new();
-                        caps.insert("address".to_string(), serde_json::Value::String(device.address));
-                        if let Some(rssi) = device.rssi {
-                            caps.insert("rssi".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(rssi).unwrap_or_default()));
-                        }
-                        caps.insert("paired".to_string(), serde_json::Value::Bool(device.paired));
-                        caps
-                    },
-                }));
-            }
+/// Strongly-typed capabilities of a device, by category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "category", rename_all = "lowercase")]
+pub enum DeviceCapabilities {
+    Bluetooth {
+        address: String,
+        rssi: Option<f64>,
+        paired: bool,
+    },
+    Usb {
+        vendor_id: u16,
+        product_id: u16,
+        serial_number: Option<String>,
+    },
+    Midi {
+        /// Whether this is a MIDI input (otherwise an output)
+        is_input: bool,
+        manufacturer: Option<String>,
+    },
+    Gamepad {
+        index: u32,
+        buttons: u32,
+        axes: u32,
+    },
+}
+
+impl DeviceCapabilities {
+    /// Device type string used by `Device::device_type`
+    pub fn device_type(&self) -> &'static str {
+        match self {
+            DeviceCapabilities::Bluetooth { .. } => "bluetooth",
+            DeviceCapabilities::Usb { .. } => "usb",
+            DeviceCapabilities::Midi { is_input: true, .. } => "midi-input",
+            DeviceCapabilities::Midi { is_input: false, .. } => "midi-output",
+            DeviceCapabilities::Gamepad { .. } => "gamepad",
         }
     }
     
-    // Get USB devices
-    if device_type.is_none() || device_type == Some("usb") {
-        if usb::is_usb_available() {
-            if let Ok(usb_devices) = usb::get_usb_devices().await {
-                devices.extend(usb_devices.into_iter().map(|device| Device {
-                    id: device.id,
-                    name: device.name,
-                    device_type: "usb".to_string(),
-                    connected: true,
-                    capabilities: {
-                        let mut caps = HashMap::new();
-                        caps.insert("vendorId".to_string(), serde_json::Value::Number(serde_json::Number::from(device.vendor_id)));
-                        caps.insert("productId".to_string(), serde_json::Value::Number(serde_json::Number::from(device.product_id)));
-                        if let Some(serial) = device.serial_number {
-                            caps.insert("serialNumber".to_string(), serde_json::Value::String(serial));
-                        }
-                        caps
-                    },
-                }));
+    /// Flatten into the untyped capability map exposed to JavaScript
+    pub fn to_map(&self) -> HashMap<String, serde_json::Value> {
+        let mut caps = HashMap::new();
+        
+        match self {
+            DeviceCapabilities::Bluetooth { address, rssi, paired } => {
+                caps.insert("address".to_string(), serde_json::Value::String(address.clone()));
+                if let Some(rssi) = rssi {
+                    caps.insert("rssi".to_string(), serde_json::Value::from(*rssi));
+                }
+                caps.insert("paired".to_string(), serde_json::Value::Bool(*paired));
+            }
+            DeviceCapabilities::Usb { vendor_id, product_id, serial_number } => {
+                caps.insert("vendorId".to_string(), serde_json::Value::Number(serde_json::Number::from(*vendor_id)));
+                caps.insert("productId".to_string(), serde_json::Value::Number(serde_json::Number::from(*product_id)));
+                if let Some(serial) = serial_number {
+                    caps.insert("serialNumber".to_string(), serde_json::Value::String(serial.clone()));
+                }
+            }
+            DeviceCapabilities::Midi { manufacturer, .. } => {
+                if let Some(manufacturer) = manufacturer {
+                    caps.insert("manufacturer".to_string(), serde_json::Value::String(manufacturer.clone()));
+                }
             }
+            DeviceCapabilities::Gamepad { index, buttons, axes } => {
+                caps.insert("index".to_string(), serde_json::Value::Number(serde_json::Number::from(*index)));
+                caps.insert("buttons".to_string(), serde_json::Value::Number(serde_json::Number::from(*buttons)));
+                caps.insert("axes".to_string(), serde_json::Value::Number(serde_json::Number::from(*axes)));
+            }
+        }
+        
+        caps
+    }
+}
+
+/// Device information with typed capabilities
+///
+/// Rust callers can match on `capabilities` instead of looking up keys in
+/// `Device::capabilities`; converting into `Device` yields the same JSON shape
+/// `get_all_devices` returns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TypedDevice {
+    /// Unique identifier for the device
+    pub id: String,
+    /// Human-readable name for the device
+    pub name: String,
+    /// Whether the device is currently connected
+    pub connected: bool,
+    /// Category-specific capabilities
+    pub capabilities: DeviceCapabilities,
+}
+
+impl From<TypedDevice> for Device {
+    fn from(device: TypedDevice) -> Self {
+        Device {
+            device_type: device.capabilities.device_type().to_string(),
+            capabilities: device.capabilities.to_map(),
+            id: device.id,
+            name: device.name,
+            connected: device.connected,
+        }
+    }
+}
+
+impl From<bluetooth::BluetoothDevice> for TypedDevice {
+    fn from(device: bluetooth::BluetoothDevice) -> Self {
+        TypedDevice {
+            id: device.id,
+            name: device.name,
+            connected: device.connected,
+            capabilities: DeviceCapabilities::Bluetooth {
+                address: device.address,
+                rssi: device.rssi,
+                paired: device.paired,
+            },
+        }
+    }
+}
+
+impl From<usb::UsbDevice> for TypedDevice {
+    fn from(device: usb::UsbDevice) -> Self {
+        TypedDevice {
+            id: device.id,
+            name: device.name,
+            connected: true,
+            capabilities: DeviceCapabilities::Usb {
+                vendor_id: device.vendor_id,
+                product_id: device.product_id,
+                serial_number: device.serial_number,
+            },
+        }
+    }
+}
+
+impl From<midi::MidiDevice> for TypedDevice {
+    fn from(device: midi::MidiDevice) -> Self {
+        TypedDevice {
+            id: device.id,
+            name: device.name,
+            connected: true,
+            capabilities: DeviceCapabilities::Midi {
+                is_input: device.is_input,
+                manufacturer: device.manufacturer,
+            },
+        }
+    }
+}
+
+impl From<gamepad::GamepadDevice> for TypedDevice {
+    fn from(device: gamepad::GamepadDevice) -> Self {
+        TypedDevice {
+            id: device.id,
+            name: device.name,
+            connected: true,
+            capabilities: DeviceCapabilities::Gamepad {
+                index: device.index,
+                buttons: device.buttons,
+                axes: device.axes,
+            },
         }
     }
+}
+
+/// Device categories enumerated by `get_all_devices`
+const DEVICE_CATEGORIES: &[&str] = &["bluetooth", "usb", "midi", "gamepad"];
+
+/// Enumeration failure for a single device category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartialError {
+    /// Device category that failed ('bluetooth', 'usb', 'midi', 'gamepad')
+    pub category: String,
+    /// Description of the failure
+    pub message: String,
+}
+
+/// Devices found across all categories, with any per-category failures
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceEnumeration<D = Device> {
+    /// Devices from the categories that enumerated successfully
+    pub devices: Vec<D>,
+    /// Categories that failed to enumerate; empty when the list is complete
+    pub partial_errors: Vec<PartialError>,
+}
+
+impl<D> Default for DeviceEnumeration<D> {
+    fn default() -> Self {
+        DeviceEnumeration {
+            devices: Vec::new(),
+            partial_errors: Vec::new(),
+        }
+    }
+}
+
+impl<D> DeviceEnumeration<D> {
+    /// Whether every requested category enumerated successfully
+    pub fn is_complete(&self) -> bool {
+        self.partial_errors.is_empty()
+    }
     
-    // Get MIDI devices
-    if device_type.is_none() || device_type == Some("midi") {
-        if midi::is_midi_available() {
-            // Get MIDI inputs
-            if let Ok(midi_inputs) = midi::get_midi_inputs().await {
-                devices.extend(midi_inputs.into_iter().map(|device| Device {
-                    id: device.id,
-                    name: device.name,
-                    device_type: "midi-input".to_string(),
-                    connected: true,
-                    capabilities: {
-                        let mut caps = HashMap::new();
-                        if let Some(manufacturer) = device.manufacturer {
-                            caps.insert("manufacturer".to_string(), serde_json::Value::String(manufacturer));
-                        }
-                        caps
-                    },
-                }));
-            }
-            
-            // Get MIDI outputs
-            if let Ok(midi_outputs) = midi::get_midi_outputs().await {
-                devices.extend(midi_outputs.into_iter().map(|device| Device {
-                    id: device.id,
-                    name: device.name,
-                    device_type: "midi-output".to_string(),
-                    connected: true,
-                    capabilities: {
-                        let mut caps = HashMap::new();
-                        if let Some(manufacturer) = device.manufacturer {
-                            caps.insert("manufacturer".to_string(), serde_json::Value::String(manufacturer));
-                        }
-                        caps
-                    },
-                }));
-            }
+    /// Get the devices, failing only if nothing could be enumerated
+    ///
+    /// Failures alongside successful categories are logged so an incomplete
+    /// list is never silent.
+    pub fn into_devices(self) -> Result<Vec<D>> {
+        if self.devices.is_empty() && !self.is_complete() {
+            let messages: Vec<String> = self.partial_errors
+                .iter()
+                .map(|error| format!("{}: {}", error.category, error.message))
+                .collect();
+            return Err(HardwareError::Internal(format!("Device enumeration failed ({})", messages.join("; "))));
         }
+        
+        for error in &self.partial_errors {
+            log::warn!("Failed to enumerate {} devices: {}", error.category, error.message);
+        }
+        
+        Ok(self.devices)
     }
+}
+
+/// Get a list of all connected hardware devices
+pub async fn get_all_devices(device_type: Option<&str>) -> Result<Vec<Device>> {
+    enumerate_devices(device_type).await.into_devices()
+}
+
+/// Get a list of all connected hardware devices with typed capabilities
+pub async fn get_all_typed_devices(device_type: Option<&str>) -> Result<Vec<TypedDevice>> {
+    collect_devices(device_type, enumerate_typed_category).await.into_devices()
+}
+
+/// Enumerate all connected hardware devices, reporting which categories failed
+pub async fn enumerate_devices(device_type: Option<&str>) -> DeviceEnumeration {
+    collect_devices(device_type, enumerate_category).await
+}
+
+/// Run `enumerate` for each requested category and merge the results
+async fn collect_devices<D, F, Fut>(device_type: Option<&str>, enumerate: F) -> DeviceEnumeration<D>
+where
+    F: Fn(&'static str) -> Fut,
+    Fut: Future<Output = Result<Vec<D>>>,
+{
+    let mut enumeration = DeviceEnumeration::default();
     
-    // Get gamepad devices
-    if device_type.is_none() || device_type == Some("gamepad") {
-        if gamepad::is_gamepad_available() {
-            if let Ok(gamepad_devices) = gamepad::get_gamepad_devices().await {
-                devices.extend(gamepad_devices.into_iter().map(|device| Device {
-                    id: device.id,
-                    name: device.name,
-                    device_type: "gamepad".to_string(),
-                    connected: true,
-                    capabilities: {
-                        let mut caps = HashMap::new();
-                        caps.insert("index".to_string(), serde_json::Value::Number(serde_json::Number::from(device.index)));
-                        caps.insert("buttons".to_string(), serde_json::Value::Number(serde_json::Number::from(device.buttons)));
-                        caps.insert("axes".to_string(), serde_json::Value::Number(serde_json::Number::from(device.axes)));
-                        caps
-                    },
-                }));
-            }
+    for &category in DEVICE_CATEGORIES {
+        if device_type.is_some() && device_type != Some(category) {
+            continue;
+        }
+        
+        match enumerate(category).await {
+            Ok(devices) => enumeration.devices.extend(devices),
+            Err(e) => enumeration.partial_errors.push(PartialError {
+                category: category.to_string(),
+                message: e.to_string(),
+            }),
         }
     }
     
+    enumeration
+}
+
+/// Enumerate the devices of a single category
+async fn enumerate_category(category: &str) -> Result<Vec<Device>> {
+    let devices = enumerate_typed_category(category).await?;
+    Ok(devices.into_iter().map(Device::from).collect())
+}
+
+/// Enumerate the devices of a single category with typed capabilities
+///
+/// Categories whose subsystem is unavailable yield no devices rather than an error.
+async fn enumerate_typed_category(category: &str) -> Result<Vec<TypedDevice>> {
+    let mut devices = Vec::new();
+    
+    match category {
+        "bluetooth" if bluetooth::is_bluetooth_available() => {
+            let bluetooth_devices = bluetooth::get_bluetooth_devices().await?;
+            devices.extend(bluetooth_devices.into_iter().map(TypedDevice::from));
+        },
+        "usb" if usb::is_usb_available() => {
+            let usb_devices = usb::get_usb_devices().await?;
+            devices.extend(usb_devices.into_iter().map(TypedDevice::from));
+        },
+        "midi" if midi::is_midi_available() => {
+            devices.extend(midi::get_midi_inputs().await?.into_iter().map(TypedDevice::from));
+            devices.extend(midi::get_midi_outputs().await?.into_iter().map(TypedDevice::from));
+        },
+        "gamepad" if gamepad::is_gamepad_available() => {
+            let gamepad_devices = gamepad::get_gamepad_devices().await?;
+            devices.extend(gamepad_devices.into_iter().map(TypedDevice::from));
+        },
+        _ => {}
+    }
+    
     Ok(devices)
 }
 
@@ -167,7 +349,7 @@ pub async fn monitor_devices(device_type: Option<&str>) -> Result<String> {
     };
     
     // Store the monitor
-    let mut monitors = DEVICE_MONITORS.lock().unwrap();
+    let mut monitors = DEVICE_MONITORS.lock_or_recover();
     monitors.insert(monitor_id.clone(), monitor);
     
     // In a real implementation, we would start a background thread to monitor devices
@@ -178,7 +360,7 @@ pub async fn monitor_devices(device_type: Option<&str>) -> Result<String> {
 
 /// Stop monitoring device changes
 pub async fn stop_monitoring(monitor_id: &str) -> Result<()> {
-    let mut monitors = DEVICE_MONITORS.lock().unwrap();
+    let mut monitors = DEVICE_MONITORS.lock_or_recover();
     
     if monitors.remove(monitor_id).is_none() {
         return Err(HardwareError::InvalidId(format!("Device monitor not found: {}", monitor_id)));
@@ -186,3 +368,141 @@ pub async fn stop_monitoring(monitor_id: &str) -> Result<()> {
     
     Ok(())
 }
+
+/// Close all device connections and stop all device monitors
+pub(crate) fn close_all() {
+    bluetooth::disconnect_all();
+    usb::close_all();
+    midi::close_all();
+    gamepad::close_all();
+    
+    DEVICE_MONITORS.lock_or_recover().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn mock_device(category: &str) -> Device {
+        Device {
+            id: format!("{}_0", category),
+            name: format!("Mock {} device", category),
+            device_type: category.to_string(),
+            connected: true,
+            capabilities: HashMap::new(),
+        }
+    }
+    
+    async fn mock_enumerator(category: &'static str) -> Result<Vec<Device>> {
+        match category {
+            "usb" => Err(HardwareError::DeviceError("libusb context unavailable".to_string())),
+            _ => Ok(vec![mock_device(category)]),
+        }
+    }
+    
+    #[test]
+    fn test_enumeration_surfaces_category_errors() {
+        let enumeration = futures::executor::block_on(collect_devices(None, mock_enumerator));
+        
+        assert!(!enumeration.is_complete());
+        assert_eq!(enumeration.devices.len(), 3);
+        assert!(enumeration.devices.iter().all(|device| device.device_type != "usb"));
+        
+        assert_eq!(enumeration.partial_errors.len(), 1);
+        assert_eq!(enumeration.partial_errors[0].category, "usb");
+        assert!(enumeration.partial_errors[0].message.contains("libusb context unavailable"));
+        
+        // Devices from the other categories are still returned
+        assert_eq!(enumeration.into_devices().unwrap().len(), 3);
+    }
+    
+    #[test]
+    fn test_enumeration_fails_when_only_category_errors() {
+        let enumeration = futures::executor::block_on(collect_devices(Some("usb"), mock_enumerator));
+        assert!(enumeration.devices.is_empty());
+        
+        match enumeration.into_devices() {
+            Err(HardwareError::Internal(message)) => assert!(message.contains("usb: ")),
+            other => panic!("expected an internal error, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_categories_map_to_typed_capabilities() {
+        let bluetooth = TypedDevice::from(bluetooth::BluetoothDevice {
+            id: "bt_1".to_string(),
+            name: "Speaker".to_string(),
+            address: "00:11:22:33:44:55".to_string(),
+            rssi: Some(-60.0),
+            paired: true,
+            connected: false,
+            device_class: None,
+            services: Vec::new(),
+        });
+        assert!(matches!(bluetooth.capabilities, DeviceCapabilities::Bluetooth { paired: true, .. }));
+        
+        let usb = TypedDevice::from(usb::UsbDevice {
+            id: "usb_1".to_string(),
+            name: "Keyboard".to_string(),
+            vendor_id: 0x046d,
+            product_id: 0xc31c,
+            serial_number: None,
+            manufacturer: None,
+            product: None,
+            device_class: 3,
+            device_subclass: 1,
+            device_protocol: 1,
+            bus_number: 1,
+            device_address: 2,
+            endpoints: Vec::new(),
+        });
+        assert!(matches!(usb.capabilities, DeviceCapabilities::Usb { vendor_id: 0x046d, product_id: 0xc31c, .. }));
+        
+        let midi = TypedDevice::from(midi::MidiDevice {
+            id: "midi_out_1".to_string(),
+            name: "Synth".to_string(),
+            manufacturer: Some("SmashLang".to_string()),
+            is_input: false,
+            is_output: true,
+            is_virtual: false,
+        });
+        assert!(matches!(midi.capabilities, DeviceCapabilities::Midi { is_input: false, .. }));
+        
+        let gamepad = TypedDevice::from(gamepad::GamepadDevice {
+            id: "gamepad_0".to_string(),
+            name: "Controller".to_string(),
+            index: 0,
+            buttons: 16,
+            axes: 4,
+            has_force_feedback: false,
+            vendor_id: None,
+            product_id: None,
+        });
+        assert!(matches!(gamepad.capabilities, DeviceCapabilities::Gamepad { buttons: 16, axes: 4, .. }));
+        
+        // The untyped form keeps the keys JavaScript callers rely on
+        let device_types: Vec<String> = [bluetooth, usb.clone(), midi, gamepad]
+            .into_iter()
+            .map(|device| Device::from(device).device_type)
+            .collect();
+        assert_eq!(device_types, vec!["bluetooth", "usb", "midi-output", "gamepad"]);
+        
+        let usb = Device::from(usb);
+        assert_eq!(usb.capabilities["vendorId"], serde_json::json!(0x046d));
+        assert_eq!(usb.capabilities["productId"], serde_json::json!(0xc31c));
+        assert!(!usb.capabilities.contains_key("serialNumber"));
+    }
+    
+    #[test]
+    fn test_monitoring_survives_poisoned_lock() {
+        // Panic while holding the monitor map to poison it
+        let _ = std::thread::spawn(|| {
+            let _monitors = DEVICE_MONITORS.lock().unwrap();
+            panic!("poison the device monitor map");
+        }).join();
+        assert!(DEVICE_MONITORS.is_poisoned());
+        
+        let monitor_id = futures::executor::block_on(monitor_devices(Some("usb"))).unwrap();
+        assert!(futures::executor::block_on(stop_monitoring(&monitor_id)).is_ok());
+    }
+}
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 
 /// USB device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,6 +38,73 @@ pub struct UsbDevice {
     pub bus_number: u8,
     /// Device address on the bus
     pub device_address: u8,
+    /// Endpoints of the active configuration
+    #[serde(default)]
+    pub endpoints: Vec<UsbEndpoint>,
+}
+
+/// USB endpoint transfer type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsbTransferType {
+    Control,
+    Isochronous,
+    #[default]
+    Bulk,
+    Interrupt,
+}
+
+/// USB endpoint descriptor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UsbEndpoint {
+    /// Endpoint address; bit 7 set means IN (device to host)
+    pub address: u8,
+    /// Transfer type the endpoint supports
+    pub transfer_type: UsbTransferType,
+    /// Largest packet the endpoint sends or receives
+    pub max_packet_size: u16,
+    /// Polling interval in milliseconds (interrupt endpoints only)
+    pub interval: u8,
+}
+
+impl UsbEndpoint {
+    /// Whether data flows from the device to the host
+    pub fn is_in(&self) -> bool {
+        self.address & 0x80 != 0
+    }
+}
+
+/// Options for `transfer_usb_data`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbTransferOptions {
+    /// Endpoint address to transfer on
+    pub endpoint: u8,
+    /// Transfer type; must match the endpoint's type
+    #[serde(default)]
+    pub transfer_type: UsbTransferType,
+    /// Data to send to an OUT endpoint
+    #[serde(default)]
+    pub data: Vec<u8>,
+    /// Number of bytes to read from an IN endpoint
+    #[serde(default)]
+    pub length: usize,
+}
+
+/// Result of `transfer_usb_data`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsbTransferResult {
+    /// Endpoint address the transfer used
+    pub endpoint: u8,
+    /// Transfer type used
+    pub transfer_type: UsbTransferType,
+    /// Data read from an IN endpoint
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub data: Vec<u8>,
+    /// Number of bytes sent or received
+    pub bytes_transferred: usize,
+    /// How often the host polls an interrupt endpoint, in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub polling_interval_ms: Option<u8>,
 }
 
 // Global USB state
@@ -51,6 +119,7 @@ struct UsbConnection {
     vendor_id: u16,
     product_id: u16,
     connected_at: std::time::SystemTime,
+    endpoints: Vec<UsbEndpoint>,
     // In a real implementation, we would store platform-specific connection handles here
 }
 
@@ -110,6 +179,7 @@ pub async fn get_usb_devices() -> Result<Vec<UsbDevice>> {
             device_protocol: 0x01,
             bus_number: 1,
             device_address: 2,
+            endpoints: vec![interrupt_in_endpoint(0x81, 8, 10)],
         },
         UsbDevice {
             id: "usb_2".to_string(),
@@ -124,6 +194,7 @@ pub async fn get_usb_devices() -> Result<Vec<UsbDevice>> {
             device_protocol: 0x02,
             bus_number: 1,
             device_address: 3,
+            endpoints: vec![interrupt_in_endpoint(0x81, 4, 10)],
         },
         UsbDevice {
             id: "usb_3".to_string(),
@@ -138,12 +209,25 @@ pub async fn get_usb_devices() -> Result<Vec<UsbDevice>> {
             device_protocol: 0x50,
             bus_number: 2,
             device_address: 1,
+            endpoints: vec![
+                UsbEndpoint { address: 0x81, transfer_type: UsbTransferType::Bulk, max_packet_size: 512, interval: 0 },
+                UsbEndpoint { address: 0x02, transfer_type: UsbTransferType::Bulk, max_packet_size: 512, interval: 0 },
+            ],
         },
     ];
     
     Ok(devices)
 }
 
+fn interrupt_in_endpoint(address: u8, max_packet_size: u16, interval: u8) -> UsbEndpoint {
+    UsbEndpoint {
+        address,
+        transfer_type: UsbTransferType::Interrupt,
+        max_packet_size,
+        interval,
+    }
+}
+
 /// Get information about a specific USB device
 pub async fn get_usb_device(device_id: &str) -> Result<UsbDevice> {
     if !is_usb_available() {
@@ -171,7 +255,7 @@ pub async fn open_usb_device(device_id: &str) -> Result<bool> {
     let device = get_usb_device(device_id).await?;
     
     // Check if already connected
-    let connections = USB_CONNECTIONS.lock().unwrap();
+    let mut connections = USB_CONNECTIONS.lock_or_recover();
     if connections.contains_key(device_id) {
         return Ok(true);
     }
@@ -183,15 +267,56 @@ pub async fn open_usb_device(device_id: &str) -> Result<bool> {
         vendor_id: device.vendor_id,
         product_id: device.product_id,
         connected_at: std::time::SystemTime::now(),
+        endpoints: device.endpoints.clone(),
     };
     
     // Store the connection
-    let mut connections = USB_CONNECTIONS.lock().unwrap();
     connections.insert(device.id.clone(), connection);
     
     Ok(true)
 }
 
+/// Find and open the USB device with the given vendor and product ids
+///
+/// Errors if no device matches. If several devices match, this errors unless
+/// `pick_first` is set, in which case the first enumerated match is opened.
+pub async fn open_by_ids(vendor_id: u16, product_id: u16, pick_first: bool) -> Result<UsbDevice> {
+    if !is_usb_available() {
+        return Err(HardwareError::UnsupportedOperation("USB access is not available on this device".to_string()));
+    }
+    
+    let devices = get_usb_devices().await?;
+    let device = find_by_ids(&devices, vendor_id, product_id, pick_first)?.clone();
+    
+    open_usb_device(&device.id).await?;
+    
+    Ok(device)
+}
+
+/// Select the device matching a vendor/product id pair from an enumerated list
+fn find_by_ids(devices: &[UsbDevice], vendor_id: u16, product_id: u16, pick_first: bool) -> Result<&UsbDevice> {
+    let matches: Vec<&UsbDevice> = devices
+        .iter()
+        .filter(|device| device.vendor_id == vendor_id && device.product_id == product_id)
+        .collect();
+    
+    match matches.as_slice() {
+        [] => Err(HardwareError::InvalidId(format!(
+            "No USB device found with vendor id {:04x} and product id {:04x}",
+            vendor_id, product_id
+        ))),
+        [device] => Ok(device),
+        [device, ..] if pick_first => Ok(device),
+        _ => Err(HardwareError::InvalidOperation(format!(
+            "{} USB devices match vendor id {:04x} and product id {:04x} ({}); pass pick_first to open the first",
+            matches.len(),
+            vendor_id,
+            product_id,
+            matches.iter().map(|device| device.id.as_str()).collect::<Vec<_>>().join(", ")
+        ))),
+    }
+}
+
 /// Close a connection to a USB device
 pub async fn close_usb_device(device_id: &str) -> Result<bool> {
     if !is_usb_available() {
@@ -199,7 +324,7 @@ pub async fn close_usb_device(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let mut connections = USB_CONNECTIONS.lock().unwrap();
+    let mut connections = USB_CONNECTIONS.lock_or_recover();
     if connections.remove(device_id).is_none() {
         return Err(HardwareError::InvalidOperation(format!("Not connected to USB device: {}", device_id)));
     }
@@ -207,6 +332,11 @@ pub async fn close_usb_device(device_id: &str) -> Result<bool> {
     Ok(true)
 }
 
+/// Close every open USB device connection
+pub(crate) fn close_all() {
+    USB_CONNECTIONS.lock_or_recover().clear();
+}
+
 /// Send data to a USB device
 pub async fn send_usb_data(device_id: &str, endpoint: u8, data: &[u8]) -> Result<usize> {
     if !is_usb_available() {
@@ -214,7 +344,7 @@ pub async fn send_usb_data(device_id: &str, endpoint: u8, data: &[u8]) -> Result
     }
     
     // Check if connected
-    let connections = USB_CONNECTIONS.lock().unwrap();
+    let connections = USB_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to USB device: {}", device_id)));
     }
@@ -232,7 +362,7 @@ pub async fn receive_usb_data(device_id: &str, endpoint: u8, length: usize) -> R
     }
     
     // Check if connected
-    let connections = USB_CONNECTIONS.lock().unwrap();
+    let connections = USB_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to USB device: {}", device_id)));
     }
@@ -248,6 +378,75 @@ pub async fn receive_usb_data(device_id: &str, endpoint: u8, length: usize) -> R
     Ok(buffer)
 }
 
+/// Transfer data on a bulk or interrupt endpoint
+///
+/// The direction follows the endpoint address: IN endpoints read up to
+/// `options.length` bytes, OUT endpoints send `options.data`.
+///
+/// Interrupt endpoints are not read on demand: the host polls them once every
+/// `interval` milliseconds (as given in the endpoint descriptor), and an IN
+/// transfer completes at the first poll where the device has data. Each poll
+/// carries at most `max_packet_size` bytes, so interrupt reads are capped to
+/// one packet. The polling interval is reported in the result.
+pub async fn transfer_usb_data(device_id: &str, options: UsbTransferOptions) -> Result<UsbTransferResult> {
+    if !is_usb_available() {
+        return Err(HardwareError::UnsupportedOperation("USB access is not available on this device".to_string()));
+    }
+    
+    // Check if connected
+    let connections = USB_CONNECTIONS.lock_or_recover();
+    let connection = connections.get(device_id).ok_or_else(|| {
+        HardwareError::InvalidOperation(format!("Not connected to USB device: {}", device_id))
+    })?;
+    
+    let endpoint = select_endpoint(&connection.endpoints, &options)?;
+    let interrupt = endpoint.transfer_type == UsbTransferType::Interrupt;
+    
+    // In a real implementation, we would submit the transfer to the device
+    // For simplicity, reads return a dummy buffer and writes report the data length
+    let data = if endpoint.is_in() {
+        let length = if interrupt {
+            options.length.min(endpoint.max_packet_size as usize)
+        } else {
+            options.length
+        };
+        (0..length).map(|i| (i % 256) as u8).collect()
+    } else {
+        Vec::new()
+    };
+    
+    Ok(UsbTransferResult {
+        endpoint: endpoint.address,
+        transfer_type: endpoint.transfer_type,
+        bytes_transferred: if endpoint.is_in() { data.len() } else { options.data.len() },
+        data,
+        polling_interval_ms: interrupt.then_some(endpoint.interval),
+    })
+}
+
+/// Find the endpoint a transfer targets and check it supports the transfer type
+fn select_endpoint<'a>(endpoints: &'a [UsbEndpoint], options: &UsbTransferOptions) -> Result<&'a UsbEndpoint> {
+    if !matches!(options.transfer_type, UsbTransferType::Bulk | UsbTransferType::Interrupt) {
+        return Err(HardwareError::UnsupportedOperation(format!(
+            "{:?} transfers are not supported; use bulk or interrupt",
+            options.transfer_type
+        )));
+    }
+    
+    let endpoint = endpoints.iter().find(|endpoint| endpoint.address == options.endpoint).ok_or_else(|| {
+        HardwareError::InvalidParameter(format!("USB endpoint 0x{:02X} not found", options.endpoint))
+    })?;
+    
+    if endpoint.transfer_type != options.transfer_type {
+        return Err(HardwareError::InvalidParameter(format!(
+            "USB endpoint 0x{:02X} is a {:?} endpoint and cannot be used for a {:?} transfer",
+            endpoint.address, endpoint.transfer_type, options.transfer_type
+        )));
+    }
+    
+    Ok(endpoint)
+}
+
 /// Reset a USB device
 pub async fn reset_usb_device(device_id: &str) -> Result<bool> {
     if !is_usb_available() {
@@ -255,7 +454,7 @@ pub async fn reset_usb_device(device_id: &str) -> Result<bool> {
     }
     
     // Check if connected
-    let connections = USB_CONNECTIONS.lock().unwrap();
+    let connections = USB_CONNECTIONS.lock_or_recover();
     if !connections.contains_key(device_id) {
         return Err(HardwareError::InvalidOperation(format!("Not connected to USB device: {}", device_id)));
     }
@@ -265,3 +464,106 @@ pub async fn reset_usb_device(device_id: &str) -> Result<bool> {
     
     Ok(true)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn transfer(endpoint: u8, transfer_type: UsbTransferType) -> UsbTransferOptions {
+        UsbTransferOptions {
+            endpoint,
+            transfer_type,
+            data: Vec::new(),
+            length: 8,
+        }
+    }
+    
+    fn endpoints() -> Vec<UsbEndpoint> {
+        vec![
+            interrupt_in_endpoint(0x81, 8, 10),
+            UsbEndpoint { address: 0x02, transfer_type: UsbTransferType::Bulk, max_packet_size: 512, interval: 0 },
+        ]
+    }
+    
+    fn device(id: &str, vendor_id: u16, product_id: u16) -> UsbDevice {
+        UsbDevice {
+            id: id.to_string(),
+            name: id.to_string(),
+            vendor_id,
+            product_id,
+            manufacturer: None,
+            product: None,
+            serial_number: None,
+            device_class: 0,
+            device_subclass: 0,
+            device_protocol: 0,
+            bus_number: 1,
+            device_address: 1,
+            endpoints: Vec::new(),
+        }
+    }
+    
+    #[test]
+    fn test_find_by_ids() {
+        let devices = vec![
+            device("usb-1", 0x046d, 0xc31c),
+            device("usb-2", 0x0781, 0x5567),
+            device("usb-3", 0x0781, 0x5567),
+        ];
+        
+        assert_eq!(find_by_ids(&devices, 0x046d, 0xc31c, false).unwrap().id, "usb-1");
+        
+        // No match
+        assert!(matches!(
+            find_by_ids(&devices, 0x1234, 0x5678, false),
+            Err(HardwareError::InvalidId(_))
+        ));
+        
+        // Multiple matches error unless the first is requested
+        let err = find_by_ids(&devices, 0x0781, 0x5567, false).unwrap_err();
+        assert!(matches!(err, HardwareError::InvalidOperation(_)));
+        assert!(err.to_string().contains("usb-2, usb-3"));
+        assert_eq!(find_by_ids(&devices, 0x0781, 0x5567, true).unwrap().id, "usb-2");
+    }
+    
+    #[test]
+    fn test_transfer_type_defaults_to_bulk() {
+        let options: UsbTransferOptions = serde_json::from_str(r#"{"endpoint": 2, "data": [1, 2]}"#).unwrap();
+        assert_eq!(options.transfer_type, UsbTransferType::Bulk);
+        
+        let options: UsbTransferOptions = serde_json::from_str(r#"{"endpoint": 129, "transfer_type": "interrupt"}"#).unwrap();
+        assert_eq!(options.transfer_type, UsbTransferType::Interrupt);
+    }
+    
+    #[test]
+    fn test_select_endpoint_by_type() {
+        let endpoints = endpoints();
+        
+        let endpoint = select_endpoint(&endpoints, &transfer(0x81, UsbTransferType::Interrupt)).unwrap();
+        assert!(endpoint.is_in());
+        assert_eq!(endpoint.interval, 10);
+        
+        let endpoint = select_endpoint(&endpoints, &transfer(0x02, UsbTransferType::Bulk)).unwrap();
+        assert!(!endpoint.is_in());
+    }
+    
+    #[test]
+    fn test_select_endpoint_type_mismatch() {
+        let endpoints = endpoints();
+        
+        // Interrupt transfer on a bulk endpoint, and the reverse
+        let err = select_endpoint(&endpoints, &transfer(0x02, UsbTransferType::Interrupt)).unwrap_err();
+        assert!(matches!(err, HardwareError::InvalidParameter(_)));
+        assert!(err.to_string().contains("Bulk endpoint"));
+        assert!(select_endpoint(&endpoints, &transfer(0x81, UsbTransferType::Bulk)).is_err());
+        
+        assert!(matches!(
+            select_endpoint(&endpoints, &transfer(0x83, UsbTransferType::Interrupt)),
+            Err(HardwareError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            select_endpoint(&endpoints, &transfer(0x81, UsbTransferType::Isochronous)),
+            Err(HardwareError::UnsupportedOperation(_))
+        ));
+    }
+}
@@ -46,6 +46,14 @@ pub enum HardwareError {
     #[error("Invalid parameter: {0}")]
     InvalidParameter(String),
     
+    /// Authentication with a device failed (e.g. wrong pairing PIN)
+    #[error("Authentication failed: {0}")]
+    AuthenticationFailed(String),
+    
+    /// Internal error in the hardware layer itself
+    #[error("Internal error: {0}")]
+    Internal(String),
+    
     /// Other error
     #[error("Error: {0}")]
     Other(String),
@@ -64,6 +72,8 @@ impl fmt::Display for HardwareError {
             HardwareError::IoError(msg) => write!(f, "I/O error: {}", msg),
             HardwareError::ProcessingError(msg) => write!(f, "Processing error: {}", msg),
             HardwareError::InvalidParameter(msg) => write!(f, "Invalid parameter: {}", msg),
+            HardwareError::AuthenticationFailed(msg) => write!(f, "Authentication failed: {}", msg),
+            HardwareError::Internal(msg) => write!(f, "Internal error: {}", msg),
             HardwareError::Other(msg) => write!(f, "Error: {}", msg),
         }
     }
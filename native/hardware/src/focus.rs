@@ -0,0 +1,163 @@
+//! Camera focus control
+//!
+//! Focus is driven through the controls a camera backend exposes. Cameras
+//! without them, such as fixed-focus webcams and the virtual camera, report
+//! `UnsupportedOperation`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::HardwareError;
+use crate::Result;
+
+/// How a camera focuses
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum FocusMode {
+    /// Focus once, then hold until `Camera::trigger_autofocus` is called
+    Auto,
+    /// Keep refocusing as the scene changes
+    Continuous,
+    /// Hold a fixed focus from 0.0 (nearest) to 1.0 (farthest)
+    Manual { distance: f32 },
+}
+
+impl FocusMode {
+    fn name(&self) -> &'static str {
+        match self {
+            FocusMode::Auto => "auto",
+            FocusMode::Continuous => "continuous",
+            FocusMode::Manual { .. } => "manual",
+        }
+    }
+}
+
+/// A single control change made to apply a focus mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FocusSetting {
+    /// Switch continuous autofocus on or off
+    ContinuousAutofocus(bool),
+    /// Set the absolute focus control to this raw value
+    Distance(i64),
+    /// Run a single autofocus pass
+    TriggerAutofocus,
+}
+
+/// Focus controls available on a camera
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct FocusSupport {
+    /// Continuous autofocus can be switched on and off
+    pub continuous: bool,
+    /// A single autofocus pass can be triggered
+    pub trigger: bool,
+    /// Raw range of the absolute focus control; higher values focus closer
+    pub distance_range: Option<(i64, i64)>,
+}
+
+impl FocusSupport {
+    /// Whether the camera has any focus control at all
+    pub(crate) fn is_supported(&self) -> bool {
+        self.continuous || self.trigger || self.distance_range.is_some()
+    }
+    
+    /// Control changes that put the camera into `mode`, in order
+    pub(crate) fn settings(&self, mode: FocusMode) -> Result<Vec<FocusSetting>> {
+        let mut settings = Vec::new();
+        
+        match mode {
+            FocusMode::Continuous if self.continuous => {
+                settings.push(FocusSetting::ContinuousAutofocus(true));
+            }
+            FocusMode::Auto if self.trigger => {
+                if self.continuous {
+                    settings.push(FocusSetting::ContinuousAutofocus(false));
+                }
+                settings.push(FocusSetting::TriggerAutofocus);
+            }
+            FocusMode::Manual { distance } => {
+                if !(0.0..=1.0).contains(&distance) {
+                    return Err(HardwareError::InvalidParameter(format!(
+                        "Focus distance must be between 0.0 and 1.0, got {}",
+                        distance
+                    )));
+                }
+                
+                let (min, max) = self.distance_range.ok_or_else(|| unsupported(mode))?;
+                if self.continuous {
+                    settings.push(FocusSetting::ContinuousAutofocus(false));
+                }
+                let raw = max - ((max - min) as f64 * distance as f64).round() as i64;
+                settings.push(FocusSetting::Distance(raw));
+            }
+            _ => return Err(unsupported(mode)),
+        }
+        
+        Ok(settings)
+    }
+    
+    /// Control change that runs a single autofocus pass
+    pub(crate) fn trigger(&self) -> Result<FocusSetting> {
+        if self.trigger {
+            Ok(FocusSetting::TriggerAutofocus)
+        } else {
+            Err(HardwareError::UnsupportedOperation("Camera does not support triggering autofocus".to_string()))
+        }
+    }
+}
+
+fn unsupported(mode: FocusMode) -> HardwareError {
+    HardwareError::UnsupportedOperation(format!("Camera does not support {} focus", mode.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_focus_mode_serialization() {
+        let cases = [
+            (FocusMode::Auto, r#"{"mode":"auto"}"#),
+            (FocusMode::Continuous, r#"{"mode":"continuous"}"#),
+            (FocusMode::Manual { distance: 0.25 }, r#"{"mode":"manual","distance":0.25}"#),
+        ];
+        
+        for (mode, json) in cases {
+            assert_eq!(serde_json::to_string(&mode).unwrap(), json);
+            assert_eq!(serde_json::from_str::<FocusMode>(json).unwrap(), mode);
+        }
+        
+        assert!(serde_json::from_str::<FocusMode>(r#"{"mode":"manual"}"#).is_err());
+    }
+    
+    #[test]
+    fn test_focus_settings() {
+        let support = FocusSupport {
+            continuous: true,
+            trigger: true,
+            distance_range: Some((0, 250)),
+        };
+        
+        assert_eq!(
+            support.settings(FocusMode::Continuous).unwrap(),
+            vec![FocusSetting::ContinuousAutofocus(true)]
+        );
+        assert_eq!(
+            support.settings(FocusMode::Manual { distance: 0.2 }).unwrap(),
+            vec![FocusSetting::ContinuousAutofocus(false), FocusSetting::Distance(200)]
+        );
+        assert!(matches!(
+            support.settings(FocusMode::Manual { distance: 1.5 }),
+            Err(HardwareError::InvalidParameter(_))
+        ));
+    }
+    
+    #[test]
+    fn test_missing_focus_controls_are_unsupported() {
+        let fixed = FocusSupport::default();
+        assert!(!fixed.is_supported());
+        
+        for mode in [FocusMode::Auto, FocusMode::Continuous, FocusMode::Manual { distance: 0.5 }] {
+            assert!(matches!(fixed.settings(mode), Err(HardwareError::UnsupportedOperation(_))));
+        }
+        assert!(matches!(fixed.trigger(), Err(HardwareError::UnsupportedOperation(_))));
+    }
+}
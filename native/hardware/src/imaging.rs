@@ -0,0 +1,503 @@
+//! Image encoding shared by camera photos and screenshots
+//!
+//! PNG and JPEG output is always available. WebP and AVIF output are behind
+//! the `webp` and `avif` features so default builds stay small; without the
+//! feature, encoding to that format fails with `UnsupportedOperation`.
+
+use std::path::Path;
+
+use image::imageops::{self, FilterType};
+use image::RgbImage;
+use serde::{Deserialize, Serialize};
+
+use crate::error::HardwareError;
+use crate::utils::get_file_extension;
+use crate::Result;
+
+/// Output format for photos and screenshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Avif,
+}
+
+impl ImageFormat {
+    /// Parse a format name ('jpeg', 'jpg', 'png', 'webp', 'avif')
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Some(ImageFormat::Jpeg),
+            "png" => Some(ImageFormat::Png),
+            "webp" => Some(ImageFormat::WebP),
+            "avif" => Some(ImageFormat::Avif),
+            _ => None,
+        }
+    }
+    
+    /// Infer the format from a file path's extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        get_file_extension(path).and_then(|extension| Self::from_name(&extension))
+    }
+    
+    /// Detect the format of already-encoded image data
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match image::guess_format(data).ok()? {
+            image::ImageFormat::Jpeg => Some(ImageFormat::Jpeg),
+            image::ImageFormat::Png => Some(ImageFormat::Png),
+            image::ImageFormat::WebP => Some(ImageFormat::WebP),
+            image::ImageFormat::Avif => Some(ImageFormat::Avif),
+            _ => None,
+        }
+    }
+    
+    /// Use an explicit format name if given, otherwise the file extension
+    ///
+    /// Returns `None` when the chosen name is not a known format.
+    pub fn resolve(format: Option<&str>, file_path: &str) -> Option<Self> {
+        match format {
+            Some(name) => Self::from_name(name),
+            None => Self::from_path(file_path),
+        }
+    }
+    
+    /// Canonical name of the format, as reported in photo/screenshot results
+    pub fn name(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Png => "png",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+    
+    /// MIME type of the format
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::WebP => "image/webp",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// Encode an RGB image in the given format
+///
+/// `quality` (0.0 to 1.0) applies to the lossy formats; WebP is written
+/// losslessly.
+pub fn encode_image(img: &RgbImage, format: ImageFormat, quality: f32) -> Result<Vec<u8>> {
+    let mut buffer = Vec::new();
+    let quality = (quality.clamp(0.0, 1.0) * 100.0) as u8;
+    
+    match format {
+        ImageFormat::Jpeg => {
+            let mut encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, quality);
+            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
+                return Err(HardwareError::ProcessingError(format!("Failed to encode JPEG: {}", e)));
+            }
+        },
+        ImageFormat::Png => {
+            let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
+            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
+                return Err(HardwareError::ProcessingError(format!("Failed to encode PNG: {}", e)));
+            }
+        },
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => {
+            let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut buffer);
+            if let Err(e) = encoder.encode(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
+                return Err(HardwareError::ProcessingError(format!("Failed to encode WebP: {}", e)));
+            }
+        },
+        #[cfg(feature = "avif")]
+        ImageFormat::Avif => {
+            use image::ImageEncoder;
+            
+            let encoder = image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut buffer, 8, quality.max(1));
+            if let Err(e) = encoder.write_image(img.as_raw(), img.width(), img.height(), image::ColorType::Rgb8) {
+                return Err(HardwareError::ProcessingError(format!("Failed to encode AVIF: {}", e)));
+            }
+        },
+        #[allow(unreachable_patterns)]
+        unsupported => {
+            return Err(HardwareError::UnsupportedOperation(format!(
+                "{} output requires the '{}' feature",
+                unsupported.name().to_uppercase(),
+                unsupported.name()
+            )));
+        },
+    }
+    
+    Ok(buffer)
+}
+
+/// Decode encoded image data into an RGB image
+pub fn decode_image(data: &[u8]) -> Result<RgbImage> {
+    image::load_from_memory(data)
+        .map(|img| img.to_rgb8())
+        .map_err(|e| HardwareError::ProcessingError(format!("Failed to decode image: {}", e)))
+}
+
+/// Convert encoded image data to `format`, leaving it untouched if it is
+/// already in that format
+pub fn transcode(data: Vec<u8>, format: ImageFormat, quality: f32) -> Result<Vec<u8>> {
+    if ImageFormat::detect(&data) == Some(format) {
+        return Ok(data);
+    }
+    
+    encode_image(&decode_image(&data)?, format, quality)
+}
+
+/// Build a `data:` URI from base64 image data in the named format
+pub fn data_uri(format: &str, base64_data: &str) -> String {
+    let mime_type = match ImageFormat::from_name(format) {
+        Some(format) => format.mime_type().to_string(),
+        None => format!("image/{}", format.to_lowercase()),
+    };
+    
+    format!("data:{};base64,{}", mime_type, base64_data)
+}
+
+/// Strip a `data:...;base64,` prefix, leaving plain base64 data as-is
+pub fn strip_data_uri(data: &str) -> &str {
+    match data.strip_prefix("data:").and_then(|rest| rest.split_once(";base64,")) {
+        Some((_, base64_data)) => base64_data,
+        None => data,
+    }
+}
+
+/// Path of the thumbnail written next to `file_path` (`shot.png` -> `shot_thumb.png`)
+pub fn thumbnail_path(file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}_thumb.{}", stem, extension.to_string_lossy()),
+        None => format!("{}_thumb", stem),
+    };
+    
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
+/// Write a thumbnail of the image saved at `file_path`, returning its path
+///
+/// The thumbnail keeps the aspect ratio with its longest side at most
+/// `max_dimension`, in the same format as the original. Images that already
+/// fit are copied as-is rather than upscaled.
+pub fn write_thumbnail(file_path: &str, max_dimension: u32, quality: f32) -> Result<String> {
+    if max_dimension == 0 {
+        return Err(HardwareError::InvalidParameter("Thumbnail size must be greater than 0".to_string()));
+    }
+    
+    let data = std::fs::read(file_path)
+        .map_err(|e| HardwareError::IoError(format!("Failed to read image for thumbnail: {}", e)))?;
+    let img = decode_image(&data)?;
+    
+    let longest = img.width().max(img.height());
+    let thumbnail_data = if longest <= max_dimension {
+        data
+    } else {
+        let scale = |side: u32| ((side as u64 * max_dimension as u64) / longest as u64).max(1) as u32;
+        let thumbnail = image::imageops::thumbnail(&img, scale(img.width()), scale(img.height()));
+        let format = ImageFormat::detect(&data).unwrap_or(ImageFormat::Png);
+        encode_image(&thumbnail, format, quality)?
+    };
+    
+    let path = thumbnail_path(file_path);
+    std::fs::write(&path, &thumbnail_data)
+        .map_err(|e| HardwareError::IoError(format!("Failed to save thumbnail: {}", e)))?;
+    
+    Ok(path)
+}
+
+/// How a resized image is fitted to the requested dimensions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeMode {
+    /// Keep the aspect ratio and letterbox the remaining area
+    #[default]
+    Fit,
+    /// Keep the aspect ratio and crop whatever overflows
+    Fill,
+    /// Ignore the aspect ratio
+    Stretch,
+}
+
+/// Resize applied to a captured image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResizeOptions {
+    /// Output width in pixels
+    pub width: u32,
+    /// Output height in pixels
+    pub height: u32,
+    /// How the image is fitted to the output size
+    #[serde(default)]
+    pub mode: ResizeMode,
+}
+
+/// Resize an image to exactly `options.width` x `options.height`
+pub fn resize_image(img: &RgbImage, options: &ResizeOptions) -> Result<RgbImage> {
+    let (width, height) = (options.width, options.height);
+    if width == 0 || height == 0 {
+        return Err(HardwareError::InvalidParameter("Resize dimensions must be greater than 0".to_string()));
+    }
+    
+    if img.dimensions() == (width, height) {
+        return Ok(img.clone());
+    }
+    
+    // Scaled size of the source for the aspect-preserving modes
+    let scaled = |scale: f64| {
+        (
+            ((img.width() as f64 * scale).round() as u32).max(1),
+            ((img.height() as f64 * scale).round() as u32).max(1),
+        )
+    };
+    let scale_x = width as f64 / img.width() as f64;
+    let scale_y = height as f64 / img.height() as f64;
+    
+    let resized = match options.mode {
+        ResizeMode::Stretch => imageops::resize(img, width, height, FilterType::Triangle),
+        ResizeMode::Fit => {
+            let (scaled_width, scaled_height) = scaled(scale_x.min(scale_y));
+            let (scaled_width, scaled_height) = (scaled_width.min(width), scaled_height.min(height));
+            let scaled_img = imageops::resize(img, scaled_width, scaled_height, FilterType::Triangle);
+            
+            // Center on a black canvas
+            let mut canvas = RgbImage::new(width, height);
+            let x = (width - scaled_width) / 2;
+            let y = (height - scaled_height) / 2;
+            imageops::replace(&mut canvas, &scaled_img, x as i64, y as i64);
+            canvas
+        }
+        ResizeMode::Fill => {
+            let (scaled_width, scaled_height) = scaled(scale_x.max(scale_y));
+            let (scaled_width, scaled_height) = (scaled_width.max(width), scaled_height.max(height));
+            let scaled_img = imageops::resize(img, scaled_width, scaled_height, FilterType::Triangle);
+            
+            // Crop the center
+            let x = (scaled_width - width) / 2;
+            let y = (scaled_height - height) / 2;
+            imageops::crop_imm(&scaled_img, x, y, width, height).to_image()
+        }
+    };
+    
+    Ok(resized)
+}
+
+/// Rectangular region of an image, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropRegion {
+    /// Left edge
+    pub x: u32,
+    /// Top edge
+    pub y: u32,
+    /// Region width
+    pub width: u32,
+    /// Region height
+    pub height: u32,
+}
+
+/// Cut `region` out of an image; the region must lie inside the image
+pub fn crop_image(img: &RgbImage, region: &CropRegion) -> Result<RgbImage> {
+    let fits = |start: u32, len: u32, size: u32| len > 0 && start.checked_add(len).map_or(false, |end| end <= size);
+    
+    if !fits(region.x, region.width, img.width()) || !fits(region.y, region.height, img.height()) {
+        return Err(HardwareError::InvalidParameter(format!(
+            "Crop region {}x{} at ({}, {}) is outside the {}x{} image",
+            region.width, region.height, region.x, region.y, img.width(), img.height()
+        )));
+    }
+    
+    Ok(imageops::crop_imm(img, region.x, region.y, region.width, region.height).to_image())
+}
+
+/// Centered region of a `width` x `height` image shown at a zoom `factor`
+pub(crate) fn zoom_region(width: u32, height: u32, factor: f32) -> CropRegion {
+    let zoomed = |size: u32| ((size as f64 / factor as f64).round() as u32).clamp(1, size.max(1));
+    let (zoomed_width, zoomed_height) = (zoomed(width), zoomed(height));
+    
+    CropRegion {
+        x: (width - zoomed_width) / 2,
+        y: (height - zoomed_height) / 2,
+        width: zoomed_width,
+        height: zoomed_height,
+    }
+}
+
+/// Digitally zoom into the center of an image, keeping its dimensions
+///
+/// The centered region covering `1 / factor` of each side is cropped and
+/// scaled back up. A factor of 1.0 leaves the image unchanged.
+pub fn digital_zoom(img: &RgbImage, factor: f32) -> Result<RgbImage> {
+    if factor.is_nan() || factor < 1.0 {
+        return Err(HardwareError::InvalidParameter(format!("Zoom factor must be at least 1.0, got {}", factor)));
+    }
+    
+    let region = zoom_region(img.width(), img.height(), factor);
+    if (region.width, region.height) == img.dimensions() {
+        return Ok(img.clone());
+    }
+    
+    let cropped = crop_image(img, &region)?;
+    Ok(imageops::resize(&cropped, img.width(), img.height(), FilterType::Triangle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn sample_image() -> RgbImage {
+        RgbImage::from_fn(8, 6, |x, y| image::Rgb([(x * 30) as u8, (y * 40) as u8, 128]))
+    }
+    
+    #[test]
+    fn test_crop_image_bounds() {
+        let img = sample_image();
+        
+        let cropped = crop_image(&img, &CropRegion { x: 2, y: 1, width: 3, height: 2 }).unwrap();
+        assert_eq!(cropped.dimensions(), (3, 2));
+        assert_eq!(cropped.get_pixel(0, 0), img.get_pixel(2, 1));
+        
+        for region in [
+            CropRegion { x: 6, y: 0, width: 3, height: 1 },
+            CropRegion { x: 0, y: 0, width: 0, height: 1 },
+            CropRegion { x: u32::MAX, y: 0, width: 2, height: 1 },
+        ] {
+            assert!(matches!(crop_image(&img, &region), Err(HardwareError::InvalidParameter(_))));
+        }
+    }
+    
+    #[test]
+    fn test_digital_zoom_crops_center() {
+        assert_eq!(zoom_region(640, 480, 2.0), CropRegion { x: 160, y: 120, width: 320, height: 240 });
+        assert_eq!(zoom_region(640, 480, 1.0), CropRegion { x: 0, y: 0, width: 640, height: 480 });
+        
+        // A white center quarter on black fills the whole frame at 2x
+        let frame = RgbImage::from_fn(16, 12, |x, y| {
+            let center = (4..12).contains(&x) && (3..9).contains(&y);
+            image::Rgb(if center { [255, 255, 255] } else { [0, 0, 0] })
+        });
+        
+        let zoomed = digital_zoom(&frame, 2.0).unwrap();
+        assert_eq!(zoomed.dimensions(), (16, 12));
+        assert!(zoomed.pixels().all(|pixel| pixel.0 == [255, 255, 255]));
+        
+        assert_eq!(digital_zoom(&frame, 1.0).unwrap(), frame);
+        assert!(matches!(digital_zoom(&frame, 0.5), Err(HardwareError::InvalidParameter(_))));
+    }
+    
+    #[test]
+    fn test_format_from_name_and_path() {
+        assert_eq!(ImageFormat::from_name("JPG"), Some(ImageFormat::Jpeg));
+        assert_eq!(ImageFormat::from_path("/tmp/shot.webp"), Some(ImageFormat::WebP));
+        assert_eq!(ImageFormat::from_path("/tmp/shot.bmp"), None);
+        
+        // An explicit format wins over the extension
+        assert_eq!(ImageFormat::resolve(Some("avif"), "/tmp/shot.png"), Some(ImageFormat::Avif));
+        assert_eq!(ImageFormat::resolve(None, "/tmp/shot.png"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::resolve(Some("tiff"), "/tmp/shot.png"), None);
+    }
+    
+    #[test]
+    fn test_transcode_png_round_trip() {
+        let png = encode_image(&sample_image(), ImageFormat::Png, 0.9).unwrap();
+        assert_eq!(ImageFormat::detect(&png), Some(ImageFormat::Png));
+        
+        let jpeg = transcode(png.clone(), ImageFormat::Jpeg, 0.9).unwrap();
+        assert_eq!(ImageFormat::detect(&jpeg), Some(ImageFormat::Jpeg));
+        
+        // Already in the requested format: passed through unchanged
+        assert_eq!(transcode(png.clone(), ImageFormat::Png, 0.9).unwrap(), png);
+    }
+    
+    #[test]
+    fn test_data_uri_round_trip() {
+        assert_eq!(data_uri("png", "iVBORw0KGgo="), "data:image/png;base64,iVBORw0KGgo=");
+        assert_eq!(data_uri("jpg", "/9j/"), "data:image/jpeg;base64,/9j/");
+        assert_eq!(data_uri("BMP", "Qk0="), "data:image/bmp;base64,Qk0=");
+        
+        assert_eq!(strip_data_uri("data:image/png;base64,iVBORw0KGgo="), "iVBORw0KGgo=");
+        assert_eq!(strip_data_uri("iVBORw0KGgo="), "iVBORw0KGgo=");
+    }
+    
+    #[test]
+    fn test_resize_modes_yield_requested_dimensions() {
+        // 4:3 source resized into 16:9 and portrait targets
+        let source = RgbImage::from_pixel(320, 240, image::Rgb([200, 100, 50]));
+        
+        for mode in [ResizeMode::Fit, ResizeMode::Fill, ResizeMode::Stretch] {
+            for (width, height) in [(160, 90), (90, 160), (1, 1), (640, 480)] {
+                let resized = resize_image(&source, &ResizeOptions { width, height, mode }).unwrap();
+                assert_eq!(resized.dimensions(), (width, height), "{:?} to {}x{}", mode, width, height);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_resize_fit_letterboxes_and_fill_crops() {
+        let source = RgbImage::from_pixel(320, 240, image::Rgb([255, 255, 255]));
+        
+        // Fitting 4:3 into 16:9 leaves black bars at the sides
+        let fit = resize_image(&source, &ResizeOptions { width: 160, height: 90, mode: ResizeMode::Fit }).unwrap();
+        assert_eq!(fit.get_pixel(0, 45), &image::Rgb([0, 0, 0]));
+        assert_eq!(fit.get_pixel(80, 45), &image::Rgb([255, 255, 255]));
+        
+        // Filling covers the whole output
+        let fill = resize_image(&source, &ResizeOptions { width: 160, height: 90, mode: ResizeMode::Fill }).unwrap();
+        assert!(fill.pixels().all(|pixel| pixel == &image::Rgb([255, 255, 255])));
+        
+        assert!(resize_image(&source, &ResizeOptions { width: 0, height: 90, mode: ResizeMode::Fit }).is_err());
+    }
+    
+    #[test]
+    fn test_thumbnail_path() {
+        assert_eq!(thumbnail_path("/tmp/photo.jpg"), "/tmp/photo_thumb.jpg");
+        assert_eq!(thumbnail_path("shot"), "shot_thumb");
+    }
+    
+    #[test]
+    fn test_thumbnail_fits_max_dimension() {
+        let dir = std::env::temp_dir();
+        let wide = dir.join(format!("smash_wide_{}.png", std::process::id()));
+        let small = dir.join(format!("smash_small_{}.png", std::process::id()));
+        
+        let img = RgbImage::from_fn(40, 25, |x, _| image::Rgb([x as u8, 0, 0]));
+        std::fs::write(&wide, encode_image(&img, ImageFormat::Png, 0.9).unwrap()).unwrap();
+        let small_png = encode_image(&sample_image(), ImageFormat::Png, 0.9).unwrap();
+        std::fs::write(&small, &small_png).unwrap();
+        
+        let wide_thumb = write_thumbnail(wide.to_str().unwrap(), 16, 0.9).unwrap();
+        let small_thumb = write_thumbnail(small.to_str().unwrap(), 16, 0.9).unwrap();
+        let wide_img = decode_image(&std::fs::read(&wide_thumb).unwrap()).unwrap();
+        let small_data = std::fs::read(&small_thumb).unwrap();
+        
+        for path in [wide.to_str().unwrap(), small.to_str().unwrap(), &wide_thumb, &small_thumb] {
+            std::fs::remove_file(path).unwrap();
+        }
+        
+        // Longest side capped, aspect ratio kept
+        assert_eq!(wide_img.dimensions(), (16, 10));
+        
+        // Already smaller than the limit: copied without upscaling
+        assert_eq!(small_data, small_png);
+        
+        assert!(matches!(write_thumbnail("/nonexistent.png", 0, 0.9), Err(HardwareError::InvalidParameter(_))));
+    }
+    
+    #[cfg(feature = "webp")]
+    #[test]
+    fn test_webp_output_has_riff_magic() {
+        let webp = encode_image(&sample_image(), ImageFormat::WebP, 0.9).unwrap();
+        assert_eq!(&webp[0..4], b"RIFF");
+        assert_eq!(&webp[8..12], b"WEBP");
+    }
+    
+    #[cfg(not(feature = "webp"))]
+    #[test]
+    fn test_webp_requires_feature() {
+        let result = encode_image(&sample_image(), ImageFormat::WebP, 0.9);
+        assert!(matches!(result, Err(HardwareError::UnsupportedOperation(message)) if message.contains("'webp' feature")));
+    }
+}
@@ -5,12 +5,14 @@
 
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
 
 use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::MutexExt;
 use crate::platform;
 use crate::platform::common::InputDevice;
 
@@ -85,6 +87,267 @@ pub struct InputEvent {
     pub modifiers: HashMap<String, bool>,
 }
 
+/// Direction of a swipe gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SwipeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Gesture recognized from successive touch point snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Gesture {
+    /// Short single-finger touch without significant movement
+    Tap { x: f32, y: f32 },
+    /// Single-finger movement along the dominant axis
+    Swipe { direction: SwipeDirection, distance: f32 },
+    /// Two-finger gesture; scale > 1.0 is a pinch-out, < 1.0 a pinch-in
+    Pinch { scale: f32 },
+}
+
+/// Thresholds used by the gesture recognizer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GestureOptions {
+    /// Minimum distance (in pixels) a finger must travel to count as a swipe
+    pub min_swipe_distance: f32,
+    /// Maximum duration (in milliseconds) of a tap
+    pub max_tap_duration_ms: u64,
+    /// Maximum distance (in pixels) a finger may move during a tap
+    pub max_tap_movement: f32,
+    /// Minimum relative change in finger distance to count as a pinch
+    pub min_pinch_scale_change: f32,
+}
+
+impl Default for GestureOptions {
+    fn default() -> Self {
+        GestureOptions {
+            min_swipe_distance: 50.0,
+            max_tap_duration_ms: 300,
+            max_tap_movement: 10.0,
+            min_pinch_scale_change: 0.1,
+        }
+    }
+}
+
+/// Stateful recognizer turning touch point snapshots into gestures
+///
+/// Feed it the result of `Input::get_touch_points` on every poll; gestures are
+/// reported once all fingers have been lifted.
+#[derive(Debug, Clone, Default)]
+pub struct GestureRecognizer {
+    options: GestureOptions,
+    /// Touch points at the start of the current gesture, keyed by touch ID
+    start_points: HashMap<u32, (f32, f32)>,
+    /// Most recent position of each touch in the current gesture
+    last_points: HashMap<u32, (f32, f32)>,
+    /// Timestamp of the first snapshot of the current gesture
+    start_time: u64,
+    /// Touch IDs of the two fingers taking part in a pinch
+    pinch_ids: Option<(u32, u32)>,
+    /// Distance between the pinch fingers when both were last seen
+    last_pinch_distance: Option<f32>,
+}
+
+impl GestureRecognizer {
+    /// Create a recognizer with the given thresholds
+    pub fn new(options: GestureOptions) -> Self {
+        GestureRecognizer {
+            options,
+            ..Default::default()
+        }
+    }
+    
+    /// Process a snapshot of the current touch points taken at `timestamp` (milliseconds)
+    pub fn update(&mut self, points: &[TouchPoint], timestamp: u64) -> Vec<Gesture> {
+        if points.is_empty() {
+            return self.finish(timestamp).into_iter().collect();
+        }
+        
+        if self.start_points.is_empty() {
+            self.start_time = timestamp;
+        }
+        
+        for point in points {
+            self.start_points.entry(point.id).or_insert((point.x, point.y));
+            self.last_points.insert(point.id, (point.x, point.y));
+        }
+        
+        if self.pinch_ids.is_none() && points.len() >= 2 {
+            self.pinch_ids = Some((points[0].id, points[1].id));
+        }
+        
+        if let Some((a, b)) = self.pinch_ids {
+            let position = |id: u32| points.iter().find(|p| p.id == id).map(|p| (p.x, p.y));
+            if let (Some(a), Some(b)) = (position(a), position(b)) {
+                self.last_pinch_distance = Some(distance(a, b));
+            }
+        }
+        
+        Vec::new()
+    }
+    
+    /// Classify the gesture that just ended and reset for the next one
+    fn finish(&mut self, timestamp: u64) -> Option<Gesture> {
+        let start_points = std::mem::take(&mut self.start_points);
+        let last_points = std::mem::take(&mut self.last_points);
+        let pinch_ids = self.pinch_ids.take();
+        let last_pinch_distance = self.last_pinch_distance.take();
+        
+        if let Some((a, b)) = pinch_ids {
+            let start_distance = distance(start_points[&a], start_points[&b]);
+            let scale = last_pinch_distance? / start_distance;
+            if start_distance > 0.0 && (scale - 1.0).abs() >= self.options.min_pinch_scale_change {
+                return Some(Gesture::Pinch { scale });
+            }
+            return None;
+        }
+        
+        let (id, start) = start_points.into_iter().next()?;
+        let end = last_points[&id];
+        let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+        let moved = distance(start, end);
+        let duration = timestamp.saturating_sub(self.start_time);
+        
+        if moved <= self.options.max_tap_movement && duration <= self.options.max_tap_duration_ms {
+            Some(Gesture::Tap { x: start.0, y: start.1 })
+        } else if moved >= self.options.min_swipe_distance {
+            let direction = if dx.abs() >= dy.abs() {
+                if dx > 0.0 { SwipeDirection::Right } else { SwipeDirection::Left }
+            } else if dy > 0.0 {
+                SwipeDirection::Down
+            } else {
+                SwipeDirection::Up
+            };
+            Some(Gesture::Swipe { direction, distance: moved })
+        } else {
+            None
+        }
+    }
+}
+
+/// Euclidean distance between two points
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt()
+}
+
+/// Layout identifier reported when the active keyboard layout cannot be determined
+pub const UNKNOWN_KEYBOARD_LAYOUT: &str = "unknown";
+
+/// Keyboard state together with the layout needed to interpret it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyboardState {
+    /// Pressed state of each key, keyed by key code
+    pub keys: HashMap<String, bool>,
+    /// Active keyboard layout identifier (e.g. "us", "fr"), or `UNKNOWN_KEYBOARD_LAYOUT`
+    pub layout: String,
+}
+
+/// A parsed global hotkey combination such as `Ctrl+Shift+S`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hotkey {
+    /// Modifier keys in canonical order ("ctrl", "shift", "alt", "meta")
+    pub modifiers: Vec<String>,
+    /// The non-modifier key, lowercased (e.g. "s", "f5", "enter")
+    pub key: String,
+}
+
+impl Hotkey {
+    /// Parse a combo string like `"Ctrl+Shift+S"`
+    pub fn parse(combo: &str) -> Result<Self> {
+        let mut modifiers = Vec::new();
+        let mut key: Option<String> = None;
+        
+        for part in combo.split('+') {
+            let part = part.trim().to_lowercase();
+            if part.is_empty() {
+                return Err(HardwareError::InvalidParameter(format!("Invalid hotkey '{}': empty key in combination", combo)));
+            }
+            
+            let modifier = match part.as_str() {
+                "ctrl" | "control" => Some("ctrl"),
+                "shift" => Some("shift"),
+                "alt" | "option" => Some("alt"),
+                "meta" | "cmd" | "command" | "super" | "win" => Some("meta"),
+                _ => None,
+            };
+            
+            match modifier {
+                Some(modifier) if modifiers.iter().any(|m| m == modifier) => {
+                    return Err(HardwareError::InvalidParameter(format!("Invalid hotkey '{}': duplicate modifier '{}'", combo, modifier)));
+                },
+                Some(modifier) => modifiers.push(modifier.to_string()),
+                None if key.is_some() => {
+                    return Err(HardwareError::InvalidParameter(format!("Invalid hotkey '{}': only one non-modifier key is allowed", combo)));
+                },
+                None if !is_hotkey_key(&part) => {
+                    return Err(HardwareError::InvalidParameter(format!("Invalid hotkey '{}': unknown key '{}'", combo, part)));
+                },
+                None => key = Some(part),
+            }
+        }
+        
+        let key = key.ok_or_else(|| HardwareError::InvalidParameter(format!("Invalid hotkey '{}': missing non-modifier key", combo)))?;
+        
+        // Keep modifiers in a canonical order so equivalent combos compare equal
+        const ORDER: [&str; 4] = ["ctrl", "shift", "alt", "meta"];
+        modifiers.sort_by_key(|m| ORDER.iter().position(|o| o == m));
+        
+        Ok(Hotkey { modifiers, key })
+    }
+    
+    /// Check whether a key-down event triggers this hotkey
+    pub fn matches(&self, event: &InputEvent) -> bool {
+        let key = match &event.event_type {
+            InputEventType::KeyDown(key) => key,
+            _ => return false,
+        };
+        
+        if !key.key.eq_ignore_ascii_case(&self.key) {
+            return false;
+        }
+        
+        ["ctrl", "shift", "alt", "meta"].iter().all(|modifier| {
+            let pressed = event.modifiers.get(*modifier).copied().unwrap_or(false);
+            pressed == self.modifiers.iter().any(|m| m == modifier)
+        })
+    }
+}
+
+/// Check whether a (lowercased) key name can be used as the key of a hotkey
+fn is_hotkey_key(key: &str) -> bool {
+    const NAMED_KEYS: &[&str] = &[
+        "enter", "space", "tab", "escape", "esc", "backspace", "delete", "insert",
+        "home", "end", "pageup", "pagedown", "up", "down", "left", "right", "printscreen",
+    ];
+    
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => c.is_ascii_alphanumeric() || "`-=[]\\;',./".contains(c),
+        _ => {
+            NAMED_KEYS.contains(&key)
+                || key.strip_prefix('f')
+                    .and_then(|n| n.parse::<u8>().ok())
+                    .map_or(false, |n| (1..=24).contains(&n))
+        }
+    }
+}
+
+/// A registered global hotkey
+struct HotkeyRegistration {
+    hotkey: Hotkey,
+    /// Underlying keyboard event registration
+    event_registration_id: String,
+}
+
+lazy_static! {
+    static ref HOTKEY_REGISTRATIONS: Arc<Mutex<HashMap<String, HotkeyRegistration>>> = Arc::new(Mutex::new(HashMap::new()));
+    static ref PENDING_HOTKEY_EVENTS: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+    /// Device types listened to by each `register_events` registration
+    static ref EVENT_REGISTRATIONS: Arc<Mutex<HashMap<String, Vec<String>>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
 /// Input API for SmashLang
 pub struct Input;
 
@@ -98,13 +361,17 @@ impl Input {
     /// Register for input events
     pub async fn register_events(device_types: Vec<String>) -> Result<String> {
         let input_device = create_input_device();
-        input_device.register_events(device_types).await
+        let registration_id = input_device.register_events(device_types.clone()).await?;
+        EVENT_REGISTRATIONS.lock_or_recover().insert(registration_id.clone(), device_types);
+        Ok(registration_id)
     }
     
     /// Unregister from input events
     pub fn unregister_events(registration_id: &str) -> Result<bool> {
         let input_device = create_input_device();
-        input_device.unregister_events(registration_id)
+        let unregistered = input_device.unregister_events(registration_id)?;
+        EVENT_REGISTRATIONS.lock_or_recover().remove(registration_id);
+        Ok(unregistered)
     }
     
     /// Simulate input event
@@ -113,10 +380,23 @@ impl Input {
         input_device.simulate_input(event).await
     }
     
-    /// Get current keyboard state
-    pub fn get_keyboard_state() -> Result<HashMap<String, bool>> {
+    /// Get current keyboard state, including the active keyboard layout
+    pub fn get_keyboard_state() -> Result<KeyboardState> {
         let input_device = create_input_device();
-        input_device.get_keyboard_state()
+        let keys = input_device.get_keyboard_state()?;
+        let layout = input_device.current_layout()
+            .unwrap_or_else(|| UNKNOWN_KEYBOARD_LAYOUT.to_string());
+        
+        Ok(KeyboardState { keys, layout })
+    }
+    
+    /// Get the active keyboard layout identifier
+    ///
+    /// Returns `UNKNOWN_KEYBOARD_LAYOUT` when the platform can't report it.
+    pub fn current_layout() -> String {
+        create_input_device()
+            .current_layout()
+            .unwrap_or_else(|| UNKNOWN_KEYBOARD_LAYOUT.to_string())
     }
     
     /// Get current mouse position
@@ -130,6 +410,248 @@ impl Input {
         let input_device = create_input_device();
         input_device.get_touch_points()
     }
+    
+    /// Type a whole string by simulating the key presses for each character
+    pub async fn type_text(text: &str) -> Result<bool> {
+        let events = text_to_key_events(text)?;
+        let input_device = create_input_device();
+        
+        for event in events {
+            input_device.simulate_input(event).await?;
+        }
+        
+        Ok(true)
+    }
+    
+    /// Poll the current touch points and feed them to `recognizer`
+    pub fn recognize_gestures(recognizer: &mut GestureRecognizer) -> Result<Vec<Gesture>> {
+        let points = Self::get_touch_points()?;
+        Ok(recognizer.update(&points, current_timestamp()))
+    }
+    
+    /// Register a system-wide hotkey such as `"Ctrl+Shift+S"`, returning its registration ID
+    pub async fn register_hotkey(combo: &str) -> Result<String> {
+        let hotkey = Hotkey::parse(combo)?;
+        
+        if HOTKEY_REGISTRATIONS.lock_or_recover().values().any(|r| r.hotkey == hotkey) {
+            return Err(HardwareError::AlreadyInUse(format!("Hotkey '{}' is already registered", combo)));
+        }
+        
+        let event_registration_id = Self::register_events(vec!["keyboard".to_string()]).await?;
+        let hotkey_id = format!("hotkey_{}", uuid::Uuid::new_v4().to_string());
+        
+        let mut registrations = HOTKEY_REGISTRATIONS.lock_or_recover();
+        
+        // Another registration may have raced us while the keyboard listener was being set up
+        if registrations.values().any(|r| r.hotkey == hotkey) {
+            drop(registrations);
+            let _ = Self::unregister_events(&event_registration_id);
+            return Err(HardwareError::AlreadyInUse(format!("Hotkey '{}' is already registered", combo)));
+        }
+        
+        registrations.insert(hotkey_id.clone(), HotkeyRegistration {
+            hotkey,
+            event_registration_id,
+        });
+        
+        Ok(hotkey_id)
+    }
+    
+    /// Unregister a hotkey previously returned by `register_hotkey`
+    pub fn unregister_hotkey(hotkey_id: &str) -> Result<bool> {
+        let registration = HOTKEY_REGISTRATIONS.lock_or_recover().remove(hotkey_id)
+            .ok_or_else(|| HardwareError::InvalidId(format!("Hotkey registration not found: {}", hotkey_id)))?;
+        
+        Self::unregister_events(&registration.event_registration_id)
+    }
+    
+    /// Take the IDs of hotkeys that fired since the last call, in order
+    pub fn take_hotkey_events() -> Vec<String> {
+        std::mem::take(&mut *PENDING_HOTKEY_EVENTS.lock_or_recover())
+    }
+    
+    /// Move the mouse cursor relative to its current position
+    pub async fn move_mouse_relative(dx: f32, dy: f32) -> Result<bool> {
+        let input_device = create_input_device();
+        let (x, y) = input_device.get_mouse_position()?;
+        input_device.simulate_input(mouse_move_event(x + dx, y + dy)).await
+    }
+    
+    /// Move the mouse cursor to (x, y) over roughly `duration_ms` milliseconds
+    pub async fn move_mouse_smooth(x: f32, y: f32, duration_ms: u64) -> Result<bool> {
+        let input_device = create_input_device();
+        let start = input_device.get_mouse_position()?;
+        let path = interpolate_mouse_path(start, (x, y), duration_ms, MOUSE_STEP_INTERVAL_MS);
+        
+        for (i, (px, py)) in path.into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(Duration::from_millis(MOUSE_STEP_INTERVAL_MS)).await;
+            }
+            input_device.simulate_input(mouse_move_event(px, py)).await?;
+        }
+        
+        Ok(true)
+    }
+}
+
+/// Handle an event read by a platform listener started through `register_events`
+///
+/// Platform backends call this for every event they receive. Events of a
+/// device type nobody registered for are dropped; keyboard events are
+/// checked against the registered hotkeys.
+pub(crate) fn receive_event(event: &InputEvent) {
+    let device_type = match event.event_type {
+        InputEventType::KeyDown(_) | InputEventType::KeyUp(_) => "keyboard",
+        InputEventType::MouseMove { .. }
+        | InputEventType::MouseDown { .. }
+        | InputEventType::MouseUp { .. }
+        | InputEventType::MouseWheel { .. } => "mouse",
+        _ => "touch",
+    };
+    
+    let listening = EVENT_REGISTRATIONS
+        .lock_or_recover()
+        .values()
+        .any(|device_types| device_types.iter().any(|t| t == device_type));
+    
+    if listening && device_type == "keyboard" {
+        dispatch_hotkeys(event);
+    }
+}
+
+/// Record a hotkey event for every registered hotkey triggered by `event`
+fn dispatch_hotkeys(event: &InputEvent) {
+    let registrations = HOTKEY_REGISTRATIONS.lock_or_recover();
+    let triggered: Vec<String> = registrations
+        .iter()
+        .filter(|(_, registration)| registration.hotkey.matches(event))
+        .map(|(id, _)| id.clone())
+        .collect();
+    
+    if !triggered.is_empty() {
+        PENDING_HOTKEY_EVENTS.lock_or_recover().extend(triggered);
+    }
+}
+
+/// Interval between intermediate positions for smooth mouse movement
+const MOUSE_STEP_INTERVAL_MS: u64 = 10;
+
+/// Current time in milliseconds since the Unix epoch
+fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Build a mouse move event to an absolute position
+fn mouse_move_event(x: f32, y: f32) -> InputEvent {
+    InputEvent {
+        event_type: InputEventType::MouseMove { x, y },
+        timestamp: current_timestamp(),
+        modifiers: HashMap::new(),
+    }
+}
+
+/// Interpolate the positions visited when moving from `start` to `end`
+///
+/// Produces one position per `step_ms` over `duration_ms` (at least one step),
+/// using an ease-in-out curve. The last position is always exactly `end`.
+pub(crate) fn interpolate_mouse_path(start: (f32, f32), end: (f32, f32), duration_ms: u64, step_ms: u64) -> Vec<(f32, f32)> {
+    let steps = (duration_ms / step_ms.max(1)).max(1);
+    
+    (1..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            let eased = t * t * (3.0 - 2.0 * t);
+            (
+                start.0 + (end.0 - start.0) * eased,
+                start.1 + (end.1 - start.1) * eased,
+            )
+        })
+        .collect()
+}
+
+/// Map a character to its key code and whether shift is needed (US QWERTY layout)
+fn char_to_key(c: char) -> Option<(KeyCode, bool)> {
+    let standard = |code: &str, key: &str| KeyCode {
+        code: code.to_string(),
+        key: key.to_string(),
+        location: "standard".to_string(),
+    };
+    
+    let (code, key, shift) = match c {
+        'a'..='z' => (format!("Key{}", c.to_ascii_uppercase()), c.to_string(), false),
+        'A'..='Z' => (format!("Key{}", c), c.to_ascii_lowercase().to_string(), true),
+        '0'..='9' => (format!("Digit{}", c), c.to_string(), false),
+        ' ' => ("Space".to_string(), "space".to_string(), false),
+        '\n' => ("Enter".to_string(), "enter".to_string(), false),
+        '\t' => ("Tab".to_string(), "tab".to_string(), false),
+        _ => {
+            // Punctuation: (code, unshifted key, shifted character)
+            const PUNCTUATION: &[(&str, char, char)] = &[
+                ("Digit1", '1', '!'), ("Digit2", '2', '@'), ("Digit3", '3', '#'),
+                ("Digit4", '4', '$'), ("Digit5", '5', '%'), ("Digit6", '6', '^'),
+                ("Digit7", '7', '&'), ("Digit8", '8', '*'), ("Digit9", '9', '('),
+                ("Digit0", '0', ')'), ("Minus", '-', '_'), ("Equal", '=', '+'),
+                ("BracketLeft", '[', '{'), ("BracketRight", ']', '}'), ("Backslash", '\\', '|'),
+                ("Semicolon", ';', ':'), ("Quote", '\'', '"'), ("Backquote", '`', '~'),
+                ("Comma", ',', '<'), ("Period", '.', '>'), ("Slash", '/', '?'),
+            ];
+            
+            let (code, unshifted, shifted) = PUNCTUATION
+                .iter()
+                .find(|(_, unshifted, shifted)| *unshifted == c || *shifted == c)?;
+            (code.to_string(), unshifted.to_string(), *shifted == c)
+        }
+    };
+    
+    Some((standard(&code, &key), shift))
+}
+
+/// Build the key-down/key-up event sequence needed to type `text`
+pub(crate) fn text_to_key_events(text: &str) -> Result<Vec<InputEvent>> {
+    let unsupported: Vec<String> = text
+        .chars()
+        .filter(|c| char_to_key(*c).is_none())
+        .map(|c| format!("{:?}", c))
+        .collect();
+    
+    if !unsupported.is_empty() {
+        return Err(HardwareError::UnsupportedOperation(
+            format!("Cannot type characters with the current keyboard layout: {}", unsupported.join(", "))
+        ));
+    }
+    
+    let shift_key = KeyCode {
+        code: "ShiftLeft".to_string(),
+        key: "shift".to_string(),
+        location: "left".to_string(),
+    };
+    
+    let timestamp = current_timestamp();
+    
+    let event = |event_type: InputEventType, shift: bool| {
+        let mut modifiers = HashMap::new();
+        modifiers.insert("shift".to_string(), shift);
+        InputEvent { event_type, timestamp, modifiers }
+    };
+    
+    let mut events = Vec::new();
+    for c in text.chars() {
+        let (key, shift) = char_to_key(c).expect("characters validated above");
+        
+        if shift {
+            events.push(event(InputEventType::KeyDown(shift_key.clone()), true));
+        }
+        events.push(event(InputEventType::KeyDown(key.clone()), shift));
+        events.push(event(InputEventType::KeyUp(key), shift));
+        if shift {
+            events.push(event(InputEventType::KeyUp(shift_key.clone()), false));
+        }
+    }
+    
+    Ok(events)
 }
 
 /// Create a platform-specific input device implementation
@@ -159,3 +681,176 @@ fn create_input_device() -> Box<dyn InputDevice> {
         Box::new(platform::common::DefaultInputDevice::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn is_shift(event: &InputEvent) -> bool {
+        matches!(&event.event_type, InputEventType::KeyDown(key) if key.key == "shift")
+    }
+    
+    #[test]
+    fn test_type_text_shifts_exclamation_mark() {
+        let events = text_to_key_events("Hi!").unwrap();
+        
+        // "H" and "!" are shifted, "i" is not: 4 + 2 + 4 events
+        assert_eq!(events.len(), 10);
+        assert!(is_shift(&events[0]));
+        assert!(!is_shift(&events[4]));
+        assert!(is_shift(&events[6]));
+        
+        match &events[7].event_type {
+            InputEventType::KeyDown(key) => assert_eq!(key.code, "Digit1"),
+            other => panic!("Expected key down for '!', got {:?}", other),
+        }
+        assert_eq!(events[7].modifiers.get("shift"), Some(&true));
+    }
+    
+    #[test]
+    fn test_type_text_rejects_unsupported_characters() {
+        let err = text_to_key_events("caf\u{e9}").unwrap_err();
+        assert!(err.to_string().contains("'é'"));
+    }
+    
+    fn touch(id: u32, x: f32, y: f32) -> TouchPoint {
+        TouchPoint { id, x, y, force: None, radius: None }
+    }
+    
+    #[test]
+    fn test_gesture_recognizer_tap() {
+        let mut recognizer = GestureRecognizer::new(GestureOptions::default());
+        assert!(recognizer.update(&[touch(1, 100.0, 100.0)], 0).is_empty());
+        assert!(recognizer.update(&[touch(1, 102.0, 101.0)], 50).is_empty());
+        
+        let gestures = recognizer.update(&[], 100);
+        assert_eq!(gestures, vec![Gesture::Tap { x: 100.0, y: 100.0 }]);
+    }
+    
+    #[test]
+    fn test_gesture_recognizer_swipe_right() {
+        let mut recognizer = GestureRecognizer::new(GestureOptions::default());
+        for (i, x) in [10.0, 60.0, 110.0, 160.0].iter().enumerate() {
+            recognizer.update(&[touch(1, *x, 200.0 + i as f32)], i as u64 * 30);
+        }
+        
+        match recognizer.update(&[], 120).as_slice() {
+            [Gesture::Swipe { direction, distance }] => {
+                assert_eq!(*direction, SwipeDirection::Right);
+                assert!(*distance > 150.0);
+            },
+            other => panic!("Expected a swipe, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_gesture_recognizer_pinch_out() {
+        let mut recognizer = GestureRecognizer::new(GestureOptions::default());
+        recognizer.update(&[touch(1, 90.0, 100.0), touch(2, 110.0, 100.0)], 0);
+        recognizer.update(&[touch(1, 70.0, 100.0), touch(2, 130.0, 100.0)], 50);
+        recognizer.update(&[touch(2, 130.0, 100.0)], 80);
+        
+        match recognizer.update(&[], 100).as_slice() {
+            [Gesture::Pinch { scale }] => assert!((scale - 3.0).abs() < 1e-5),
+            other => panic!("Expected a pinch, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_keyboard_state_serializes_layout() {
+        let mut keys = HashMap::new();
+        keys.insert("KeyA".to_string(), true);
+        let state = KeyboardState { keys, layout: "fr".to_string() };
+        
+        let json: serde_json::Value = serde_json::to_value(&state).unwrap();
+        assert_eq!(json["layout"], "fr");
+        assert_eq!(json["keys"]["KeyA"], true);
+    }
+    
+    #[test]
+    fn test_parse_hotkey_combo() {
+        let hotkey = Hotkey::parse("Shift+Ctrl+S").unwrap();
+        assert_eq!(hotkey.modifiers, vec!["ctrl", "shift"]);
+        assert_eq!(hotkey.key, "s");
+        assert_eq!(hotkey, Hotkey::parse("ctrl + shift + s").unwrap());
+        
+        let hotkey = Hotkey::parse("Cmd+F5").unwrap();
+        assert_eq!(hotkey.modifiers, vec!["meta"]);
+        assert_eq!(hotkey.key, "f5");
+    }
+    
+    #[test]
+    fn test_parse_hotkey_rejects_invalid_combos() {
+        for combo in ["", "Ctrl+", "Ctrl+Shift", "Ctrl+A+B", "Ctrl+Ctrl+A", "Ctrl+Banana", "F25"] {
+            assert!(
+                matches!(Hotkey::parse(combo), Err(HardwareError::InvalidParameter(_))),
+                "expected '{}' to be rejected", combo
+            );
+        }
+    }
+    
+    #[test]
+    fn test_hotkey_matches_exact_modifiers() {
+        let hotkey = Hotkey::parse("Ctrl+Shift+S").unwrap();
+        let key_down = |ctrl: bool, shift: bool| {
+            let mut modifiers = HashMap::new();
+            modifiers.insert("ctrl".to_string(), ctrl);
+            modifiers.insert("shift".to_string(), shift);
+            InputEvent {
+                event_type: InputEventType::KeyDown(char_to_key('s').unwrap().0),
+                timestamp: 0,
+                modifiers,
+            }
+        };
+        
+        assert!(hotkey.matches(&key_down(true, true)));
+        assert!(!hotkey.matches(&key_down(true, false)));
+    }
+    
+    #[test]
+    fn test_received_key_event_fires_registered_hotkey() {
+        let hotkey_id = futures::executor::block_on(Input::register_hotkey("Ctrl+Alt+F9")).unwrap();
+        let key_down = |ctrl: bool| {
+            let mut modifiers = HashMap::new();
+            modifiers.insert("ctrl".to_string(), ctrl);
+            modifiers.insert("alt".to_string(), true);
+            InputEvent {
+                event_type: InputEventType::KeyDown(KeyCode {
+                    code: "F9".to_string(),
+                    key: "f9".to_string(),
+                    location: "standard".to_string(),
+                }),
+                timestamp: 0,
+                modifiers,
+            }
+        };
+        
+        receive_event(&key_down(false));
+        receive_event(&key_down(true));
+        let fired: Vec<String> = Input::take_hotkey_events().into_iter().filter(|id| *id == hotkey_id).collect();
+        assert_eq!(fired, vec![hotkey_id.clone()]);
+        
+        // Once unregistered, the keyboard events no longer reach it
+        Input::unregister_hotkey(&hotkey_id).unwrap();
+        receive_event(&key_down(true));
+        assert!(!Input::take_hotkey_events().contains(&hotkey_id));
+    }
+    
+    #[test]
+    fn test_smooth_mouse_path_is_monotonic() {
+        let path = interpolate_mouse_path((0.0, 100.0), (200.0, 50.0), 250, 10);
+        
+        assert_eq!(path.len(), 25);
+        assert_eq!(*path.last().unwrap(), (200.0, 50.0));
+        for pair in path.windows(2) {
+            assert!(pair[1].0 >= pair[0].0);
+            assert!(pair[1].1 <= pair[0].1);
+        }
+    }
+    
+    #[test]
+    fn test_smooth_mouse_path_zero_duration_jumps() {
+        let path = interpolate_mouse_path((5.0, 5.0), (10.0, 20.0), 0, 10);
+        assert_eq!(path, vec![(10.0, 20.0)]);
+    }
+}
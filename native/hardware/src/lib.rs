@@ -18,19 +18,36 @@ mod camera;
 mod microphone;
 mod screen;
 mod input;
+mod stream;
+mod audio_processor;
 mod devices;
 mod error;
+mod focus;
+mod imaging;
+mod logging;
+mod permission;
 mod utils;
+#[cfg(feature = "test-devices")]
+mod virtual_camera;
+#[cfg(feature = "test-devices")]
+mod virtual_microphone;
 
 pub use error::HardwareError;
 pub type Result<T> = std::result::Result<T, HardwareError>;
 
 /// Re-export all modules for direct use
 pub use camera::Camera;
+pub use focus::FocusMode;
 pub use microphone::Microphone;
 pub use screen::Screen;
 pub use input::Input;
-pub use devices::{bluetooth, usb, midi, gamepad};
+pub use stream::{ActiveStream, MediaStream};
+pub use imaging::{CropRegion, ImageFormat, ResizeMode, ResizeOptions};
+pub use permission::PermissionStatus;
+pub use logging::init_logging;
+pub use devices::{bluetooth, usb, midi, gamepad, DeviceCapabilities, DeviceEnumeration, PartialError, TypedDevice};
+#[cfg(feature = "test-devices")]
+pub use virtual_microphone::VirtualSignal;
 
 #[cfg(feature = "node")]
 #[napi]
@@ -39,6 +56,34 @@ pub fn init_hardware() -> bool {
     true
 }
 
+/// Release every hardware resource held by this crate
+///
+/// Stops all camera and microphone streams and screen recordings, closes
+/// Bluetooth, USB, MIDI and gamepad connections, and clears device monitors.
+/// Safe to call repeatedly, including when nothing is open.
+#[cfg_attr(feature = "node", napi)]
+pub async fn shutdown_hardware() {
+    camera::Camera::stop_all();
+    microphone::Microphone::stop_all();
+    screen::Screen::stop_all_recordings().await;
+    devices::close_all();
+}
+
+#[cfg(feature = "node")]
+#[napi]
+pub fn hardware_init_logging(level: String) -> napi::Result<bool> {
+    match logging::init_logging(&level) {
+        Ok(()) => Ok(true),
+        Err(e) => Err(napi::Error::from_reason(e.to_string())),
+    }
+}
+
+#[cfg(feature = "node")]
+#[napi]
+pub fn hardware_active_streams() -> String {
+    serde_json::to_string(&stream::active_streams()).unwrap_or_default()
+}
+
 #[cfg(feature = "node")]
 mod node_bindings {
     use super::*;
@@ -59,6 +104,11 @@ mod node_bindings {
             Camera::request_permission().await.unwrap_or(false)
         }
         
+        #[napi]
+        pub fn camera_permission_status() -> String {
+            serde_json::to_string(&Camera::permission_status()).unwrap_or_default()
+        }
+        
         #[napi]
         pub async fn camera_get_devices() -> napi::Result<String> {
             match Camera::get_devices().await {
@@ -67,6 +117,14 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub async fn camera_get_supported_formats(device_id: Option<String>) -> napi::Result<String> {
+            match Camera::get_supported_formats(device_id.as_deref()).await {
+                Ok(formats) => Ok(serde_json::to_string(&formats).unwrap_or_default()),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
         #[napi]
         pub async fn camera_start(options: String) -> napi::Result<String> {
             let options: CameraOptions = match serde_json::from_str(&options) {
@@ -130,6 +188,30 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub async fn camera_save_replay(stream_id: String, file_path: String) -> napi::Result<String> {
+            match Camera::save_replay(&stream_id, &file_path).await {
+                Ok(result) => Ok(serde_json::to_string(&result).unwrap_or_default()),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub async fn camera_pause_recording(stream_id: String) -> napi::Result<bool> {
+            match Camera::pause_recording(&stream_id).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub async fn camera_resume_recording(stream_id: String) -> napi::Result<bool> {
+            match Camera::resume_recording(&stream_id).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
         #[napi]
         pub async fn camera_apply_filter(stream_id: String, filter_name: String, options: String) -> napi::Result<bool> {
             let options: serde_json::Value = match serde_json::from_str(&options) {
@@ -143,6 +225,35 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub fn camera_set_focus_mode(stream_id: String, mode: String) -> napi::Result<bool> {
+            let mode: FocusMode = match serde_json::from_str(&mode) {
+                Ok(mode) => mode,
+                Err(e) => return Err(napi::Error::from_reason(format!("Invalid focus mode: {}", e)))
+            };
+            
+            match Camera::set_focus_mode(&stream_id, mode) {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub fn camera_set_zoom(stream_id: String, factor: f64) -> napi::Result<f64> {
+            match Camera::set_zoom(&stream_id, factor as f32) {
+                Ok(factor) => Ok(factor as f64),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub fn camera_trigger_autofocus(stream_id: String) -> napi::Result<bool> {
+            match Camera::trigger_autofocus(&stream_id) {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
         #[napi]
         pub async fn camera_remove_filters(stream_id: String) -> napi::Result<bool> {
             match Camera::remove_filters(&stream_id).await {
@@ -167,6 +278,11 @@ mod node_bindings {
             Microphone::request_permission().await.unwrap_or(false)
         }
         
+        #[napi]
+        pub fn microphone_permission_status() -> String {
+            serde_json::to_string(&Microphone::permission_status()).unwrap_or_default()
+        }
+        
         #[napi]
         pub async fn microphone_get_devices() -> napi::Result<String> {
             match Microphone::get_devices().await {
@@ -175,6 +291,14 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub async fn microphone_get_supported_configs(device_id: Option<String>) -> napi::Result<String> {
+            match Microphone::get_supported_configs(device_id.as_deref()).await {
+                Ok(configs) => Ok(serde_json::to_string(&configs).unwrap_or_default()),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
         #[napi]
         pub async fn microphone_start(options: String) -> napi::Result<String> {
             let options: MicrophoneOptions = match serde_json::from_str(&options) {
@@ -217,6 +341,46 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub fn microphone_watch_default_device() -> napi::Result<String> {
+            match Microphone::watch_default_device() {
+                Ok(watcher_id) => Ok(watcher_id),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub fn microphone_unwatch_default_device(watcher_id: String) -> napi::Result<bool> {
+            match Microphone::unwatch_default_device(&watcher_id) {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub fn microphone_take_default_device_changes(watcher_id: String) -> napi::Result<String> {
+            match Microphone::take_default_device_changes(&watcher_id) {
+                Ok(changes) => Ok(serde_json::to_string(&changes).unwrap_or_default()),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub async fn microphone_pause_recording(stream_id: String) -> napi::Result<bool> {
+            match Microphone::pause_recording(&stream_id).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
+        #[napi]
+        pub async fn microphone_resume_recording(stream_id: String) -> napi::Result<bool> {
+            match Microphone::resume_recording(&stream_id).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+        
         #[napi]
         pub async fn microphone_save_recording(recording_data: String, file_path: String, format: Option<String>) -> napi::Result<String> {
             match Microphone::save_recording(&recording_data, &file_path, format.as_deref()).await {
@@ -426,6 +590,11 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub fn input_current_layout() -> String {
+            Input::current_layout()
+        }
+        
         #[napi]
         pub fn input_get_mouse_position() -> napi::Result<String> {
             match Input::get_mouse_position() {
@@ -447,6 +616,51 @@ mod node_bindings {
                 Err(e) => Err(napi::Error::from_reason(e.to_string()))
             }
         }
+
+        #[napi]
+        pub async fn input_type_text(text: String) -> napi::Result<bool> {
+            match Input::type_text(&text).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+
+        #[napi]
+        pub async fn input_register_hotkey(combo: String) -> napi::Result<String> {
+            match Input::register_hotkey(&combo).await {
+                Ok(id) => Ok(id),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+
+        #[napi]
+        pub fn input_unregister_hotkey(hotkey_id: String) -> napi::Result<bool> {
+            match Input::unregister_hotkey(&hotkey_id) {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+
+        #[napi]
+        pub fn input_take_hotkey_events() -> String {
+            serde_json::to_string(&Input::take_hotkey_events()).unwrap_or_default()
+        }
+
+        #[napi]
+        pub async fn input_move_mouse_relative(dx: f64, dy: f64) -> napi::Result<bool> {
+            match Input::move_mouse_relative(dx as f32, dy as f32).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
+
+        #[napi]
+        pub async fn input_move_mouse_smooth(x: f64, y: f64, duration_ms: u32) -> napi::Result<bool> {
+            match Input::move_mouse_smooth(x as f32, y as f32, duration_ms as u64).await {
+                Ok(_) => Ok(true),
+                Err(e) => Err(napi::Error::from_reason(e.to_string()))
+            }
+        }
     }
     
     // Device management bindings
@@ -462,6 +676,12 @@ mod node_bindings {
             }
         }
         
+        #[napi]
+        pub async fn devices_enumerate(device_type: Option<String>) -> String {
+            let enumeration = enumerate_devices(device_type.as_deref()).await;
+            serde_json::to_string(&enumeration).unwrap_or_default()
+        }
+        
         // Bluetooth bindings
         pub mod bluetooth {
             use super::*;
@@ -509,6 +729,16 @@ mod node_bindings {
                 }
             }
             
+            #[napi]
+            pub async fn bluetooth_pair(device_id: String, pin: Option<String>) -> napi::Result<String> {
+                let options = PairingOptions { pin, confirm_passkey: None };
+                
+                match pair_device(&device_id, options).await {
+                    Ok(pairing) => Ok(serde_json::to_string(&pairing).unwrap_or_default()),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
+            
             #[napi]
             pub async fn bluetooth_disconnect(device_id: String) -> napi::Result<bool> {
                 match disconnect_bluetooth_device(&device_id).await {
@@ -552,6 +782,14 @@ mod node_bindings {
                 }
             }
             
+            #[napi]
+            pub async fn usb_open_by_ids(vendor_id: u32, product_id: u32, pick_first: Option<bool>) -> napi::Result<String> {
+                match open_by_ids(vendor_id as u16, product_id as u16, pick_first.unwrap_or(false)).await {
+                    Ok(device) => Ok(serde_json::to_string(&device).unwrap_or_default()),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
+            
             #[napi]
             pub async fn usb_close(device_id: String) -> napi::Result<bool> {
                 match close_usb_device(&device_id).await {
@@ -638,6 +876,43 @@ mod node_bindings {
                     Err(e) => Err(napi::Error::from_reason(e.to_string()))
                 }
             }
+            
+            #[napi]
+            pub async fn midi_send_sysex(device_id: String, data: String) -> napi::Result<bool> {
+                let data: Vec<u8> = match serde_json::from_str(&data) {
+                    Ok(data) => data,
+                    Err(e) => return Err(napi::Error::from_reason(format!("Invalid SysEx data: {}", e)))
+                };
+                
+                match send_sysex(&device_id, &data).await {
+                    Ok(_) => Ok(true),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
+            
+            #[napi]
+            pub fn midi_start_clock(device_id: String, bpm: f64) -> napi::Result<String> {
+                match start_midi_clock(&device_id, bpm) {
+                    Ok(clock_id) => Ok(clock_id),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
+            
+            #[napi]
+            pub fn midi_stop_clock(clock_id: String) -> napi::Result<bool> {
+                match stop_midi_clock(&clock_id) {
+                    Ok(_) => Ok(true),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
+            
+            #[napi]
+            pub fn midi_take_messages(device_id: String) -> napi::Result<String> {
+                match take_midi_messages(&device_id) {
+                    Ok(messages) => Ok(serde_json::to_string(&messages).unwrap_or_default()),
+                    Err(e) => Err(napi::Error::from_reason(e.to_string()))
+                }
+            }
         }
         
         // Gamepad bindings
@@ -686,3 +961,27 @@ mod node_bindings {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::microphone::MicrophoneOptions;
+
+    #[test]
+    fn test_shutdown_hardware_stops_microphone_streams() {
+        // Requires a real microphone
+        if !Microphone::is_available() {
+            return;
+        }
+
+        let options: MicrophoneOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        let stream = futures::executor::block_on(Microphone::start(options)).unwrap();
+        assert!(Microphone::is_active(&stream.id));
+
+        futures::executor::block_on(shutdown_hardware());
+        assert!(Microphone::active_streams().is_empty());
+
+        // A second shutdown with nothing open is a no-op
+        futures::executor::block_on(shutdown_hardware());
+    }
+}
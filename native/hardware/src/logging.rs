@@ -0,0 +1,57 @@
+//! Diagnostics logging
+//!
+//! Hardware modules report hints and recoverable failures through the `log`
+//! facade, so embedders can route and filter them with their own logger.
+//! `init_logging` installs a simple stderr logger for hosts without one.
+
+use std::str::FromStr;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::error::HardwareError;
+use crate::Result;
+
+/// Logger writing records to stderr
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+    
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+    
+    fn flush(&self) {}
+}
+
+static STDERR_LOGGER: StderrLogger = StderrLogger;
+
+/// Log hardware diagnostics to stderr at `level` and above
+///
+/// `level` is one of "off", "error", "warn", "info", "debug" or "trace".
+/// If the host has already installed a logger it is kept, and only the
+/// maximum level is changed.
+pub fn init_logging(level: &str) -> Result<()> {
+    let filter = LevelFilter::from_str(level)
+        .map_err(|_| HardwareError::InvalidParameter(format!("Invalid log level: {}", level)))?;
+    
+    // Fails only when a logger is already installed, which is fine
+    let _ = log::set_logger(&STDERR_LOGGER);
+    log::set_max_level(filter);
+    
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_invalid_level_is_rejected() {
+        assert!(matches!(init_logging("loud"), Err(HardwareError::InvalidParameter(_))));
+    }
+}
@@ -6,13 +6,23 @@
 use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use serde::{Deserialize, Serialize};
 
+use crate::audio_processor::AudioProcessor;
 use crate::error::HardwareError;
+#[cfg(target_os = "windows")]
+use crate::permission::consent_store_status;
+use crate::permission::PermissionStatus;
+#[cfg(target_os = "linux")]
+use crate::permission::warn_group_hint;
+use crate::stream::{MediaStream, RecordingClock};
+#[cfg(feature = "test-devices")]
+use crate::virtual_microphone::{SignalGenerator, VirtualMicrophone, VirtualSignal, VIRTUAL_MICROPHONE_ID};
 use crate::Result;
+use crate::utils::MutexExt;
 
 /// Microphone device information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +48,19 @@ pub struct MicrophoneStream {
     pub channels: u16,
 }
 
+/// A range of stream configurations supported by a microphone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioConfig {
+    /// Number of channels
+    pub channels: u16,
+    /// Lowest supported sample rate in Hz
+    pub min_sample_rate: u32,
+    /// Highest supported sample rate in Hz
+    pub max_sample_rate: u32,
+    /// Sample format (e.g. 'f32', 'i16')
+    pub sample_format: String,
+}
+
 /// Microphone configuration options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MicrophoneOptions {
@@ -59,6 +82,10 @@ pub struct MicrophoneOptions {
     /// Whether to enable automatic gain control
     #[serde(default = "default_true")]
     pub auto_gain_control: bool,
+    /// Signal produced by the virtual microphone
+    #[cfg(feature = "test-devices")]
+    #[serde(default)]
+    pub virtual_signal: VirtualSignal,
 }
 
 /// Audio recording options
@@ -116,6 +143,30 @@ pub struct SpeechRecognitionResult {
     pub alternatives: Vec<String>,
 }
 
+/// Change of the system default microphone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefaultDeviceChange {
+    /// Previous default device, if there was one
+    pub previous: Option<String>,
+    /// New default device, if there still is one
+    pub current: Option<String>,
+}
+
+impl Default for MicrophoneOptions {
+    fn default() -> Self {
+        MicrophoneOptions {
+            device_id: None,
+            sample_rate: default_sample_rate(),
+            channels: default_channels(),
+            echo_cancellation: true,
+            noise_suppression: true,
+            auto_gain_control: true,
+            #[cfg(feature = "test-devices")]
+            virtual_signal: VirtualSignal::default(),
+        }
+    }
+}
+
 // Default values for microphone options
 fn default_sample_rate() -> u32 { 44100 }
 fn default_channels() -> u16 { 1 }
@@ -128,21 +179,39 @@ fn default_language() -> String { "en-US".to_string() }
 lazy_static! {
     static ref MICROPHONE_INSTANCES: Arc<Mutex<HashMap<String, MicrophoneInstance>>> = Arc::new(Mutex::new(HashMap::new()));
     static ref RECORDING_INSTANCES: Arc<Mutex<HashMap<String, RecordingInstance>>> = Arc::new(Mutex::new(HashMap::new()));
+    /// Pending default-device changes for each watcher
+    static ref DEFAULT_DEVICE_WATCHERS: Arc<Mutex<HashMap<String, Vec<DefaultDeviceChange>>>> = Arc::new(Mutex::new(HashMap::new()));
 }
 
+/// How often watchers check the default microphone
+const DEFAULT_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Microphone instance with the underlying device and settings
 struct MicrophoneInstance {
-    device: cpal::Device,
     config: cpal::StreamConfig,
-    stream: Option<cpal::Stream>,
+    /// Capture source; dropping it stops capture
+    _source: MicrophoneSource,
     options: MicrophoneOptions,
-    processors: Vec<String>,
+    /// Processors run on every captured chunk, in order
+    processors: Arc<Mutex<Vec<AudioProcessor>>>,
     buffer: Arc<Mutex<Vec<f32>>>,
 }
 
+/// Where a microphone stream's samples come from
+///
+/// The `test-devices` feature swaps real hardware for the virtual microphone.
+enum MicrophoneSource {
+    #[cfg(not(feature = "test-devices"))]
+    Device { _stream: cpal::Stream },
+    #[cfg(feature = "test-devices")]
+    Virtual { _microphone: VirtualMicrophone },
+}
+
 /// Recording instance with metadata
 struct RecordingInstance {
-    start_time: SystemTime,
+    clock: RecordingClock,
+    /// Length of the stream buffer when the recording was paused
+    paused_at_sample: Option<usize>,
     format: String,
     quality: f32,
     sample_rate: u32,
@@ -156,6 +225,10 @@ pub struct Microphone;
 impl Microphone {
     /// Check if microphone access is available on this device
     pub fn is_available() -> bool {
+        if cfg!(feature = "test-devices") {
+            return true;
+        }
+        
         // Try platform-specific implementations first
         #[cfg(target_os = "linux")]
         {
@@ -229,9 +302,7 @@ impl Microphone {
                         }
                     } else {
                         // User is not in the audio group, suggest adding them
-                        eprintln!("Microphone access may be restricted. You may need to add your user to the 'audio' group.");
-                        eprintln!("Try running: sudo usermod -a -G audio {}", current_user);
-                        eprintln!("Then log out and log back in.");
+                        warn_group_hint("Microphone", "audio", &current_user);
                     }
                 }
             }
@@ -263,8 +334,7 @@ impl Microphone {
                             None => Err(HardwareError::DeviceAccessError("No audio input devices found".to_string())),
                         }
                     } else {
-                        eprintln!("Microphone access is disabled in Windows privacy settings.");
-                        eprintln!("Please enable it in Settings > Privacy > Microphone.");
+                        log::warn!("Microphone access is disabled in Windows privacy settings. Please enable it in Settings > Privacy > Microphone.");
                         Err(HardwareError::PermissionDenied("Microphone access denied in Windows privacy settings".to_string()))
                     }
                 },
@@ -273,8 +343,7 @@ impl Microphone {
                     match cpal::default_host().default_input_device() {
                         Some(_) => Ok(true),
                         None => {
-                            eprintln!("Could not determine microphone permission status.");
-                            eprintln!("Please ensure microphone access is enabled in Settings > Privacy > Microphone.");
+                            log::warn!("Could not determine microphone permission status. Please ensure microphone access is enabled in Settings > Privacy > Microphone.");
                             Err(HardwareError::PermissionDenied("Microphone access denied".to_string()))
                         }
                     }
@@ -291,9 +360,7 @@ impl Microphone {
             match cpal::default_host().default_input_device() {
                 Some(_) => Ok(true),
                 None => {
-                    eprintln!("Microphone access may be disabled in macOS privacy settings.");
-                    eprintln!("Please check System Preferences > Security & Privacy > Privacy > Microphone");
-                    eprintln!("and ensure this application has microphone access.");
+                    log::warn!("Microphone access may be disabled in macOS privacy settings. Please check System Preferences > Security & Privacy > Privacy > Microphone and ensure this application has microphone access.");
                     Err(HardwareError::PermissionDenied("Microphone access denied in macOS privacy settings".to_string()))
                 }
             }
@@ -309,8 +376,44 @@ impl Microphone {
         }
     }
     
+    /// Read the current microphone permission without prompting the user
+    pub fn permission_status() -> PermissionStatus {
+        #[cfg(target_os = "linux")]
+        {
+            // There is no prompt on Linux; access follows from the device being reachable
+            match cpal::default_host().default_input_device() {
+                Some(_) => PermissionStatus::Granted,
+                None => PermissionStatus::Unknown,
+            }
+        }
+        
+        #[cfg(target_os = "windows")]
+        {
+            consent_store_status("microphone")
+        }
+        
+        #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+        {
+            // Reading the status on macOS and mobile requires the platform permission APIs
+            PermissionStatus::Unknown
+        }
+    }
+    
     /// Get a list of available microphone devices
+    ///
+    /// With the `test-devices` feature this is just the virtual microphone.
     pub async fn get_devices() -> Result<Vec<MicrophoneDevice>> {
+        #[cfg(feature = "test-devices")]
+        let devices = Ok(vec![VirtualMicrophone::device()]);
+        #[cfg(not(feature = "test-devices"))]
+        let devices = Self::get_hardware_devices().await;
+        
+        devices
+    }
+    
+    /// Enumerate the microphone devices attached to the system
+    #[cfg(not(feature = "test-devices"))]
+    async fn get_hardware_devices() -> Result<Vec<MicrophoneDevice>> {
         let mut microphone_devices = Vec::new();
         
         // Platform-specific device enumeration
@@ -482,8 +585,104 @@ impl Microphone {
         Ok(microphone_devices)
     }
     
+    /// Get the configurations supported by a microphone, or by the default microphone if no id is given
+    pub async fn get_supported_configs(device_id: Option<&str>) -> Result<Vec<AudioConfig>> {
+        let device = find_input_device(device_id)?;
+        
+        let supported_configs = device.supported_input_configs()
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to get supported microphone configs: {}", e)))?;
+        
+        Ok(supported_configs
+            .map(|config| AudioConfig {
+                channels: config.channels(),
+                min_sample_rate: config.min_sample_rate().0,
+                max_sample_rate: config.max_sample_rate().0,
+                sample_format: format!("{:?}", config.sample_format()).to_lowercase(),
+            })
+            .collect())
+    }
+    
+    /// Start a stream on a specific microphone, failing if the device id does not resolve
+    ///
+    /// Unlike `start`, an unknown device id is an error rather than a fallback
+    /// to the default microphone.
+    pub async fn start_device(device_id: &str, options: MicrophoneOptions) -> Result<MicrophoneStream> {
+        #[cfg(feature = "test-devices")]
+        if device_id != VIRTUAL_MICROPHONE_ID {
+            return Err(HardwareError::InvalidId(format!("Microphone device not found: {}", device_id)));
+        }
+        #[cfg(not(feature = "test-devices"))]
+        find_input_device(Some(device_id))?;
+        
+        Self::start(MicrophoneOptions {
+            device_id: Some(device_id.to_string()),
+            ..options
+        }).await
+    }
+    
     /// Start a microphone stream
     pub async fn start(options: MicrophoneOptions) -> Result<MicrophoneStream> {
+        // Create a buffer for audio data
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let processors = Arc::new(Mutex::new(Vec::new()));
+        
+        let capture = {
+            let buffer = buffer.clone();
+            let processors = processors.clone();
+            move |data: &[f32]| capture_samples(data, &processors, &buffer)
+        };
+        
+        #[cfg(feature = "test-devices")]
+        let (source, config) = Self::start_virtual(&options, capture);
+        #[cfg(not(feature = "test-devices"))]
+        let (source, config) = Self::start_hardware(&options, capture)?;
+        
+        // Generate a unique ID for this microphone stream
+        let stream_id = format!("microphone_{}", uuid::Uuid::new_v4().to_string());
+        
+        // Store the microphone instance
+        let microphone_instance = MicrophoneInstance {
+            config,
+            _source: source,
+            options: options.clone(),
+            processors,
+            buffer,
+        };
+        
+        MICROPHONE_INSTANCES.lock_or_recover().insert(stream_id.clone(), microphone_instance);
+        
+        // Return the stream information
+        Ok(MicrophoneStream {
+            id: stream_id,
+            sample_rate: options.sample_rate,
+            channels: options.channels,
+        })
+    }
+    
+    /// Start generating the configured signal on the virtual microphone
+    #[cfg(feature = "test-devices")]
+    fn start_virtual(
+        options: &MicrophoneOptions,
+        capture: impl FnMut(&[f32]) + Send + 'static,
+    ) -> (MicrophoneSource, cpal::StreamConfig) {
+        let config = cpal::StreamConfig {
+            channels: options.channels,
+            sample_rate: cpal::SampleRate(options.sample_rate),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        
+        let generator = SignalGenerator::new(options.virtual_signal, options.sample_rate, options.channels);
+        let microphone = VirtualMicrophone::start(generator, capture);
+        
+        (MicrophoneSource::Virtual { _microphone: microphone }, config)
+    }
+    
+    /// Open and start capturing from the microphone selected by the options
+    #[cfg(not(feature = "test-devices"))]
+    fn start_hardware(
+        options: &MicrophoneOptions,
+        mut capture: impl FnMut(&[f32]) + Send + 'static,
+    ) -> Result<(MicrophoneSource, cpal::StreamConfig)> {
         let host = cpal::default_host();
         
         // Determine which microphone to use
@@ -524,71 +723,29 @@ impl Microphone {
         
         let config = supported_config.with_sample_rate(cpal::SampleRate(options.sample_rate)).config();
         
-        // Generate a unique ID for this microphone stream
-        let stream_id = format!("microphone_{}", uuid::Uuid::new_v4().to_string());
-        
-        // Create a buffer for audio data
-        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let err_fn = move |err| log::error!("An error occurred on the audio stream: {}", err);
         
-        // Store the microphone instance
-        let microphone_instance = MicrophoneInstance {
-            device,
-            config: config.clone(),
-            stream: None,
-            options: options.clone(),
-            processors: Vec::new(),
-            buffer: buffer.clone(),
-        };
-        
-        let mut instances = MICROPHONE_INSTANCES.lock().unwrap();
-        instances.insert(stream_id.clone(), microphone_instance);
-        
-        // Start the microphone stream
-        let instance = instances.get_mut(&stream_id).unwrap();
-        let buffer_clone = buffer.clone();
-        
-        let err_fn = move |err| eprintln!("an error occurred on the audio stream: {}", err);
-        
-        let stream = match instance.device.build_input_stream(
-            &instance.config,
-            move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                // Store the audio data in the buffer
-                let mut buffer = buffer_clone.lock().unwrap();
-                buffer.extend_from_slice(data);
-            },
+        let stream = device.build_input_stream(
+            &config,
+            move |data: &[f32], _: &cpal::InputCallbackInfo| capture(data),
             err_fn,
             None
-        ) {
-            Ok(stream) => stream,
-            Err(e) => {
-                instances.remove(&stream_id);
-                return Err(HardwareError::DeviceError(format!("Failed to build microphone stream: {}", e)));
-            }
-        };
+        ).map_err(|e| HardwareError::DeviceError(format!("Failed to build microphone stream: {}", e)))?;
         
         // Start the stream
-        if let Err(e) = stream.play() {
-            instances.remove(&stream_id);
-            return Err(HardwareError::DeviceError(format!("Failed to start microphone stream: {}", e)));
-        }
-        
-        instance.stream = Some(stream);
+        stream.play()
+            .map_err(|e| HardwareError::DeviceError(format!("Failed to start microphone stream: {}", e)))?;
         
-        // Return the stream information
-        Ok(MicrophoneStream {
-            id: stream_id,
-            sample_rate: options.sample_rate,
-            channels: options.channels,
-        })
+        Ok((MicrophoneSource::Device { _stream: stream }, config))
     }
     
     /// Stop a microphone stream
     pub fn stop(stream_id: &str) -> Result<()> {
-        let mut instances = MICROPHONE_INSTANCES.lock().unwrap();
+        let mut instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         if let Some(instance) = instances.remove(stream_id) {
             // Stop any active recording
-            let mut recordings = RECORDING_INSTANCES.lock().unwrap();
+            let mut recordings = RECORDING_INSTANCES.lock_or_recover();
             recordings.remove(stream_id);
             
             // Drop the stream to stop it
@@ -601,21 +758,22 @@ impl Microphone {
     
     /// Start recording audio from a microphone stream
     pub async fn start_recording(stream_id: &str, options: AudioRecordingOptions) -> Result<()> {
-        let instances = MICROPHONE_INSTANCES.lock().unwrap();
+        let instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         let instance = instances.get(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
         })?;
         
         // Check if already recording
-        let mut recordings = RECORDING_INSTANCES.lock().unwrap();
+        let mut recordings = RECORDING_INSTANCES.lock_or_recover();
         if recordings.contains_key(stream_id) {
             return Err(HardwareError::AlreadyInUse("Microphone is already recording".to_string()));
         }
         
         // Create a new recording instance
         let recording = RecordingInstance {
-            start_time: SystemTime::now(),
+            clock: RecordingClock::start(),
+            paused_at_sample: None,
             format: options.format.clone(),
             quality: options.quality,
             sample_rate: instance.config.sample_rate.0,
@@ -630,26 +788,28 @@ impl Microphone {
     
     /// Stop recording audio and optionally save to a file
     pub async fn stop_recording(stream_id: &str, file_path: Option<&str>) -> Result<AudioRecordingResult> {
-        let instances = MICROPHONE_INSTANCES.lock().unwrap();
+        let instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         let instance = instances.get(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
         })?;
         
         // Get the recording instance
-        let mut recordings = RECORDING_INSTANCES.lock().unwrap();
+        let mut recordings = RECORDING_INSTANCES.lock_or_recover();
         let recording = recordings.remove(stream_id).ok_or_else(|| {
             HardwareError::InvalidOperation("Microphone is not recording".to_string())
         })?;
         
-        // Get the audio data from the instance buffer
-        let buffer = instance.buffer.lock().unwrap();
-        let audio_data = buffer.clone();
+        // Get the audio data from the instance buffer, leaving out anything
+        // captured after a pause that was never resumed
+        let buffer = instance.buffer.lock_or_recover();
+        let mut audio_data = buffer.clone();
+        if let Some(paused_at_sample) = recording.paused_at_sample {
+            audio_data.truncate(paused_at_sample);
+        }
         
-        // Calculate duration
-        let duration = recording.start_time.elapsed()
-            .unwrap_or_else(|_| Duration::from_secs(0))
-            .as_secs_f64();
+        // Calculate duration, excluding time spent paused
+        let duration = recording.clock.elapsed().as_secs_f64();
         
         // In a real implementation, we would encode the audio data to the requested format
         // For simplicity, we'll just create a dummy file or return the raw data
@@ -681,6 +841,42 @@ impl Microphone {
         }
     }
     
+    /// Pause recording audio from a microphone stream
+    pub async fn pause_recording(stream_id: &str) -> Result<()> {
+        Self::with_recording(stream_id, |instance, recording| {
+            recording.clock.pause()?;
+            recording.paused_at_sample = Some(instance.buffer.lock_or_recover().len());
+            Ok(())
+        })
+    }
+    
+    /// Resume a paused audio recording, discarding audio captured while paused
+    pub async fn resume_recording(stream_id: &str) -> Result<()> {
+        Self::with_recording(stream_id, |instance, recording| {
+            recording.clock.resume()?;
+            if let Some(paused_at_sample) = recording.paused_at_sample.take() {
+                instance.buffer.lock_or_recover().truncate(paused_at_sample);
+            }
+            Ok(())
+        })
+    }
+    
+    /// Run `f` on a microphone stream and its active recording
+    fn with_recording<T>(stream_id: &str, f: impl FnOnce(&MicrophoneInstance, &mut RecordingInstance) -> Result<T>) -> Result<T> {
+        let instances = MICROPHONE_INSTANCES.lock_or_recover();
+        
+        let instance = instances.get(stream_id).ok_or_else(|| {
+            HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
+        })?;
+        
+        let mut recordings = RECORDING_INSTANCES.lock_or_recover();
+        let recording = recordings.get_mut(stream_id).ok_or_else(|| {
+            HardwareError::InvalidOperation("Microphone is not recording".to_string())
+        })?;
+        
+        f(instance, recording)
+    }
+    
     /// Save a recording to a file
     pub async fn save_recording(recording_data: &str, file_path: &str, format: Option<&str>) -> Result<AudioRecordingResult> {
         // Decode the base64 data
@@ -717,14 +913,14 @@ impl Microphone {
     
     /// Get the current audio level from a microphone stream
     pub async fn get_audio_level(stream_id: &str) -> Result<f64> {
-        let instances = MICROPHONE_INSTANCES.lock().unwrap();
+        let instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         let instance = instances.get(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
         })?;
         
         // Calculate the RMS (Root Mean Square) of the audio buffer
-        let buffer = instance.buffer.lock().unwrap();
+        let buffer = instance.buffer.lock_or_recover();
         
         if buffer.is_empty() {
             return Ok(0.0);
@@ -746,29 +942,61 @@ impl Microphone {
     }
     
     /// Apply an audio processor to the microphone stream
-    pub async fn apply_processor(stream_id: &str, processor_name: &str, _options: serde_json::Value) -> Result<()> {
-        let mut instances = MICROPHONE_INSTANCES.lock().unwrap();
+    pub async fn apply_processor(stream_id: &str, processor_name: &str, options: serde_json::Value) -> Result<()> {
+        Self::add_processor(stream_id, processor_name, options)
+    }
+    
+    /// Remove all audio processors from the microphone stream
+    pub async fn remove_processors(stream_id: &str) -> Result<()> {
+        Self::clear_processors(stream_id)
+    }
+    
+    /// Check whether a microphone stream is open
+    pub fn is_active(stream_id: &str) -> bool {
+        MICROPHONE_INSTANCES.lock_or_recover().contains_key(stream_id)
+    }
+    
+    /// Stop every open microphone stream and discard any active recordings
+    pub(crate) fn stop_all() {
+        // Dropping the instances releases the underlying devices
+        MICROPHONE_INSTANCES.lock_or_recover().clear();
+        RECORDING_INSTANCES.lock_or_recover().clear();
+        
+        // Watcher threads exit once their entry is gone
+        DEFAULT_DEVICE_WATCHERS.lock_or_recover().clear();
+    }
+    
+    /// Get the ids of all open microphone streams, sorted
+    pub fn active_streams() -> Vec<String> {
+        let mut ids: Vec<String> = MICROPHONE_INSTANCES.lock_or_recover().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+    
+    fn add_processor(stream_id: &str, processor_name: &str, options: serde_json::Value) -> Result<()> {
+        let mut instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         let instance = instances.get_mut(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
         })?;
         
-        // Add the processor to the list
-        instance.processors.push(processor_name.to_string());
+        let processor = AudioProcessor::from_name(processor_name, &options, instance.config.sample_rate.0, instance.config.channels)?;
+        
+        // Add the processor to the end of the chain
+        instance.processors.lock_or_recover().push(processor);
         
         Ok(())
     }
     
-    /// Remove all audio processors from the microphone stream
-    pub async fn remove_processors(stream_id: &str) -> Result<()> {
-        let mut instances = MICROPHONE_INSTANCES.lock().unwrap();
+    fn clear_processors(stream_id: &str) -> Result<()> {
+        let mut instances = MICROPHONE_INSTANCES.lock_or_recover();
         
         let instance = instances.get_mut(stream_id).ok_or_else(|| {
             HardwareError::InvalidId(format!("Microphone stream not found: {}", stream_id))
         })?;
         
         // Clear all processors
-        instance.processors.clear();
+        instance.processors.lock_or_recover().clear();
         
         Ok(())
     }
@@ -788,4 +1016,278 @@ impl Microphone {
             alternatives: vec!["Hello from SmashLang".to_string()],
         })
     }
+    
+    /// Watch for changes of the system default microphone, returning a watcher ID
+    ///
+    /// Changes are collected with `take_default_device_changes` so apps can
+    /// offer to switch running streams to the new device.
+    pub fn watch_default_device() -> Result<String> {
+        let watcher_id = format!("default_device_watcher_{}", uuid::Uuid::new_v4().to_string());
+        DEFAULT_DEVICE_WATCHERS.lock_or_recover().insert(watcher_id.clone(), Vec::new());
+        
+        let id = watcher_id.clone();
+        std::thread::spawn(move || {
+            let mut previous = default_input_device_id();
+            
+            loop {
+                std::thread::sleep(DEFAULT_DEVICE_POLL_INTERVAL);
+                
+                let current = default_input_device_id();
+                let change = diff_default_device(&previous, &current);
+                
+                match DEFAULT_DEVICE_WATCHERS.lock_or_recover().get_mut(&id) {
+                    Some(changes) => changes.extend(change),
+                    None => break,
+                }
+                
+                previous = current;
+            }
+        });
+        
+        Ok(watcher_id)
+    }
+    
+    /// Stop a watcher returned by `watch_default_device`
+    pub fn unwatch_default_device(watcher_id: &str) -> Result<()> {
+        DEFAULT_DEVICE_WATCHERS.lock_or_recover().remove(watcher_id)
+            .map(|_| ())
+            .ok_or_else(|| HardwareError::InvalidId(format!("Default device watcher not found: {}", watcher_id)))
+    }
+    
+    /// Take the default-device changes seen by a watcher since the last call, in order
+    pub fn take_default_device_changes(watcher_id: &str) -> Result<Vec<DefaultDeviceChange>> {
+        DEFAULT_DEVICE_WATCHERS.lock_or_recover().get_mut(watcher_id)
+            .map(std::mem::take)
+            .ok_or_else(|| HardwareError::InvalidId(format!("Default device watcher not found: {}", watcher_id)))
+    }
+}
+
+/// Compare two snapshots of the default microphone
+fn diff_default_device(previous: &Option<String>, current: &Option<String>) -> Option<DefaultDeviceChange> {
+    if previous == current {
+        return None;
+    }
+    
+    Some(DefaultDeviceChange {
+        previous: previous.clone(),
+        current: current.clone(),
+    })
+}
+
+/// Identify the current default microphone
+fn default_input_device_id() -> Option<String> {
+    // PulseAudio reports the default source directly
+    #[cfg(target_os = "linux")]
+    {
+        if Path::new("/usr/bin/pactl").exists() {
+            if let Ok(output) = std::process::Command::new("pactl").arg("info").output() {
+                if output.status.success() {
+                    if let Some(source) = parse_pactl_default_source(&String::from_utf8_lossy(&output.stdout)) {
+                        return Some(format!("pulse:{}", source));
+                    }
+                }
+            }
+        }
+    }
+    
+    cpal::default_host().default_input_device().and_then(|device| device.name().ok())
+}
+
+/// Extract the `Default Source` from `pactl info` output
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_pactl_default_source(info: &str) -> Option<String> {
+    info.lines()
+        .find_map(|line| line.trim().strip_prefix("Default Source:"))
+        .map(|source| source.trim().to_string())
+        .filter(|source| !source.is_empty())
+}
+
+/// Run captured samples through the stream's processors into its buffer
+fn capture_samples(data: &[f32], processors: &Mutex<Vec<AudioProcessor>>, buffer: &Mutex<Vec<f32>>) {
+    let mut processors = processors.lock_or_recover();
+    
+    // Store the audio data in the buffer, processed if needed
+    let mut buffer = buffer.lock_or_recover();
+    if processors.is_empty() {
+        buffer.extend_from_slice(data);
+    } else {
+        let mut samples = data.to_vec();
+        for processor in processors.iter_mut() {
+            processor.process(&mut samples);
+        }
+        buffer.extend_from_slice(&samples);
+    }
+}
+
+/// Resolve a microphone device id returned by `get_devices`, or the default microphone
+///
+/// Ids are an index into the input devices, `alsa:<card>:<n>` for an ALSA
+/// capture card or `pulse:<source>` for a PulseAudio source. PulseAudio
+/// sources are opened through PulseAudio's ALSA device, which records from
+/// the server's default source.
+fn find_input_device(device_id: Option<&str>) -> Result<cpal::Device> {
+    let host = cpal::default_host();
+    let device_id = match device_id {
+        Some(device_id) => device_id,
+        None => return host.default_input_device()
+            .ok_or_else(|| HardwareError::DeviceError("No default microphone device available".to_string())),
+    };
+    let not_found = || HardwareError::InvalidId(format!("Microphone device not found: {}", device_id));
+    
+    let mut devices = host.input_devices()
+        .map_err(|e| HardwareError::DeviceError(format!("Failed to query microphone devices: {}", e)))?;
+    
+    // cpal names ALSA devices like `hw:CARD=PCH,DEV=0`; match one of the parts
+    let wanted = match device_id.split(':').collect::<Vec<_>>().as_slice() {
+        [index] => {
+            let index = index.parse::<usize>().map_err(|_| not_found())?;
+            return devices.nth(index).ok_or_else(not_found);
+        },
+        ["alsa", card, _] => {
+            let card_id = std::fs::read_to_string(format!("/proc/asound/card{}/id", card)).map_err(|_| not_found())?;
+            format!("CARD={}", card_id.trim())
+        },
+        ["pulse", source] => {
+            let sources = std::process::Command::new("pactl")
+                .args(["list", "short", "sources"])
+                .output()
+                .map(|output| parse_pactl_short_sources(&String::from_utf8_lossy(&output.stdout)))
+                .unwrap_or_default();
+            if !sources.iter().any(|s| s == source) {
+                return Err(not_found());
+            }
+            "pulse".to_string()
+        },
+        _ => return Err(not_found()),
+    };
+    
+    let device = devices.find(|device| {
+        device.name().is_ok_and(|name| name.split([':', ',']).any(|part| part == wanted))
+    });
+    match device {
+        Some(device) => Ok(device),
+        // Without a separate `pulse` device, the default input is PulseAudio
+        None if wanted == "pulse" => host.default_input_device().ok_or_else(not_found),
+        None => Err(not_found()),
+    }
+}
+
+/// Extract the source numbers from `pactl list short sources` output
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_pactl_short_sources(output: &str) -> Vec<String> {
+    output.lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|number| number.parse::<u32>().is_ok())
+        .map(str::to_string)
+        .collect()
+}
+
+impl MediaStream for MicrophoneStream {
+    fn id(&self) -> &str {
+        &self.id
+    }
+    
+    fn stop(&self) -> Result<()> {
+        Microphone::stop(&self.id)
+    }
+    
+    fn is_active(&self) -> bool {
+        Microphone::is_active(&self.id)
+    }
+    
+    fn apply_effect(&self, name: &str, options: serde_json::Value) -> Result<()> {
+        Microphone::add_processor(&self.id, name, options)
+    }
+    
+    fn clear_effects(&self) -> Result<()> {
+        Microphone::clear_processors(&self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_microphone_has_supported_configs() {
+        // Requires a real microphone
+        if !Microphone::is_available() {
+            return;
+        }
+        
+        let configs = futures::executor::block_on(Microphone::get_supported_configs(None)).unwrap();
+        assert!(!configs.is_empty());
+        assert!(configs.iter().all(|config| config.channels > 0 && config.min_sample_rate <= config.max_sample_rate));
+    }
+    
+    #[cfg(feature = "test-devices")]
+    #[test]
+    fn test_virtual_microphone_level() {
+        let options: MicrophoneOptions = serde_json::from_value(serde_json::json!({
+            "sample_rate": 48000,
+            "virtual_signal": { "type": "sine", "frequency": 375.0, "amplitude": 0.4 }
+        })).unwrap();
+        let stream = futures::executor::block_on(Microphone::start(options)).unwrap();
+        
+        // Wait for the level window (1024 samples) to fill
+        let deadline = std::time::Instant::now() + Duration::from_secs(2);
+        while MICROPHONE_INSTANCES.lock_or_recover()[&stream.id].buffer.lock_or_recover().len() < 1024 {
+            assert!(std::time::Instant::now() < deadline, "virtual microphone produced no samples");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        
+        // A sine wave's RMS is its amplitude over sqrt(2)
+        let level = futures::executor::block_on(Microphone::get_audio_level(&stream.id)).unwrap();
+        assert!((level - 0.4 / 2f64.sqrt()).abs() < 0.01, "level {}", level);
+        
+        Microphone::stop(&stream.id).unwrap();
+    }
+    
+    #[test]
+    fn test_default_device_diff() {
+        let headset = Some("pulse:alsa_input.usb-headset".to_string());
+        let builtin = Some("pulse:alsa_input.pci-builtin".to_string());
+        
+        assert_eq!(diff_default_device(&builtin, &builtin), None);
+        assert_eq!(diff_default_device(&None, &None), None);
+        
+        // Plugging in a headset switches the default
+        assert_eq!(diff_default_device(&builtin, &headset), Some(DefaultDeviceChange {
+            previous: builtin.clone(),
+            current: headset.clone(),
+        }));
+        
+        // Losing every input device is reported as a change to none
+        assert_eq!(diff_default_device(&headset, &None), Some(DefaultDeviceChange {
+            previous: headset,
+            current: None,
+        }));
+    }
+    
+    #[test]
+    fn test_parse_pactl_default_source() {
+        let info = "Server Name: pulseaudio\nDefault Sink: alsa_output.pci.analog-stereo\nDefault Source: alsa_input.usb-headset.mono\n";
+        assert_eq!(parse_pactl_default_source(info), Some("alsa_input.usb-headset.mono".to_string()));
+        assert_eq!(parse_pactl_default_source("Default Source: \n"), None);
+        assert_eq!(parse_pactl_default_source("Server Name: pulseaudio"), None);
+    }
+    
+    #[test]
+    fn test_parse_pactl_short_sources() {
+        let output = "0\talsa_output.pci.analog-stereo.monitor\tmodule-alsa-card.c\ts16le 2ch 44100Hz\tSUSPENDED\n\
+                      3\talsa_input.usb-headset.mono\tmodule-alsa-card.c\ts16le 1ch 48000Hz\tRUNNING\n";
+        assert_eq!(parse_pactl_short_sources(output), vec!["0", "3"]);
+        assert!(parse_pactl_short_sources("Connection failure: Connection refused").is_empty());
+    }
+    
+    #[test]
+    fn test_listed_device_ids_resolve() {
+        let devices = futures::executor::block_on(Microphone::get_devices()).unwrap_or_default();
+        for device in devices {
+            assert!(find_input_device(Some(&device.id)).is_ok(), "listed microphone {} does not resolve", device.id);
+        }
+        
+        assert!(matches!(find_input_device(Some("pulse:no-such-source")), Err(HardwareError::InvalidId(_))));
+        assert!(matches!(find_input_device(Some("bogus")), Err(HardwareError::InvalidId(_))));
+    }
 }
@@ -0,0 +1,125 @@
+//! Permission status shared by camera and microphone access
+//!
+//! Unlike `request_permission`, reading the status never prompts the user.
+//! Platforms that cannot report it without prompting return `Unknown`.
+
+use serde::{Deserialize, Serialize};
+
+/// Current permission to access a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionStatus {
+    /// Access is allowed
+    Granted,
+    /// Access was refused
+    Denied,
+    /// The user has not been asked yet
+    Prompt,
+    /// The status cannot be read without prompting
+    Unknown,
+}
+
+impl PermissionStatus {
+    /// Map a Windows privacy `ConsentStore` `Value` entry to a status
+    ///
+    /// A missing entry means the user has never been asked.
+    pub(crate) fn from_consent_store_value(value: Option<&str>) -> Self {
+        match value {
+            Some("Allow") => PermissionStatus::Granted,
+            Some("Deny") => PermissionStatus::Denied,
+            Some(_) => PermissionStatus::Unknown,
+            None => PermissionStatus::Prompt,
+        }
+    }
+}
+
+/// Read the Windows privacy setting for a capability ('webcam', 'microphone')
+#[cfg(target_os = "windows")]
+pub(crate) fn consent_store_status(capability: &str) -> PermissionStatus {
+    use winreg::enums::*;
+    use winreg::RegKey;
+    
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let key = hkcu.open_subkey(format!(
+        "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\{}",
+        capability
+    ));
+    
+    let value: Option<String> = key.ok().and_then(|key| key.get_value("Value").ok());
+    PermissionStatus::from_consent_store_value(value.as_deref())
+}
+
+/// Warn that `device` access may need the user added to a Unix `group`
+#[cfg(any(target_os = "linux", test))]
+pub(crate) fn warn_group_hint(device: &str, group: &str, user: &str) {
+    log::warn!(
+        "{} access may be restricted. You may need to add your user to the '{}' group: \
+         run `sudo usermod -a -G {} {}`, then log out and log back in.",
+        device, group, group, user
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::{Level, Log, Metadata, Record};
+    use std::sync::Mutex;
+    
+    /// Logger keeping every record for inspection
+    struct CaptureLogger {
+        records: Mutex<Vec<(Level, String)>>,
+    }
+    
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+        
+        fn log(&self, record: &Record) {
+            self.records.lock().unwrap().push((record.level(), record.args().to_string()));
+        }
+        
+        fn flush(&self) {}
+    }
+    
+    static CAPTURE: CaptureLogger = CaptureLogger { records: Mutex::new(Vec::new()) };
+    
+    #[test]
+    fn test_permission_status_serialization() {
+        let cases = [
+            (PermissionStatus::Granted, "\"granted\""),
+            (PermissionStatus::Denied, "\"denied\""),
+            (PermissionStatus::Prompt, "\"prompt\""),
+            (PermissionStatus::Unknown, "\"unknown\""),
+        ];
+        
+        for (status, json) in cases {
+            assert_eq!(serde_json::to_string(&status).unwrap(), json);
+            assert_eq!(serde_json::from_str::<PermissionStatus>(json).unwrap(), status);
+        }
+    }
+    
+    #[test]
+    fn test_consent_store_value_mapping() {
+        assert_eq!(PermissionStatus::from_consent_store_value(Some("Allow")), PermissionStatus::Granted);
+        assert_eq!(PermissionStatus::from_consent_store_value(Some("Deny")), PermissionStatus::Denied);
+        assert_eq!(PermissionStatus::from_consent_store_value(None), PermissionStatus::Prompt);
+        assert_eq!(PermissionStatus::from_consent_store_value(Some("Prompt")), PermissionStatus::Unknown);
+    }
+    
+    #[test]
+    fn test_group_hint_logs_warning() {
+        let _ = log::set_logger(&CAPTURE);
+        log::set_max_level(log::LevelFilter::Trace);
+        
+        warn_group_hint("Camera", "video", "alice");
+        
+        let records = CAPTURE.records.lock().unwrap();
+        assert!(records.iter().any(|(level, message)| {
+            *level == Level::Warn
+                && message.starts_with("Camera access may be restricted")
+                && message.contains("'video' group")
+                && message.contains("sudo usermod -a -G video alice")
+        }));
+    }
+}
@@ -70,6 +70,11 @@ pub trait InputDevice: Send + Sync {
     
     /// Get current touch points
     fn get_touch_points(&self) -> Result<Vec<TouchPoint>>;
+    
+    /// Get the active keyboard layout identifier (e.g. "us", "fr"), if it can be determined
+    fn current_layout(&self) -> Option<String> {
+        None
+    }
 }
 
 /// Default implementation for unsupported platforms
@@ -10,6 +10,7 @@ use std::thread;
 
 use crate::error::HardwareError;
 use crate::Result;
+use crate::utils::{MutexExt, RwLockExt};
 use crate::input::{InputEvent, TouchPoint};
 use crate::platform::common::InputDevice;
 use super::get_display_server_type;
@@ -47,7 +48,7 @@ impl LinuxInputDevice {
     
     /// Generate a unique registration ID
     fn generate_registration_id(&self) -> String {
-        let mut counter = REGISTRATION_COUNTER.lock().unwrap();
+        let mut counter = REGISTRATION_COUNTER.lock_or_recover();
         *counter += 1;
         format!("input_registration_{}", *counter)
     }
@@ -102,7 +103,7 @@ impl InputDevice for LinuxInputDevice {
         let registration_id = self.generate_registration_id();
         
         // Store the registration
-        let mut registrations = ACTIVE_REGISTRATIONS.write().unwrap();
+        let mut registrations = ACTIVE_REGISTRATIONS.write_or_recover();
         registrations.insert(registration_id.clone(), device_types.clone());
         
         // In a real implementation, we would set up event listeners for the specified device types
@@ -113,7 +114,7 @@ impl InputDevice for LinuxInputDevice {
     }
     
     fn unregister_events(&self, registration_id: &str) -> Result<bool> {
-        let mut registrations = ACTIVE_REGISTRATIONS.write().unwrap();
+        let mut registrations = ACTIVE_REGISTRATIONS.write_or_recover();
         if registrations.remove(registration_id).is_some() {
             // In a real implementation, we would clean up event listeners
             Ok(true)
@@ -22,6 +22,7 @@ use {
 use crate::error::HardwareError;
 use crate::screen::{ScreenSource, ScreenshotData, RecordingOptions};
 use crate::Result;
+use crate::utils::MutexExt;
 use crate::platform::common::ScreenCapture;
 
 /// iOS implementation of screen capture using ReplayKit
@@ -63,7 +64,7 @@ impl IOSScreenCapture {
             
             // Request permission
             let _: () = msg_send![shared_recorder, requestRecordingPermissionWithHandler:^(BOOL granted) {
-                let mut permission = semaphore_clone.lock().unwrap();
+                let mut permission = semaphore_clone.lock_or_recover();
                 *permission = granted;
             }];
             
@@ -73,7 +74,7 @@ impl IOSScreenCapture {
             
             loop {
                 {
-                    let permission = semaphore.lock().unwrap();
+                    let permission = semaphore.lock_or_recover();
                     if *permission {
                         return Ok(true);
                     }
@@ -297,12 +298,12 @@ impl ScreenCapture for IOSScreenCapture {
                 
                 // Start recording
                 let _: () = msg_send![shared_recorder, startRecordingWithHandler:^(NSError *error) {
-                    let mut result = semaphore_clone.lock().unwrap();
+                    let mut result = semaphore_clone.lock_or_recover();
                     
                     if !error.is_null() {
                         let description: *mut Object = msg_send![error, localizedDescription];
                         let desc_str = NSString::from_retained_ptr(description);
-                        let mut err_msg = error_message_clone.lock().unwrap();
+                        let mut err_msg = error_message_clone.lock_or_recover();
                         *err_msg = desc_str.as_str().to_string();
                         *result = false;
                     } else {
@@ -316,7 +317,7 @@ impl ScreenCapture for IOSScreenCapture {
                 
                 loop {
                     {
-                        let result = semaphore.lock().unwrap();
+                        let result = semaphore.lock_or_recover();
                         if *result {
                             // Recording started successfully
                             // Create recording instance
@@ -331,7 +332,7 @@ impl ScreenCapture for IOSScreenCapture {
                             };
                             
                             // Store in our map
-                            self.recording_instances.lock().unwrap().insert(recorder_id.clone(), recorder_id.clone());
+                            self.recording_instances.lock_or_recover().insert(recorder_id.clone(), recorder_id.clone());
                             
                             return Ok(recorder_id);
                         }
@@ -345,7 +346,7 @@ impl ScreenCapture for IOSScreenCapture {
                 }
                 
                 // If we timed out or got an error
-                let err_msg = error_message.lock().unwrap();
+                let err_msg = error_message.lock_or_recover();
                 if err_msg.is_empty() {
                     Err(HardwareError::DeviceAccessError("Failed to start screen recording (timed out)".to_string()))
                 } else {
@@ -390,12 +391,12 @@ impl ScreenCapture for IOSScreenCapture {
                 
                 // Stop recording and save to file
                 let _: () = msg_send![shared_recorder, stopRecordingWithOutputURL:file_url handler:^(RPPreviewViewController *previewViewController, NSError *error) {
-                    let mut result = semaphore_clone.lock().unwrap();
+                    let mut result = semaphore_clone.lock_or_recover();
                     
                     if !error.is_null() {
                         let description: *mut Object = msg_send![error, localizedDescription];
                         let desc_str = NSString::from_retained_ptr(description);
-                        let mut err_msg = error_message_clone.lock().unwrap();
+                        let mut err_msg = error_message_clone.lock_or_recover();
                         *err_msg = desc_str.as_str().to_string();
                         *result = false;
                     } else {
@@ -409,11 +410,11 @@ impl ScreenCapture for IOSScreenCapture {
                 
                 loop {
                     {
-                        let result = semaphore.lock().unwrap();
+                        let result = semaphore.lock_or_recover();
                         if *result {
                             // Recording stopped successfully
                             // Get recording instance
-                            let mut instances = self.recording_instances.lock().unwrap();
+                            let mut instances = self.recording_instances.lock_or_recover();
                             let instance = match instances.remove(recorder_id) {
                                 Some(instance) => instance,
                                 None => return Err(HardwareError::InvalidParameter(format!("Recording with ID {} not found", recorder_id))),
@@ -443,7 +444,7 @@ impl ScreenCapture for IOSScreenCapture {
                 }
                 
                 // If we timed out or got an error
-                let err_msg = error_message.lock().unwrap();
+                let err_msg = error_message.lock_or_recover();
                 if err_msg.is_empty() {
                     Err(HardwareError::DeviceAccessError("Failed to stop screen recording (timed out)".to_string()))
                 } else {
@@ -463,7 +464,7 @@ impl ScreenCapture for IOSScreenCapture {
         {
             // ReplayKit doesn't support pausing recordings directly
             // We'll just update our internal state
-            let mut instances = self.recording_instances.lock().unwrap();
+            let mut instances = self.recording_instances.lock_or_recover();
             if let Some(instance) = instances.get_mut(recorder_id) {
                 instance.paused = true;
                 Ok(())
@@ -483,7 +484,7 @@ impl ScreenCapture for IOSScreenCapture {
         {
             // ReplayKit doesn't support pausing recordings directly
             // We'll just update our internal state
-            let mut instances = self.recording_instances.lock().unwrap();
+            let mut instances = self.recording_instances.lock_or_recover();
             if let Some(instance) = instances.get_mut(recorder_id) {
                 instance.paused = false;
                 Ok(())
@@ -503,7 +504,7 @@ impl ScreenCapture for IOSScreenCapture {
         {
             // ReplayKit doesn't support markers directly
             // We'll just update our internal state
-            let mut instances = self.recording_instances.lock().unwrap();
+            let mut instances = self.recording_instances.lock_or_recover();
             if let Some(instance) = instances.get_mut(recorder_id) {
                 let elapsed = instance.start_time.elapsed().unwrap_or_default();
                 instance.markers.push(RecordingMarker {
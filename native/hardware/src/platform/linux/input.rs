@@ -3,14 +3,16 @@
 //! This module provides Linux-specific implementations for keyboard, mouse, and touch input
 //! using native Linux APIs and libraries, with support for both X11 and Wayland.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
 use std::sync::{Arc, Mutex, RwLock};
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime};
 use std::thread;
 
 use crate::error::HardwareError;
 use crate::Result;
-use crate::input::{InputEvent, TouchPoint};
+use crate::utils::{MutexExt, RwLockExt};
+use crate::input::{InputEvent, InputEventType, KeyCode, TouchPoint};
 use crate::platform::common::InputDevice;
 use super::DisplayServer;
 use super::get_display_server_type;
@@ -29,8 +31,21 @@ use wayland_client::{Display, GlobalManager};
 lazy_static! {
     static ref REGISTRATION_COUNTER: Arc<Mutex<u64>> = Arc::new(Mutex::new(0));
     static ref ACTIVE_REGISTRATIONS: Arc<RwLock<HashMap<String, Vec<String>>>> = Arc::new(RwLock::new(HashMap::new()));
+    /// Event device nodes a keyboard listener thread is reading
+    static ref KEYBOARD_LISTENERS: Arc<Mutex<HashSet<String>>> = Arc::new(Mutex::new(HashSet::new()));
 }
 
+/// Size of a `struct input_event` read from an evdev device: a `timeval`
+/// followed by the 16-bit type and code and the 32-bit value
+const INPUT_EVENT_SIZE: usize = std::mem::size_of::<usize>() * 2 + 8;
+
+/// evdev event type of key presses and releases
+const EV_KEY: u16 = 1;
+
+/// Bit of the `EV=` capability mask set for devices with key autorepeat,
+/// which tells keyboards apart from power buttons and the like
+const EV_REP_BIT: u32 = 1 << 20;
+
 /// Linux input device implementation
 pub struct LinuxInputDevice {
     display_server: DisplayServer,
@@ -45,7 +60,7 @@ impl LinuxInputDevice {
     
     /// Generate a unique registration ID
     fn generate_registration_id(&self) -> String {
-        let mut counter = REGISTRATION_COUNTER.lock().unwrap();
+        let mut counter = REGISTRATION_COUNTER.lock_or_recover();
         *counter += 1;
         format!("input_registration_{}", *counter)
     }
@@ -107,18 +122,22 @@ impl InputDevice for LinuxInputDevice {
         let registration_id = self.generate_registration_id();
         
         // Store the registration
-        let mut registrations = ACTIVE_REGISTRATIONS.write().unwrap();
-        registrations.insert(registration_id.clone(), device_types.clone());
+        ACTIVE_REGISTRATIONS.write_or_recover().insert(registration_id.clone(), device_types.clone());
         
-        // In a real implementation, we would set up event listeners for the specified device types
-        // For X11, this would involve setting up XInput event listeners
-        // For Wayland, this would involve setting up Wayland input device listeners
+        // Keyboards are read through evdev, which works the same under X11,
+        // Wayland and the console. Mouse and touch events aren't read yet.
+        if device_types.iter().any(|t| t == "keyboard") {
+            let devices = std::fs::read_to_string("/proc/bus/input/devices").unwrap_or_default();
+            for node in keyboard_event_nodes(&devices) {
+                spawn_keyboard_listener(node);
+            }
+        }
         
         Ok(registration_id)
     }
     
     fn unregister_events(&self, registration_id: &str) -> Result<bool> {
-        let mut registrations = ACTIVE_REGISTRATIONS.write().unwrap();
+        let mut registrations = ACTIVE_REGISTRATIONS.write_or_recover();
         if registrations.remove(registration_id).is_some() {
             // In a real implementation, we would clean up event listeners
             Ok(true)
@@ -249,4 +268,265 @@ impl InputDevice for LinuxInputDevice {
         // Linux doesn't provide a standard way to get touch points outside of event handling
         Err(HardwareError::UnsupportedOperation("Touch point retrieval not supported on Linux".to_string()))
     }
+    
+    fn current_layout(&self) -> Option<String> {
+        // An explicit XKB configuration takes precedence on both X11 and Wayland
+        if let Ok(layout) = std::env::var("XKB_DEFAULT_LAYOUT") {
+            if !layout.trim().is_empty() {
+                return Some(layout.trim().to_string());
+            }
+        }
+        
+        if self.display_server == DisplayServer::X11 {
+            if let Ok(output) = std::process::Command::new("setxkbmap").arg("-query").output() {
+                if let Some(layout) = parse_layout_field(&String::from_utf8_lossy(&output.stdout), "layout:") {
+                    return Some(layout);
+                }
+            }
+        }
+        
+        // Fall back to the system-wide layout configured through systemd-localed
+        let output = std::process::Command::new("localectl").arg("status").output().ok()?;
+        parse_layout_field(&String::from_utf8_lossy(&output.stdout), "X11 Layout:")
+    }
+}
+
+/// Event device nodes (e.g. `/dev/input/event3`) of the keyboards listed in
+/// the contents of `/proc/bus/input/devices`
+fn keyboard_event_nodes(devices: &str) -> Vec<String> {
+    devices
+        .split("\n\n")
+        .filter(|block| {
+            block
+                .lines()
+                .find_map(|line| line.strip_prefix("B: EV="))
+                .and_then(|mask| u32::from_str_radix(mask.trim(), 16).ok())
+                .is_some_and(|mask| mask & EV_REP_BIT != 0)
+        })
+        .filter_map(|block| {
+            let handlers = block.lines().find_map(|line| line.strip_prefix("H: Handlers="))?;
+            let handlers: Vec<&str> = handlers.split_whitespace().collect();
+            if !handlers.contains(&"kbd") {
+                return None;
+            }
+            handlers
+                .iter()
+                .find(|handler| handler.starts_with("event"))
+                .map(|handler| format!("/dev/input/{}", handler))
+        })
+        .collect()
+}
+
+/// Read key events from the evdev device at `node` on a new thread, passing
+/// each one to `crate::input::receive_event`, for as long as some
+/// registration listens to the keyboard
+///
+/// Devices that can't be opened, usually for lack of permission on
+/// `/dev/input`, are skipped, as are devices that already have a listener.
+fn spawn_keyboard_listener(node: String) {
+    if !KEYBOARD_LISTENERS.lock_or_recover().insert(node.clone()) {
+        return;
+    }
+    let mut device = match std::fs::File::open(&node) {
+        Ok(device) => device,
+        Err(_) => {
+            KEYBOARD_LISTENERS.lock_or_recover().remove(&node);
+            return;
+        }
+    };
+    
+    thread::spawn(move || {
+        let mut modifiers = HashMap::new();
+        let mut buffer = [0u8; INPUT_EVENT_SIZE];
+        
+        // A read blocks until the next event, so the listener only notices
+        // the last keyboard registration is gone at the next key press
+        while device.read_exact(&mut buffer).is_ok() {
+            let listening = ACTIVE_REGISTRATIONS
+                .read_or_recover()
+                .values()
+                .any(|device_types| device_types.iter().any(|t| t == "keyboard"));
+            if !listening {
+                break;
+            }
+            
+            let (event_type, code, value) = decode_input_event(&buffer);
+            if event_type != EV_KEY {
+                continue;
+            }
+            if let Some(event) = key_event(code, value, &mut modifiers) {
+                crate::input::receive_event(&event);
+            }
+        }
+        
+        KEYBOARD_LISTENERS.lock_or_recover().remove(&node);
+    });
+}
+
+/// Split a raw `struct input_event` into its type, code and value
+fn decode_input_event(buffer: &[u8; INPUT_EVENT_SIZE]) -> (u16, u16, i32) {
+    let fields = &buffer[INPUT_EVENT_SIZE - 8..];
+    (
+        u16::from_ne_bytes([fields[0], fields[1]]),
+        u16::from_ne_bytes([fields[2], fields[3]]),
+        i32::from_ne_bytes([fields[4], fields[5], fields[6], fields[7]]),
+    )
+}
+
+/// Build the event for an evdev key press (`value` 1) or release (0),
+/// updating the pressed state of the modifier keys
+///
+/// Autorepeats (2) and keys without a name are skipped.
+fn key_event(code: u16, value: i32, modifiers: &mut HashMap<String, bool>) -> Option<InputEvent> {
+    let key = evdev_key(code)?;
+    if matches!(key.key.as_str(), "ctrl" | "shift" | "alt" | "meta") && value != 2 {
+        modifiers.insert(key.key.clone(), value == 1);
+    }
+    
+    let event_type = match value {
+        0 => InputEventType::KeyUp(key),
+        1 => InputEventType::KeyDown(key),
+        _ => return None,
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    
+    Some(InputEvent { event_type, timestamp, modifiers: modifiers.clone() })
+}
+
+/// Key code and name of an evdev key code (US QWERTY layout)
+fn evdev_key(code: u16) -> Option<KeyCode> {
+    let key_code = |code: String, key: String, location: &str| KeyCode {
+        code,
+        key,
+        location: location.to_string(),
+    };
+    
+    // Runs of consecutive codes: the number row and the three letter rows
+    const ROWS: &[(u16, &str)] = &[(2, "1234567890"), (16, "qwertyuiop"), (30, "asdfghjkl"), (44, "zxcvbnm")];
+    for (first, keys) in ROWS {
+        if let Some(c) = code.checked_sub(*first).and_then(|i| keys.chars().nth(i as usize)) {
+            let dom_code = if c.is_ascii_digit() { format!("Digit{}", c) } else { format!("Key{}", c.to_ascii_uppercase()) };
+            return Some(key_code(dom_code, c.to_string(), "standard"));
+        }
+    }
+    
+    let function_key = |n: u16| Some(key_code(format!("F{}", n), format!("f{}", n), "standard"));
+    let (dom_code, key, location) = match code {
+        59..=68 => return function_key(code - 58),
+        87 | 88 => return function_key(code - 76),
+        183..=194 => return function_key(code - 170),
+        1 => ("Escape", "escape", "standard"),
+        12 => ("Minus", "-", "standard"),
+        13 => ("Equal", "=", "standard"),
+        14 => ("Backspace", "backspace", "standard"),
+        15 => ("Tab", "tab", "standard"),
+        26 => ("BracketLeft", "[", "standard"),
+        27 => ("BracketRight", "]", "standard"),
+        28 => ("Enter", "enter", "standard"),
+        29 => ("ControlLeft", "ctrl", "left"),
+        39 => ("Semicolon", ";", "standard"),
+        40 => ("Quote", "'", "standard"),
+        41 => ("Backquote", "`", "standard"),
+        42 => ("ShiftLeft", "shift", "left"),
+        43 => ("Backslash", "\\", "standard"),
+        51 => ("Comma", ",", "standard"),
+        52 => ("Period", ".", "standard"),
+        53 => ("Slash", "/", "standard"),
+        54 => ("ShiftRight", "shift", "right"),
+        56 => ("AltLeft", "alt", "left"),
+        57 => ("Space", "space", "standard"),
+        97 => ("ControlRight", "ctrl", "right"),
+        99 => ("PrintScreen", "printscreen", "standard"),
+        100 => ("AltRight", "alt", "right"),
+        102 => ("Home", "home", "standard"),
+        103 => ("ArrowUp", "up", "standard"),
+        104 => ("PageUp", "pageup", "standard"),
+        105 => ("ArrowLeft", "left", "standard"),
+        106 => ("ArrowRight", "right", "standard"),
+        107 => ("End", "end", "standard"),
+        108 => ("ArrowDown", "down", "standard"),
+        109 => ("PageDown", "pagedown", "standard"),
+        110 => ("Insert", "insert", "standard"),
+        111 => ("Delete", "delete", "standard"),
+        125 => ("MetaLeft", "meta", "left"),
+        126 => ("MetaRight", "meta", "right"),
+        _ => return None,
+    };
+    Some(key_code(dom_code.to_string(), key.to_string(), location))
+}
+
+/// Extract the first layout from a `<field> <value>` line of `setxkbmap`/`localectl` output
+fn parse_layout_field(output: &str, field: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(field))
+        .and_then(|value| value.split(',').next())
+        .map(|layout| layout.trim().to_string())
+        .filter(|layout| !layout.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_layout_field() {
+        let setxkbmap = "rules:      evdev\nmodel:      pc105\nlayout:     fr,us\n";
+        assert_eq!(parse_layout_field(setxkbmap, "layout:"), Some("fr".to_string()));
+        
+        let localectl = "   System Locale: LANG=en_US.UTF-8\n       X11 Layout: de\n";
+        assert_eq!(parse_layout_field(localectl, "X11 Layout:"), Some("de".to_string()));
+        
+        assert_eq!(parse_layout_field("layout:\n", "layout:"), None);
+    }
+    
+    #[test]
+    fn test_keyboard_event_nodes() {
+        let devices = "I: Bus=0019 Vendor=0000 Product=0001 Version=0000\n\
+                       N: Name=\"Power Button\"\n\
+                       H: Handlers=kbd event0 \n\
+                       B: EV=3\n\
+                       \n\
+                       I: Bus=0011 Vendor=0001 Product=0001 Version=ab41\n\
+                       N: Name=\"AT Translated Set 2 keyboard\"\n\
+                       H: Handlers=sysrq kbd leds event3 \n\
+                       B: EV=120013\n\
+                       \n\
+                       I: Bus=0011 Vendor=0002 Product=0013 Version=0006\n\
+                       N: Name=\"VirtualPS/2 VMware VMMouse\"\n\
+                       H: Handlers=mouse0 event4 \n\
+                       B: EV=b\n";
+        assert_eq!(keyboard_event_nodes(devices), vec!["/dev/input/event3".to_string()]);
+    }
+    
+    #[test]
+    fn test_key_event_tracks_modifiers() {
+        let mut buffer = [0u8; INPUT_EVENT_SIZE];
+        buffer[INPUT_EVENT_SIZE - 8..INPUT_EVENT_SIZE - 6].copy_from_slice(&EV_KEY.to_ne_bytes());
+        buffer[INPUT_EVENT_SIZE - 6..INPUT_EVENT_SIZE - 4].copy_from_slice(&67u16.to_ne_bytes());
+        buffer[INPUT_EVENT_SIZE - 4..].copy_from_slice(&1i32.to_ne_bytes());
+        assert_eq!(decode_input_event(&buffer), (EV_KEY, 67, 1));
+        
+        // Left Ctrl down, then F9 down: the F9 event carries the modifier
+        let mut modifiers = HashMap::new();
+        assert!(key_event(29, 1, &mut modifiers).is_some());
+        let event = key_event(67, 1, &mut modifiers).unwrap();
+        match &event.event_type {
+            InputEventType::KeyDown(key) => assert_eq!((key.code.as_str(), key.key.as_str()), ("F9", "f9")),
+            other => panic!("expected a key down event, got {:?}", other),
+        }
+        assert_eq!(event.modifiers.get("ctrl"), Some(&true));
+        
+        // Autorepeats are skipped and releasing Ctrl clears it
+        assert!(key_event(67, 2, &mut modifiers).is_none());
+        assert!(matches!(key_event(29, 0, &mut modifiers).map(|e| e.event_type), Some(InputEventType::KeyUp(_))));
+        assert_eq!(modifiers.get("ctrl"), Some(&false));
+        
+        assert_eq!(evdev_key(30).map(|key| key.key), Some("a".to_string()));
+        assert_eq!(evdev_key(88).map(|key| key.key), Some("f12".to_string()));
+        assert!(evdev_key(0).is_none());
+    }
 }
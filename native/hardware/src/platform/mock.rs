@@ -0,0 +1,167 @@
+//! Mock screen for tests and headless CI
+//!
+//! With the `test-devices` feature enabled, `Screen` captures from this
+//! single synthetic display instead of the platform backend. The displayed
+//! image is configured with `set_mock_screen`.
+
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+use crate::error::HardwareError;
+use crate::imaging::{encode_image, ImageFormat};
+use crate::screen::{RecordingOptions, ScreenSource, ScreenshotData};
+use crate::platform::common::ScreenCapture;
+use crate::Result;
+use crate::utils::MutexExt;
+
+/// Source id of the mock display
+pub const MOCK_SCREEN_ID: &str = "mock:0";
+
+/// Image shown on the mock display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MockPattern {
+    /// Red grows left to right, green top to bottom, blue is constant
+    Gradient,
+    /// Black and white squares of `size` pixels
+    Checkerboard { size: u32 },
+    /// A single color
+    Solid { color: [u8; 3] },
+}
+
+/// Size and pattern of the mock display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockScreen {
+    pub width: u32,
+    pub height: u32,
+    pub pattern: MockPattern,
+}
+
+impl Default for MockScreen {
+    fn default() -> Self {
+        MockScreen {
+            width: 640,
+            height: 480,
+            pattern: MockPattern::Gradient,
+        }
+    }
+}
+
+impl MockScreen {
+    /// Color of the pixel at (`x`, `y`)
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 3] {
+        match self.pattern {
+            MockPattern::Gradient => [
+                (x * 255 / (self.width - 1).max(1)) as u8,
+                (y * 255 / (self.height - 1).max(1)) as u8,
+                128,
+            ],
+            MockPattern::Checkerboard { size } => {
+                let size = size.max(1);
+                if (x / size + y / size) % 2 == 0 { [0, 0, 0] } else { [255, 255, 255] }
+            }
+            MockPattern::Solid { color } => color,
+        }
+    }
+    
+    /// Render the whole display
+    pub fn render(&self) -> image::RgbImage {
+        image::RgbImage::from_fn(self.width.max(1), self.height.max(1), |x, y| image::Rgb(self.pixel(x, y)))
+    }
+}
+
+lazy_static! {
+    static ref MOCK_SCREEN: Arc<Mutex<MockScreen>> = Arc::new(Mutex::new(MockScreen::default()));
+}
+
+/// Change what the mock display shows
+pub fn set_mock_screen(screen: MockScreen) {
+    *MOCK_SCREEN.lock_or_recover() = screen;
+}
+
+/// Screen capture backend serving the mock display
+#[derive(Default)]
+pub struct MockScreenCapture;
+
+impl MockScreenCapture {
+    pub fn new() -> Self {
+        MockScreenCapture
+    }
+    
+    fn check_source(source_id: Option<&str>) -> Result<()> {
+        match source_id {
+            Some(id) if id != MOCK_SCREEN_ID => Err(HardwareError::InvalidId(format!("Screen source not found: {}", id))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl ScreenCapture for MockScreenCapture {
+    async fn is_available(&self) -> bool {
+        true
+    }
+    
+    async fn request_permission(&self) -> Result<bool> {
+        Ok(true)
+    }
+    
+    async fn get_sources(&self, source_type: Option<&str>) -> Result<Vec<ScreenSource>> {
+        if source_type.map_or(false, |source_type| source_type != "screen") {
+            return Ok(Vec::new());
+        }
+        
+        Ok(vec![ScreenSource {
+            id: MOCK_SCREEN_ID.to_string(),
+            name: "Mock Screen".to_string(),
+            source_type: "screen".to_string(),
+            thumbnail: None,
+        }])
+    }
+    
+    async fn take_screenshot(&self, source_id: Option<&str>) -> Result<ScreenshotData> {
+        Self::check_source(source_id)?;
+        
+        let img = MOCK_SCREEN.lock_or_recover().render();
+        let data = encode_image(&img, ImageFormat::Png, 1.0)?;
+        
+        Ok(ScreenshotData {
+            data: base64::encode(&data),
+            width: img.width(),
+            height: img.height(),
+            format: ImageFormat::Png.name().to_string(),
+        })
+    }
+    
+    async fn save_screenshot(&self, source_id: Option<&str>, file_path: &str, format: Option<&str>) -> Result<String> {
+        Self::check_source(source_id)?;
+        
+        let format = ImageFormat::resolve(format, file_path).unwrap_or(ImageFormat::Png);
+        let img = MOCK_SCREEN.lock_or_recover().render();
+        let data = encode_image(&img, format, 1.0)?;
+        
+        std::fs::write(file_path, &data)
+            .map_err(|e| HardwareError::IoError(format!("Failed to save screenshot: {}", e)))?;
+        
+        Ok(file_path.to_string())
+    }
+    
+    async fn start_recording(&self, _source_id: Option<&str>, _options: Option<RecordingOptions>) -> Result<String> {
+        Err(HardwareError::UnsupportedOperation("The mock screen does not support recording".to_string()))
+    }
+    
+    async fn stop_recording(&self, _recording_id: &str) -> Result<String> {
+        Err(HardwareError::UnsupportedOperation("The mock screen does not support recording".to_string()))
+    }
+    
+    async fn pause_recording(&self, _recording_id: &str) -> Result<bool> {
+        Err(HardwareError::UnsupportedOperation("The mock screen does not support recording".to_string()))
+    }
+    
+    async fn resume_recording(&self, _recording_id: &str) -> Result<bool> {
+        Err(HardwareError::UnsupportedOperation("The mock screen does not support recording".to_string()))
+    }
+    
+    async fn add_marker(&self, _recording_id: &str, _marker_name: &str) -> Result<bool> {
+        Err(HardwareError::UnsupportedOperation("The mock screen does not support recording".to_string()))
+    }
+}
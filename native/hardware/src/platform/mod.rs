@@ -22,3 +22,7 @@ pub mod ios;
 
 // Common platform-agnostic traits and utilities
 pub mod common;
+
+// Synthetic devices for tests and headless CI
+#[cfg(feature = "test-devices")]
+pub mod mock;
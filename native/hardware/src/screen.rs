@@ -12,7 +12,12 @@ use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
 
 use crate::error::HardwareError;
+use crate::imaging::{
+    crop_image, data_uri, decode_image, encode_image, resize_image, strip_data_uri, transcode,
+    write_thumbnail, CropRegion, ImageFormat, ResizeOptions,
+};
 use crate::Result;
+use crate::utils::{base64_to_bytes, MutexExt};
 use crate::platform;
 use crate::platform::common::ScreenCapture;
 
@@ -33,7 +38,7 @@ pub struct ScreenSource {
 /// Screenshot data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScreenshotData {
-    /// Base64-encoded image data
+    /// Base64-encoded image data, or a data URI if requested
     pub data: String,
     /// Width of the image
     pub width: u32,
@@ -43,6 +48,69 @@ pub struct ScreenshotData {
     pub format: String,
 }
 
+/// Options for taking and saving a screenshot
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScreenshotOptions {
+    /// Image format; defaults to the file extension
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Longest side, in pixels, of a thumbnail to write alongside the screenshot
+    #[serde(default)]
+    pub thumbnail: Option<u32>,
+    /// Return captured data as a `data:image/...;base64,` URI instead of raw base64
+    #[serde(default)]
+    pub as_data_uri: bool,
+    /// Crop captured data to this region before any resize
+    #[serde(default)]
+    pub crop: Option<CropRegion>,
+    /// Resize captured data to these dimensions
+    #[serde(default)]
+    pub resize: Option<ResizeOptions>,
+}
+
+impl ScreenshotData {
+    /// Replace the raw base64 data with a data URI for the screenshot's format
+    pub fn into_data_uri(mut self) -> Self {
+        if !self.data.starts_with("data:") {
+            self.data = data_uri(&self.format, &self.data);
+        }
+        self
+    }
+    
+    /// Resize the screenshot, re-encoding it in the same format
+    pub fn resized(self, options: &ResizeOptions) -> Result<Self> {
+        self.transformed(|img| resize_image(img, options))
+    }
+    
+    /// Crop the screenshot to a region, re-encoding it in the same format
+    pub fn cropped(self, region: &CropRegion) -> Result<Self> {
+        self.transformed(|img| crop_image(img, region))
+    }
+    
+    fn transformed(mut self, transform: impl FnOnce(&image::RgbImage) -> Result<image::RgbImage>) -> Result<Self> {
+        let img = decode_image(&base64_to_bytes(strip_data_uri(&self.data))?)?;
+        let img = transform(&img)?;
+        let format = ImageFormat::from_name(&self.format).unwrap_or(ImageFormat::Png);
+        let data = base64::encode(encode_image(&img, format, SCREENSHOT_QUALITY)?);
+        
+        self.data = if self.data.starts_with("data:") { data_uri(format.name(), &data) } else { data };
+        self.width = img.width();
+        self.height = img.height();
+        self.format = format.name().to_string();
+        Ok(self)
+    }
+}
+
+/// Result of saving a screenshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScreenshotSaveResult {
+    /// Path where the screenshot was saved
+    pub path: String,
+    /// Path of the thumbnail, if one was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_path: Option<String>,
+}
+
 /// Recording options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecordingOptions {
@@ -64,6 +132,14 @@ pub struct RecordingOptions {
     pub quality: Option<f32>,
 }
 
+/// Quality used when encoding screenshots to lossy formats
+const SCREENSHOT_QUALITY: f32 = 0.9;
+
+// Recordings started through this module, keyed by id with their start time
+lazy_static! {
+    static ref ACTIVE_RECORDINGS: Arc<Mutex<HashMap<String, SystemTime>>> = Arc::new(Mutex::new(HashMap::new()));
+}
+
 /// Screen API for SmashLang
 pub struct Screen;
 
@@ -92,22 +168,89 @@ impl Screen {
         screen_capture.take_screenshot(source_id).await
     }
     
+    /// Take a screenshot, cropping and resizing it if `options.crop` /
+    /// `options.resize` are set and returning its data as a data URI if
+    /// `options.as_data_uri` is set
+    pub async fn take_screenshot_with_options(source_id: Option<&str>, options: &ScreenshotOptions) -> Result<ScreenshotData> {
+        let mut screenshot = Self::take_screenshot(source_id).await?;
+        
+        if let Some(region) = &options.crop {
+            screenshot = screenshot.cropped(region)?;
+        }
+        
+        if let Some(resize) = &options.resize {
+            screenshot = screenshot.resized(resize)?;
+        }
+        
+        if options.as_data_uri {
+            Ok(screenshot.into_data_uri())
+        } else {
+            Ok(screenshot)
+        }
+    }
+    
     /// Save a screenshot to a file
+    ///
+    /// The format is taken from `format`, or else from the file extension.
+    /// WebP and AVIF are encoded here (behind the `webp` / `avif` features);
+    /// other formats are written by the platform backend.
     pub async fn save_screenshot(source_id: Option<&str>, file_path: &str, format: Option<&str>) -> Result<String> {
+        if let Some(image_format @ (ImageFormat::WebP | ImageFormat::Avif)) = ImageFormat::resolve(format, file_path) {
+            let screenshot = Self::take_screenshot(source_id).await?;
+            let data = transcode(base64_to_bytes(strip_data_uri(&screenshot.data))?, image_format, SCREENSHOT_QUALITY)?;
+            
+            std::fs::write(file_path, &data)
+                .map_err(|e| HardwareError::IoError(format!("Failed to save screenshot: {}", e)))?;
+            
+            return Ok(file_path.to_string());
+        }
+        
         let screen_capture = create_screen_capture();
         screen_capture.save_screenshot(source_id, file_path, format).await
     }
     
+    /// Save a screenshot to a file, writing a `*_thumb.*` thumbnail next to
+    /// it if `options.thumbnail` is set
+    pub async fn save_screenshot_with_options(source_id: Option<&str>, file_path: &str, options: &ScreenshotOptions) -> Result<ScreenshotSaveResult> {
+        let path = Self::save_screenshot(source_id, file_path, options.format.as_deref()).await?;
+        
+        let thumbnail_path = match options.thumbnail {
+            Some(max_dimension) => Some(write_thumbnail(&path, max_dimension, SCREENSHOT_QUALITY)?),
+            None => None,
+        };
+        
+        Ok(ScreenshotSaveResult { path, thumbnail_path })
+    }
+    
     /// Start recording the screen
     pub async fn start_recording(source_id: Option<&str>, options: Option<RecordingOptions>) -> Result<String> {
         let screen_capture = create_screen_capture();
-        screen_capture.start_recording(source_id, options).await
+        let recording_id = screen_capture.start_recording(source_id, options).await?;
+        
+        ACTIVE_RECORDINGS.lock_or_recover().insert(recording_id.clone(), SystemTime::now());
+        
+        Ok(recording_id)
     }
     
     /// Stop recording the screen
     pub async fn stop_recording(recording_id: &str) -> Result<String> {
         let screen_capture = create_screen_capture();
-        screen_capture.stop_recording(recording_id).await
+        let path = screen_capture.stop_recording(recording_id).await?;
+        
+        ACTIVE_RECORDINGS.lock_or_recover().remove(recording_id);
+        
+        Ok(path)
+    }
+    
+    /// Stop every recording started through this module
+    pub(crate) async fn stop_all_recordings() {
+        let recording_ids: Vec<String> = ACTIVE_RECORDINGS.lock_or_recover().drain().map(|(id, _)| id).collect();
+        
+        let screen_capture = create_screen_capture();
+        for recording_id in recording_ids {
+            // The recording may already have finished on the platform side
+            let _ = screen_capture.stop_recording(&recording_id).await;
+        }
     }
     
     /// Pause recording the screen
@@ -130,6 +273,15 @@ impl Screen {
 }
 
 /// Create a platform-specific screen capture implementation
+///
+/// With the `test-devices` feature this is always the mock screen.
+#[cfg(feature = "test-devices")]
+fn create_screen_capture() -> Box<dyn ScreenCapture> {
+    Box::new(platform::mock::MockScreenCapture::new())
+}
+
+/// Create a platform-specific screen capture implementation
+#[cfg(not(feature = "test-devices"))]
 fn create_screen_capture() -> Box<dyn ScreenCapture> {
     #[cfg(target_os = "android")]
     {
@@ -156,3 +308,72 @@ fn create_screen_capture() -> Box<dyn ScreenCapture> {
         Box::new(platform::common::DefaultScreenCapture::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imaging::ResizeMode;
+    
+    #[test]
+    fn test_png_screenshot_as_data_uri() {
+        let png = encode_image(&image::RgbImage::new(2, 2), ImageFormat::Png, 0.9).unwrap();
+        let screenshot = ScreenshotData {
+            data: base64::encode(&png),
+            width: 2,
+            height: 2,
+            format: "png".to_string(),
+        };
+        
+        let screenshot = screenshot.into_data_uri();
+        assert!(screenshot.data.starts_with("data:image/png;base64,"));
+        assert_eq!(base64::decode(strip_data_uri(&screenshot.data)).unwrap(), png);
+        
+        // Converting twice keeps a single prefix
+        assert_eq!(screenshot.clone().into_data_uri().data, screenshot.data);
+    }
+    
+    #[cfg(feature = "test-devices")]
+    #[test]
+    fn test_crop_mock_screen() {
+        use crate::platform::mock::{set_mock_screen, MockPattern, MockScreen};
+        
+        let screen = MockScreen { width: 64, height: 48, pattern: MockPattern::Gradient };
+        set_mock_screen(screen.clone());
+        
+        let sources = futures::executor::block_on(Screen::get_sources(None)).unwrap();
+        assert_eq!(sources.len(), 1);
+        
+        let options = ScreenshotOptions {
+            crop: Some(CropRegion { x: 16, y: 8, width: 10, height: 4 }),
+            ..Default::default()
+        };
+        let screenshot = futures::executor::block_on(Screen::take_screenshot_with_options(Some(&sources[0].id), &options)).unwrap();
+        assert_eq!((screenshot.width, screenshot.height), (10, 4));
+        
+        let img = decode_image(&base64::decode(&screenshot.data).unwrap()).unwrap();
+        assert_eq!(img.get_pixel(0, 0).0, screen.pixel(16, 8));
+        assert_eq!(img.get_pixel(9, 3).0, screen.pixel(25, 11));
+        
+        // The gradient encodes the source coordinates
+        assert_eq!(screen.pixel(16, 8), [(16 * 255 / 63) as u8, (8 * 255 / 47) as u8, 128]);
+    }
+    
+    #[test]
+    fn test_resized_screenshot_matches_requested_dimensions() {
+        let png = encode_image(&image::RgbImage::new(320, 240), ImageFormat::Png, 0.9).unwrap();
+        let screenshot = ScreenshotData {
+            data: base64::encode(&png),
+            width: 320,
+            height: 240,
+            format: "png".to_string(),
+        };
+        
+        for mode in [ResizeMode::Fit, ResizeMode::Fill, ResizeMode::Stretch] {
+            let resized = screenshot.clone().resized(&ResizeOptions { width: 160, height: 90, mode }).unwrap();
+            assert_eq!((resized.width, resized.height), (160, 90));
+            
+            let img = decode_image(&base64::decode(&resized.data).unwrap()).unwrap();
+            assert_eq!(img.dimensions(), (160, 90));
+        }
+    }
+}
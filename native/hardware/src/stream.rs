@@ -0,0 +1,226 @@
+//! Shared media stream abstraction for SmashLang hardware interfaces
+//!
+//! Camera and microphone streams are both identified by an id and support
+//! start/stop and a stack of named effects (filters for video, processors for
+//! audio). The `MediaStream` trait lets callers manage either kind uniformly.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::error::HardwareError;
+use crate::microphone::Microphone;
+use crate::Result;
+
+/// A media stream that can be stopped and have effects applied to it
+pub trait MediaStream: Send {
+    /// Unique identifier of the stream
+    fn id(&self) -> &str;
+
+    /// Stop the stream and release the underlying device
+    fn stop(&self) -> Result<()>;
+
+    /// Whether the stream is still open
+    fn is_active(&self) -> bool;
+
+    /// Apply a named effect (camera filter or microphone processor)
+    fn apply_effect(&self, name: &str, options: serde_json::Value) -> Result<()>;
+
+    /// Remove all effects from the stream
+    fn clear_effects(&self) -> Result<()>;
+}
+
+/// Summary of an open media stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveStream {
+    /// Unique identifier of the stream
+    pub id: String,
+    /// Kind of stream ('camera' or 'microphone')
+    pub kind: String,
+}
+
+/// List every open camera and microphone stream
+pub fn active_streams() -> Vec<ActiveStream> {
+    let cameras = Camera::active_streams().into_iter().map(|id| ActiveStream {
+        id,
+        kind: "camera".to_string(),
+    });
+    let microphones = Microphone::active_streams().into_iter().map(|id| ActiveStream {
+        id,
+        kind: "microphone".to_string(),
+    });
+
+    cameras.chain(microphones).collect()
+}
+
+/// Elapsed time of a recording, excluding the intervals it was paused
+#[derive(Debug, Clone)]
+pub(crate) struct RecordingClock {
+    started: Instant,
+    paused_since: Option<Instant>,
+    paused_total: Duration,
+}
+
+impl RecordingClock {
+    /// Start a clock running from now
+    pub(crate) fn start() -> Self {
+        Self::start_at(Instant::now())
+    }
+
+    fn start_at(now: Instant) -> Self {
+        RecordingClock {
+            started: now,
+            paused_since: None,
+            paused_total: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+
+    pub(crate) fn pause(&mut self) -> Result<()> {
+        self.pause_at(Instant::now())
+    }
+
+    fn pause_at(&mut self, now: Instant) -> Result<()> {
+        if self.is_paused() {
+            return Err(HardwareError::InvalidOperation("Recording is already paused".to_string()));
+        }
+
+        self.paused_since = Some(now);
+        Ok(())
+    }
+
+    pub(crate) fn resume(&mut self) -> Result<()> {
+        self.resume_at(Instant::now())
+    }
+
+    fn resume_at(&mut self, now: Instant) -> Result<()> {
+        let paused_since = self.paused_since.take().ok_or_else(|| {
+            HardwareError::InvalidOperation("Recording is not paused".to_string())
+        })?;
+
+        self.paused_total += now.saturating_duration_since(paused_since);
+        Ok(())
+    }
+
+    /// Recorded time so far, not counting any pauses
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.elapsed_at(Instant::now())
+    }
+
+    fn elapsed_at(&self, now: Instant) -> Duration {
+        let end = self.paused_since.unwrap_or(now);
+        end.saturating_duration_since(self.started).saturating_sub(self.paused_total)
+    }
+}
+
+/// Check that an effect name is one the stream kind supports
+pub(crate) fn validate_effect(kind: &str, name: &str, supported: &[&str]) -> Result<()> {
+    if name.trim().is_empty() {
+        return Err(HardwareError::InvalidParameter(format!("{} effect name must not be empty", kind)));
+    }
+
+    if !supported.contains(&name) {
+        return Err(HardwareError::InvalidParameter(format!(
+            "Unsupported {} effect '{}' (expected one of: {})",
+            kind,
+            name,
+            supported.join(", ")
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraOptions;
+    use crate::microphone::MicrophoneOptions;
+
+    #[test]
+    fn test_validate_effect() {
+        assert!(validate_effect("camera", "sepia", &["grayscale", "sepia"]).is_ok());
+        assert!(matches!(
+            validate_effect("camera", "", &["sepia"]),
+            Err(HardwareError::InvalidParameter(_))
+        ));
+
+        let err = validate_effect("microphone", "reverb", &["noise_suppression"]).unwrap_err();
+        assert!(err.to_string().contains("reverb"));
+        assert!(err.to_string().contains("noise_suppression"));
+    }
+
+    #[test]
+    fn test_recording_clock_excludes_paused_time() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut clock = RecordingClock::start_at(start);
+
+        // Record 2s, pause for 5s, then record another 3s
+        clock.pause_at(at(2)).unwrap();
+        assert_eq!(clock.elapsed_at(at(4)), Duration::from_secs(2));
+        clock.resume_at(at(7)).unwrap();
+        assert_eq!(clock.elapsed_at(at(10)), Duration::from_secs(5));
+
+        // A second pause still at the end counts up to when it started
+        clock.pause_at(at(12)).unwrap();
+        assert_eq!(clock.elapsed_at(at(20)), Duration::from_secs(7));
+
+        assert!(matches!(clock.pause_at(at(21)), Err(HardwareError::InvalidOperation(_))));
+        clock.resume_at(at(22)).unwrap();
+        assert!(matches!(clock.resume_at(at(23)), Err(HardwareError::InvalidOperation(_))));
+    }
+
+    #[test]
+    fn test_streams_stop_uniformly() {
+        // Requires a real camera and microphone
+        if !Camera::is_available() || !Microphone::is_available() {
+            return;
+        }
+
+        let camera_options: CameraOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        let microphone_options: MicrophoneOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+
+        let streams: Vec<Box<dyn MediaStream>> = vec![
+            Box::new(futures::executor::block_on(Camera::start(camera_options)).unwrap()),
+            Box::new(futures::executor::block_on(Microphone::start(microphone_options)).unwrap()),
+        ];
+
+        for stream in &streams {
+            assert!(stream.is_active(), "stream {} should be active", stream.id());
+            stream.stop().unwrap();
+            assert!(!stream.is_active(), "stream {} should be stopped", stream.id());
+        }
+    }
+
+    #[test]
+    fn test_active_streams_tracks_open_streams() {
+        // Requires a real microphone
+        if !Microphone::is_available() {
+            return;
+        }
+
+        let options: MicrophoneOptions = serde_json::from_value(serde_json::json!({})).unwrap();
+        let first = futures::executor::block_on(Microphone::start(options.clone())).unwrap();
+        let second = futures::executor::block_on(Microphone::start(options)).unwrap();
+
+        let ids = Microphone::active_streams();
+        assert!(ids.contains(&first.id));
+        assert!(ids.contains(&second.id));
+        assert!(active_streams().contains(&ActiveStream {
+            id: first.id.clone(),
+            kind: "microphone".to_string(),
+        }));
+
+        Microphone::stop(&first.id).unwrap();
+        let ids = Microphone::active_streams();
+        assert!(!ids.contains(&first.id));
+        assert!(ids.contains(&second.id));
+
+        Microphone::stop(&second.id).unwrap();
+    }
+}
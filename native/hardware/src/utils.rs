@@ -1,6 +1,7 @@
 //! Utility functions for SmashLang hardware interfaces
 
 use std::path::Path;
+use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 use crate::error::HardwareError;
 use crate::Result;
@@ -143,3 +144,74 @@ pub fn create_directory_if_not_exists(dir_path: &str) -> Result<()> {
     
     Ok(())
 }
+
+/// Poison-tolerant locking for the global state maps
+///
+/// A panic while a lock is held poisons it; the data behind our locks stays
+/// consistent between statements, so we take the guard back instead of
+/// propagating the panic to every later caller.
+pub(crate) trait MutexExt<T> {
+    /// Lock the mutex, recovering the guard if it was poisoned
+    fn lock_or_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> MutexExt<T> for Mutex<T> {
+    fn lock_or_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Poison-tolerant locking for `RwLock`, see `MutexExt`
+pub(crate) trait RwLockExt<T> {
+    /// Acquire a read guard, recovering it if the lock was poisoned
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T>;
+    
+    /// Acquire a write guard, recovering it if the lock was poisoned
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T>;
+}
+
+impl<T> RwLockExt<T> for RwLock<T> {
+    fn read_or_recover(&self) -> RwLockReadGuard<'_, T> {
+        self.read().unwrap_or_else(|e| e.into_inner())
+    }
+    
+    fn write_or_recover(&self) -> RwLockWriteGuard<'_, T> {
+        self.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    
+    #[test]
+    fn test_lock_or_recover_after_poison() {
+        let mutex = Arc::new(Mutex::new(vec![1]));
+        
+        let poisoner = Arc::clone(&mutex);
+        let _ = std::thread::spawn(move || {
+            let mut guard = poisoner.lock().unwrap();
+            guard.push(2);
+            panic!("poison the lock");
+        }).join();
+        
+        assert!(mutex.is_poisoned());
+        mutex.lock_or_recover().push(3);
+        assert_eq!(*mutex.lock_or_recover(), vec![1, 2, 3]);
+    }
+    
+    #[test]
+    fn test_rwlock_recover_after_poison() {
+        let lock = Arc::new(RwLock::new(0));
+        
+        let poisoner = Arc::clone(&lock);
+        let _ = std::thread::spawn(move || {
+            let _guard = poisoner.write().unwrap();
+            panic!("poison the lock");
+        }).join();
+        
+        *lock.write_or_recover() += 1;
+        assert_eq!(*lock.read_or_recover(), 1);
+    }
+}
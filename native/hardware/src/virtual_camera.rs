@@ -0,0 +1,79 @@
+//! Virtual camera producing synthetic frames for tests and headless CI
+//!
+//! With the `test-devices` feature enabled, `Camera` enumerates and streams
+//! from this camera instead of real hardware. Frames are deterministic color
+//! bars that scroll sideways, so consecutive frames always differ.
+
+use image::{Rgb, RgbImage};
+
+use crate::camera::{CameraDevice, CameraFormat};
+
+/// Device id reported for the virtual camera
+pub(crate) const VIRTUAL_CAMERA_ID: &str = "virtual:0";
+
+/// Columns the bars move per frame
+const SCROLL_STEP: u32 = 4;
+
+/// 75% color bars: white, yellow, cyan, green, magenta, red, blue
+const BARS: [[u8; 3]; 7] = [
+    [191, 191, 191],
+    [191, 191, 0],
+    [0, 191, 191],
+    [0, 191, 0],
+    [191, 0, 191],
+    [191, 0, 0],
+    [0, 0, 191],
+];
+
+/// Synthetic camera generating scrolling color bars
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualCamera {
+    width: u32,
+    height: u32,
+    frame_index: u32,
+}
+
+impl VirtualCamera {
+    pub(crate) fn new(width: u32, height: u32) -> Self {
+        VirtualCamera {
+            width: width.max(1),
+            height: height.max(1),
+            frame_index: 0,
+        }
+    }
+    
+    /// Device entry returned by `Camera::get_devices`
+    pub(crate) fn device() -> CameraDevice {
+        CameraDevice {
+            id: VIRTUAL_CAMERA_ID.to_string(),
+            label: "Virtual Camera".to_string(),
+            index: 0,
+            capabilities: vec!["photo".to_string(), "video".to_string(), "virtual".to_string()],
+        }
+    }
+    
+    /// Format reported for a virtual camera stream
+    pub(crate) fn format(width: u32, height: u32, frame_rate: u32) -> CameraFormat {
+        CameraFormat {
+            width: width.max(1),
+            height: height.max(1),
+            frame_rate,
+            format: "RAWRGB".to_string(),
+            max_zoom: crate::camera::MAX_ZOOM,
+        }
+    }
+    
+    /// Generate the next frame
+    pub(crate) fn next_frame(&mut self) -> RgbImage {
+        let bar_width = (self.width / BARS.len() as u32).max(1);
+        let offset = self.frame_index.wrapping_mul(SCROLL_STEP);
+        
+        let frame = RgbImage::from_fn(self.width, self.height, |x, _| {
+            let bar = (x.wrapping_add(offset) / bar_width) as usize % BARS.len();
+            Rgb(BARS[bar])
+        });
+        
+        self.frame_index = self.frame_index.wrapping_add(1);
+        frame
+    }
+}
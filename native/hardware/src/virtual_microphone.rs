@@ -0,0 +1,178 @@
+//! Virtual microphone producing a synthetic signal for tests and headless CI
+//!
+//! With the `test-devices` feature enabled, `Microphone` enumerates and
+//! records from this microphone instead of real hardware. Samples are
+//! generated in real time on a background thread, so levels, recordings and
+//! processors see the same kind of data a real device would deliver.
+
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::microphone::MicrophoneDevice;
+
+/// Device id reported for the virtual microphone
+pub(crate) const VIRTUAL_MICROPHONE_ID: &str = "virtual:0";
+
+/// Length of each generated chunk
+const CHUNK_DURATION: Duration = Duration::from_millis(10);
+
+/// Signal produced by the virtual microphone
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum VirtualSignal {
+    /// Sine wave of `frequency` Hz peaking at `amplitude`
+    Sine { frequency: f32, amplitude: f32 },
+    /// Uniform white noise between `-amplitude` and `amplitude`
+    Noise { amplitude: f32 },
+}
+
+impl Default for VirtualSignal {
+    fn default() -> Self {
+        VirtualSignal::Sine {
+            frequency: 440.0,
+            amplitude: 0.5,
+        }
+    }
+}
+
+/// Deterministic generator for a `VirtualSignal`
+///
+/// Every channel of an interleaved frame carries the same sample.
+#[derive(Debug, Clone)]
+pub(crate) struct SignalGenerator {
+    signal: VirtualSignal,
+    sample_rate: u32,
+    channels: u16,
+    /// Frames generated so far
+    position: u64,
+    /// xorshift state for the noise signal
+    seed: u32,
+}
+
+impl SignalGenerator {
+    pub(crate) fn new(signal: VirtualSignal, sample_rate: u32, channels: u16) -> Self {
+        SignalGenerator {
+            signal,
+            sample_rate: sample_rate.max(1),
+            channels: channels.max(1),
+            position: 0,
+            seed: 0x2545_f491,
+        }
+    }
+    
+    /// Fill `samples` with the next interleaved frames of the signal
+    pub(crate) fn fill(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_mut(self.channels as usize) {
+            let value = self.next_sample();
+            frame.iter_mut().for_each(|sample| *sample = value);
+        }
+    }
+    
+    fn next_sample(&mut self) -> f32 {
+        let t = self.position as f64 / self.sample_rate as f64;
+        self.position += 1;
+        
+        match self.signal {
+            VirtualSignal::Sine { frequency, amplitude } => {
+                amplitude * (2.0 * std::f64::consts::PI * frequency as f64 * t).sin() as f32
+            }
+            VirtualSignal::Noise { amplitude } => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 17;
+                self.seed ^= self.seed << 5;
+                amplitude * (self.seed as f32 / u32::MAX as f32 * 2.0 - 1.0)
+            }
+        }
+    }
+}
+
+/// Running virtual microphone; the generator thread stops when this is dropped
+pub(crate) struct VirtualMicrophone {
+    stop_tx: mpsc::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl VirtualMicrophone {
+    /// Device entry returned by `Microphone::get_devices`
+    pub(crate) fn device() -> MicrophoneDevice {
+        MicrophoneDevice {
+            id: VIRTUAL_MICROPHONE_ID.to_string(),
+            label: "Virtual Microphone".to_string(),
+            index: 0,
+            capabilities: vec!["audio".to_string(), "virtual".to_string()],
+        }
+    }
+    
+    /// Start delivering chunks of `generator` output to `on_samples` in real time
+    pub(crate) fn start(mut generator: SignalGenerator, mut on_samples: impl FnMut(&[f32]) + Send + 'static) -> Self {
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let frames = (generator.sample_rate as u64 * CHUNK_DURATION.as_millis() as u64 / 1000).max(1) as usize;
+        let mut chunk = vec![0.0; frames * generator.channels as usize];
+        
+        let thread = std::thread::spawn(move || {
+            let mut next_chunk = Instant::now();
+            
+            loop {
+                generator.fill(&mut chunk);
+                on_samples(&chunk);
+                
+                // Schedule from the start time so chunks don't drift
+                next_chunk += CHUNK_DURATION;
+                let wait = next_chunk.saturating_duration_since(Instant::now());
+                match stop_rx.recv_timeout(wait) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+        });
+        
+        VirtualMicrophone {
+            stop_tx,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for VirtualMicrophone {
+    fn drop(&mut self) {
+        let _ = self.stop_tx.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+    
+    #[test]
+    fn test_signal_rms_matches_amplitude() {
+        // One second covers a whole number of 440 Hz periods
+        let mut samples = vec![0.0; 48000];
+        let sine = VirtualSignal::Sine { frequency: 440.0, amplitude: 0.5 };
+        SignalGenerator::new(sine, 48000, 1).fill(&mut samples);
+        assert!((rms(&samples) - 0.5 / 2f32.sqrt()).abs() < 1e-3);
+        
+        // Uniform noise has an RMS of amplitude / sqrt(3)
+        let noise = VirtualSignal::Noise { amplitude: 0.6 };
+        SignalGenerator::new(noise, 48000, 1).fill(&mut samples);
+        assert!((rms(&samples) - 0.6 / 3f32.sqrt()).abs() < 0.01);
+    }
+    
+    #[test]
+    fn test_channels_share_each_frame() {
+        let mut samples = vec![0.0; 8];
+        SignalGenerator::new(VirtualSignal::default(), 44100, 2).fill(&mut samples);
+        
+        assert!(samples.chunks(2).all(|frame| frame[0] == frame[1]));
+        assert_ne!(samples[2], samples[4]);
+    }
+}
@@ -1,7 +1,12 @@
 use std::error::Error;
 use std::fmt;
+use serde::{Deserialize, Serialize};
 use crate::parser::AstNode;
 
+pub mod optimize;
+
+pub use optimize::{eliminate_dead_code, fold_constants};
+
 /// CompiledFunction represents a compiled function that can be executed
 pub struct CompiledFunction {
     // In a real implementation, this would contain JIT-compiled code
@@ -24,17 +29,54 @@ impl CompiledFunction {
     }
 }
 
+/// Options controlling optimization and compiler output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompilerOptions {
+    /// Run AST optimization passes (constant folding, dead-code elimination).
+    /// Not used yet: there is no code generator to consume the optimized AST
+    pub optimize: bool,
+    /// Emit debug information in the generated code. Not used yet: there is
+    /// no native code generator to emit it
+    pub emit_debug_info: bool,
+    /// Keep the intermediate C source next to the output. Not used yet: no
+    /// C source is generated
+    pub keep_c_source: bool,
+}
+
+impl Default for CompilerOptions {
+    fn default() -> Self {
+        Self {
+            optimize: true,
+            emit_debug_info: false,
+            keep_c_source: false,
+        }
+    }
+}
+
 /// Compiler for SmashLang
 pub struct Compiler {
-    // In a real implementation, this would contain the compilation context
+    options: CompilerOptions,
 }
 
 impl Compiler {
     pub fn new() -> Self {
-        Self {}
+        Self::with_options(CompilerOptions::default())
+    }
+    
+    /// Create a compiler with explicit options
+    pub fn with_options(options: CompilerOptions) -> Self {
+        Self { options }
+    }
+    
+    /// The options this compiler was created with
+    pub fn options(&self) -> &CompilerOptions {
+        &self.options
     }
     
     /// Compile an AST into a native function
+    ///
+    /// The `optimize` passes, `fold_constants` and `eliminate_dead_code`,
+    /// belong just before code generation, which doesn't exist yet.
     pub fn compile(&mut self, _ast: &AstNode) -> Result<CompiledFunction, String> {
         // For now, just return a simple function that returns 42
         Ok(CompiledFunction::new(42))
@@ -64,3 +106,33 @@ impl fmt::Display for CompilationError {
 }
 
 impl Error for CompilationError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_default_options() {
+        let compiler = Compiler::new();
+        assert_eq!(compiler.options(), &CompilerOptions::default());
+        assert!(compiler.options().optimize);
+        assert!(!compiler.options().emit_debug_info);
+        assert!(!compiler.options().keep_c_source);
+    }
+    
+    #[test]
+    fn test_options_round_trip() {
+        let options = CompilerOptions {
+            optimize: false,
+            emit_debug_info: true,
+            keep_c_source: true,
+        };
+        
+        let json = serde_json::to_string(&options).unwrap();
+        let parsed: CompilerOptions = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, options);
+        
+        let compiler = Compiler::with_options(options.clone());
+        assert_eq!(compiler.options(), &options);
+    }
+}
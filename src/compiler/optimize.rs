@@ -0,0 +1,294 @@
+//! AST optimization passes for `Compiler::compile` to run before code
+//! generation, once there is a code generator
+
+use crate::parser::{AstNode, ClassMember};
+
+/// Fold constant numeric, string and boolean subexpressions
+///
+/// Only `BinaryOp`/`UnaryOp` trees whose operands are all literals are
+/// evaluated; anything involving identifiers or calls is left untouched.
+/// Operations that would fail at runtime, such as division by zero, are not
+/// folded so the error is still reported when the program runs.
+pub fn fold_constants(mut ast: AstNode) -> AstNode {
+    fold_in_place(&mut ast);
+    ast
+}
+
+fn fold_in_place(node: &mut AstNode) {
+    // Fold children first so nested constants collapse bottom-up
+    for child in node.children_mut() {
+        fold_in_place(child);
+    }
+    
+    let folded = match node {
+        AstNode::BinaryOp { left, op, right } => fold_binary(left, op, right),
+        AstNode::UnaryOp { op, expr } => fold_unary(op, expr),
+        _ => None,
+    };
+    if let Some(folded) = folded {
+        *node = folded;
+    }
+}
+
+fn fold_binary(left: &AstNode, op: &str, right: &AstNode) -> Option<AstNode> {
+    match (left, right) {
+        (AstNode::Number(l), AstNode::Number(r)) => fold_integers(*l, op, *r),
+        (AstNode::Number(l), AstNode::Float(r)) => fold_floats(*l as f64, op, *r),
+        (AstNode::Float(l), AstNode::Number(r)) => fold_floats(*l, op, *r as f64),
+        (AstNode::Float(l), AstNode::Float(r)) => fold_floats(*l, op, *r),
+        (AstNode::String(l), AstNode::String(r)) => match op {
+            "+" => Some(AstNode::String(format!("{}{}", l, r))),
+            "==" | "===" => Some(AstNode::Boolean(l == r)),
+            "!=" | "!==" => Some(AstNode::Boolean(l != r)),
+            _ => None,
+        },
+        (AstNode::Boolean(l), AstNode::Boolean(r)) => match op {
+            "&&" => Some(AstNode::Boolean(*l && *r)),
+            "||" => Some(AstNode::Boolean(*l || *r)),
+            "==" | "===" => Some(AstNode::Boolean(l == r)),
+            "!=" | "!==" => Some(AstNode::Boolean(l != r)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_integers(l: i64, op: &str, r: i64) -> Option<AstNode> {
+    let value = match op {
+        "+" => AstNode::Number(l.checked_add(r)?),
+        "-" => AstNode::Number(l.checked_sub(r)?),
+        "*" => AstNode::Number(l.checked_mul(r)?),
+        // Division by zero is left for the runtime
+        "/" => AstNode::Number(l.checked_div(r)?),
+        "%" => AstNode::Number(l.checked_rem(r)?),
+        _ => return compare(l.partial_cmp(&r)?, op),
+    };
+    Some(value)
+}
+
+fn fold_floats(l: f64, op: &str, r: f64) -> Option<AstNode> {
+    let value = match op {
+        "+" => l + r,
+        "-" => l - r,
+        "*" => l * r,
+        "/" | "%" if r == 0.0 => return None,
+        "/" => l / r,
+        "%" => l % r,
+        _ => return compare(l.partial_cmp(&r)?, op),
+    };
+    Some(AstNode::Float(value))
+}
+
+fn compare(ordering: std::cmp::Ordering, op: &str) -> Option<AstNode> {
+    use std::cmp::Ordering::*;
+    
+    let result = match op {
+        "<" => ordering == Less,
+        "<=" => ordering != Greater,
+        ">" => ordering == Greater,
+        ">=" => ordering != Less,
+        "==" | "===" => ordering == Equal,
+        "!=" | "!==" => ordering != Equal,
+        _ => return None,
+    };
+    Some(AstNode::Boolean(result))
+}
+
+fn fold_unary(op: &str, expr: &AstNode) -> Option<AstNode> {
+    match (op, expr) {
+        ("-", AstNode::Number(n)) => Some(AstNode::Number(n.checked_neg()?)),
+        ("-", AstNode::Float(f)) => Some(AstNode::Float(-f)),
+        ("+", AstNode::Number(_) | AstNode::Float(_)) => Some(expr.clone()),
+        ("!", AstNode::Boolean(b)) => Some(AstNode::Boolean(!b)),
+        ("~", AstNode::Number(n)) => Some(AstNode::Number(!n)),
+        _ => None,
+    }
+}
+
+/// Drop statements that follow an unconditional `return`, `throw`, `break`
+/// or `continue` in the same statement list
+///
+/// Function declarations are kept since they are hoisted and may still be
+/// called from the reachable code above them.
+///
+/// This only prunes the AST. There is no C code generator yet, so nothing
+/// is emitted from the result.
+pub fn eliminate_dead_code(mut ast: AstNode) -> AstNode {
+    prune_in_place(&mut ast);
+    ast
+}
+
+fn prune_in_place(node: &mut AstNode) {
+    match node {
+        AstNode::Block(statements)
+        | AstNode::Program(statements)
+        | AstNode::Function { body: statements, .. }
+        | AstNode::ArrowFunction { body: statements, .. } => prune_after_jump(statements),
+        AstNode::Switch { cases, default, .. } => {
+            for case in cases {
+                prune_after_jump(&mut case.body);
+            }
+            if let Some(default) = default {
+                prune_after_jump(default);
+            }
+        }
+        AstNode::Try { body, catch_body, finally_body, .. } => {
+            prune_after_jump(body);
+            prune_after_jump(catch_body);
+            if let Some(finally_body) = finally_body {
+                prune_after_jump(finally_body);
+            }
+        }
+        AstNode::ClassDeclaration { body, .. } => {
+            for member in body {
+                if let ClassMember::Constructor { body, .. } | ClassMember::Method { body, .. } = member {
+                    prune_after_jump(body);
+                }
+            }
+        }
+        _ => {}
+    }
+    
+    for child in node.children_mut() {
+        prune_in_place(child);
+    }
+}
+
+fn prune_after_jump(statements: &mut Vec<AstNode>) {
+    let jump = statements.iter().position(|stmt| {
+        matches!(
+            stmt,
+            AstNode::Return(_) | AstNode::Throw(_) | AstNode::Break(_) | AstNode::Continue(_)
+        )
+    });
+    
+    if let Some(jump) = jump {
+        let mut index = 0;
+        statements.retain(|stmt| {
+            let keep = index <= jump || matches!(stmt, AstNode::Function { name, .. } if !name.is_empty());
+            index += 1;
+            keep
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn binop(left: AstNode, op: &str, right: AstNode) -> AstNode {
+        AstNode::BinaryOp {
+            left: Box::new(left),
+            op: op.to_string(),
+            right: Box::new(right),
+        }
+    }
+    
+    #[test]
+    fn test_folds_literal_arithmetic() {
+        assert_eq!(fold_constants(binop(AstNode::Number(2), "+", AstNode::Number(3))), AstNode::Number(5));
+        
+        // 2 + 3 * 4
+        let nested = binop(AstNode::Number(2), "+", binop(AstNode::Number(3), "*", AstNode::Number(4)));
+        assert_eq!(fold_constants(nested), AstNode::Number(14));
+        
+        let concat = binop(AstNode::String("a".to_string()), "+", AstNode::String("b".to_string()));
+        assert_eq!(fold_constants(concat), AstNode::String("ab".to_string()));
+        
+        let negated = AstNode::UnaryOp {
+            op: "!".to_string(),
+            expr: Box::new(binop(AstNode::Number(1), "<", AstNode::Number(2))),
+        };
+        assert_eq!(fold_constants(negated), AstNode::Boolean(false));
+    }
+    
+    #[test]
+    fn test_leaves_identifiers_unfolded() {
+        let expr = binop(AstNode::Identifier("x".to_string()), "+", AstNode::Number(3));
+        assert_eq!(fold_constants(expr.clone()), expr);
+        
+        // Constant operands inside a non-constant expression still fold
+        let expr = binop(
+            AstNode::Identifier("x".to_string()),
+            "+",
+            binop(AstNode::Number(1), "+", AstNode::Number(2)),
+        );
+        assert_eq!(
+            fold_constants(expr),
+            binop(AstNode::Identifier("x".to_string()), "+", AstNode::Number(3))
+        );
+    }
+    
+    #[test]
+    fn test_defers_division_by_zero_to_runtime() {
+        let expr = binop(AstNode::Number(1), "/", AstNode::Number(0));
+        assert_eq!(fold_constants(expr.clone()), expr);
+        
+        let expr = binop(AstNode::Float(1.0), "%", AstNode::Number(0));
+        assert_eq!(fold_constants(expr.clone()), expr);
+    }
+    
+    #[test]
+    fn test_folds_inside_statements() {
+        let program = AstNode::Program(vec![AstNode::LetDecl {
+            name: "x".to_string(),
+            value: Box::new(binop(AstNode::Number(6), "*", AstNode::Number(7))),
+        }]);
+        let expected = AstNode::Program(vec![AstNode::LetDecl {
+            name: "x".to_string(),
+            value: Box::new(AstNode::Number(42)),
+        }]);
+        assert_eq!(fold_constants(program), expected);
+    }
+    
+    fn call(name: &str) -> AstNode {
+        AstNode::FunctionCall {
+            callee: Box::new(AstNode::Identifier(name.to_string())),
+            args: vec![],
+        }
+    }
+    
+    #[test]
+    fn test_drops_statements_after_return() {
+        let block = AstNode::Block(vec![
+            call("before"),
+            AstNode::Return(Box::new(AstNode::Number(1))),
+            call("after"),
+            AstNode::Break(None),
+        ]);
+        let expected = AstNode::Block(vec![call("before"), AstNode::Return(Box::new(AstNode::Number(1)))]);
+        assert_eq!(eliminate_dead_code(block), expected);
+    }
+    
+    #[test]
+    fn test_prunes_nested_bodies_and_keeps_hoisted_functions() {
+        let helper = AstNode::Function {
+            name: "helper".to_string(),
+            params: vec![],
+            body: vec![],
+            is_async: false,
+        };
+        let loop_body = AstNode::While {
+            condition: Box::new(AstNode::Boolean(true)),
+            body: Box::new(AstNode::Block(vec![AstNode::Continue(None), call("unreachable")])),
+        };
+        let function = AstNode::Function {
+            name: "f".to_string(),
+            params: vec![],
+            body: vec![
+                loop_body,
+                AstNode::Return(Box::new(call("helper"))),
+                call("unreachable"),
+                helper.clone(),
+            ],
+            is_async: false,
+        };
+        
+        let pruned = eliminate_dead_code(function);
+        let AstNode::Function { body, .. } = pruned else { panic!("expected a function") };
+        assert_eq!(body.len(), 3);
+        assert_eq!(body[2], helper);
+        let AstNode::While { body: loop_body, .. } = &body[0] else { panic!("expected a loop") };
+        assert_eq!(**loop_body, AstNode::Block(vec![AstNode::Continue(None)]));
+    }
+}
@@ -0,0 +1,263 @@
+//! Line editing support for the interactive REPL
+//!
+//! `ReplHelper` plugs into rustyline to complete variable names, built-ins
+//! and members of the built-in objects, to color the line being typed, and
+//! to keep reading lines while brackets are still open.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+use rustyline::completion::Completer;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+
+use crate::lexer::{Lexer, Token, TokenWithSpan};
+
+/// Global functions and objects every program can use
+const BUILTINS: &[&str] = &[
+    "JSON", "Math", "Promise", "clearInterval", "clearTimeout", "console", "fs",
+    "print", "process", "queueMicrotask", "setInterval", "setTimeout",
+];
+
+/// Members offered after `<object>.` for the built-in objects
+const MEMBERS: &[(&str, &[&str])] = &[
+    ("JSON", &["parse", "stringify"]),
+    ("Math", &["E", "PI", "abs", "ceil", "floor", "max", "min", "pow", "random", "round", "sqrt"]),
+    ("Promise", &["all", "race", "reject", "resolve"]),
+    ("console", &["error", "info", "log", "warn"]),
+    ("fs", &["existsSync", "readFileSync", "writeFileSync"]),
+    ("process", &["argv", "cwd", "env", "exit"]),
+];
+
+const KEYWORD_COLOR: &str = "\x1b[1;35m";
+const STRING_COLOR: &str = "\x1b[32m";
+const NUMBER_COLOR: &str = "\x1b[33m";
+const COMMENT_COLOR: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// rustyline helper for the REPL
+#[derive(Debug, Default)]
+pub struct ReplHelper {
+    /// Variables and functions declared so far in the session
+    variables: BTreeSet<String>,
+}
+
+impl ReplHelper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Remember the names declared by `let`, `const` and `fn` in `source`
+    pub fn record_declarations(&mut self, source: &str) {
+        let tokens = Lexer::new(source).tokenize();
+        for pair in tokens.windows(2) {
+            if let (Token::Let | Token::Const | Token::Fn, Token::Identifier(name)) = (&pair[0].token, &pair[1].token) {
+                self.variables.insert(name.clone());
+            }
+        }
+    }
+    
+    /// Completions for the word ending at `pos`, and where that word starts
+    ///
+    /// After `<object>.` the members of a built-in object are offered;
+    /// otherwise declared variables and built-ins starting with the word.
+    pub fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = word_start(before);
+        let prefix = &before[start..];
+        
+        let names: Vec<&str> = match before[..start].strip_suffix('.') {
+            Some(object_source) => {
+                let object = &object_source[word_start(object_source)..];
+                MEMBERS
+                    .iter()
+                    .find(|(name, _)| *name == object)
+                    .map_or_else(Vec::new, |(_, members)| members.to_vec())
+            },
+            None => self.variables.iter().map(String::as_str).chain(BUILTINS.iter().copied()).collect(),
+        };
+        
+        let matches: BTreeSet<String> = names
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .map(str::to_string)
+            .collect();
+        (start, matches.into_iter().collect())
+    }
+}
+
+/// Byte offset where the identifier ending at the end of `text` starts
+fn word_start(text: &str) -> usize {
+    text.char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .last()
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// ANSI color for a token, or `None` to leave it uncolored
+fn token_color(token: &Token) -> Option<&'static str> {
+    match token {
+        token if token.is_keyword() => Some(KEYWORD_COLOR),
+        token if token.is_comment() => Some(COMMENT_COLOR),
+        Token::String(_) | Token::SingleQuoteString(_) | Token::TemplateChunk(_) | Token::Backtick => Some(STRING_COLOR),
+        Token::Number(_) | Token::Float(_) | Token::BigInt(_) => Some(NUMBER_COLOR),
+        _ => None,
+    }
+}
+
+/// Color the keywords, strings, numbers and comments of `line`
+///
+/// Only escape codes are added, so the display width stays the same.
+pub fn highlight_line(line: &str) -> String {
+    let mut highlighted = String::with_capacity(line.len());
+    let mut copied = 0;
+    
+    for TokenWithSpan { token, span } in Lexer::new(line).tokenize_with_comments() {
+        let Some(color) = token_color(&token) else {
+            continue;
+        };
+        if span.start < copied {
+            continue;
+        }
+        
+        highlighted.push_str(&line[copied..span.start]);
+        highlighted.push_str(color);
+        highlighted.push_str(&line[span.clone()]);
+        highlighted.push_str(RESET);
+        copied = span.end;
+    }
+    
+    highlighted.push_str(&line[copied..]);
+    highlighted
+}
+
+/// Whether `source` stops inside an open bracket, brace, parenthesis or
+/// template literal, so the statement continues on the next line
+///
+/// Brackets inside strings and comments don't count. Mismatched closers make
+/// the input complete, so that evaluating it reports the syntax error.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut open = Vec::new();
+    
+    for TokenWithSpan { token, .. } in Lexer::new(source).tokenize() {
+        let closer = match token {
+            Token::LParen => Token::RParen,
+            Token::LBracket => Token::RBracket,
+            Token::LBrace => Token::RBrace,
+            Token::TemplateExprStart => Token::TemplateExprEnd,
+            Token::Backtick if open.last() == Some(&Token::Backtick) => {
+                open.pop();
+                continue;
+            },
+            Token::Backtick => Token::Backtick,
+            Token::RParen | Token::RBracket | Token::RBrace | Token::TemplateExprEnd => {
+                if open.pop() != Some(token) {
+                    return false;
+                }
+                continue;
+            },
+            _ => continue,
+        };
+        open.push(closer);
+    }
+    
+    !open.is_empty()
+}
+
+/// Whether the REPL should submit `input` or keep reading lines
+pub fn validate_input(input: &str) -> ValidationResult {
+    if is_incomplete(input) {
+        ValidationResult::Incomplete
+    } else {
+        ValidationResult::Valid(None)
+    }
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+    
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        // Follows `--color` and `NO_COLOR` like the rest of the output
+        if colored::control::SHOULD_COLORIZE.should_colorize() {
+            Cow::Owned(highlight_line(line))
+        } else {
+            Cow::Borrowed(line)
+        }
+    }
+    
+    // Any edit can start or end a string or comment, so always re-highlight
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
+    }
+}
+
+impl Validator for ReplHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(validate_input(ctx.input()))
+    }
+}
+
+impl Helper for ReplHelper {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_completes_variables_and_builtins() {
+        let mut helper = ReplHelper::new();
+        helper.record_declarations("let count = 1; const total = 2; fn compute(x) { return x; }");
+        
+        let (start, candidates) = helper.candidates("print(co", 8);
+        assert_eq!(start, 6);
+        assert_eq!(candidates, vec!["compute", "console", "count"]);
+        
+        let (_, candidates) = helper.candidates("t", 1);
+        assert_eq!(candidates, vec!["total"]);
+    }
+    
+    #[test]
+    fn test_completes_members_of_builtin_objects() {
+        let helper = ReplHelper::new();
+        
+        let (start, candidates) = helper.candidates("Math.r", 6);
+        assert_eq!(start, 5);
+        assert_eq!(candidates, vec!["random", "round"]);
+        
+        // Members of unknown objects aren't guessed
+        let (_, candidates) = helper.candidates("count.r", 7);
+        assert!(candidates.is_empty());
+    }
+    
+    #[test]
+    fn test_highlights_keywords_and_literals() {
+        assert_eq!(
+            highlight_line("let x = 1; // one"),
+            "\x1b[1;35mlet\x1b[0m x = \x1b[33m1\x1b[0m; \x1b[90m// one\x1b[0m"
+        );
+        assert_eq!(highlight_line("print('hi')"), "print(\x1b[32m'hi'\x1b[0m)");
+    }
+    
+    #[test]
+    fn test_open_brackets_continue_the_statement() {
+        assert!(matches!(validate_input("fn add(a, b) {"), ValidationResult::Incomplete));
+        assert!(matches!(validate_input("fn add(a, b) {\n  return a + b;\n}"), ValidationResult::Valid(None)));
+        
+        assert!(is_incomplete("let s = `total: ${"));
+        assert!(is_incomplete("let s = `first line"));
+        assert!(!is_incomplete("let s = `total: ${[1, 2]}`;"));
+        assert!(!is_incomplete("print(\"{\"); // ("));
+    }
+}
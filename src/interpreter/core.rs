@@ -1,10 +1,16 @@
+use std::collections::HashMap;
 use crate::parser::AstNode;
 use crate::interpreter::value::Value;
 use crate::interpreter::environment::Environment;
 
+/// Callback run before each top-level statement, see
+/// [`Interpreter::set_on_statement`]
+pub type StatementHook = Box<dyn FnMut(&AstNode, &Environment)>;
+
 /// Interpreter for SmashLang
 pub struct Interpreter {
     environment: Environment,
+    on_statement: Option<StatementHook>,
 }
 
 impl Interpreter {
@@ -13,18 +19,63 @@ impl Interpreter {
         
         // Define global functions and objects
         env.define("console", Value::Object({
-            let mut console = std::collections::HashMap::new();
+            let mut console = HashMap::new();
             console.insert("log".to_string(), Value::Undefined);
             console
         }));
         
         Self {
             environment: env,
+            on_statement: None,
         }
     }
     
-    pub fn evaluate(&self, _node: &AstNode) -> Result<Value, String> {
+    /// Look up a global variable
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.environment.get(name)
+    }
+    
+    /// Define a global variable, replacing any existing one of that name
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.environment.define(name, value);
+    }
+    
+    /// Snapshot of every variable in scope, built-in globals included,
+    /// sorted by name
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        self.environment.variables()
+    }
+    
+    /// Call `hook` with each top-level statement and the environment it
+    /// will run in, just before the statement is evaluated
+    ///
+    /// Debuggers can use this to step through a program: evaluation waits
+    /// until the hook returns.
+    pub fn set_on_statement<F>(&mut self, hook: F)
+    where
+        F: FnMut(&AstNode, &Environment) + 'static,
+    {
+        self.on_statement = Some(Box::new(hook));
+    }
+    
+    pub fn evaluate(&mut self, node: &AstNode) -> Result<Value, String> {
+        match node {
+            AstNode::Program(statements) => {
+                let mut result = Value::Undefined;
+                for statement in statements {
+                    if let Some(hook) = &mut self.on_statement {
+                        hook(statement, &self.environment);
+                    }
+                    result = self.evaluate_statement(statement)?;
+                }
+                Ok(result)
+            },
+            _ => self.evaluate_statement(node),
+        }
+    }
+    
+    fn evaluate_statement(&mut self, _node: &AstNode) -> Result<Value, String> {
         // For now, just return a simple value
         Ok(Value::Number(42.0))
     }
-}
\ No newline at end of file
+}
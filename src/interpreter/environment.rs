@@ -47,4 +47,20 @@ impl Environment {
             None
         }
     }
+    
+    /// Every variable visible from this scope, sorted by name
+    ///
+    /// Inner bindings shadow outer ones of the same name. The values are
+    /// copies, so the snapshot doesn't change as the program keeps running.
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let mut variables = match &self.parent {
+            Some(parent) => parent.variables().into_iter().collect(),
+            None => HashMap::new(),
+        };
+        variables.extend(self.values.iter().map(|(name, value)| (name.clone(), value.clone())));
+        
+        let mut variables: Vec<(String, Value)> = variables.into_iter().collect();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use std::rc::Rc;
 use crate::parser::AstNode;
 use crate::interpreter::value::Value;
 use crate::interpreter::environment::Environment;
@@ -7,7 +8,7 @@ pub struct Function {
     pub name: Option<String>,
     pub params: Vec<String>,
     pub body: Vec<AstNode>,
-    pub native_fn: Option<Box<dyn Fn(Value, &[Value], &Environment) -> Result<Value, String> + 'static>>,
+    pub native_fn: Option<Rc<dyn Fn(Value, &[Value], &Environment) -> Result<Value, String> + 'static>>,
 }
 
 impl std::fmt::Debug for Function {
@@ -27,7 +28,8 @@ impl Clone for Function {
             name: self.name.clone(),
             params: self.params.clone(),
             body: self.body.clone(),
-            native_fn: None // Cannot clone closures
+            // Clones share the closure, so a cloned native function still runs
+            native_fn: self.native_fn.clone(),
         }
     }
 }
@@ -50,7 +52,7 @@ impl Function {
             name,
             params,
             body: Vec::new(),
-            native_fn: Some(Box::new(f)),
+            native_fn: Some(Rc::new(f)),
         }
     }
     
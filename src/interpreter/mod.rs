@@ -10,4 +10,4 @@ pub mod core;
 pub use value::Value;
 pub use function::Function;
 pub use environment::Environment;
-pub use core::Interpreter;
\ No newline at end of file
+pub use core::{Interpreter, StatementHook};
\ No newline at end of file
@@ -1,6 +1,7 @@
 use std::ops::Range;
 use logos::Logos;
 use crate::lexer::token::Token;
+use crate::lexer::utils::unescape_string;
 
 /// TokenWithSpan represents a token with its position in the source code
 #[derive(Debug, Clone)]
@@ -18,26 +19,161 @@ impl TokenWithSpan {
 /// Lexer for SmashLang
 pub struct Lexer<'a> {
     logos_lexer: logos::Lexer<'a, Token>,
+    // Open template literals, innermost last. `None` while lexing template
+    // text, `Some(depth)` inside a `${ }` with `depth` unclosed `{`s.
+    templates: Vec<Option<usize>>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             logos_lexer: Token::lexer(input),
+            templates: Vec::new(),
         }
     }
     
     pub fn tokenize(&mut self) -> Vec<TokenWithSpan> {
+        self.tokenize_with_comments()
+            .into_iter()
+            .filter(|t| !t.token.is_comment())
+            .collect()
+    }
+    
+    /// Tokenize the input, keeping line and block comments as tokens
+    /// interleaved with the rest in source order (for formatters and doc tools)
+    pub fn tokenize_with_comments(&mut self) -> Vec<TokenWithSpan> {
         let mut tokens = Vec::new();
         
-        while let Some(token) = self.logos_lexer.next() {
-            if let Ok(token) = token {
-                let span = self.logos_lexer.span();
-                tokens.push(TokenWithSpan::new(token, span));
+        loop {
+            if let Some(None) = self.templates.last() {
+                self.lex_template_text(&mut tokens);
+                continue;
             }
-            // Skip errors
+            
+            let token = match self.logos_lexer.next() {
+                Some(Ok(token)) => token,
+                Some(Err(_)) => continue, // Skip errors
+                None => break,
+            };
+            let span = self.logos_lexer.span();
+            
+            let token = match (token, self.templates.last_mut()) {
+                (Token::Backtick, _) => {
+                    self.templates.push(None);
+                    Token::Backtick
+                }
+                (Token::LBrace, Some(Some(depth))) => {
+                    *depth += 1;
+                    Token::LBrace
+                }
+                (Token::RBrace, Some(Some(depth))) => {
+                    if *depth == 0 {
+                        // Back to the template text
+                        *self.templates.last_mut().unwrap() = None;
+                        Token::TemplateExprEnd
+                    } else {
+                        *depth -= 1;
+                        Token::RBrace
+                    }
+                }
+                (token @ (Token::Slash | Token::SlashEqual), _) => {
+                    let after_value = tokens
+                        .iter()
+                        .rev()
+                        .find(|t| !t.token.is_comment())
+                        .is_some_and(|t| t.token.ends_expression());
+                    if after_value {
+                        token
+                    } else {
+                        match self.lex_regex(span.start) {
+                            Some(regex) => {
+                                tokens.push(TokenWithSpan::new(regex, self.logos_lexer.span()));
+                                continue;
+                            }
+                            None => token,
+                        }
+                    }
+                }
+                (token, _) => token,
+            };
+            tokens.push(TokenWithSpan::new(token, span));
         }
         
         tokens
     }
+    
+    // Scan a regex literal (pattern and flags) starting at the `/` at `start`.
+    // Returns None, consuming nothing more, if the literal is unterminated.
+    fn lex_regex(&mut self, start: usize) -> Option<Token> {
+        let text = &self.logos_lexer.source()[start..];
+        let bytes = text.as_bytes();
+        
+        let mut end = 1;
+        let mut in_class = false;
+        loop {
+            match bytes.get(end)? {
+                b'\n' | b'\r' => return None,
+                b'\\' => end += 2,
+                b'[' => {
+                    in_class = true;
+                    end += 1;
+                }
+                b']' => {
+                    in_class = false;
+                    end += 1;
+                }
+                b'/' if !in_class => break,
+                _ => end += 1,
+            }
+        }
+        end += 1;
+        if end == 2 {
+            return None; // `//` is a comment, never an empty regex
+        }
+        while bytes.get(end).is_some_and(|b| b.is_ascii_alphabetic()) {
+            end += 1;
+        }
+        
+        let consumed = self.logos_lexer.span().end - start;
+        self.logos_lexer.bump(end - consumed);
+        Some(Token::Regex(text[..end].to_string()))
+    }
+    
+    // Lex template text up to and including the closing backtick or the `${`
+    // that opens an interpolation
+    fn lex_template_text(&mut self, tokens: &mut Vec<TokenWithSpan>) {
+        let start = self.logos_lexer.span().end;
+        let rest = self.logos_lexer.remainder();
+        let bytes = rest.as_bytes();
+        
+        let mut end = 0;
+        while end < bytes.len() {
+            match bytes[end] {
+                b'\\' => end += 2,
+                b'`' => break,
+                b'$' if bytes.get(end + 1) == Some(&b'{') => break,
+                _ => end += 1,
+            }
+        }
+        let end = end.min(bytes.len());
+        
+        if end > 0 {
+            tokens.push(TokenWithSpan::new(Token::TemplateChunk(unescape_string(&rest[..end])), start..start + end));
+        }
+        self.logos_lexer.bump(end);
+        
+        let position = start + end;
+        if rest[end..].starts_with("${") {
+            tokens.push(TokenWithSpan::new(Token::TemplateExprStart, position..position + 2));
+            self.logos_lexer.bump(2);
+            *self.templates.last_mut().unwrap() = Some(0);
+        } else if rest[end..].starts_with('`') {
+            tokens.push(TokenWithSpan::new(Token::Backtick, position..position + 1));
+            self.logos_lexer.bump(1);
+            self.templates.pop();
+        } else {
+            // Unterminated template at end of input
+            self.templates.clear();
+        }
+    }
 }
\ No newline at end of file
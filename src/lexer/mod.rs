@@ -8,7 +8,7 @@ pub mod utils;
 // Re-export main types for easier access
 pub use token::Token;
 pub use core::{Lexer, TokenWithSpan};
-pub use utils::unescape_string;
+pub use utils::{parse_bigint_literal, parse_float_literal, parse_integer_literal, unescape_string};
 
 // Tests
 #[cfg(test)]
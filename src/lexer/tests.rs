@@ -1,4 +1,4 @@
-use crate::lexer::{Lexer, Token};
+use crate::lexer::{parse_float_literal, parse_integer_literal, Lexer, Token};
 
 #[test]
 fn test_lexer_basic() {
@@ -31,4 +31,149 @@ fn test_lexer_regex() {
     let tokens = lexer.tokenize();
     
     assert!(matches!(tokens[3].token, Token::Regex(ref s) if s == "/[a-z]+/g"));
+}
+
+#[test]
+fn test_lexer_preserves_comments_when_asked() {
+    let input = "let x = 1; // line\n/* block */ x;";
+    
+    let tokens = Lexer::new(input).tokenize_with_comments();
+    assert_eq!(tokens.len(), 9);
+    assert!(matches!(tokens[5].token, Token::LineComment(ref s) if s == " line"));
+    assert_eq!(&input[tokens[5].span.clone()], "// line");
+    assert!(matches!(tokens[6].token, Token::BlockComment(ref s) if s == " block "));
+    assert_eq!(&input[tokens[6].span.clone()], "/* block */");
+    assert!(matches!(tokens[7].token, Token::Identifier(ref s) if s == "x"));
+    
+    // The default tokenizer still skips them
+    let tokens = Lexer::new(input).tokenize();
+    assert_eq!(tokens.len(), 7);
+    assert!(tokens.iter().all(|t| !t.token.is_comment()));
+}
+
+fn single_number(input: &str) -> Option<i64> {
+    match Lexer::new(input).tokenize().as_slice() {
+        [t] => match t.token {
+            Token::Number(n) => Some(n),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[test]
+fn test_lexer_radix_and_separated_integers() {
+    assert_eq!(single_number("0xFF"), Some(255));
+    assert_eq!(single_number("0b1010"), Some(10));
+    assert_eq!(single_number("0o17"), Some(15));
+    assert_eq!(single_number("1_000"), Some(1000));
+    assert_eq!(single_number("0x_ff_ff"), None);
+}
+
+#[test]
+fn test_lexer_rejects_misplaced_separators() {
+    assert_eq!(single_number("1__0"), None);
+    assert!(parse_integer_literal("1__0").unwrap_err().contains("separator"));
+    assert!(parse_integer_literal("1000_").is_err());
+    assert!(parse_integer_literal("0b_1").is_err());
+    assert!(parse_integer_literal("0x").is_err());
+}
+
+#[test]
+fn test_lexer_scientific_floats() {
+    let floats: Vec<f64> = Lexer::new("1e3 2.5e-1 1. 4E+2")
+        .tokenize()
+        .into_iter()
+        .map(|t| match t.token {
+            Token::Float(f) => f,
+            other => panic!("expected a float, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(floats, vec![1000.0, 0.25, 1.0, 400.0]);
+}
+
+#[test]
+fn test_lexer_rejects_malformed_exponents() {
+    assert!(Lexer::new("1e").tokenize().is_empty());
+    assert!(Lexer::new("1e+").tokenize().is_empty());
+    assert!(parse_float_literal("1e").unwrap_err().contains("Malformed exponent"));
+    assert!(parse_float_literal("1e+").unwrap_err().contains("Malformed exponent"));
+}
+
+#[test]
+fn test_lexer_bigint_literal() {
+    let tokens = Lexer::new("10n").tokenize();
+    assert_eq!(tokens.len(), 1);
+    assert!(matches!(tokens[0].token, Token::BigInt(ref s) if s == "10"));
+    
+    // Larger than i64 is fine
+    let tokens = Lexer::new("123_456_789_012_345_678_901n").tokenize();
+    assert!(matches!(tokens[0].token, Token::BigInt(ref s) if s == "123456789012345678901"));
+}
+
+fn token_kinds(input: &str) -> Vec<Token> {
+    Lexer::new(input).tokenize().into_iter().map(|t| t.token).collect()
+}
+
+#[test]
+fn test_lexer_template_with_object_literal_interpolation() {
+    let tokens = token_kinds("`a${ {a:1}.a }b`");
+    assert_eq!(tokens, vec![
+        Token::Backtick,
+        Token::TemplateChunk("a".to_string()),
+        Token::TemplateExprStart,
+        Token::LBrace,
+        Token::Identifier("a".to_string()),
+        Token::Colon,
+        Token::Number(1),
+        Token::RBrace,
+        Token::Dot,
+        Token::Identifier("a".to_string()),
+        Token::TemplateExprEnd,
+        Token::TemplateChunk("b".to_string()),
+        Token::Backtick,
+    ]);
+}
+
+#[test]
+fn test_lexer_nested_template_literal() {
+    let tokens = token_kinds("`${`x${y}`}` + 1");
+    assert_eq!(tokens, vec![
+        Token::Backtick,
+        Token::TemplateExprStart,
+        Token::Backtick,
+        Token::TemplateChunk("x".to_string()),
+        Token::TemplateExprStart,
+        Token::Identifier("y".to_string()),
+        Token::TemplateExprEnd,
+        Token::Backtick,
+        Token::TemplateExprEnd,
+        Token::Backtick,
+        Token::Plus,
+        Token::Number(1),
+    ]);
+}
+
+#[test]
+fn test_lexer_division_after_values() {
+    let tokens = token_kinds("a / b / c");
+    assert_eq!(tokens.iter().filter(|t| **t == Token::Slash).count(), 2);
+    assert!(tokens.iter().all(|t| !matches!(t, Token::Regex(_))));
+    
+    let tokens = token_kinds("(a + 1) / 2 / x[0]");
+    assert_eq!(tokens.iter().filter(|t| **t == Token::Slash).count(), 2);
+}
+
+#[test]
+fn test_lexer_regex_after_operators_and_keywords() {
+    let tokens = token_kinds("x = /ab+c/gi;");
+    assert_eq!(tokens[2], Token::Regex("/ab+c/gi".to_string()));
+    assert_eq!(tokens[3], Token::Semicolon);
+    
+    let tokens = token_kinds("return /x/");
+    assert_eq!(tokens, vec![Token::Return, Token::Regex("/x/".to_string())]);
+    
+    // A `/` inside a character class does not end the literal
+    let tokens = token_kinds("f(/[/]+/, 1)");
+    assert_eq!(tokens[2], Token::Regex("/[/]+/".to_string()));
 }
\ No newline at end of file
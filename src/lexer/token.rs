@@ -1,5 +1,5 @@
 use logos::Logos;
-use crate::lexer::utils::unescape_string;
+use crate::lexer::utils::{parse_bigint_literal, parse_float_literal, parse_integer_literal, unescape_string};
 
 /// Token represents all possible token types in the SmashLang language
 #[derive(Logos, Debug, Clone, PartialEq)]
@@ -93,13 +93,21 @@ pub enum Token {
     #[regex(r"[a-zA-Z_][a-zA-Z0-9_]*", |lex| lex.slice().to_string(), priority = 2)]
     Identifier(String),
     
-    // Number literals
-    #[regex(r"[0-9]+", |lex| lex.slice().parse().ok(), priority = 2)]
+    // Number literals (decimal, 0x/0o/0b radix prefixes, `_` separators)
+    #[regex(r"[0-9][0-9_]*", |lex| parse_integer_literal(lex.slice()).ok(), priority = 2)]
+    #[regex(r"0[xX][0-9a-fA-F_]+", |lex| parse_integer_literal(lex.slice()).ok(), priority = 2)]
+    #[regex(r"0[oO][0-7_]+", |lex| parse_integer_literal(lex.slice()).ok(), priority = 2)]
+    #[regex(r"0[bB][01_]+", |lex| parse_integer_literal(lex.slice()).ok(), priority = 2)]
     Number(i64),
     
-    #[regex(r"[0-9]+\.[0-9]+", |lex| lex.slice().parse().ok(), priority = 2)]
+    #[regex(r"[0-9][0-9_]*\.[0-9_]*", |lex| parse_float_literal(lex.slice()).ok(), priority = 2)]
+    #[regex(r"[0-9][0-9_]*(\.[0-9_]*)?[eE][+-]?[0-9_]*", |lex| parse_float_literal(lex.slice()).ok(), priority = 2)]
     Float(f64),
     
+    // BigInt literals (digits without the `n` suffix or separators)
+    #[regex(r"[0-9][0-9_]*n", |lex| parse_bigint_literal(lex.slice()).ok(), priority = 2)]
+    BigInt(String),
+    
     // String literals
     #[regex(r#""([^"\\]|\\["\\nt])*""#, |lex| {
         let slice = lex.slice();
@@ -115,19 +123,14 @@ pub enum Token {
     }, priority = 2)]
     SingleQuoteString(String),
     
-    // Template literals
-    #[regex(r"`[^`]*`", |lex| {
-        let slice = lex.slice();
-        let content = &slice[1..slice.len()-1]; // Remove backticks
-        unescape_string(content)
-    }, priority = 2)]
-    TemplateString(String),
+    // Template literals: `Lexer` splits the text between backticks into
+    // chunks and `${ }` interpolations, lexing the interpolated code normally
+    TemplateChunk(String),
+    TemplateExprStart,
+    TemplateExprEnd,
     
-    // Regular expressions
-    #[regex(r"/([^/\\]|\\.)+/[gimuy]*", |lex| {
-        let slice = lex.slice();
-        slice.to_string()
-    }, priority = 2)]
+    // Regular expressions: `Lexer` decides whether a `/` starts a regex or is
+    // a division from the previous token, then scans the literal itself
     Regex(String),
     
     // Basic operators
@@ -311,11 +314,82 @@ pub enum Token {
     #[token("@")]
     At,
     
-    // Skip whitespace and comments
+    // Comments (dropped by `Lexer::tokenize`, kept by `Lexer::tokenize_with_comments`)
+    #[regex(r"//[^\n]*", |lex| lex.slice()[2..].to_string(), priority = 3)]
+    LineComment(String),
+    
+    #[regex(r"/\*([^*]|\*[^/])*\*/", |lex| {
+        let slice = lex.slice();
+        slice[2..slice.len()-2].to_string() // Remove delimiters
+    }, priority = 3)]
+    BlockComment(String),
+    
+    // Skip whitespace
     #[regex(r"[ \t\n\r]+", logos::skip, priority = 3)]
-    #[regex(r"//[^\n]*", logos::skip, priority = 3)]
-    #[regex(r"/\*([^*]|\*[^/])*\*/", logos::skip, priority = 3)]
     // Error fallback
     #[regex(r".", logos::skip, priority = 1)]
     Error,
+}
+
+impl Token {
+    /// Whether this token is a line or block comment
+    pub fn is_comment(&self) -> bool {
+        matches!(self, Token::LineComment(_) | Token::BlockComment(_))
+    }
+    
+    /// Whether this token is a keyword or a literal keyword such as `true`
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            Token::Const
+                | Token::Let
+                | Token::Fn
+                | Token::Return
+                | Token::Import
+                | Token::Async
+                | Token::Await
+                | Token::Try
+                | Token::Catch
+                | Token::Finally
+                | Token::Throw
+                | Token::New
+                | Token::Break
+                | Token::Continue
+                | Token::If
+                | Token::Else
+                | Token::While
+                | Token::For
+                | Token::Do
+                | Token::Switch
+                | Token::Case
+                | Token::Default
+                | Token::In
+                | Token::Of
+                | Token::Bool(_)
+                | Token::Null
+        )
+    }
+    
+    /// Whether this token can end an expression, so that a following `/` is
+    /// a division rather than the start of a regex literal
+    pub fn ends_expression(&self) -> bool {
+        matches!(
+            self,
+            Token::Identifier(_)
+                | Token::Number(_)
+                | Token::Float(_)
+                | Token::BigInt(_)
+                | Token::String(_)
+                | Token::SingleQuoteString(_)
+                | Token::Regex(_)
+                | Token::Bool(_)
+                | Token::Null
+                | Token::RParen
+                | Token::RBracket
+                | Token::RBrace
+                | Token::Increment
+                | Token::Decrement
+                | Token::Backtick
+        )
+    }
 }
\ No newline at end of file
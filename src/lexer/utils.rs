@@ -28,4 +28,68 @@ pub fn unescape_string(s: &str) -> String {
     }
     
     result
+}
+
+/// Parse an integer literal with an optional `0x`/`0o`/`0b` radix prefix and
+/// `_` digit separators. Separators may only appear between two digits.
+pub fn parse_integer_literal(s: &str) -> Result<i64, String> {
+    let (digits, radix) = match s.get(..2) {
+        Some("0x") | Some("0X") => (&s[2..], 16),
+        Some("0o") | Some("0O") => (&s[2..], 8),
+        Some("0b") | Some("0B") => (&s[2..], 2),
+        _ => (s, 10),
+    };
+    
+    if digits.is_empty() {
+        return Err(format!("Missing digits in numeric literal '{}'", s));
+    }
+    check_separators(s, digits)?;
+    
+    i64::from_str_radix(&digits.replace('_', ""), radix)
+        .map_err(|e| format!("Invalid numeric literal '{}': {}", s, e))
+}
+
+/// Parse a decimal float literal such as `1.5`, `1.`, `1e3` or `2.5e-4`
+pub fn parse_float_literal(s: &str) -> Result<f64, String> {
+    let mantissa = match s.find(['e', 'E']) {
+        Some(index) => {
+            let exponent = &s[index + 1..];
+            let exponent_digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            if exponent_digits.is_empty() {
+                return Err(format!("Malformed exponent in numeric literal '{}': expected digits after '{}'", s, &s[index..]));
+            }
+            check_separators(s, exponent_digits)?;
+            &s[..index]
+        }
+        None => s,
+    };
+    
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    check_separators(s, integer)?;
+    if !fraction.is_empty() {
+        check_separators(s, fraction)?;
+    }
+    
+    s.replace('_', "")
+        .parse()
+        .map_err(|e| format!("Invalid numeric literal '{}': {}", s, e))
+}
+
+/// Parse a BigInt literal such as `42n`, returning its decimal digits
+pub fn parse_bigint_literal(s: &str) -> Result<String, String> {
+    let digits = s.strip_suffix('n').ok_or_else(|| format!("BigInt literal '{}' must end in 'n'", s))?;
+    if digits.is_empty() {
+        return Err(format!("Missing digits in numeric literal '{}'", s));
+    }
+    check_separators(s, digits)?;
+    
+    Ok(digits.replace('_', ""))
+}
+
+// Separators may only appear between two digits
+fn check_separators(literal: &str, digits: &str) -> Result<(), String> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(format!("Misplaced numeric separator in '{}'", literal));
+    }
+    Ok(())
 }
\ No newline at end of file
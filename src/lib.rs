@@ -20,11 +20,12 @@ pub mod parser;
 pub mod interpreter;
 pub mod compiler;
 pub mod runtime;
+pub mod editor;
+pub mod repl;
 
 /// Re-export main components for easier access
 pub use lexer::Lexer;
 pub use parser::{SmashParser, AstNode};
-use pest::Parser as PestParser;
 pub use interpreter::{Interpreter, Value};
 pub use compiler::Compiler;
 
@@ -42,28 +43,35 @@ pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// assert_eq!(result.to_string(), "84");
 /// ```
 pub fn execute(source: &str) -> Result<Value, String> {
-    // Parse the source code
-    let mut lexer = Lexer::new(source);
-    let _tokens = lexer.tokenize();
+    let ast = parse(source)?;
 
-    let mut pairs = match <SmashParser as PestParser<parser::Rule>>::parse(parser::Rule::program, source) {
+    // Interpret the AST
+    let mut interpreter = Interpreter::new();
+    let result = interpreter.evaluate(&ast);
+    
+    // Let timers started by the program finish
+    runtime::run_event_loop();
+    
+    match result {
+        Ok(value) => Ok(value),
+        Err(err) => Err(format!("Runtime error: {}", err)),
+    }
+}
+
+/// Parses SmashLang code into an AST
+///
+/// The pest grammar is the authoritative parser, so the source is parsed
+/// directly without a separate tokenization pass.
+pub fn parse(source: &str) -> Result<AstNode, String> {
+    let mut pairs = match SmashParser::parse(source) {
         Ok(pairs) => pairs,
         Err(err) => {
             return Err(format!("Parse error: {}", err));
         }
     };
-    let ast = match pairs.next().and_then(AstNode::from_pair) {
-        Some(ast) => ast,
-        None => {
-            return Err("Failed to convert parse tree to AST".to_string());
-        }
-    };
-
-    // Interpret the AST
-    let interpreter = Interpreter::new();
-    match interpreter.evaluate(&ast) {
-        Ok(value) => Ok(value),
-        Err(err) => Err(format!("Runtime error: {}", err)),
+    match pairs.next().and_then(AstNode::from_pair) {
+        Some(ast) => Ok(ast),
+        None => Err("Failed to convert parse tree to AST".to_string()),
     }
 }
 
@@ -79,24 +87,24 @@ pub fn execute(source: &str) -> Result<Value, String> {
 /// assert_eq!(result, 84);
 /// ```
 pub fn compile(source: &str) -> Result<compiler::CompiledFunction, String> {
-    // Parse the source code
-    let mut lexer = Lexer::new(source);
-    let _tokens = lexer.tokenize();
-
-    let mut pairs = match <SmashParser as PestParser<parser::Rule>>::parse(parser::Rule::program, source) {
-        Ok(pairs) => pairs,
-        Err(err) => {
-            return Err(format!("Parse error: {}", err));
-        }
-    };
-    let ast = match pairs.next().and_then(AstNode::from_pair) {
-        Some(ast) => ast,
-        None => {
-            return Err("Failed to convert parse tree to AST".to_string());
-        }
-    };
+    let ast = parse(source)?;
 
     // Compile the AST
     let mut compiler = Compiler::new();
     compiler.compile(&ast)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_errors_are_shared_by_execute_and_compile() {
+        // execute and compile go through the same single parse pass
+        let source = "let x = ;";
+        let parse_error = parse(source).unwrap_err();
+        assert!(parse_error.starts_with("Parse error:"));
+        assert_eq!(execute(source).unwrap_err(), parse_error);
+        assert_eq!(compile(source).err(), Some(parse_error));
+    }
+}
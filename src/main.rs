@@ -2,23 +2,18 @@ use std::env;
 use std::fs;
 use std::path::Path;
 use std::process;
-use colored::*;
-use rustyline::DefaultEditor;
-use rustyline::error::ReadlineError;
+use std::thread;
+use std::time::Instant;
+use serde::Serialize;
 
-mod lexer;
-mod parser;
-mod interpreter;
-mod compiler;
-
-use lexer::Lexer;
-use parser::SmashParser as Parser;
-use parser::AstNode;
-use interpreter::Interpreter;
-use compiler::Compiler;
+use smashlang::lexer::Lexer;
+use smashlang::parser::{SmashParser as Parser, AstNode};
+use smashlang::compiler::Compiler;
+use smashlang::repl::{Repl, ReplOptions};
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    apply_color_flag(&mut args);
     
     if args.len() < 2 {
         // No arguments provided, start REPL
@@ -27,14 +22,48 @@ fn main() {
         // First argument is the command
         match args[1].as_str() {
             "run" => {
-                if args.len() < 3 {
-                    eprintln!("Error: No file specified");
-                    print_usage();
-                    process::exit(1);
+                // Flags may come before or after the file
+                let report_timing = args[2..].iter().any(|arg| arg == "--time");
+                let file_index = match args[2..].iter().position(|arg| !arg.starts_with("--")) {
+                    Some(index) => index + 2,
+                    None => {
+                        eprintln!("Error: No file specified");
+                        print_usage();
+                        process::exit(1);
+                    }
+                };
+                
+                // Everything after the file except our own flags goes to the script
+                let script_args: Vec<String> = args[file_index + 1..].iter()
+                    .filter(|arg| *arg != "--time")
+                    .cloned()
+                    .collect();
+                
+                run_file(&args[file_index], &script_args, report_timing);
+            },
+            "check" | "--check" => {
+                let mut json = false;
+                let mut file_path = None;
+                for arg in &args[2..] {
+                    match arg.as_str() {
+                        "--format=json" => json = true,
+                        "--format=text" => json = false,
+                        other if other.starts_with("--format=") => {
+                            eprintln!("Error: Unknown format '{}', expected text or json", &other["--format=".len()..]);
+                            process::exit(1);
+                        },
+                        _ => file_path = file_path.or(Some(arg)),
+                    }
                 }
                 
-                let file_path = &args[2];
-                run_file(file_path);
+                match file_path {
+                    Some(file_path) => check_file(file_path, json),
+                    None => {
+                        eprintln!("Error: No file specified");
+                        print_usage();
+                        process::exit(1);
+                    }
+                }
             },
             "compile" => {
                 if args.len() < 3 {
@@ -49,7 +78,7 @@ fn main() {
             "help" => {
                 print_usage();
             },
-            "version" => {
+            "version" | "--version" | "-V" => {
                 print_version();
             },
             _ => {
@@ -66,82 +95,98 @@ fn print_usage() {
     println!();
     println!("Usage:");
     println!("  smash                   Start the REPL");
-    println!("  smash run <file>        Run a SmashLang file");
+    println!("  smash run <file> [args] Run a SmashLang file; args are available as process.argv");
+    println!("      --time              Report lex/parse/eval timing on stderr");
+    println!("  smash check <file>      Check a SmashLang file for syntax errors without running it");
+    println!("      --format=json       Print diagnostics as a JSON array for editors");
     println!("  smash compile <file>    Compile a SmashLang file");
     println!("  smash help              Show this help message");
-    println!("  smash version           Show version information");
+    println!("  smash version           Show version information and enabled features");
+    println!();
+    println!("Options:");
+    println!("  --color=<when>          Color output: always, never or auto (the default);");
+    println!("                          auto disables color when NO_COLOR is set or output isn't a terminal");
 }
 
+/// Remove `--color=<when>` from the arguments and apply it to all output
+///
+/// In `auto` mode color is off when `NO_COLOR` is set to a non-empty value;
+/// otherwise it's on only when stdout is a terminal.
+fn apply_color_flag(args: &mut Vec<String>) {
+    let mut when = "auto".to_string();
+    args.retain(|arg| match arg.strip_prefix("--color=") {
+        Some(value) => {
+            when = value.to_string();
+            false
+        },
+        None => true,
+    });
+    
+    match when.as_str() {
+        "always" => colored::control::set_override(true),
+        "never" => colored::control::set_override(false),
+        "auto" => {
+            if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+                colored::control::set_override(false);
+            }
+        },
+        other => {
+            eprintln!("Error: Invalid --color value '{}', expected always, never or auto", other);
+            print_usage();
+            process::exit(1);
+        }
+    }
+}
+
+/// Optional features and whether this build has them
+const FEATURES: &[(&str, bool)] = &[
+    ("jit", cfg!(feature = "jit")),
+    ("native", cfg!(feature = "native")),
+    ("wasm", cfg!(feature = "wasm")),
+];
+
 fn print_version() {
     let version = env!("CARGO_PKG_VERSION");
     let git_hash = option_env!("GIT_HASH").unwrap_or("unknown");
     
     println!("SmashLang v{} ({})", version, git_hash);
     println!("A JavaScript-like language in Rust");
+    
+    let enabled: Vec<&str> = FEATURES.iter().filter(|(_, enabled)| *enabled).map(|(name, _)| *name).collect();
+    if enabled.is_empty() {
+        println!("Features: none");
+    } else {
+        println!("Features: {}", enabled.join(", "));
+    }
 }
 
-fn start_repl() {
-    println!("{}", "SmashLang REPL".bright_green().bold());
-    println!("Type .help for commands, .exit to quit");
-    
-    let mut rl = DefaultEditor::new().unwrap();
-    let _interpreter = Interpreter::new();
+/// Stack for the thread evaluating code; calls fail once it is nearly used
+/// up, so more stack than the main thread has lets recursive code go deeper
+const REPL_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Run `f` on a thread named `name` with `REPL_STACK_SIZE` of stack
+fn with_repl_stack<T: Send + 'static>(name: &str, f: impl FnOnce() -> T + Send + 'static) -> T {
+    let thread = thread::Builder::new()
+        .name(name.to_string())
+        .stack_size(REPL_STACK_SIZE)
+        .spawn(f);
     
-    loop {
-        let readline = rl.readline(">> ");
-        match readline {
-            Ok(line) => {
-                rl.add_history_entry(&line).unwrap();
-                
-                if line.trim().is_empty() {
-                    continue;
-                }
-                
-                // Handle REPL commands
-                if line.starts_with(".") {
-                    match line.trim() {
-                        ".exit" | ".quit" => break,
-                        ".help" => {
-                            println!("REPL Commands:");
-                            println!("  .exit, .quit    Exit the REPL");
-                            println!("  .help           Show this help message");
-                            println!("  .version        Show version information");
-                            continue;
-                        },
-                        ".version" => {
-                            print_version();
-                            continue;
-                        },
-                        _ => {
-                            println!("Unknown command: {}", line);
-                            continue;
-                        }
-                    }
-                }
-                
-                // Execute the code
-                match execute_code(&line) {
-                    Ok(result) => println!("{}", result.to_string().bright_cyan()),
-                    Err(err) => println!("{}: {}", "Error".bright_red(), err),
-                }
-            },
-            Err(ReadlineError::Interrupted) => {
-                println!("Ctrl-C");
-                break;
-            },
-            Err(ReadlineError::Eof) => {
-                println!("Ctrl-D");
-                break;
-            },
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;
-            }
+    match thread.map(|handle| handle.join()) {
+        Ok(Ok(value)) => value,
+        // The panic message has already been printed
+        Ok(Err(_)) => process::exit(1),
+        Err(err) => {
+            eprintln!("Error: Could not start the {} thread: {}", name, err);
+            process::exit(1);
         }
     }
 }
 
-fn run_file(file_path: &str) {
+fn start_repl() {
+    with_repl_stack("repl", || Repl::new().run());
+}
+
+fn run_file(file_path: &str, script_args: &[String], report_timing: bool) {
     let path = Path::new(file_path);
     
     if !path.exists() {
@@ -157,8 +202,14 @@ fn run_file(file_path: &str) {
         }
     };
     
-    match execute_code(&source) {
-        Ok(_) => {},
+    let mut options = ReplOptions { args: script_args.to_vec(), ..ReplOptions::default() };
+    // Relative imports resolve against the script's directory
+    if let Some(dir) = path.canonicalize().ok().and_then(|path| path.parent().map(Path::to_path_buf)) {
+        options.base_dir = dir;
+    }
+    
+    match with_repl_stack("script", move || execute_code(&source, options, report_timing)) {
+        Ok(()) => {},
         Err(err) => {
             eprintln!("Error: {}", err);
             process::exit(1);
@@ -166,6 +217,59 @@ fn run_file(file_path: &str) {
     }
 }
 
+/// A problem found by `smash check`
+#[derive(Debug, Serialize)]
+struct Diagnostic {
+    severity: &'static str,
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+fn check_file(file_path: &str, json: bool) {
+    let path = Path::new(file_path);
+    
+    if !path.exists() {
+        eprintln!("Error: File '{}' not found", file_path);
+        process::exit(1);
+    }
+    
+    let source = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            eprintln!("Error reading file '{}': {}", file_path, err);
+            process::exit(1);
+        }
+    };
+    
+    // Only parse; nothing in the file is ever executed
+    let mut diagnostics = Vec::new();
+    if let Err(err) = Parser::parse(&source) {
+        let (line, column) = match err.line_col {
+            pest::error::LineColLocation::Pos(pos) => pos,
+            pest::error::LineColLocation::Span(start, _) => start,
+        };
+        diagnostics.push(Diagnostic {
+            severity: "error",
+            line,
+            column,
+            message: format!("Syntax error: {}", err.variant.message()),
+        });
+    }
+    
+    if json {
+        println!("{}", serde_json::to_string(&diagnostics).unwrap());
+    } else {
+        for diagnostic in &diagnostics {
+            eprintln!("{}:{}:{}: {}", file_path, diagnostic.line, diagnostic.column, diagnostic.message);
+        }
+    }
+    
+    if !diagnostics.is_empty() {
+        process::exit(1);
+    }
+}
+
 fn compile_file(file_path: &str) {
     let path = Path::new(file_path);
     
@@ -225,12 +329,15 @@ fn compile_file(file_path: &str) {
     println!("Execution result: {}", result);
 }
 
-fn execute_code(source: &str) -> Result<interpreter::Value, String> {
+fn execute_code(source: &str, options: ReplOptions, report_timing: bool) -> Result<(), String> {
     // Parse the source code
+    let lex_start = Instant::now();
     let mut lexer = Lexer::new(source);
     let _tokens = lexer.tokenize();
+    let lex_time = lex_start.elapsed();
     
     // Parse the source code
+    let parse_start = Instant::now();
     let mut pairs = match Parser::parse(source) {
         Ok(pairs) => pairs,
         Err(err) => {
@@ -239,17 +346,28 @@ fn execute_code(source: &str) -> Result<interpreter::Value, String> {
     };
     
     // Convert to AST
-    let ast = match pairs.next().and_then(AstNode::from_pair) {
-        Some(ast) => ast,
-        None => {
+    let statements = match pairs.next().and_then(AstNode::from_pair) {
+        Some(AstNode::Program(statements)) => statements,
+        _ => {
             return Err("Failed to convert parse tree to AST".to_string());
         }
     };
+    let parse_time = parse_start.elapsed();
     
     // Interpret the AST
-    let interpreter = Interpreter::new();
-    match interpreter.evaluate(&ast) {
-        Ok(value) => Ok(value),
+    let eval_start = Instant::now();
+    let result = Repl::with_options(options).execute(&statements);
+    let eval_time = eval_start.elapsed();
+    
+    // Timing goes to stderr so program output stays clean
+    if report_timing {
+        eprintln!("lex:   {:.3?}", lex_time);
+        eprintln!("parse: {:.3?}", parse_time);
+        eprintln!("eval:  {:.3?}", eval_time);
+    }
+    
+    match result {
+        Ok(_) => Ok(()),
         Err(err) => Err(format!("Runtime error: {}", err)),
     }
 }
@@ -1,7 +1,5 @@
-use std::collections::HashMap;
-
 /// Abstract Syntax Tree node for SmashLang
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum AstNode {
     // Literals
     Number(i64),
@@ -42,6 +40,9 @@ pub enum AstNode {
         false_expr: Box<AstNode>,
     },
     
+    // Comma operator: evaluates each expression, yields the last
+    Sequence(Vec<AstNode>),
+    
     // Nullish coalescing
     NullishCoalescing {
         left: Box<AstNode>,
@@ -176,7 +177,7 @@ pub enum AstNode {
     
     // Data structures
     ArrayLiteral(Vec<AstNode>),
-    ObjectLiteral(HashMap<String, AstNode>),
+    ObjectLiteral(Vec<ObjectProperty>),
     SpreadElement(Box<AstNode>),
     
     // Control flow
@@ -236,9 +237,13 @@ pub enum AstNode {
         args: Vec<AstNode>,
     },
     
-    // Loop control
-    Break,
-    Continue,
+    // Loop control (with optional target label)
+    Break(Option<String>),
+    Continue(Option<String>),
+    Labeled {
+        label: String,
+        body: Box<AstNode>,
+    },
     
     // Modules
     Import {
@@ -267,13 +272,13 @@ pub enum AstNode {
     Program(Vec<AstNode>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SwitchCase {
     pub value: AstNode,
     pub body: Vec<AstNode>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DestructuringTarget {
     pub name: String,
     pub alias: Option<String>,
@@ -307,7 +312,7 @@ impl DestructuringTarget {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Parameter {
     pub name: String,
     pub default_value: Option<Box<AstNode>>,
@@ -334,7 +339,7 @@ impl Parameter {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ClassMember {
     Constructor {
         params: Vec<Parameter>,
@@ -356,14 +361,951 @@ pub enum ClassMember {
     },
 }
 
-#[derive(Debug, Clone)]
+/// A property of an object literal; properties are kept in source order so
+/// later ones override earlier ones
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectProperty {
+    /// `key: value`
+    KeyValue {
+        key: String,
+        value: AstNode,
+    },
+    /// `[key]: value`, with the key expression evaluated when the object is built
+    Computed {
+        key: AstNode,
+        value: AstNode,
+    },
+    /// `name`, short for `name: name`
+    Shorthand(String),
+    /// `name(params) { body }`, short for a function-valued property
+    Method {
+        name: String,
+        params: Vec<Parameter>,
+        body: Vec<AstNode>,
+        is_async: bool,
+    },
+    /// `...expr`, copying the own properties of `expr`
+    Spread(AstNode),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ImportSpecifier {
     pub name: String,
     pub alias: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ExportSpecifier {
     pub name: String,
     pub exported_name: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Indentation used by `AstNode::to_source`
+const INDENT: &str = "    ";
+
+impl AstNode {
+    /// Render the AST back to readable SmashLang source
+    ///
+    /// Statements are indented four spaces per block level. Nested operator
+    /// expressions are parenthesized, so re-parsing the output yields the
+    /// same tree regardless of operator precedence.
+    pub fn to_source(&self) -> String {
+        let mut out = String::new();
+        match self {
+            AstNode::Program(statements) => {
+                for stmt in statements {
+                    stmt.write_statement(&mut out, 0);
+                }
+            }
+            node => node.write_statement(&mut out, 0),
+        }
+        out
+    }
+    
+    fn write_statement(&self, out: &mut String, level: usize) {
+        let indent = INDENT.repeat(level);
+        match self {
+            AstNode::LetDecl { name, value } => {
+                out.push_str(&format!("{}let {} = {};\n", indent, name, value.expr_source()));
+            }
+            AstNode::ConstDecl { name, value } => {
+                out.push_str(&format!("{}const {} = {};\n", indent, name, value.expr_source()));
+            }
+            AstNode::ArrayDestructuring { targets, value } => {
+                let targets: Vec<String> = targets.iter().map(DestructuringTarget::to_source).collect();
+                out.push_str(&format!("{}let [{}] = {};\n", indent, targets.join(", "), value.expr_source()));
+            }
+            AstNode::ObjectDestructuring { targets, value } => {
+                let targets: Vec<String> = targets.iter().map(DestructuringTarget::to_source).collect();
+                out.push_str(&format!("{}let {{ {} }} = {};\n", indent, targets.join(", "), value.expr_source()));
+            }
+            AstNode::Function { name, params, body, is_async } if !name.is_empty() => {
+                out.push_str(&format!(
+                    "{}{}fn {}({}) {}\n",
+                    indent,
+                    if *is_async { "async " } else { "" },
+                    name,
+                    params_source(params),
+                    block_source(body, level)
+                ));
+            }
+            AstNode::ClassDeclaration { name, parent, body } => {
+                out.push_str(&format!("{}class {}", indent, name));
+                if let Some(parent) = parent {
+                    out.push_str(&format!(" extends {}", parent));
+                }
+                out.push_str(" {\n");
+                for member in body {
+                    member.write_source(out, level + 1);
+                }
+                out.push_str(&format!("{}}}\n", indent));
+            }
+            AstNode::Block(statements) => {
+                out.push_str(&format!("{}{}\n", indent, block_source(statements, level)));
+            }
+            AstNode::Return(value) => {
+                out.push_str(&format!("{}return {};\n", indent, value.expr_source()));
+            }
+            AstNode::If { condition, then_branch, else_branch } => {
+                out.push_str(&format!("{}if ({}) {}", indent, condition.expr_source(), branch_source(then_branch, level)));
+                if let Some(else_branch) = else_branch {
+                    out.push_str(&format!(" else {}", branch_source(else_branch, level)));
+                }
+                out.push('\n');
+            }
+            AstNode::While { condition, body } => {
+                out.push_str(&format!("{}while ({}) {}\n", indent, condition.expr_source(), branch_source(body, level)));
+            }
+            AstNode::DoWhile { body, condition } => {
+                out.push_str(&format!("{}do {} while ({});\n", indent, branch_source(body, level), condition.expr_source()));
+            }
+            AstNode::For { init, condition, update, body } => {
+                let init = match init.as_deref() {
+                    Some(AstNode::LetDecl { name, value }) => format!("let {} = {}", name, value.expr_source()),
+                    Some(AstNode::ConstDecl { name, value }) => format!("const {} = {}", name, value.expr_source()),
+                    Some(expr) => expr.expr_source(),
+                    None => String::new(),
+                };
+                let condition = condition.as_ref().map(|c| c.expr_source()).unwrap_or_default();
+                let update = update.as_ref().map(|u| u.expr_source()).unwrap_or_default();
+                out.push_str(&format!(
+                    "{}for ({}; {}; {}) {}\n",
+                    indent,
+                    init,
+                    condition,
+                    update,
+                    branch_source(body, level)
+                ));
+            }
+            AstNode::ForIn { var_name, object, body } => {
+                out.push_str(&format!("{}for (let {} in {}) {}\n", indent, var_name, object.expr_source(), branch_source(body, level)));
+            }
+            AstNode::ForOf { var_name, iterable, body } => {
+                out.push_str(&format!("{}for (let {} of {}) {}\n", indent, var_name, iterable.expr_source(), branch_source(body, level)));
+            }
+            AstNode::Switch { expression, cases, default } => {
+                let case_indent = INDENT.repeat(level + 1);
+                out.push_str(&format!("{}switch ({}) {{\n", indent, expression.expr_source()));
+                for case in cases {
+                    out.push_str(&format!("{}case {}:\n", case_indent, case.value.expr_source()));
+                    for stmt in &case.body {
+                        stmt.write_statement(out, level + 2);
+                    }
+                }
+                if let Some(default) = default {
+                    out.push_str(&format!("{}default:\n", case_indent));
+                    for stmt in default {
+                        stmt.write_statement(out, level + 2);
+                    }
+                }
+                out.push_str(&format!("{}}}\n", indent));
+            }
+            AstNode::Try { body, catch_param, catch_body, finally_body } => {
+                out.push_str(&format!("{}try {}", indent, block_source(body, level)));
+                if let Some(param) = catch_param {
+                    out.push_str(&format!(" catch ({}) {}", param, block_source(catch_body, level)));
+                }
+                if let Some(finally_body) = finally_body {
+                    out.push_str(&format!(" finally {}", block_source(finally_body, level)));
+                }
+                out.push('\n');
+            }
+            AstNode::Throw(value) => {
+                out.push_str(&format!("{}throw {};\n", indent, value.expr_source()));
+            }
+            AstNode::Break(label) => {
+                out.push_str(&format!("{}break{};\n", indent, label_suffix(label)));
+            }
+            AstNode::Continue(label) => {
+                out.push_str(&format!("{}continue{};\n", indent, label_suffix(label)));
+            }
+            AstNode::Labeled { label, body } => {
+                out.push_str(&format!("{}{}:\n", indent, label));
+                body.write_statement(out, level);
+            }
+            AstNode::Import { source, default_import, named_imports, namespace_import, side_effect_only } => {
+                if *side_effect_only {
+                    out.push_str(&format!("{}import {};\n", indent, quote(source)));
+                    return;
+                }
+                let mut clauses = Vec::new();
+                if let Some(default_import) = default_import {
+                    clauses.push(default_import.clone());
+                }
+                if let Some(namespace) = namespace_import {
+                    clauses.push(format!("* as {}", namespace));
+                }
+                if !named_imports.is_empty() {
+                    let names: Vec<String> = named_imports
+                        .iter()
+                        .map(|s| alias_source(&s.name, &s.alias))
+                        .collect();
+                    clauses.push(format!("{{ {} }}", names.join(", ")));
+                }
+                out.push_str(&format!("{}import {} from {};\n", indent, clauses.join(", "), quote(source)));
+            }
+            AstNode::Export { declaration } => {
+                out.push_str(&format!("{}export ", indent));
+                let mut declaration_source = String::new();
+                declaration.write_statement(&mut declaration_source, level);
+                out.push_str(declaration_source.trim_start());
+            }
+            AstNode::ExportDefault { expression } => {
+                out.push_str(&format!("{}export default {};\n", indent, expression.expr_source()));
+            }
+            AstNode::ExportNamed { specifiers, source } => {
+                let names: Vec<String> = specifiers
+                    .iter()
+                    .map(|s| alias_source(&s.name, &s.exported_name))
+                    .collect();
+                out.push_str(&format!("{}export {{ {} }}", indent, names.join(", ")));
+                if let Some(source) = source {
+                    out.push_str(&format!(" from {}", quote(source)));
+                }
+                out.push_str(";\n");
+            }
+            AstNode::ExportAll { source, exported_name } => {
+                out.push_str(&format!("{}export *", indent));
+                if let Some(name) = exported_name {
+                    out.push_str(&format!(" as {}", name));
+                }
+                out.push_str(&format!(" from {};\n", quote(source)));
+            }
+            AstNode::Program(statements) => {
+                for stmt in statements {
+                    stmt.write_statement(out, level);
+                }
+            }
+            expr => {
+                out.push_str(&format!("{}{};\n", indent, expr.expr_source()));
+            }
+        }
+    }
+    
+    fn expr_source(&self) -> String {
+        match self {
+            AstNode::Number(n) => n.to_string(),
+            AstNode::Float(f) => format!("{:?}", f),
+            AstNode::String(s) => quote(s),
+            AstNode::TemplateLiteral(parts) => {
+                let mut out = String::from("`");
+                for part in parts {
+                    match part {
+                        AstNode::String(s) => out.push_str(
+                            &s.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${"),
+                        ),
+                        expr => out.push_str(&format!("${{{}}}", expr.expr_source())),
+                    }
+                }
+                out.push('`');
+                out
+            }
+            AstNode::Regex(pattern) => pattern.clone(),
+            AstNode::Boolean(b) => b.to_string(),
+            AstNode::Null => "null".to_string(),
+            AstNode::Undefined => "undefined".to_string(),
+            AstNode::Identifier(name) => name.clone(),
+            
+            AstNode::BinaryOp { left, op, right } => {
+                format!("{} {} {}", left.operand_source(), op, right.operand_source())
+            }
+            AstNode::UnaryOp { op, expr } => {
+                let separator = if op.chars().all(|c| c.is_ascii_alphabetic()) { " " } else { "" };
+                format!("{}{}{}", op, separator, expr.operand_source())
+            }
+            AstNode::TernaryOp { condition, true_expr, false_expr } => format!(
+                "{} ? {} : {}",
+                condition.operand_source(),
+                true_expr.operand_source(),
+                false_expr.operand_source()
+            ),
+            AstNode::Sequence(expressions) => {
+                let expressions: Vec<String> = expressions.iter().map(|e| e.expr_source()).collect();
+                format!("({})", expressions.join(", "))
+            }
+            AstNode::NullishCoalescing { left, right } => {
+                format!("{} ?? {}", left.operand_source(), right.operand_source())
+            }
+            
+            AstNode::PropertyAccess { object, property } => format!("{}.{}", object.operand_source(), property),
+            AstNode::OptionalPropertyAccess { object, property } => format!("{}?.{}", object.operand_source(), property),
+            AstNode::ComputedPropertyAccess { object, property } => {
+                format!("{}[{}]", object.operand_source(), property.expr_source())
+            }
+            AstNode::OptionalComputedPropertyAccess { object, property } => {
+                format!("{}?.[{}]", object.operand_source(), property.expr_source())
+            }
+            AstNode::MethodCall { object, method, args } => {
+                format!("{}.{}({})", object.operand_source(), method, args_source(args))
+            }
+            AstNode::OptionalMethodCall { object, method, args } => {
+                format!("{}?.{}({})", object.operand_source(), method, args_source(args))
+            }
+            
+            AstNode::PreIncrement(expr) => format!("++{}", expr.operand_source()),
+            AstNode::PostIncrement(expr) => format!("{}++", expr.operand_source()),
+            AstNode::PreDecrement(expr) => format!("--{}", expr.operand_source()),
+            AstNode::PostDecrement(expr) => format!("{}--", expr.operand_source()),
+            
+            AstNode::Assignment { target, value } => format!("{} = {}", target.expr_source(), value.expr_source()),
+            AstNode::CompoundAssignment { target, op, value } => {
+                let op = if op.ends_with('=') { op.clone() } else { format!("{}=", op) };
+                format!("{} {} {}", target.expr_source(), op, value.expr_source())
+            }
+            
+            AstNode::Function { name, params, body, is_async } => format!(
+                "{}function{}({}) {}",
+                if *is_async { "async " } else { "" },
+                if name.is_empty() { String::new() } else { format!(" {}", name) },
+                params_source(params),
+                block_source(body, 0)
+            ),
+            AstNode::ArrowFunction { params, body, expression, is_async } => {
+                let body = match body.as_slice() {
+                    [expr] if *expression => expr.operand_source(),
+                    statements => block_source(statements, 0),
+                };
+                format!("{}({}) => {}", if *is_async { "async " } else { "" }, params_source(params), body)
+            }
+            AstNode::AwaitExpr { expr } => format!("await {}", expr.operand_source()),
+            AstNode::FunctionCall { callee, args } => format!("{}({})", callee.operand_source(), args_source(args)),
+            AstNode::SuperCall { args } => format!("super({})", args_source(args)),
+            AstNode::SuperMethodCall { method, args } => format!("super.{}({})", method, args_source(args)),
+            AstNode::NewExpr { constructor, args } => format!("new {}({})", constructor.operand_source(), args_source(args)),
+            
+            AstNode::NewPromise { executor } => format!("new Promise({})", executor.expr_source()),
+            AstNode::PromiseResolve { value } => format!("Promise.resolve({})", value.expr_source()),
+            AstNode::PromiseReject { reason } => format!("Promise.reject({})", reason.expr_source()),
+            AstNode::PromiseThen { promise, on_fulfilled, on_rejected } => {
+                let mut args = vec![on_fulfilled.as_ref().map_or("undefined".to_string(), |f| f.expr_source())];
+                if let Some(on_rejected) = on_rejected {
+                    args.push(on_rejected.expr_source());
+                }
+                format!("{}.then({})", promise.operand_source(), args.join(", "))
+            }
+            AstNode::PromiseCatch { promise, on_rejected } => {
+                format!("{}.catch({})", promise.operand_source(), on_rejected.expr_source())
+            }
+            AstNode::PromiseFinally { promise, on_finally } => {
+                format!("{}.finally({})", promise.operand_source(), on_finally.expr_source())
+            }
+            AstNode::PromiseAll { iterable } => format!("Promise.all({})", iterable.expr_source()),
+            AstNode::PromiseRace { iterable } => format!("Promise.race({})", iterable.expr_source()),
+            AstNode::PromiseAllSettled { iterable } => format!("Promise.allSettled({})", iterable.expr_source()),
+            AstNode::PromiseAny { iterable } => format!("Promise.any({})", iterable.expr_source()),
+            
+            AstNode::ArrayLiteral(elements) => format!("[{}]", args_source(elements)),
+            AstNode::ObjectLiteral(properties) => {
+                if properties.is_empty() {
+                    return "{}".to_string();
+                }
+                let properties: Vec<String> = properties.iter().map(ObjectProperty::to_source).collect();
+                format!("{{ {} }}", properties.join(", "))
+            }
+            AstNode::SpreadElement(expr) => format!("...{}", expr.operand_source()),
+            
+            // Statements in expression position (e.g. a class as a value)
+            stmt => {
+                let mut out = String::new();
+                stmt.write_statement(&mut out, 0);
+                out.trim_end().trim_end_matches(';').to_string()
+            }
+        }
+    }
+    
+    // Source for a sub-expression, parenthesized unless it is atomic
+    fn operand_source(&self) -> String {
+        match self {
+            AstNode::BinaryOp { .. }
+            | AstNode::UnaryOp { .. }
+            | AstNode::TernaryOp { .. }
+            | AstNode::NullishCoalescing { .. }
+            | AstNode::Assignment { .. }
+            | AstNode::CompoundAssignment { .. }
+            | AstNode::ArrowFunction { .. }
+            | AstNode::Function { .. }
+            | AstNode::AwaitExpr { .. } => format!("({})", self.expr_source()),
+            _ => self.expr_source(),
+        }
+    }
+}
+
+impl ClassMember {
+    fn write_source(&self, out: &mut String, level: usize) {
+        let indent = INDENT.repeat(level);
+        match self {
+            ClassMember::Constructor { params, body } => {
+                out.push_str(&format!("{}constructor({}) {}\n", indent, params_source(params), block_source(body, level)));
+            }
+            ClassMember::Method { name, params, body, is_async, is_static, is_private } => {
+                out.push_str(&format!(
+                    "{}{}{}{}{}({}) {}\n",
+                    indent,
+                    if *is_static { "static " } else { "" },
+                    if *is_async { "async " } else { "" },
+                    if *is_private { "#" } else { "" },
+                    name,
+                    params_source(params),
+                    block_source(body, level)
+                ));
+            }
+            ClassMember::Property { name, value, is_static, is_private } => {
+                out.push_str(&format!(
+                    "{}{}{}{} = {};\n",
+                    indent,
+                    if *is_static { "static " } else { "" },
+                    if *is_private { "#" } else { "" },
+                    name,
+                    value.expr_source()
+                ));
+            }
+        }
+    }
+}
+
+impl ObjectProperty {
+    fn to_source(&self) -> String {
+        match self {
+            ObjectProperty::KeyValue { key, value } => format!("{}: {}", property_key(key), value.expr_source()),
+            ObjectProperty::Computed { key, value } => format!("[{}]: {}", key.expr_source(), value.expr_source()),
+            ObjectProperty::Shorthand(name) => name.clone(),
+            ObjectProperty::Method { name, params, body, is_async } => format!(
+                "{}{}({}) {}",
+                if *is_async { "async " } else { "" },
+                property_key(name),
+                params_source(params),
+                block_source(body, 0)
+            ),
+            ObjectProperty::Spread(expr) => format!("...{}", expr.operand_source()),
+        }
+    }
+}
+
+impl DestructuringTarget {
+    fn to_source(&self) -> String {
+        let mut out = if self.is_rest { format!("...{}", self.name) } else { self.name.clone() };
+        if let Some(alias) = &self.alias {
+            out.push_str(&format!(": {}", alias));
+        }
+        if let Some(default_value) = &self.default_value {
+            out.push_str(&format!(" = {}", default_value.expr_source()));
+        }
+        out
+    }
+}
+
+// `{ ... }` with each statement on its own line, closing brace at `level`
+fn block_source(statements: &[AstNode], level: usize) -> String {
+    if statements.is_empty() {
+        return "{}".to_string();
+    }
+    let mut out = String::from("{\n");
+    for stmt in statements {
+        stmt.write_statement(&mut out, level + 1);
+    }
+    out.push_str(&INDENT.repeat(level));
+    out.push('}');
+    out
+}
+
+// Loop and if bodies are always rendered as blocks
+fn branch_source(body: &AstNode, level: usize) -> String {
+    match body {
+        AstNode::Block(statements) => block_source(statements, level),
+        stmt => block_source(std::slice::from_ref(stmt), level),
+    }
+}
+
+fn params_source(params: &[Parameter]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|param| match (&param.default_value, param.is_rest) {
+            (_, true) => format!("...{}", param.name),
+            (Some(default_value), false) => format!("{} = {}", param.name, default_value.expr_source()),
+            (None, false) => param.name.clone(),
+        })
+        .collect();
+    params.join(", ")
+}
+
+fn args_source(args: &[AstNode]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| arg.expr_source()).collect();
+    args.join(", ")
+}
+
+fn alias_source(name: &str, alias: &Option<String>) -> String {
+    match alias {
+        Some(alias) => format!("{} as {}", name, alias),
+        None => name.to_string(),
+    }
+}
+
+fn label_suffix(label: &Option<String>) -> String {
+    label.as_ref().map(|label| format!(" {}", label)).unwrap_or_default()
+}
+
+fn property_key(key: &str) -> String {
+    let is_identifier = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier { key.to_string() } else { quote(key) }
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+
+/// A read-only pass over an AST
+///
+/// Every hook defaults to walking the node's children, so an implementation
+/// only overrides the nodes it cares about and calls `walk` from the override
+/// to keep descending.
+pub trait Visitor {
+    /// Called for every node; dispatches to the specific hooks below
+    fn visit_node(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Identifier(_) => self.visit_identifier(node),
+            AstNode::LetDecl { .. } | AstNode::ConstDecl { .. } => self.visit_declaration(node),
+            AstNode::BinaryOp { .. } | AstNode::UnaryOp { .. } => self.visit_operator(node),
+            AstNode::Function { .. } | AstNode::ArrowFunction { .. } => self.visit_function(node),
+            AstNode::FunctionCall { .. } => self.visit_function_call(node),
+            AstNode::MethodCall { .. } | AstNode::OptionalMethodCall { .. } => self.visit_method_call(node),
+            AstNode::ClassDeclaration { .. } => self.visit_class(node),
+            AstNode::If { .. }
+            | AstNode::While { .. }
+            | AstNode::For { .. }
+            | AstNode::ForIn { .. }
+            | AstNode::ForOf { .. }
+            | AstNode::DoWhile { .. }
+            | AstNode::Switch { .. }
+            | AstNode::Try { .. } => self.visit_control_flow(node),
+            _ => walk(node, self),
+        }
+    }
+    
+    fn visit_identifier(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_declaration(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_operator(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_function(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_function_call(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_method_call(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_class(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+    
+    fn visit_control_flow(&mut self, node: &AstNode) {
+        walk(node, self)
+    }
+}
+
+/// Visit each child of `node` in source order
+pub fn walk<V: Visitor + ?Sized>(node: &AstNode, visitor: &mut V) {
+    let mut visit = |child: &AstNode| visitor.visit_node(child);
+    let visit_params = |params: &[Parameter], visit: &mut dyn FnMut(&AstNode)| {
+        for param in params {
+            if let Some(default_value) = &param.default_value {
+                visit(default_value);
+            }
+        }
+    };
+    
+    match node {
+        AstNode::Number(_)
+        | AstNode::Float(_)
+        | AstNode::String(_)
+        | AstNode::Regex(_)
+        | AstNode::Boolean(_)
+        | AstNode::Null
+        | AstNode::Undefined
+        | AstNode::Identifier(_)
+        | AstNode::Break(_)
+        | AstNode::Continue(_)
+        | AstNode::Import { .. }
+        | AstNode::ExportNamed { .. }
+        | AstNode::ExportAll { .. } => {}
+        
+        AstNode::TemplateLiteral(nodes)
+        | AstNode::Sequence(nodes)
+        | AstNode::ArrayLiteral(nodes)
+        | AstNode::Block(nodes)
+        | AstNode::Program(nodes)
+        | AstNode::SuperCall { args: nodes }
+        | AstNode::SuperMethodCall { args: nodes, .. } => nodes.iter().for_each(visit),
+        
+        AstNode::LetDecl { value, .. } | AstNode::ConstDecl { value, .. } => visit(value),
+        AstNode::ArrayDestructuring { targets, value } | AstNode::ObjectDestructuring { targets, value } => {
+            for target in targets {
+                if let Some(default_value) = &target.default_value {
+                    visit(default_value);
+                }
+            }
+            visit(value);
+        }
+        
+        AstNode::BinaryOp { left, right, .. } | AstNode::NullishCoalescing { left, right } => {
+            visit(left);
+            visit(right);
+        }
+        AstNode::TernaryOp { condition, true_expr, false_expr } => {
+            visit(condition);
+            visit(true_expr);
+            visit(false_expr);
+        }
+        AstNode::UnaryOp { expr, .. }
+        | AstNode::PreIncrement(expr)
+        | AstNode::PostIncrement(expr)
+        | AstNode::PreDecrement(expr)
+        | AstNode::PostDecrement(expr)
+        | AstNode::AwaitExpr { expr }
+        | AstNode::SpreadElement(expr)
+        | AstNode::Return(expr)
+        | AstNode::Throw(expr)
+        | AstNode::NewPromise { executor: expr }
+        | AstNode::PromiseResolve { value: expr }
+        | AstNode::PromiseReject { reason: expr }
+        | AstNode::PromiseAll { iterable: expr }
+        | AstNode::PromiseRace { iterable: expr }
+        | AstNode::PromiseAllSettled { iterable: expr }
+        | AstNode::PromiseAny { iterable: expr }
+        | AstNode::PropertyAccess { object: expr, .. }
+        | AstNode::OptionalPropertyAccess { object: expr, .. }
+        | AstNode::Labeled { body: expr, .. }
+        | AstNode::Export { declaration: expr }
+        | AstNode::ExportDefault { expression: expr } => visit(expr),
+        
+        AstNode::ComputedPropertyAccess { object, property }
+        | AstNode::OptionalComputedPropertyAccess { object, property } => {
+            visit(object);
+            visit(property);
+        }
+        AstNode::MethodCall { object, args, .. } | AstNode::OptionalMethodCall { object, args, .. } => {
+            visit(object);
+            args.iter().for_each(visit);
+        }
+        AstNode::FunctionCall { callee, args } | AstNode::NewExpr { constructor: callee, args } => {
+            visit(callee);
+            args.iter().for_each(visit);
+        }
+        AstNode::Assignment { target, value } | AstNode::CompoundAssignment { target, value, .. } => {
+            visit(target);
+            visit(value);
+        }
+        
+        AstNode::Function { params, body, .. } | AstNode::ArrowFunction { params, body, .. } => {
+            visit_params(params, &mut visit);
+            body.iter().for_each(visit);
+        }
+        AstNode::ClassDeclaration { body, .. } => {
+            for member in body {
+                match member {
+                    ClassMember::Constructor { params, body } | ClassMember::Method { params, body, .. } => {
+                        visit_params(params, &mut visit);
+                        body.iter().for_each(&mut visit);
+                    }
+                    ClassMember::Property { value, .. } => visit(value),
+                }
+            }
+        }
+        
+        AstNode::PromiseThen { promise, on_fulfilled, on_rejected } => {
+            visit(promise);
+            if let Some(on_fulfilled) = on_fulfilled {
+                visit(on_fulfilled);
+            }
+            if let Some(on_rejected) = on_rejected {
+                visit(on_rejected);
+            }
+        }
+        AstNode::PromiseCatch { promise, on_rejected: handler }
+        | AstNode::PromiseFinally { promise, on_finally: handler } => {
+            visit(promise);
+            visit(handler);
+        }
+        AstNode::ObjectLiteral(properties) => {
+            for property in properties {
+                match property {
+                    ObjectProperty::KeyValue { value, .. } => visit(value),
+                    ObjectProperty::Computed { key, value } => {
+                        visit(key);
+                        visit(value);
+                    }
+                    ObjectProperty::Shorthand(_) => {}
+                    ObjectProperty::Method { params, body, .. } => {
+                        visit_params(params, &mut visit);
+                        body.iter().for_each(&mut visit);
+                    }
+                    ObjectProperty::Spread(expr) => visit(expr),
+                }
+            }
+        }
+        
+        AstNode::If { condition, then_branch, else_branch } => {
+            visit(condition);
+            visit(then_branch);
+            if let Some(else_branch) = else_branch {
+                visit(else_branch);
+            }
+        }
+        AstNode::While { condition, body } => {
+            visit(condition);
+            visit(body);
+        }
+        AstNode::DoWhile { body, condition } => {
+            visit(body);
+            visit(condition);
+        }
+        AstNode::For { init, condition, update, body } => {
+            for part in [init, condition, update].into_iter().flatten() {
+                visit(part);
+            }
+            visit(body);
+        }
+        AstNode::ForIn { object: source, body, .. } | AstNode::ForOf { iterable: source, body, .. } => {
+            visit(source);
+            visit(body);
+        }
+        AstNode::Switch { expression, cases, default } => {
+            visit(expression);
+            for case in cases {
+                visit(&case.value);
+                case.body.iter().for_each(&mut visit);
+            }
+            if let Some(default) = default {
+                default.iter().for_each(visit);
+            }
+        }
+        AstNode::Try { body, catch_body, finally_body, .. } => {
+            body.iter().for_each(&mut visit);
+            catch_body.iter().for_each(&mut visit);
+            if let Some(finally_body) = finally_body {
+                finally_body.iter().for_each(visit);
+            }
+        }
+    }
+}
+
+impl AstNode {
+    /// Mutable references to each child of this node in source order, for
+    /// passes that rewrite the tree in place
+    pub fn children_mut(&mut self) -> Vec<&mut AstNode> {
+        fn params_mut(params: &mut [Parameter]) -> impl Iterator<Item = &mut AstNode> {
+            params.iter_mut().filter_map(|param| param.default_value.as_deref_mut())
+        }
+        
+        let mut children: Vec<&mut AstNode> = Vec::new();
+        match self {
+            AstNode::Number(_)
+            | AstNode::Float(_)
+            | AstNode::String(_)
+            | AstNode::Regex(_)
+            | AstNode::Boolean(_)
+            | AstNode::Null
+            | AstNode::Undefined
+            | AstNode::Identifier(_)
+            | AstNode::Break(_)
+            | AstNode::Continue(_)
+            | AstNode::Import { .. }
+            | AstNode::ExportNamed { .. }
+            | AstNode::ExportAll { .. } => {}
+            
+            AstNode::TemplateLiteral(nodes)
+            | AstNode::Sequence(nodes)
+            | AstNode::ArrayLiteral(nodes)
+            | AstNode::Block(nodes)
+            | AstNode::Program(nodes)
+            | AstNode::SuperCall { args: nodes }
+            | AstNode::SuperMethodCall { args: nodes, .. } => children.extend(nodes.iter_mut()),
+            
+            AstNode::LetDecl { value, .. } | AstNode::ConstDecl { value, .. } => children.push(value),
+            AstNode::ArrayDestructuring { targets, value } | AstNode::ObjectDestructuring { targets, value } => {
+                children.extend(targets.iter_mut().filter_map(|target| target.default_value.as_deref_mut()));
+                children.push(value);
+            }
+            
+            AstNode::BinaryOp { left, right, .. } | AstNode::NullishCoalescing { left, right } => {
+                children.push(left);
+                children.push(right);
+            }
+            AstNode::TernaryOp { condition, true_expr, false_expr } => {
+                children.push(condition);
+                children.push(true_expr);
+                children.push(false_expr);
+            }
+            AstNode::UnaryOp { expr, .. }
+            | AstNode::PreIncrement(expr)
+            | AstNode::PostIncrement(expr)
+            | AstNode::PreDecrement(expr)
+            | AstNode::PostDecrement(expr)
+            | AstNode::AwaitExpr { expr }
+            | AstNode::SpreadElement(expr)
+            | AstNode::Return(expr)
+            | AstNode::Throw(expr)
+            | AstNode::NewPromise { executor: expr }
+            | AstNode::PromiseResolve { value: expr }
+            | AstNode::PromiseReject { reason: expr }
+            | AstNode::PromiseAll { iterable: expr }
+            | AstNode::PromiseRace { iterable: expr }
+            | AstNode::PromiseAllSettled { iterable: expr }
+            | AstNode::PromiseAny { iterable: expr }
+            | AstNode::PropertyAccess { object: expr, .. }
+            | AstNode::OptionalPropertyAccess { object: expr, .. }
+            | AstNode::Labeled { body: expr, .. }
+            | AstNode::Export { declaration: expr }
+            | AstNode::ExportDefault { expression: expr } => children.push(expr),
+            
+            AstNode::ComputedPropertyAccess { object, property }
+            | AstNode::OptionalComputedPropertyAccess { object, property } => {
+                children.push(object);
+                children.push(property);
+            }
+            AstNode::MethodCall { object, args, .. } | AstNode::OptionalMethodCall { object, args, .. } => {
+                children.push(object);
+                children.extend(args.iter_mut());
+            }
+            AstNode::FunctionCall { callee, args } | AstNode::NewExpr { constructor: callee, args } => {
+                children.push(callee);
+                children.extend(args.iter_mut());
+            }
+            AstNode::Assignment { target, value } | AstNode::CompoundAssignment { target, value, .. } => {
+                children.push(target);
+                children.push(value);
+            }
+            
+            AstNode::Function { params, body, .. } | AstNode::ArrowFunction { params, body, .. } => {
+                children.extend(params_mut(params));
+                children.extend(body.iter_mut());
+            }
+            AstNode::ClassDeclaration { body, .. } => {
+                for member in body {
+                    match member {
+                        ClassMember::Constructor { params, body } | ClassMember::Method { params, body, .. } => {
+                            children.extend(params_mut(params));
+                            children.extend(body.iter_mut());
+                        }
+                        ClassMember::Property { value, .. } => children.push(value),
+                    }
+                }
+            }
+            
+            AstNode::PromiseThen { promise, on_fulfilled, on_rejected } => {
+                children.push(promise);
+                children.extend(on_fulfilled.as_deref_mut());
+                children.extend(on_rejected.as_deref_mut());
+            }
+            AstNode::PromiseCatch { promise, on_rejected: handler }
+            | AstNode::PromiseFinally { promise, on_finally: handler } => {
+                children.push(promise);
+                children.push(handler);
+            }
+            AstNode::ObjectLiteral(properties) => {
+                for property in properties {
+                    match property {
+                        ObjectProperty::KeyValue { value, .. } => children.push(value),
+                        ObjectProperty::Computed { key, value } => {
+                            children.push(key);
+                            children.push(value);
+                        }
+                        ObjectProperty::Shorthand(_) => {}
+                        ObjectProperty::Method { params, body, .. } => {
+                            children.extend(params_mut(params));
+                            children.extend(body.iter_mut());
+                        }
+                        ObjectProperty::Spread(expr) => children.push(expr),
+                    }
+                }
+            }
+            
+            AstNode::If { condition, then_branch, else_branch } => {
+                children.push(condition);
+                children.push(then_branch);
+                children.extend(else_branch.as_deref_mut());
+            }
+            AstNode::While { condition, body } => {
+                children.push(condition);
+                children.push(body);
+            }
+            AstNode::DoWhile { body, condition } => {
+                children.push(body);
+                children.push(condition);
+            }
+            AstNode::For { init, condition, update, body } => {
+                children.extend(init.as_deref_mut());
+                children.extend(condition.as_deref_mut());
+                children.extend(update.as_deref_mut());
+                children.push(body);
+            }
+            AstNode::ForIn { object: source, body, .. } | AstNode::ForOf { iterable: source, body, .. } => {
+                children.push(source);
+                children.push(body);
+            }
+            AstNode::Switch { expression, cases, default } => {
+                children.push(expression);
+                for case in cases {
+                    children.push(&mut case.value);
+                    children.extend(case.body.iter_mut());
+                }
+                if let Some(default) = default {
+                    children.extend(default.iter_mut());
+                }
+            }
+            AstNode::Try { body, catch_body, finally_body, .. } => {
+                children.extend(body.iter_mut());
+                children.extend(catch_body.iter_mut());
+                if let Some(finally_body) = finally_body {
+                    children.extend(finally_body.iter_mut());
+                }
+            }
+        }
+        children
+    }
+}
@@ -1,7 +1,10 @@
 use pest_derive::Parser;
 use pest::Parser;
 use pest::iterators::{Pair, Pairs};
-use crate::parser::ast::AstNode;
+use crate::lexer::{parse_float_literal, parse_integer_literal, unescape_string};
+use crate::parser::ast::{
+    AstNode, ClassMember, DestructuringTarget, ExportSpecifier, ImportSpecifier, ObjectProperty, Parameter, SwitchCase,
+};
 
 /// Parser for SmashLang
 #[derive(Parser)]
@@ -17,22 +20,650 @@ impl SmashParser {
 
 impl AstNode {
     /// Convert a pest Pair to an AstNode
+    ///
+    /// Returns `None` for pairs that don't stand for a node, such as the end
+    /// of input, and for constructs the AST has no shape for, such as nested
+    /// destructuring patterns.
     pub fn from_pair(pair: Pair<Rule>) -> Option<Self> {
-        match pair.as_rule() {
-            Rule::program => {
-                let nodes = pair.into_inner()
-                    .filter_map(AstNode::from_pair)
-                    .collect();
-                Some(AstNode::Program(nodes))
+        let pair = innermost(pair);
+        let node = match pair.as_rule() {
+            Rule::program => AstNode::Program(statements(pair)),
+
+            // Rules that only wrap a single node
+            Rule::statement
+            | Rule::variable_declaration
+            | Rule::export_statement
+            | Rule::expression
+            | Rule::left_hand_side_expression
+            | Rule::primary_expression
+            | Rule::literal
+            | Rule::string_literal
+            | Rule::super_expression
+            | Rule::expression_statement => return first_node(pair),
+
+            // Statements
+            Rule::let_declaration | Rule::const_declaration => {
+                let is_const = pair.as_rule() == Rule::const_declaration;
+                let mut inner = pair.into_inner();
+                let target = inner.next()?;
+                let value = Box::new(AstNode::from_pair(inner.next()?)?);
+                match target.as_rule() {
+                    Rule::identifier if is_const => AstNode::ConstDecl { name: target.as_str().to_string(), value },
+                    Rule::identifier => AstNode::LetDecl { name: target.as_str().to_string(), value },
+                    _ => destructuring(target, value)?,
+                }
             }
-            // Add more rules here as needed for deeper parsing
-            _ => None,
-        }
+            Rule::function_declaration | Rule::function_expression => {
+                let (mut name, mut params, mut body, mut is_async) = (String::new(), Vec::new(), Vec::new(), false);
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::async_modifier => is_async = true,
+                        Rule::identifier => name = child.as_str().to_string(),
+                        Rule::parameter_list => params = parameters(child)?,
+                        Rule::block => body = statements(child),
+                        _ => {}
+                    }
+                }
+                AstNode::Function { name, params, body, is_async }
+            }
+            Rule::class_declaration => {
+                let mut inner = pair.into_inner();
+                let name = inner.next()?.as_str().to_string();
+                let mut parent = None;
+                let mut body = Vec::new();
+                for child in inner {
+                    match child.as_rule() {
+                        Rule::identifier => parent = Some(child.as_str().to_string()),
+                        _ => body.push(class_member(child)?),
+                    }
+                }
+                AstNode::ClassDeclaration { name, parent, body }
+            }
+            Rule::return_statement => {
+                AstNode::Return(Box::new(first_node(pair).unwrap_or(AstNode::Undefined)))
+            }
+            Rule::if_statement => {
+                let mut inner = pair.into_inner();
+                AstNode::If {
+                    condition: Box::new(AstNode::from_pair(inner.next()?)?),
+                    then_branch: Box::new(AstNode::from_pair(inner.next()?)?),
+                    else_branch: match inner.next() {
+                        Some(branch) => Some(Box::new(AstNode::from_pair(branch)?)),
+                        None => None,
+                    },
+                }
+            }
+            Rule::while_statement => {
+                let mut inner = pair.into_inner();
+                AstNode::While {
+                    condition: Box::new(AstNode::from_pair(inner.next()?)?),
+                    body: Box::new(AstNode::from_pair(inner.next()?)?),
+                }
+            }
+            Rule::do_while_statement => {
+                let mut inner = pair.into_inner();
+                AstNode::DoWhile {
+                    body: Box::new(AstNode::from_pair(inner.next()?)?),
+                    condition: Box::new(AstNode::from_pair(inner.next()?)?),
+                }
+            }
+            Rule::for_statement => for_statement(pair)?,
+            Rule::for_in_statement | Rule::for_of_statement => {
+                let is_in = pair.as_rule() == Rule::for_in_statement;
+                let mut inner = pair.into_inner();
+                let target = inner.next()?;
+                if target.as_rule() != Rule::identifier {
+                    return None;
+                }
+                let var_name = target.as_str().to_string();
+                let collection = Box::new(AstNode::from_pair(inner.next()?)?);
+                let body = Box::new(AstNode::from_pair(inner.next()?)?);
+                if is_in {
+                    AstNode::ForIn { var_name, object: collection, body }
+                } else {
+                    AstNode::ForOf { var_name, iterable: collection, body }
+                }
+            }
+            Rule::switch_statement => {
+                let mut inner = pair.into_inner();
+                let expression = Box::new(AstNode::from_pair(inner.next()?)?);
+                let mut cases = Vec::new();
+                let mut default = None;
+                for clause in inner {
+                    if clause.as_rule() == Rule::default_clause {
+                        default = Some(statements(clause));
+                    } else {
+                        let mut clause = clause.into_inner();
+                        let value = AstNode::from_pair(clause.next()?)?;
+                        cases.push(SwitchCase { value, body: clause.filter_map(AstNode::from_pair).collect() });
+                    }
+                }
+                AstNode::Switch { expression, cases, default }
+            }
+            Rule::try_statement => {
+                let mut inner = pair.into_inner();
+                let body = statements(inner.next()?);
+                let (mut catch_param, mut catch_body, mut finally_body) = (None, Vec::new(), None);
+                for clause in inner {
+                    if clause.as_rule() == Rule::catch_clause {
+                        let mut clause = clause.into_inner();
+                        catch_param = Some(clause.next()?.as_str().to_string());
+                        catch_body = statements(clause.next()?);
+                    } else {
+                        finally_body = Some(statements(clause.into_inner().next()?));
+                    }
+                }
+                AstNode::Try { body, catch_param, catch_body, finally_body }
+            }
+            Rule::throw_statement => AstNode::Throw(Box::new(first_node(pair)?)),
+            Rule::break_statement => AstNode::Break(pair.into_inner().next().map(|label| label.as_str().to_string())),
+            Rule::continue_statement => AstNode::Continue(pair.into_inner().next().map(|label| label.as_str().to_string())),
+            Rule::labeled_statement => {
+                let mut inner = pair.into_inner();
+                AstNode::Labeled {
+                    label: inner.next()?.as_str().to_string(),
+                    body: Box::new(AstNode::from_pair(inner.next()?)?),
+                }
+            }
+            Rule::block => AstNode::Block(statements(pair)),
+
+            // Modules
+            Rule::import_statement => {
+                let (mut default_import, mut named_imports, mut namespace_import, mut source) = (None, Vec::new(), None, None);
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::default_import => default_import = Some(child.as_str().to_string()),
+                        Rule::named_imports => {
+                            for specifier in child.into_inner() {
+                                let (name, alias) = renamed(specifier)?;
+                                named_imports.push(ImportSpecifier { name, alias });
+                            }
+                        }
+                        Rule::namespace_import => namespace_import = Some(child.into_inner().next()?.as_str().to_string()),
+                        _ => source = Some(string_value(child)?),
+                    }
+                }
+                let side_effect_only = default_import.is_none() && named_imports.is_empty() && namespace_import.is_none();
+                AstNode::Import { source: source?, default_import, named_imports, namespace_import, side_effect_only }
+            }
+            Rule::export_declaration => AstNode::Export { declaration: Box::new(first_node(pair)?) },
+            Rule::export_default_declaration => AstNode::ExportDefault { expression: Box::new(first_node(pair)?) },
+            Rule::export_named_declaration | Rule::export_from_declaration => {
+                let (mut specifiers, mut source, mut exported_name) = (Vec::new(), None, None);
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::export_specifier => {
+                            let (name, exported_name) = renamed(child)?;
+                            specifiers.push(ExportSpecifier { name, exported_name });
+                        }
+                        Rule::identifier => exported_name = Some(child.as_str().to_string()),
+                        _ => source = Some(string_value(child)?),
+                    }
+                }
+                match source {
+                    Some(source) if specifiers.is_empty() => AstNode::ExportAll { source, exported_name },
+                    source => AstNode::ExportNamed { specifiers, source },
+                }
+            }
+
+            // Expressions
+            Rule::sequence_expression => {
+                let mut expressions: Vec<AstNode> = pair.into_inner().filter_map(AstNode::from_pair).collect();
+                if expressions.len() == 1 {
+                    expressions.pop()?
+                } else {
+                    AstNode::Sequence(expressions)
+                }
+            }
+            Rule::assignment_expression => {
+                let mut inner = pair.into_inner();
+                let target = inner.next()?;
+                let Some(next) = inner.next() else {
+                    return AstNode::from_pair(target);
+                };
+                if target.as_rule() == Rule::destructuring_pattern {
+                    return destructuring(target, Box::new(AstNode::from_pair(next)?));
+                }
+                let op = next.as_str();
+                let target = Box::new(AstNode::from_pair(target)?);
+                let value = Box::new(AstNode::from_pair(inner.next()?)?);
+                if op == "=" {
+                    AstNode::Assignment { target, value }
+                } else {
+                    AstNode::CompoundAssignment { target, op: op.to_string(), value }
+                }
+            }
+            Rule::conditional_expression => {
+                let mut inner = pair.into_inner();
+                let condition = AstNode::from_pair(inner.next()?)?;
+                match inner.next() {
+                    Some(true_expr) => AstNode::TernaryOp {
+                        condition: Box::new(condition),
+                        true_expr: Box::new(AstNode::from_pair(true_expr)?),
+                        false_expr: Box::new(AstNode::from_pair(inner.next()?)?),
+                    },
+                    None => condition,
+                }
+            }
+            Rule::nullish_coalescing_expression => {
+                let mut operands = pair.into_inner().map(AstNode::from_pair);
+                let mut node = operands.next()??;
+                for right in operands {
+                    node = AstNode::NullishCoalescing { left: Box::new(node), right: Box::new(right?) };
+                }
+                node
+            }
+            Rule::logical_or_expression
+            | Rule::logical_and_expression
+            | Rule::bitwise_or_expression
+            | Rule::bitwise_xor_expression
+            | Rule::bitwise_and_expression
+            | Rule::equality_expression
+            | Rule::relational_expression
+            | Rule::shift_expression
+            | Rule::additive_expression
+            | Rule::multiplicative_expression
+            | Rule::exponent_expression => binary_chain(pair)?,
+            Rule::unary_expression => {
+                let text = pair.as_str();
+                let start = pair.as_span().start();
+                let operand = pair.into_inner().next()?;
+                if operand.as_rule() != Rule::unary_expression {
+                    return AstNode::from_pair(operand);
+                }
+                let op = text[..operand.as_span().start() - start].trim();
+                let expr = Box::new(AstNode::from_pair(operand)?);
+                match op {
+                    "++" => AstNode::PreIncrement(expr),
+                    "--" => AstNode::PreDecrement(expr),
+                    _ => AstNode::UnaryOp { op: op.to_string(), expr },
+                }
+            }
+            Rule::await_expression => AstNode::AwaitExpr { expr: Box::new(first_node(pair)?) },
+            Rule::postfix_expression => {
+                let text = pair.as_str();
+                let start = pair.as_span().start();
+                let operand = pair.into_inner().next()?;
+                let suffix = text[operand.as_span().end() - start..].trim();
+                let expr = AstNode::from_pair(operand)?;
+                match suffix {
+                    "++" => AstNode::PostIncrement(Box::new(expr)),
+                    "--" => AstNode::PostDecrement(Box::new(expr)),
+                    _ => expr,
+                }
+            }
+            Rule::call_expression | Rule::member_expression => {
+                let mut inner = pair.into_inner();
+                let mut node = AstNode::from_pair(inner.next()?)?;
+                for suffix in inner {
+                    node = if suffix.as_rule() == Rule::arguments {
+                        call(node, arguments(suffix)?)
+                    } else {
+                        member_access(node, suffix)?
+                    };
+                }
+                node
+            }
+            Rule::new_expression => {
+                let mut inner = pair.into_inner();
+                let constructor = Box::new(AstNode::from_pair(inner.next()?)?);
+                let mut args = match inner.next() {
+                    Some(args) => arguments(args)?,
+                    None => Vec::new(),
+                };
+                match &*constructor {
+                    AstNode::Identifier(name) if name == "Promise" && args.len() == 1 => {
+                        AstNode::NewPromise { executor: Box::new(args.pop()?) }
+                    }
+                    _ => AstNode::NewExpr { constructor, args },
+                }
+            }
+            Rule::super_call => AstNode::SuperCall { args: arguments(pair.into_inner().next()?)? },
+            Rule::super_method_call => {
+                let mut inner = pair.into_inner();
+                AstNode::SuperMethodCall {
+                    method: inner.next()?.as_str().to_string(),
+                    args: arguments(inner.next()?)?,
+                }
+            }
+            Rule::arrow_function => {
+                let (mut params, mut body, mut expression, mut is_async) = (Vec::new(), Vec::new(), false, false);
+                for child in pair.into_inner() {
+                    match child.as_rule() {
+                        Rule::async_modifier => is_async = true,
+                        Rule::parameter_list => params = parameters(child)?,
+                        Rule::block => body = statements(child),
+                        _ => {
+                            body = vec![AstNode::from_pair(child)?];
+                            expression = true;
+                        }
+                    }
+                }
+                AstNode::ArrowFunction { params, body, expression, is_async }
+            }
+            Rule::array_literal => {
+                let mut elements = Vec::new();
+                for element in pair.into_inner() {
+                    let element = element.into_inner().next()?;
+                    elements.push(match element.as_rule() {
+                        Rule::spread_element => AstNode::SpreadElement(Box::new(first_node(element)?)),
+                        _ => AstNode::from_pair(element)?,
+                    });
+                }
+                AstNode::ArrayLiteral(elements)
+            }
+            Rule::object_literal => {
+                let mut properties = Vec::new();
+                for property in pair.into_inner() {
+                    let mut inner = property.into_inner();
+                    let key = inner.next()?;
+                    properties.push(match (key.as_rule(), inner.next()) {
+                        (Rule::spread_property, _) => ObjectProperty::Spread(first_node(key)?),
+                        (Rule::computed_property_name, value) => ObjectProperty::Computed {
+                            key: first_node(key)?,
+                            value: AstNode::from_pair(value?)?,
+                        },
+                        (Rule::method_property, _) => {
+                            let (mut name, mut params, mut body, mut is_async) = (String::new(), Vec::new(), Vec::new(), false);
+                            for child in key.into_inner() {
+                                match child.as_rule() {
+                                    Rule::async_modifier => is_async = true,
+                                    Rule::identifier => name = child.as_str().to_string(),
+                                    Rule::parameter_list => params = parameters(child)?,
+                                    Rule::block => body = statements(child),
+                                    _ => {}
+                                }
+                            }
+                            ObjectProperty::Method { name, params, body, is_async }
+                        }
+                        (Rule::identifier, None) => ObjectProperty::Shorthand(key.as_str().to_string()),
+                        (Rule::identifier, Some(value)) => ObjectProperty::KeyValue {
+                            key: key.as_str().to_string(),
+                            value: AstNode::from_pair(value)?,
+                        },
+                        (_, value) => ObjectProperty::KeyValue {
+                            key: string_value(key)?,
+                            value: AstNode::from_pair(value?)?,
+                        },
+                    });
+                }
+                AstNode::ObjectLiteral(properties)
+            }
+
+            // Literals
+            Rule::number_literal => {
+                let text = pair.as_str();
+                let is_float = !text.starts_with("0x") && !text.starts_with("0X")
+                    && text.contains(['.', 'e', 'E']);
+                if is_float {
+                    AstNode::Float(parse_float_literal(text).ok()?)
+                } else {
+                    match parse_integer_literal(text) {
+                        Ok(n) => AstNode::Number(n),
+                        // Too large for an integer
+                        Err(_) => AstNode::Float(parse_float_literal(text).ok()?),
+                    }
+                }
+            }
+            Rule::bigint_literal => AstNode::Number(parse_integer_literal(pair.as_str().trim_end_matches('n')).ok()?),
+            Rule::double_quoted_string | Rule::single_quoted_string => AstNode::String(string_value(pair)?),
+            Rule::template_string => AstNode::TemplateLiteral(template_parts(pair.into_inner().next()?)?),
+            Rule::boolean_literal => AstNode::Boolean(pair.as_str() == "true"),
+            Rule::null_literal => AstNode::Null,
+            Rule::undefined_literal => AstNode::Undefined,
+            Rule::regex_literal => AstNode::Regex(pair.as_str().to_string()),
+            Rule::identifier => AstNode::Identifier(pair.as_str().to_string()),
+
+            _ => return None,
+        };
+        Some(node)
     }
-    
+
     /// Convert pest Pairs to an AstNode
     pub fn from_pairs(_pairs: Pairs<'_, Rule>) -> Self {
         // Placeholder: returns a dummy node
         AstNode::Undefined
     }
-}
\ No newline at end of file
+}
+
+/// Skip down through pairs that only pass a single child through, such as
+/// the precedence levels an expression without operators goes through
+///
+/// Each level would otherwise cost a `from_pair` frame, which is enough to
+/// overflow the stack on moderately nested expressions.
+fn innermost(mut pair: Pair<Rule>) -> Pair<Rule> {
+    loop {
+        let mut inner = pair.clone().into_inner();
+        match (inner.next(), inner.next()) {
+            (Some(child), None) if child.as_span() == pair.as_span() => pair = child,
+            _ => return pair,
+        }
+    }
+}
+
+/// The node of the first inner pair
+fn first_node(pair: Pair<Rule>) -> Option<AstNode> {
+    pair.into_inner().next().and_then(AstNode::from_pair)
+}
+
+/// The statements of a program, block or clause
+fn statements(pair: Pair<Rule>) -> Vec<AstNode> {
+    pair.into_inner().filter_map(AstNode::from_pair).collect()
+}
+
+/// Contents of a string literal, with escapes resolved
+fn string_value(pair: Pair<Rule>) -> Option<String> {
+    let mut pair = pair;
+    while pair.as_rule() != Rule::inner_string {
+        pair = pair.into_inner().next()?;
+    }
+    Some(unescape_string(pair.as_str()))
+}
+
+/// `name` or `name as alias` in an import or export list
+fn renamed(pair: Pair<Rule>) -> Option<(String, Option<String>)> {
+    let mut inner = pair.into_inner();
+    let name = inner.next()?.as_str().to_string();
+    Some((name, inner.next().map(|alias| alias.as_str().to_string())))
+}
+
+fn parameters(pair: Pair<Rule>) -> Option<Vec<Parameter>> {
+    pair.into_inner()
+        .map(|parameter| {
+            let mut inner = parameter.into_inner();
+            let first = inner.next()?;
+            if first.as_rule() == Rule::rest_parameter {
+                return Some(Parameter::new(first.into_inner().next()?.as_str().to_string()).as_rest());
+            }
+            let parameter = Parameter::new(first.as_str().to_string());
+            match inner.next() {
+                Some(default) => Some(parameter.with_default(AstNode::from_pair(default)?)),
+                None => Some(parameter),
+            }
+        })
+        .collect()
+}
+
+fn arguments(pair: Pair<Rule>) -> Option<Vec<AstNode>> {
+    pair.into_inner().map(AstNode::from_pair).collect()
+}
+
+/// Call `callee`; calling a property access is a method call
+fn call(callee: AstNode, args: Vec<AstNode>) -> AstNode {
+    match callee {
+        AstNode::PropertyAccess { object, property } => method_call(object, property, args),
+        callee => AstNode::FunctionCall { callee: Box::new(callee), args },
+    }
+}
+
+/// `object.method(args)`, using the promise nodes for the `Promise` statics
+/// and for `then`, `catch` and `finally`
+fn method_call(object: Box<AstNode>, method: String, args: Vec<AstNode>) -> AstNode {
+    let is_promise = matches!(&*object, AstNode::Identifier(name) if name == "Promise");
+    let mut operands = args.iter().cloned().map(Box::new);
+    let (first, second) = (operands.next(), operands.next());
+
+    match (is_promise, method.as_str(), args.len(), first, second) {
+        (true, "resolve", 1, Some(value), _) => AstNode::PromiseResolve { value },
+        (true, "reject", 1, Some(reason), _) => AstNode::PromiseReject { reason },
+        (true, "all", 1, Some(iterable), _) => AstNode::PromiseAll { iterable },
+        (true, "race", 1, Some(iterable), _) => AstNode::PromiseRace { iterable },
+        (true, "allSettled", 1, Some(iterable), _) => AstNode::PromiseAllSettled { iterable },
+        (true, "any", 1, Some(iterable), _) => AstNode::PromiseAny { iterable },
+        (false, "then", 1 | 2, on_fulfilled, on_rejected) => AstNode::PromiseThen { promise: object, on_fulfilled, on_rejected },
+        (false, "catch", 1, Some(on_rejected), _) => AstNode::PromiseCatch { promise: object, on_rejected },
+        (false, "finally", 1, Some(on_finally), _) => AstNode::PromiseFinally { promise: object, on_finally },
+        _ => AstNode::MethodCall { object, method, args },
+    }
+}
+
+/// Apply a `member_access` suffix to `object`
+fn member_access(object: AstNode, pair: Pair<Rule>) -> Option<AstNode> {
+    let object = Box::new(object);
+    let access = pair.into_inner().next()?;
+    Some(match access.as_rule() {
+        Rule::identifier => AstNode::PropertyAccess { object, property: access.as_str().to_string() },
+        Rule::optional_property_access => AstNode::OptionalPropertyAccess {
+            object,
+            property: access.into_inner().next()?.as_str().to_string(),
+        },
+        Rule::optional_computed_property_access => AstNode::OptionalComputedPropertyAccess {
+            object,
+            property: Box::new(first_node(access)?),
+        },
+        Rule::optional_method_call => {
+            let mut inner = access.into_inner();
+            AstNode::OptionalMethodCall {
+                object,
+                method: inner.next()?.as_str().to_string(),
+                args: arguments(inner.next()?)?,
+            }
+        }
+        _ => AstNode::ComputedPropertyAccess { object, property: Box::new(AstNode::from_pair(access)?) },
+    })
+}
+
+/// Fold `a op b op c` left to right. The operators are anonymous in the
+/// grammar, so each is read from the source between its operands.
+fn binary_chain(pair: Pair<Rule>) -> Option<AstNode> {
+    let text = pair.as_str();
+    let start = pair.as_span().start();
+    let mut inner = pair.into_inner();
+
+    let first = inner.next()?;
+    let mut end = first.as_span().end();
+    let mut node = AstNode::from_pair(first)?;
+    for operand in inner {
+        let op = text[end - start..operand.as_span().start() - start].trim().to_string();
+        end = operand.as_span().end();
+        node = AstNode::BinaryOp { left: Box::new(node), op, right: Box::new(AstNode::from_pair(operand)?) };
+    }
+    Some(node)
+}
+
+/// `for (init; condition; update) body`, where every clause is optional
+fn for_statement(pair: Pair<Rule>) -> Option<AstNode> {
+    let text = pair.as_str();
+    let start = pair.as_span().start();
+    let (mut init, mut condition, mut update, mut body) = (None, None, None, None);
+
+    for child in pair.into_inner() {
+        match child.as_rule() {
+            Rule::variable_declaration | Rule::expression_statement => init = Some(Box::new(AstNode::from_pair(child)?)),
+            // The condition is followed by `;`, the update by `)`
+            Rule::sequence_expression if text[child.as_span().end() - start..].trim_start().starts_with(';') => {
+                condition = Some(Box::new(AstNode::from_pair(child)?));
+            }
+            Rule::sequence_expression => update = Some(Box::new(AstNode::from_pair(child)?)),
+            _ => body = Some(Box::new(AstNode::from_pair(child)?)),
+        }
+    }
+
+    Some(AstNode::For { init, condition, update, body: body? })
+}
+
+/// Destructuring declaration or assignment of `value`
+fn destructuring(pattern: Pair<Rule>, value: Box<AstNode>) -> Option<AstNode> {
+    let pattern = pattern.into_inner().next()?;
+    let is_array = pattern.as_rule() == Rule::array_destructuring_pattern;
+
+    let mut targets = Vec::new();
+    for target in pattern.into_inner() {
+        let mut inner = target.into_inner();
+        let first = inner.next()?;
+        let mut target = match first.as_rule() {
+            Rule::rest_element => DestructuringTarget::new(first.into_inner().next()?.as_str().to_string()).as_rest(),
+            Rule::identifier => DestructuringTarget::new(first.as_str().to_string()),
+            // Nested patterns have no AST shape
+            _ => return None,
+        };
+        for rest in inner {
+            target = match rest.as_rule() {
+                Rule::identifier => target.with_alias(rest.as_str().to_string()),
+                Rule::destructuring_pattern => return None,
+                _ => target.with_default(AstNode::from_pair(rest)?),
+            };
+        }
+        targets.push(target);
+    }
+
+    Some(if is_array {
+        AstNode::ArrayDestructuring { targets, value }
+    } else {
+        AstNode::ObjectDestructuring { targets, value }
+    })
+}
+
+fn class_member(pair: Pair<Rule>) -> Option<ClassMember> {
+    let member = pair.into_inner().next()?;
+    let rule = member.as_rule();
+    let is_static = matches!(rule, Rule::static_method_definition | Rule::static_property_definition);
+
+    let (mut name, mut is_private, mut is_async) = (String::new(), false, false);
+    let (mut params, mut body, mut value) = (Vec::new(), Vec::new(), None);
+    for child in member.into_inner() {
+        match child.as_rule() {
+            Rule::async_modifier => is_async = true,
+            Rule::identifier => name = child.as_str().to_string(),
+            Rule::private_identifier => {
+                name = child.as_str().trim_start_matches('#').to_string();
+                is_private = true;
+            }
+            Rule::parameter_list => params = parameters(child)?,
+            Rule::block => body = statements(child),
+            _ => value = Some(Box::new(AstNode::from_pair(child)?)),
+        }
+    }
+
+    Some(match rule {
+        Rule::constructor_method => ClassMember::Constructor { params, body },
+        Rule::method_definition | Rule::static_method_definition => {
+            ClassMember::Method { name, params, body, is_async, is_static, is_private }
+        }
+        _ => ClassMember::Property { name, value: value?, is_static, is_private },
+    })
+}
+
+/// Parts of a template literal: strings between the `${}`
+/// interpolations, and the interpolated expressions
+fn template_parts(pair: Pair<Rule>) -> Option<Vec<AstNode>> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    for part in pair.into_inner() {
+        if part.as_rule() == Rule::template_char {
+            literal.push_str(part.as_str());
+            continue;
+        }
+        if !literal.is_empty() {
+            parts.push(AstNode::String(unescape_string(&literal)));
+            literal.clear();
+        }
+        parts.push(first_node(part)?);
+    }
+
+    if !literal.is_empty() {
+        parts.push(AstNode::String(unescape_string(&literal)));
+    }
+    Some(parts)
+}
@@ -8,8 +8,13 @@ pub mod methods;
 // Re-export main types for easier access
 pub use ast::AstNode;
 pub use ast::{
-    Parameter, ClassMember, DestructuringTarget,
-    SwitchCase, ImportSpecifier, ExportSpecifier
+    Parameter, ClassMember, DestructuringTarget, ObjectProperty,
+    SwitchCase, ImportSpecifier, ExportSpecifier,
+    Visitor, walk
 };
 pub use core::SmashParser;
-pub use core::Rule;
\ No newline at end of file
+pub use core::Rule;
+
+// Tests
+#[cfg(test)]
+mod tests;
\ No newline at end of file
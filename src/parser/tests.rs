@@ -0,0 +1,151 @@
+use crate::parser::{walk, AstNode, Parameter, SmashParser, Visitor};
+
+fn ident(name: &str) -> Box<AstNode> {
+    Box::new(AstNode::Identifier(name.to_string()))
+}
+
+fn parse_ast(source: &str) -> AstNode {
+    let mut pairs = SmashParser::parse(source).unwrap_or_else(|e| panic!("failed to parse:\n{}\n{}", source, e));
+    pairs.next().and_then(AstNode::from_pair).unwrap()
+}
+
+// fn add(a, b) { return a + b; }
+// let total = (1 + 2) * add(3, 4);
+// if (total > 10) { console.log("big"); } else { total = 0; }
+// for (let i = 0; i < 3; i++) { total += i; }
+fn sample_program() -> AstNode {
+    AstNode::Program(vec![
+        AstNode::Function {
+            name: "add".to_string(),
+            params: vec![Parameter::new("a".to_string()), Parameter::new("b".to_string())],
+            body: vec![AstNode::Return(Box::new(AstNode::BinaryOp {
+                left: ident("a"),
+                op: "+".to_string(),
+                right: ident("b"),
+            }))],
+            is_async: false,
+        },
+        AstNode::LetDecl {
+            name: "total".to_string(),
+            value: Box::new(AstNode::BinaryOp {
+                left: Box::new(AstNode::BinaryOp {
+                    left: Box::new(AstNode::Number(1)),
+                    op: "+".to_string(),
+                    right: Box::new(AstNode::Number(2)),
+                }),
+                op: "*".to_string(),
+                right: Box::new(AstNode::FunctionCall {
+                    callee: ident("add"),
+                    args: vec![AstNode::Number(3), AstNode::Number(4)],
+                }),
+            }),
+        },
+        AstNode::If {
+            condition: Box::new(AstNode::BinaryOp {
+                left: ident("total"),
+                op: ">".to_string(),
+                right: Box::new(AstNode::Number(10)),
+            }),
+            then_branch: Box::new(AstNode::Block(vec![AstNode::MethodCall {
+                object: ident("console"),
+                method: "log".to_string(),
+                args: vec![AstNode::String("big".to_string())],
+            }])),
+            else_branch: Some(Box::new(AstNode::Block(vec![AstNode::Assignment {
+                target: ident("total"),
+                value: Box::new(AstNode::Number(0)),
+            }]))),
+        },
+        AstNode::For {
+            init: Some(Box::new(AstNode::LetDecl {
+                name: "i".to_string(),
+                value: Box::new(AstNode::Number(0)),
+            })),
+            condition: Some(Box::new(AstNode::BinaryOp {
+                left: ident("i"),
+                op: "<".to_string(),
+                right: Box::new(AstNode::Number(3)),
+            })),
+            update: Some(Box::new(AstNode::PostIncrement(ident("i")))),
+            body: Box::new(AstNode::Block(vec![AstNode::CompoundAssignment {
+                target: ident("total"),
+                op: "+=".to_string(),
+                value: ident("i"),
+            }])),
+        },
+    ])
+}
+
+#[test]
+fn test_to_source_renders_indented_source() {
+    let expected = "\
+fn add(a, b) {
+    return a + b;
+}
+let total = (1 + 2) * add(3, 4);
+if (total > 10) {
+    console.log(\"big\");
+} else {
+    total = 0;
+}
+for (let i = 0; i < 3; i++) {
+    total += i;
+}
+";
+    assert_eq!(sample_program().to_source(), expected);
+}
+
+#[test]
+fn test_to_source_output_is_valid_syntax() {
+    let source = sample_program().to_source();
+    assert!(SmashParser::parse(&source).is_ok(), "regenerated source does not parse:\n{}", source);
+}
+
+#[test]
+fn test_to_source_round_trip() {
+    let source = "let x = 1 + 2 * 3;\nwhile (x > 0) { x -= 1; }\n";
+    let ast = parse_ast(source);
+    let regenerated = ast.to_source();
+    assert_eq!(parse_ast(&regenerated), ast);
+}
+
+#[test]
+fn test_parse_builds_statement_nodes() {
+    assert_eq!(parse_ast(&sample_program().to_source()), sample_program());
+}
+
+struct CallCounter {
+    calls: usize,
+}
+
+impl Visitor for CallCounter {
+    fn visit_function_call(&mut self, node: &AstNode) {
+        self.calls += 1;
+        walk(node, self);
+    }
+}
+
+#[test]
+fn test_visitor_counts_function_calls() {
+    let mut counter = CallCounter { calls: 0 };
+    walk(&sample_program(), &mut counter);
+    assert_eq!(counter.calls, 1);
+    
+    // Calls nested in arguments, loop bodies and closures are found too
+    let call = |args: Vec<AstNode>| AstNode::FunctionCall { callee: ident("f"), args };
+    let program = AstNode::Program(vec![
+        call(vec![call(vec![]), AstNode::Number(1)]),
+        AstNode::While {
+            condition: Box::new(call(vec![])),
+            body: Box::new(AstNode::Block(vec![AstNode::ArrowFunction {
+                params: vec![],
+                body: vec![call(vec![])],
+                expression: true,
+                is_async: false,
+            }])),
+        },
+    ]);
+    let mut counter = CallCounter { calls: 0 };
+    walk(&program, &mut counter);
+    assert_eq!(counter.calls, 4);
+}
@@ -1,8 +1,17 @@
-use std::io::{self, Write};
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Instant;
 use colored::*;
-use crate::lexer::tokenize;
-use crate::parser::{Parser, AstNode};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use unicode_normalization::UnicodeNormalization;
+use crate::editor::ReplHelper;
+use crate::parser::{AstNode, ObjectProperty, Parameter, SmashParser};
+use crate::runtime::{self, process};
 
 #[derive(Clone)]
 pub struct Scope {
@@ -39,16 +48,114 @@ impl Scope {
         self.variables.insert(name.to_string(), value);
     }
     
+    // Assign to an existing variable in whichever scope declared it,
+    // declaring it here if no scope has
+    fn update(&mut self, name: &str, value: Value) {
+        if !self.variables.contains_key(name) {
+            if let Some(parent) = self.parent.as_mut().filter(|parent| parent.get(name).is_some()) {
+                parent.update(name, value);
+                return;
+            }
+        }
+        self.set(name, value);
+    }
+    
     #[allow(dead_code)]
     fn has_own(&self, name: &str) -> bool {
         self.variables.contains_key(name)
     }
 }
 
+/// Stack that must be left for a function call to go ahead: enough for the
+/// evaluator frames of one more call in a debug build
+const MIN_CALL_STACK: usize = 1024 * 1024;
+
+/// Options controlling how the REPL evaluates code
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplOptions {
+    /// Maximum number of nested function calls before evaluation fails with
+    /// "Maximum call stack size exceeded". Calls fail the same way, whatever
+    /// the depth, once the thread is nearly out of stack
+    pub max_call_depth: usize,
+    /// Directory that relative imports typed at the prompt resolve against
+    pub base_dir: PathBuf,
+    /// Directories searched, in order, for module specifiers that aren't
+    /// relative paths
+    pub module_paths: Vec<PathBuf>,
+    /// Whether code may call `eval`; turn this off when running code that
+    /// isn't trusted
+    pub allow_eval: bool,
+    /// Whether code may use `fs` to read and write files
+    pub allow_fs: bool,
+    /// Whether code may use `process` for its arguments, environment
+    /// variables and working directory, or to exit
+    pub allow_process: bool,
+    /// Maximum number of AST nodes a top-level statement may evaluate before
+    /// failing with "script exceeded step limit", so untrusted code can't
+    /// loop forever; `None` or `Some(0)` means no limit
+    pub max_steps: Option<u64>,
+    /// Arguments given after the script path, available as `process.argv`
+    pub args: Vec<String>,
+}
+
+impl Default for ReplOptions {
+    fn default() -> Self {
+        Self {
+            max_call_depth: 3_000,
+            base_dir: std::env::current_dir().unwrap_or_default(),
+            module_paths: Vec::new(),
+            allow_eval: true,
+            allow_fs: true,
+            allow_process: true,
+            max_steps: None,
+            args: Vec::new(),
+        }
+    }
+}
+
 pub struct Repl {
     history: Vec<String>,
-    context: String, // Accumulated code for multi-line input
     global_scope: Scope, // Global scope for the REPL session
+    pending_control: Option<ControlFlow>, // Break/continue/return still propagating outwards
+    options: ReplOptions,
+    call_depth: usize, // Function calls currently being evaluated
+    steps: u64, // AST nodes evaluated for the current top-level statement
+    current_function: Option<String>, // Named function whose body is running, for self tail calls
+    microtasks: VecDeque<Microtask>, // Promise reactions waiting to run
+    thrown: Option<Value>, // Value of the exception currently propagating, for `catch`
+    modules: HashMap<PathBuf, Module>, // Imported modules by canonical path, evaluated once
+    module_stack: Vec<PathBuf>, // Modules currently being evaluated, innermost last
+}
+
+// A module loaded by `import` or `require`
+struct Module {
+    exports: HashMap<String, Value>, // The default export is stored as "default"
+    commonjs: Option<Value>, // `module.exports` of a module without ES exports
+    // False while the module body is still running. A circular import sees
+    // the exports made so far instead of evaluating the module again
+    evaluated: bool,
+}
+
+// Non-local control flow raised by a statement and consumed by the enclosing
+// loop, labeled statement or function call
+#[derive(Debug, Clone)]
+enum ControlFlow {
+    Break(Option<String>),
+    Continue(Option<String>),
+    Return(Value),
+    // `return f(args)` inside `f` itself: the caller re-runs `f` with these
+    // arguments instead of recursing
+    TailCall(Vec<Value>),
+}
+
+impl ControlFlow {
+    // Whether an unlabeled jump or one naming `label` targets this loop
+    fn targets(target: &Option<String>, label: Option<&str>) -> bool {
+        match target {
+            None => true,
+            Some(target) => Some(target.as_str()) == label,
+        }
+    }
 }
 
 // A value type for our REPL to support various SmashLang features
@@ -62,16 +169,573 @@ pub enum Value {
     Array(Vec<Value>),
     Object(HashMap<String, Value>),
     Function(String, Vec<String>, Box<AstNode>), // name, params, body
+    AsyncFunction(String, Vec<String>, Box<AstNode>), // name, params, body; calls return a promise
+    Promise(Rc<RefCell<PromiseCell>>),
+    PromiseResolver(Rc<RefCell<PromiseCell>>, bool), // promise, whether calling it rejects
     Regex(String), // Regular expression pattern
     Undefined,
 }
 
+/// Settlement state of a promise
+#[derive(Debug, Clone)]
+pub enum PromiseState {
+    Pending,
+    Fulfilled(Value),
+    Rejected(Value),
+}
+
+/// A promise, shared by every value that refers to it
+pub struct PromiseCell {
+    state: PromiseState,
+    reactions: Vec<Reaction>, // Run as microtasks once the promise settles
+}
+
+impl PromiseCell {
+    fn new() -> Rc<RefCell<PromiseCell>> {
+        Rc::new(RefCell::new(PromiseCell {
+            state: PromiseState::Pending,
+            reactions: Vec::new(),
+        }))
+    }
+    
+    /// Current state of the promise
+    pub fn state(&self) -> &PromiseState {
+        &self.state
+    }
+    
+    // The settled outcome, with a rejection as `Err`
+    fn outcome(&self) -> Option<Result<Value, Value>> {
+        match &self.state {
+            PromiseState::Pending => None,
+            PromiseState::Fulfilled(value) => Some(Ok(value.clone())),
+            PromiseState::Rejected(reason) => Some(Err(reason.clone())),
+        }
+    }
+}
+
+// Reactions can hold scopes that refer back to the promise, so only the
+// state is shown
+impl std::fmt::Debug for PromiseCell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Promise").field("state", &self.state).finish_non_exhaustive()
+    }
+}
+
+// What happens to a promise's outcome once it settles
+enum Reaction {
+    // Settle another promise the same way, for a promise resolved with a promise
+    Forward(Rc<RefCell<PromiseCell>>),
+    // `.then`/`.catch`: settle `result` with what the matching handler
+    // returns, or pass the outcome through if there is no handler. Handlers
+    // run in the scope `.then` was called from.
+    Then {
+        on_fulfilled: Option<Value>,
+        on_rejected: Option<Value>,
+        result: Rc<RefCell<PromiseCell>>,
+        scope: Scope,
+    },
+    // `.finally`: call `callback`, then settle `result` with the original
+    // outcome unless the callback throws
+    Finally {
+        callback: Value,
+        result: Rc<RefCell<PromiseCell>>,
+        scope: Scope,
+    },
+    // One element of `Promise.all`, fulfilling `result` once none remain
+    AllElement {
+        index: usize,
+        values: Rc<RefCell<Vec<Value>>>,
+        remaining: Rc<Cell<usize>>,
+        result: Rc<RefCell<PromiseCell>>,
+    },
+    // `queueMicrotask`: call `callback`, reporting anything it throws since
+    // there is no promise to reject
+    Task {
+        callback: Value,
+        scope: Scope,
+    },
+}
+
+// A reaction queued with the outcome it reacts to
+struct Microtask {
+    reaction: Reaction,
+    outcome: Result<Value, Value>,
+}
+
+impl Value {
+    /// Name of the value's runtime type, as shown by `.type`. Unlike
+    /// `typeof`, arrays, `null` and regexes get their own names.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Number(_) | Value::Float(_) => "number",
+            Value::String(_) => "string",
+            Value::Boolean(_) => "boolean",
+            Value::Null => "null",
+            Value::Undefined => "undefined",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Function(..) | Value::AsyncFunction(..) | Value::PromiseResolver(..) => "function",
+            Value::Promise(_) => "promise",
+            Value::Regex(_) => "regex",
+        }
+    }
+    
+    /// JavaScript-style truthiness.
+    ///
+    /// `false`, `0`, `0.0`, `NaN`, `null`, `undefined` and empty strings,
+    /// arrays and objects are falsy; everything else, including functions
+    /// and regexes, is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Boolean(b) => *b,
+            Value::Number(n) => *n != 0,
+            Value::Float(f) => *f != 0.0 && !f.is_nan(),
+            Value::String(s) => !s.is_empty(),
+            Value::Array(arr) => !arr.is_empty(),
+            Value::Object(obj) => !obj.is_empty(),
+            Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) => true,
+            Value::Promise(_) | Value::PromiseResolver(_, _) => true,
+            Value::Regex(_) => true,
+            Value::Null => false,
+            Value::Undefined => false,
+        }
+    }
+}
+
+/// Integer arithmetic that promotes to `Float` instead of wrapping on overflow.
+///
+/// SmashLang numbers follow JavaScript, so a result outside the `i64` range
+/// loses precision rather than silently wrapping to a different value.
+fn integer_arithmetic(l: i64, op: &str, r: i64) -> Value {
+    let checked = match op {
+        "+" => l.checked_add(r),
+        "-" => l.checked_sub(r),
+        "*" => l.checked_mul(r),
+        "/" => l.checked_div(r),
+        "%" => l.checked_rem(r),
+        _ => None,
+    };
+    
+    match checked {
+        Some(n) => Value::Number(n),
+        None => {
+            let (l, r) = (l as f64, r as f64);
+            Value::Float(match op {
+                "+" => l + r,
+                "-" => l - r,
+                "*" => l * r,
+                "/" => (l / r).trunc(),
+                "%" => l % r,
+                _ => f64::NAN,
+            })
+        }
+    }
+}
+
+/// Integer exponentiation, falling back to `Float` for negative exponents and overflow
+fn integer_power(base: i64, exponent: i64) -> Value {
+    let checked = u32::try_from(exponent)
+        .ok()
+        .and_then(|exponent| base.checked_pow(exponent));
+    
+    match checked {
+        Some(n) => Value::Number(n),
+        None => Value::Float((base as f64).powf(exponent as f64)),
+    }
+}
+
+/// Strict equality (`===`): values of different types are never equal.
+///
+/// `Number` and `Float` are both JavaScript numbers, so they compare by value.
+/// Arrays and objects have no identity in the REPL and compare structurally.
+fn strict_equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(l), Value::Number(r)) => l == r,
+        (Value::Float(l), Value::Float(r)) => l == r,
+        (Value::Number(l), Value::Float(r)) | (Value::Float(r), Value::Number(l)) => *l as f64 == *r,
+        (Value::String(l), Value::String(r)) => l == r,
+        (Value::Boolean(l), Value::Boolean(r)) => l == r,
+        (Value::Regex(l), Value::Regex(r)) => l == r,
+        (Value::Null, Value::Null) | (Value::Undefined, Value::Undefined) => true,
+        (Value::Array(l), Value::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r).all(|(a, b)| strict_equals(a, b))
+        },
+        (Value::Object(l), Value::Object(r)) => {
+            l.len() == r.len() && l.iter().all(|(key, a)| r.get(key).is_some_and(|b| strict_equals(a, b)))
+        },
+        // Named functions are equal to themselves; anonymous ones never are
+        (Value::Function(l, _, _), Value::Function(r, _, _))
+        | (Value::AsyncFunction(l, _, _), Value::AsyncFunction(r, _, _)) => !l.is_empty() && l == r,
+        (Value::Promise(l), Value::Promise(r)) => Rc::ptr_eq(l, r),
+        (Value::PromiseResolver(l, l_rejects), Value::PromiseResolver(r, r_rejects)) => {
+            Rc::ptr_eq(l, r) && l_rejects == r_rejects
+        },
+        _ => false,
+    }
+}
+
+/// Loose equality (`==`), applying JavaScript's coercions before comparing:
+///
+/// - `null` and `undefined` equal each other and nothing else
+/// - a boolean compares as the number `1` or `0`
+/// - a string compared with a number is converted to a number (`"" == 0`)
+/// - everything else falls back to strict equality
+fn loose_equals(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Null | Value::Undefined, Value::Null | Value::Undefined) => true,
+        (Value::Null | Value::Undefined, _) | (_, Value::Null | Value::Undefined) => false,
+        (Value::Boolean(b), other) | (other, Value::Boolean(b)) if !matches!(other, Value::Boolean(_)) => {
+            loose_equals(&Value::Number(*b as i64), other)
+        },
+        (Value::String(s), number @ (Value::Number(_) | Value::Float(_)))
+        | (number @ (Value::Number(_) | Value::Float(_)), Value::String(s)) => {
+            strict_equals(number, &Value::Float(string_to_number(s)))
+        },
+        _ => strict_equals(left, right),
+    }
+}
+
+/// Convert a string to a number the way JavaScript's `Number()` does
+fn string_to_number(s: &str) -> f64 {
+    let trimmed = s.trim();
+    if trimmed.is_empty() {
+        0.0
+    } else {
+        trimmed.parse::<f64>().unwrap_or(f64::NAN)
+    }
+}
+
+/// Length of a string in Unicode scalar values.
+///
+/// All string indices in the REPL (`length`, `charAt`, `slice`, `substring`,
+/// `indexOf`) count `char`s rather than bytes, so non-ASCII text behaves.
+fn char_len(s: &str) -> usize {
+    s.chars().count()
+}
+
+/// The `char`s of `s` in the index range `start..end`
+fn char_slice(s: &str, start: usize, end: usize) -> String {
+    s.chars().skip(start).take(end.saturating_sub(start)).collect()
+}
+
+/// Convert a byte offset returned by `str::find` into a `char` index
+fn char_index(s: &str, byte_index: usize) -> i64 {
+    s[..byte_index].chars().count() as i64
+}
+
+/// `console.log` and friends: print the arguments separated by spaces,
+/// errors and warnings to stderr
+fn console_method(method: &str, args: &[Value]) -> Result<Value, String> {
+    let line = args.iter().map(property_key).collect::<Vec<_>>().join(" ");
+    match method {
+        "log" | "info" | "debug" => println!("{}", line),
+        "warn" | "error" => eprintln!("{}", line),
+        _ => return Err(format!("console.{} is not a function", method)),
+    }
+    Ok(Value::Undefined)
+}
+
+/// Error for using a built-in that `ReplOptions` turned off
+fn not_permitted(builtin: &str) -> String {
+    format!("{} is not permitted in sandbox", builtin)
+}
+
+/// `String.fromCharCode` / `String.fromCodePoint`: build a string from UTF-16
+/// code units or from Unicode code points
+fn string_from_codes(method: &str, args: &[Value]) -> Result<Value, String> {
+    let mut codes = Vec::new();
+    for arg in args {
+        match arg {
+            Value::Number(n) => codes.push(*n),
+            Value::Float(f) if f.fract() == 0.0 => codes.push(*f as i64),
+            other => return Err(format!("String.{} requires integer arguments, got {:?}", method, other)),
+        }
+    }
+    
+    match method {
+        // Code units are truncated to 16 bits like JavaScript's ToUint16
+        "fromCharCode" => {
+            let units: Vec<u16> = codes.iter().map(|&code| code as u16).collect();
+            Ok(Value::String(String::from_utf16_lossy(&units)))
+        },
+        "fromCodePoint" => codes
+            .iter()
+            .map(|&code| {
+                u32::try_from(code)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("Invalid code point {}", code))
+            })
+            .collect::<Result<String, String>>()
+            .map(Value::String),
+        _ => Err(format!("Method '{}' not found on String", method)),
+    }
+}
+
+/// JSON text for `value`, or None for values JSON can't represent
+///
+/// Floats are always written with a decimal point or exponent (`5.0`, never
+/// `5`) so they parse back as floats. Object keys are sorted since objects
+/// don't keep insertion order. As in JavaScript, properties that can't be
+/// represented are left out and array elements that can't be become `null`.
+/// A non-empty `indent` puts each member on its own line, nested `depth`
+/// levels deep.
+fn to_json(value: &Value, indent: &str, depth: usize) -> Option<String> {
+    match value {
+        Value::Number(n) => Some(n.to_string()),
+        // Debug formatting keeps the ".0" that Display drops
+        Value::Float(f) if f.is_finite() => Some(format!("{:?}", f)),
+        Value::Float(_) | Value::Null => Some("null".to_string()),
+        Value::Boolean(b) => Some(b.to_string()),
+        Value::String(s) => serde_json::to_string(s).ok(),
+        Value::Array(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| to_json(item, indent, depth + 1).unwrap_or_else(|| "null".to_string()))
+                .collect();
+            Some(json_members('[', ']', &items, indent, depth))
+        },
+        Value::Object(fields) => {
+            let separator = if indent.is_empty() { ":" } else { ": " };
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            let fields: Vec<String> = keys
+                .into_iter()
+                .filter_map(|key| {
+                    let field = to_json(&fields[key], indent, depth + 1)?;
+                    Some(format!("{}{}{}", serde_json::to_string(key).ok()?, separator, field))
+                })
+                .collect();
+            Some(json_members('{', '}', &fields, indent, depth))
+        },
+        Value::Regex(_) | Value::Promise(_) => Some("{}".to_string()),
+        Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _) | Value::Undefined => None,
+    }
+}
+
+/// Wrap the members of a JSON array or object in `open` and `close`, one
+/// member per line when indenting
+fn json_members(open: char, close: char, members: &[String], indent: &str, depth: usize) -> String {
+    if members.is_empty() || indent.is_empty() {
+        return format!("{}{}{}", open, members.join(","), close);
+    }
+    let inner = indent.repeat(depth + 1);
+    format!("{}\n{}{}\n{}{}", open, inner, members.join(&format!(",\n{}", inner)), indent.repeat(depth), close)
+}
+
+/// Convert parsed JSON to a value; numbers with a fraction or exponent, or
+/// too large for an integer, become floats
+fn from_json(json: &serde_json::Value) -> Value {
+    match json {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(n) => Value::Number(n),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        serde_json::Value::Array(items) => Value::Array(items.iter().map(from_json).collect()),
+        serde_json::Value::Object(fields) => Value::Object(
+            fields.iter().map(|(key, field)| (key.clone(), from_json(field))).collect(),
+        ),
+    }
+}
+
+/// `structuredClone`: deep-copy arrays and objects, copying primitives as-is.
+///
+/// Functions are rejected rather than shared, like JavaScript's
+/// `DataCloneError`, so a clone never aliases anything in the original.
+fn structured_clone(value: &Value) -> Result<Value, String> {
+    match value {
+        Value::Array(items) => items.iter().map(structured_clone).collect::<Result<_, _>>().map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, field)| Ok((key.clone(), structured_clone(field)?)))
+            .collect::<Result<_, String>>()
+            .map(Value::Object),
+        Value::Function(name, _, _) | Value::AsyncFunction(name, _, _) => Err(format!(
+            "structuredClone: function '{}' could not be cloned",
+            if name.is_empty() { "<anonymous>" } else { name }
+        )),
+        Value::PromiseResolver(_, rejects) => Err(format!(
+            "structuredClone: function '{}' could not be cloned",
+            if *rejects { "reject" } else { "resolve" }
+        )),
+        Value::Promise(_) => Err("structuredClone: a promise could not be cloned".to_string()),
+        primitive => Ok(primitive.clone()),
+    }
+}
+
+/// Elements produced by spreading `value` into an array literal.
+///
+/// Arrays spread their elements and strings their characters; any other
+/// value is not iterable, which is an error as in JavaScript.
+fn spread_elements(value: Value) -> Result<Vec<Value>, String> {
+    match value {
+        Value::Array(items) => Ok(items),
+        Value::String(s) => Ok(s.chars().map(|c| Value::String(c.to_string())).collect()),
+        other => Err(format!("Spread syntax requires an iterable, got {:?}", other)),
+    }
+}
+
+/// Own properties copied by spreading `value` into an object literal.
+///
+/// Arrays and strings contribute their indices as keys. Values without own
+/// properties, including `null` and `undefined`, contribute nothing rather
+/// than failing, as in JavaScript.
+fn spread_properties(value: Value) -> Vec<(String, Value)> {
+    match value {
+        Value::Object(fields) => fields.into_iter().collect(),
+        Value::Array(items) => items.into_iter().enumerate().map(|(i, item)| (i.to_string(), item)).collect(),
+        Value::String(s) => s.chars().enumerate().map(|(i, c)| (i.to_string(), Value::String(c.to_string()))).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// The key a computed property name evaluates to, following JavaScript's
+/// string conversion: arrays join their elements with commas and plain
+/// objects become "[object Object]".
+fn property_key(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Float(f) if f.is_infinite() => if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+        Value::Undefined => "undefined".to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::Null | Value::Undefined => String::new(),
+                item => property_key(item),
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        Value::Object(_) => "[object Object]".to_string(),
+        Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _) => "[Function]".to_string(),
+        Value::Promise(_) => "[object Promise]".to_string(),
+        Value::Regex(r) => format!("/{}/", r),
+    }
+}
+
+/// Message reported for an uncaught exception: the `message` of an error
+/// object, or the thrown value converted to a string
+fn exception_message(value: &Value) -> String {
+    match value {
+        Value::Object(fields) => match fields.get("message") {
+            Some(Value::String(message)) => message.clone(),
+            _ => property_key(value),
+        },
+        value => property_key(value),
+    }
+}
+
+/// A function value, async or not
+fn make_function(name: String, params: Vec<String>, body: AstNode, is_async: bool) -> Value {
+    if is_async {
+        Value::AsyncFunction(name, params, Box::new(body))
+    } else {
+        Value::Function(name, params, Box::new(body))
+    }
+}
+
+/// Names of a function's parameters
+fn parameter_names(params: &[Parameter]) -> Vec<String> {
+    params.iter().map(|param| param.name.clone()).collect()
+}
+
+/// Parse source typed at the prompt or read from a module file into its
+/// statements
+fn parse_program(source: &str) -> Result<Vec<AstNode>, String> {
+    let mut pairs = SmashParser::parse(source).map_err(|e| e.to_string())?;
+    match pairs.next().and_then(AstNode::from_pair) {
+        Some(AstNode::Program(statements)) => Ok(statements),
+        _ => Err("Failed to convert parse tree to AST".to_string()),
+    }
+}
+
+/// Parse code typed at the prompt or passed to `eval`, where a trailing `;`
+/// may be left off
+fn parse_input(input: &str) -> Result<Vec<AstNode>, String> {
+    parse_program(input).or_else(|err| parse_program(&format!("{};", input.trim_end())).map_err(|_| err))
+}
+
+/// What a CommonJS module exported: `module.exports`, plus any properties
+/// set on `exports` when `module.exports` is still an object
+fn commonjs_exports(scope: &Scope) -> Value {
+    let exports = match scope.get("module") {
+        Some(Value::Object(module)) => module.get("exports").cloned().unwrap_or(Value::Undefined),
+        _ => Value::Undefined,
+    };
+    match (exports, scope.get("exports")) {
+        (Value::Object(mut fields), Some(Value::Object(shorthand))) => {
+            for (name, value) in shorthand {
+                fields.entry(name).or_insert(value);
+            }
+            Value::Object(fields)
+        },
+        (exports, _) => exports,
+    }
+}
+
+/// `toLocaleString`: group the integer digits of a formatted number in
+/// threes with `,`, leaving the sign and fractional part as they are
+fn group_thousands(formatted: &str) -> String {
+    let (sign, unsigned) = match formatted.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", formatted),
+    };
+    let (integer, fraction) = match unsigned.find('.') {
+        Some(dot) => unsigned.split_at(dot),
+        None => (unsigned, ""),
+    };
+    
+    // Leave non-numeric text such as "inf" or "NaN" alone
+    if !integer.chars().all(|c| c.is_ascii_digit()) {
+        return formatted.to_string();
+    }
+    
+    let mut grouped = String::new();
+    for (i, digit) in integer.chars().enumerate() {
+        if i > 0 && (integer.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    
+    format!("{}{}{}", sign, grouped, fraction)
+}
+
+/// Resolve a `slice` index: negative values count back from the end, and
+/// the result is clamped to `0..=len`
+fn relative_index(index: i64, len: usize) -> usize {
+    if index < 0 {
+        len.saturating_sub(index.unsigned_abs() as usize)
+    } else {
+        (index as usize).min(len)
+    }
+}
+
 impl Repl {
     pub fn new() -> Self {
+        Self::with_options(ReplOptions::default())
+    }
+    
+    /// Create a REPL with explicit options
+    pub fn with_options(options: ReplOptions) -> Self {
         let mut repl = Repl {
             history: Vec::new(),
-            context: String::new(),
             global_scope: Scope::new(),
+            pending_control: None,
+            options,
+            call_depth: 0,
+            steps: 0,
+            current_function: None,
+            microtasks: VecDeque::new(),
+            thrown: None,
+            modules: HashMap::new(),
+            module_stack: Vec::new(),
         };
         
         // Add example variables to the global scope
@@ -80,35 +744,82 @@ impl Repl {
         
         repl
     }
+    
+    /// Options this REPL was created with
+    pub fn options(&self) -> &ReplOptions {
+        &self.options
+    }
 
     pub fn run(&mut self) {
-        println!("{}", "SmashLang REPL v0.1.0".bright_cyan().bold());
+        println!("{}", format!("SmashLang REPL v{}", env!("CARGO_PKG_VERSION")).bright_cyan().bold());
         println!("Type {} for available commands or {} to quit", ".help".green(), ".exit".red());
 
-        loop {
-            print!("{} ", ">".bright_green());
-            io::stdout().flush().unwrap();
+        let mut editor: Editor<ReplHelper, DefaultHistory> = match Editor::new() {
+            Ok(editor) => editor,
+            Err(err) => {
+                eprintln!("{}: {}", "Could not start line editor".red(), err);
+                return;
+            }
+        };
+        editor.set_helper(Some(ReplHelper::new()));
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
-            let input = input.trim();
+        loop {
+            let line = match editor.readline(">> ") {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => {
+                    println!("Ctrl-C");
+                    break;
+                },
+                Err(ReadlineError::Eof) => {
+                    println!("Ctrl-D");
+                    break;
+                },
+                Err(err) => {
+                    println!("{}: {:?}", "Error".red(), err);
+                    break;
+                }
+            };
+            let input = line.trim();
 
             if input.is_empty() {
                 continue;
             }
+            let _ = editor.add_history_entry(input);
 
             // Handle REPL commands
             match input {
-                ".exit" => break,
+                ".exit" | ".quit" => break,
                 ".help" => self.show_help(),
                 ".history" => self.show_history(),
                 ".clear" => self.clear_context(),
                 ".vars" => self.show_variables(),
+                ".version" => println!("SmashLang v{}", env!("CARGO_PKG_VERSION")),
+                _ if input == ".type" || input.starts_with(".type ") => {
+                    match self.type_of(&input[".type".len()..]) {
+                        Ok(type_name) => println!("{}", type_name.bright_blue()),
+                        Err(err) => println!("{}: {}", "Evaluation error".red(), err),
+                    }
+                },
+                _ if input == ".time" || input.starts_with(".time ") => {
+                    match self.time(&input[".time".len()..]) {
+                        Ok(report) => println!("{}", report.bright_cyan()),
+                        Err(err) => println!("{}: {}", "Evaluation error".red(), err),
+                    }
+                },
                 _ if input.starts_with(".") => {
                     println!("{}: {}", "Unknown command".red(), input);
                     println!("Type {} for available commands", ".help".green());
                 },
-                _ => self.evaluate(input),
+                _ => {
+                    // Offer the names this input declares for completion
+                    if let Some(helper) = editor.helper_mut() {
+                        helper.record_declarations(input);
+                    }
+                    match self.evaluate(input) {
+                        Ok(value) => println!("{}", property_key(&value).bright_cyan()),
+                        Err(err) => println!("{}: {}", "Evaluation error".red(), err),
+                    }
+                },
             }
         }
 
@@ -118,10 +829,13 @@ impl Repl {
     fn show_help(&self) {
         println!("{}", "Available commands:".bright_cyan());
         println!("  {}  - Show this help message", ".help".green());
-        println!("  {}  - Exit the REPL", ".exit".red());
+        println!("  {}  - Exit the REPL (also {})", ".exit".red(), ".quit".red());
         println!("  {} - Show command history", ".history".green());
-        println!("  {}  - Clear the current context", ".clear".green());
+        println!("  {}  - Forget all variables", ".clear".green());
         println!("  {}   - Show all variables", ".vars".green());
+        println!("  {} - Show the type of an expression without its value", ".type <expr>".green());
+        println!("  {} - Evaluate an expression and show how long it took", ".time <expr>".green());
+        println!("  {} - Show version information", ".version".green());
         
         println!("
 {}", "Operator Examples:".bright_cyan());
@@ -160,7 +874,7 @@ impl Repl {
         
         println!("
 {}", "SmashLang expressions and statements can be entered directly.".bright_cyan());
-        println!("Multi-line input is supported - the code will be evaluated when a complete statement is detected.");
+        println!("Multi-line input is supported - lines are read until every bracket and template literal is closed.");
     }
 
     fn show_history(&self) {
@@ -175,7 +889,7 @@ impl Repl {
     }
 
     fn clear_context(&mut self) {
-        self.context.clear();
+        self.global_scope = Scope::new();
         println!("Context cleared");
     }
 
@@ -191,125 +905,810 @@ impl Repl {
         }
     }
 
-    fn evaluate(&mut self, input: &str) {
-        // Add to history
-        self.history.push(input.to_string());
-        
-        // Append to context with automatic semicolon insertion if needed
-        let mut processed_input = input.trim().to_string();
-        
-        // Add semicolon if it's missing and the input looks like a statement
-        if !processed_input.ends_with(';') && 
-           !processed_input.ends_with('}') && 
-           !processed_input.ends_with('{') && 
-           !processed_input.is_empty() {
-            processed_input.push(';');
+    /// `.type <expr>`: evaluate `source` and return the name of its type
+    pub fn type_of(&mut self, source: &str) -> Result<&'static str, String> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err("Usage: .type <expression>".to_string());
         }
         
-        self.context.push_str(&processed_input);
-        self.context.push('\n');
+        let mut value = Value::Undefined;
+        for node in parse_program(&format!("{};", source.trim_end_matches(';')))? {
+            value = self.evaluate_ast(&node)?;
+        }
+        Ok(value.type_name())
+    }
+    
+    /// `.time <expr>`: evaluate `source` and report its value together with
+    /// the wall-clock time the evaluation took
+    pub fn time(&mut self, source: &str) -> Result<String, String> {
+        let source = source.trim();
+        if source.is_empty() {
+            return Err("Usage: .time <expression>".to_string());
+        }
         
-        // Try to tokenize and parse
-        let tokens = tokenize(&self.context);
-        let mut parser = Parser::new(tokens.clone());
+        let start = Instant::now();
+        let value = self.evaluate(source)?;
+        Ok(format!("{} ({:.3?})", property_key(&value), start.elapsed()))
+    }
+    
+    /// Evaluate a line of input, recording it in the history, and return
+    /// the value of its last statement
+    ///
+    /// A trailing `;` may be left off. Promise reactions queued by the input
+    /// run before this returns.
+    pub fn evaluate(&mut self, input: &str) -> Result<Value, String> {
+        self.history.push(input.to_string());
         
-        match parser.parse() {
-            Ok(ast) => {
-                // We have a complete expression or statement
-                println!("{}: {:?}", "Parsed AST".bright_green(), ast);
-                
-                // Simple evaluation of the AST for demonstration purposes
-                // Evaluate each statement in the AST
-                let mut result = Ok(Value::Null);
-                for node in &ast {
-                    result = self.evaluate_ast(node);
-                    if result.is_err() {
-                        break;
-                    }
-                }
-                
-                match result {
-                    Ok(result) => {
-                        println!("{}: {:?}", "Result".bright_green(), result);
-                        
-                        // For increment/decrement operations, show the current value of the variable
-                        if let Some(last_node) = ast.last() {
-                            if let AstNode::PostIncrement(expr) | AstNode::PreIncrement(expr) | 
-                                   AstNode::PostDecrement(expr) | AstNode::PreDecrement(expr) = last_node {
-                                if let AstNode::Identifier(name) = &**expr {
-                                    if let Some(current_value) = self.global_scope.get(name) {
-                                        println!("{}: {} = {:?}", "Current Value".bright_blue(), name.yellow(), current_value);
-                                    }
-                                }
-                            }
-                        }
-                    },
-                    Err(err) => {
-                        println!("{}: {}", "Evaluation error".red(), err);
-                    }
-                }
-                
-                // Clear context after successful execution
-                self.context.clear();
-            },
-            Err(e) => {
-                // Check if this is an incomplete input or a syntax error
-                if e.to_string().contains("Unexpected end of input") {
-                    // This is incomplete input, wait for more
-                    print!("{} ", "...".bright_yellow());
-                    io::stdout().flush().unwrap();
-                } else {
-                    // This is a syntax error
-                    println!("{}: {}", "Syntax error".red(), e);
-                    // Clear context on error
-                    self.context.clear();
-                }
-            },
+        let statements = parse_input(input)?;
+        self.execute(&statements)
+    }
+    
+    /// Run parsed statements, such as the body of a script, and return the
+    /// value of the last one
+    ///
+    /// Evaluation stops at the first error. Promise reactions queued by the
+    /// statements run before this returns.
+    pub fn execute(&mut self, statements: &[AstNode]) -> Result<Value, String> {
+        let mut result = Ok(Value::Undefined);
+        for node in statements {
+            result = self.evaluate_ast(node);
+            if result.is_err() {
+                break;
+            }
         }
+        
+        self.run_microtasks();
+        result
+    }
+    
+    /// Inputs evaluated so far, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+    
+    /// Value of a variable in the session's global scope
+    pub fn variable(&self, name: &str) -> Option<Value> {
+        self.global_scope.get(name)
     }
     
     // Simple AST evaluation for demonstration purposes
     fn evaluate_ast(&mut self, ast: &AstNode) -> Result<Value, String> {
         // Create a mutable clone of the global scope to avoid borrowing issues
         let mut scope_clone = self.global_scope.clone();
+        self.steps = 0;
         let result = self.evaluate_ast_with_scope(ast, &mut scope_clone);
+        // A break/continue/return outside any loop or function ends here,
+        // as does an uncaught exception
+        self.pending_control = None;
+        self.thrown = None;
         // Update the global scope with any changes
         self.global_scope = scope_clone;
         result
     }
     
-    fn evaluate_ast_with_scope(&mut self, ast: &AstNode, scope: &mut Scope) -> Result<Value, String> {
+    // Evaluate a function body, consuming the value of any `return` inside it.
+    // `name` is given for named user functions so they can make self tail
+    // calls; a pending tail call is left for the caller to run.
+    fn call_function_body(&mut self, name: Option<&str>, body: &AstNode, scope: &mut Scope) -> Result<Value, String> {
+        // The depth limit alone can't tell how much stack the thread has
+        // left, and debug builds use far more per call than release builds
+        let stack_low = stacker::remaining_stack().is_some_and(|remaining| remaining < MIN_CALL_STACK);
+        if stack_low || self.call_depth >= self.options.max_call_depth {
+            return Err("Maximum call stack size exceeded".to_string());
+        }
+        
+        self.call_depth += 1;
+        let caller = std::mem::replace(&mut self.current_function, name.map(str::to_string));
+        let result = self.evaluate_ast_with_scope(body, scope);
+        self.current_function = caller;
+        self.call_depth -= 1;
+        
+        let result = result?;
+        match self.pending_control.take() {
+            Some(ControlFlow::Return(value)) => Ok(value),
+            Some(tail_call @ ControlFlow::TailCall(_)) => {
+                self.pending_control = Some(tail_call);
+                Ok(Value::Undefined)
+            },
+            _ => Ok(result),
+        }
+    }
+    
+    // Evaluate a returned expression. A call to the running function itself,
+    // directly or in a ternary branch, is in tail position: its arguments are
+    // evaluated and left as a pending tail call instead of recursing.
+    fn evaluate_tail_expression(&mut self, expr: &AstNode, scope: &mut Scope) -> Result<Value, String> {
+        match expr {
+            AstNode::TernaryOp { condition, true_expr, false_expr } => {
+                if self.evaluate_ast_with_scope(condition, scope)?.is_truthy() {
+                    self.evaluate_tail_expression(true_expr, scope)
+                } else {
+                    self.evaluate_tail_expression(false_expr, scope)
+                }
+            },
+            AstNode::FunctionCall { callee, args } if matches!(&**callee, AstNode::Identifier(name) if self.is_self_call(name, scope)) => {
+                let mut evaluated_args = Vec::new();
+                for arg in args {
+                    evaluated_args.push(self.evaluate_ast_with_scope(arg, scope)?);
+                }
+                self.pending_control = Some(ControlFlow::TailCall(evaluated_args));
+                Ok(Value::Undefined)
+            },
+            _ => self.evaluate_ast_with_scope(expr, scope),
+        }
+    }
+    
+    // Whether calling `name` would re-enter the running named function,
+    // rather than a different function shadowing its name
+    fn is_self_call(&self, name: &str, scope: &Scope) -> bool {
+        match &self.current_function {
+            Some(current) => {
+                name == current && matches!(scope.get(name), Some(Value::Function(function_name, _, _)) if function_name == *current)
+            },
+            None => false,
+        }
+    }
+    
+    // Decide whether a loop stops after its body ran, consuming any break or
+    // continue aimed at it. Jumps to an outer label and returns keep propagating.
+    fn loop_should_exit(&mut self, label: Option<&str>) -> bool {
+        match &self.pending_control {
+            None => false,
+            Some(ControlFlow::Continue(target)) if ControlFlow::targets(target, label) => {
+                self.pending_control = None;
+                false
+            },
+            Some(ControlFlow::Break(target)) if ControlFlow::targets(target, label) => {
+                self.pending_control = None;
+                true
+            },
+            Some(_) => true,
+        }
+    }
+    
+    // Evaluate a while/do-while/for loop, optionally named by a label.
+    // The loop's value is the value of the last completed body.
+    fn evaluate_loop(&mut self, ast: &AstNode, label: Option<&str>, scope: &mut Scope) -> Result<Value, String> {
+        let mut result = Value::Undefined;
+        
         match ast {
-            AstNode::Number(n) => Ok(Value::Number(*n)),
-            AstNode::Float(f) => Ok(Value::Float(*f)),
-            AstNode::String(s) => Ok(Value::String(s.clone())),
-            AstNode::Boolean(b) => Ok(Value::Boolean(*b)),
-            AstNode::Null => Ok(Value::Null),
-            AstNode::Regex(pattern) => Ok(Value::Regex(pattern.clone())),
-            
-            AstNode::TemplateLiteral(parts) => {
-                // Evaluate each part of the template literal
-                let mut result = String::new();
+            AstNode::While { condition, body } => {
+                while self.evaluate_ast_with_scope(condition, scope)?.is_truthy() {
+                    result = self.evaluate_ast_with_scope(body, scope)?;
+                    if self.loop_should_exit(label) {
+                        break;
+                    }
+                }
+            },
+            AstNode::DoWhile { body, condition } => {
+                loop {
+                    result = self.evaluate_ast_with_scope(body, scope)?;
+                    if self.loop_should_exit(label) || !self.evaluate_ast_with_scope(condition, scope)?.is_truthy() {
+                        break;
+                    }
+                }
+            },
+            AstNode::For { init, condition, update, body } => {
+                // Variables declared in the initializer are scoped to the loop
+                let mut loop_scope = Scope::with_parent(Box::new(scope.clone()));
+                if let Some(init) = init {
+                    self.evaluate_ast_with_scope(init, &mut loop_scope)?;
+                }
                 
-                for part in parts {
-                    match part {
-                        AstNode::String(s) => {
-                            // String parts are added directly
-                            result.push_str(s);
-                        },
-                        _ => {
-                            // Evaluate expressions and convert to string
-                            let value = self.evaluate_ast_with_scope(part, scope)?;
-                            match value {
-                                Value::String(s) => result.push_str(&s),
-                                Value::Number(n) => result.push_str(&n.to_string()),
-                                Value::Float(f) => result.push_str(&f.to_string()),
-                                Value::Boolean(b) => result.push_str(&b.to_string()),
-                                Value::Null => result.push_str("null"),
-                                Value::Regex(r) => result.push_str(&format!("/{}/", r)),
-                                Value::Undefined => result.push_str("undefined"),
-                                _ => result.push_str(&format!("{:?}", value))
-                            }
+                loop {
+                    if let Some(condition) = condition {
+                        if !self.evaluate_ast_with_scope(condition, &mut loop_scope)?.is_truthy() {
+                            break;
+                        }
+                    }
+                    
+                    result = self.evaluate_ast_with_scope(body, &mut loop_scope)?;
+                    if self.loop_should_exit(label) {
+                        break;
+                    }
+                    
+                    if let Some(update) = update {
+                        self.evaluate_ast_with_scope(update, &mut loop_scope)?;
+                    }
+                }
+                
+                // Keep assignments made to variables declared outside the loop
+                if let Some(parent) = loop_scope.parent {
+                    *scope = *parent;
+                }
+            },
+            _ => return Err(format!("Not a loop: {:?}", ast)),
+        }
+        
+        Ok(result)
+    }
+    
+    // Evaluate statements in a new scope whose parent is `scope`, stopping
+    // early if one of them breaks, continues or returns. The block's value is
+    // the value of its last statement.
+    fn evaluate_block(&mut self, statements: &[AstNode], scope: &mut Scope) -> Result<Value, String> {
+        let mut block_scope = Scope::with_parent(Box::new(scope.clone()));
+        let mut result = Ok(Value::Null);
+        
+        for stmt in statements {
+            let value = self.evaluate_ast_with_scope(stmt, &mut block_scope);
+            // A bare break/continue keeps the value of the previous statement
+            if value.is_err() || !matches!(stmt, AstNode::Break(_) | AstNode::Continue(_)) {
+                result = value;
+            }
+            if result.is_err() || self.pending_control.is_some() {
+                break;
+            }
+        }
+        
+        // Keep assignments made to variables declared outside the block
+        if let Some(parent) = block_scope.parent {
+            *scope = *parent;
+        }
+        result
+    }
+    
+    // Raise `value` as an exception. The error message describes it if it
+    // goes uncaught; a `catch` clause receives the value itself.
+    fn throw(&mut self, value: Value) -> Result<Value, String> {
+        let message = format!("Uncaught {}", exception_message(&value));
+        self.thrown = Some(value);
+        Err(message)
+    }
+    
+    // The value of the exception behind an error. Errors raised by the
+    // interpreter itself rather than `throw` are caught as their message.
+    fn take_exception(&mut self, message: String) -> Value {
+        self.thrown.take().unwrap_or(Value::String(message))
+    }
+    
+    // Settle a pending promise, queueing its reactions. A promise resolved
+    // with another promise follows that promise instead.
+    fn settle(&mut self, promise: &Rc<RefCell<PromiseCell>>, outcome: Result<Value, Value>) {
+        if !matches!(promise.borrow().state, PromiseState::Pending) {
+            return;
+        }
+        
+        let outcome = match outcome {
+            Ok(Value::Promise(other)) if Rc::ptr_eq(&other, promise) => {
+                Err(Value::String("TypeError: Chaining cycle detected for promise".to_string()))
+            },
+            Ok(Value::Promise(other)) => {
+                self.subscribe(&other, Reaction::Forward(promise.clone()));
+                return;
+            },
+            outcome => outcome,
+        };
+        
+        let reactions = {
+            let mut cell = promise.borrow_mut();
+            cell.state = match &outcome {
+                Ok(value) => PromiseState::Fulfilled(value.clone()),
+                Err(reason) => PromiseState::Rejected(reason.clone()),
+            };
+            std::mem::take(&mut cell.reactions)
+        };
+        for reaction in reactions {
+            self.microtasks.push_back(Microtask { reaction, outcome: outcome.clone() });
+        }
+    }
+    
+    // Run `reaction` once `promise` settles, or queue it now if it already has
+    fn subscribe(&mut self, promise: &Rc<RefCell<PromiseCell>>, reaction: Reaction) {
+        let outcome = promise.borrow().outcome();
+        match outcome {
+            Some(outcome) => self.microtasks.push_back(Microtask { reaction, outcome }),
+            None => promise.borrow_mut().reactions.push(reaction),
+        }
+    }
+    
+    // Run the oldest queued microtask, returning false if there was none
+    fn run_next_microtask(&mut self) -> bool {
+        let Some(Microtask { reaction, outcome }) = self.microtasks.pop_front() else {
+            return false;
+        };
+        
+        match reaction {
+            Reaction::Forward(promise) => self.settle(&promise, outcome),
+            Reaction::Then { on_fulfilled, on_rejected, result, scope } => {
+                let (handler, argument) = match outcome {
+                    Ok(value) => (on_fulfilled, Ok(value)),
+                    Err(reason) => (on_rejected, Err(reason)),
+                };
+                let outcome = match handler {
+                    Some(handler) => {
+                        let argument = argument.unwrap_or_else(|reason| reason);
+                        self.call_value(&handler, &[argument], &scope).map_err(|message| self.take_exception(message))
+                    },
+                    None => argument,
+                };
+                self.settle(&result, outcome);
+            },
+            Reaction::Finally { callback, result, scope } => {
+                match self.call_value(&callback, &[], &scope) {
+                    Ok(_) => self.settle(&result, outcome),
+                    Err(message) => {
+                        let exception = self.take_exception(message);
+                        self.settle(&result, Err(exception));
+                    },
+                }
+            },
+            Reaction::AllElement { index, values, remaining, result } => match outcome {
+                Ok(value) => {
+                    values.borrow_mut()[index] = value;
+                    remaining.set(remaining.get() - 1);
+                    if remaining.get() == 0 {
+                        let values = values.borrow().clone();
+                        self.settle(&result, Ok(Value::Array(values)));
+                    }
+                },
+                Err(reason) => self.settle(&result, Err(reason)),
+            },
+            Reaction::Task { callback, scope } => {
+                if let Err(message) = self.call_value(&callback, &[], &scope) {
+                    self.thrown = None;
+                    eprintln!("{}: {}", "Error".red(), message);
+                }
+            },
+        }
+        true
+    }
+    
+    /// Run queued promise reactions, including any they queue, until none are left
+    pub fn run_microtasks(&mut self) {
+        while self.run_next_microtask() {}
+    }
+    
+    // Call a function value with `args`, in a new scope whose parent is `scope`
+    fn call_value(&mut self, callee: &Value, args: &[Value], scope: &Scope) -> Result<Value, String> {
+        match callee {
+            Value::Function(_, param_names, body) => {
+                let mut function_scope = Scope::with_parent(Box::new(scope.clone()));
+                for (i, param) in param_names.iter().enumerate() {
+                    function_scope.set(param, args.get(i).cloned().unwrap_or(Value::Undefined));
+                }
+                self.call_function_body(None, body, &mut function_scope)
+            },
+            Value::AsyncFunction(_, param_names, body) => Ok(self.call_async_function(param_names, body, args, scope)),
+            Value::PromiseResolver(promise, rejects) => {
+                let value = args.first().cloned().unwrap_or(Value::Undefined);
+                self.settle(promise, if *rejects { Err(value) } else { Ok(value) });
+                Ok(Value::Undefined)
+            },
+            other => Err(format!("{} is not a function", property_key(other))),
+        }
+    }
+    
+    // `JSON.stringify(value, replacer, space)` / `JSON.parse(text, reviver)`.
+    // The replacer is a function called with each key and value, root first,
+    // or an array of the object keys to keep; the space is a number of spaces
+    // or a string to indent with, up to 10 characters. The reviver is called
+    // with each key and value, innermost first.
+    fn json_method(&mut self, method: &str, args: &[Value], scope: &Scope) -> Result<Value, String> {
+        match method {
+            "stringify" => {
+                let mut value = args.first().cloned().unwrap_or(Value::Undefined);
+                if let Some(replacer) = args.get(1).filter(|replacer| {
+                    matches!(replacer, Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::Array(_))
+                }) {
+                    value = self.apply_replacer("", value, replacer, scope)?;
+                }
+                
+                let indent: String = match args.get(2) {
+                    Some(Value::Number(n)) => " ".repeat((*n).clamp(0, 10) as usize),
+                    Some(Value::Float(f)) => " ".repeat(f.clamp(0.0, 10.0) as usize),
+                    Some(Value::String(s)) => s.chars().take(10).collect(),
+                    _ => String::new(),
+                };
+                Ok(to_json(&value, &indent, 0).map(Value::String).unwrap_or(Value::Undefined))
+            },
+            "parse" => {
+                let value = match args.first() {
+                    Some(Value::String(text)) => serde_json::from_str(text)
+                        .map(|json| from_json(&json))
+                        .map_err(|e| format!("JSON.parse: {}", e))?,
+                    other => return Err(format!("JSON.parse requires a string, got {:?}", other)),
+                };
+                match args.get(1) {
+                    Some(reviver @ (Value::Function(_, _, _) | Value::AsyncFunction(_, _, _))) => {
+                        self.apply_reviver("", value, reviver, scope)
+                    },
+                    _ => Ok(value),
+                }
+            },
+            _ => Err(format!("Method '{}' not found on JSON", method)),
+        }
+    }
+    
+    // Run `value`, found under `key`, through a `JSON.stringify` replacer,
+    // then everything nested in the result. Values replaced with undefined
+    // are left out of objects and become null in arrays when serialized.
+    fn apply_replacer(&mut self, key: &str, value: Value, replacer: &Value, scope: &Scope) -> Result<Value, String> {
+        let value = match replacer {
+            Value::Array(_) => value,
+            function => self.call_value(function, &[Value::String(key.to_string()), value], scope)?,
+        };
+        
+        match value {
+            Value::Object(fields) => {
+                let mut replaced = HashMap::new();
+                for (field_key, field) in fields {
+                    if let Value::Array(keys) = replacer {
+                        if !keys.iter().any(|allowed| property_key(allowed) == field_key) {
+                            continue;
+                        }
+                    }
+                    let field = self.apply_replacer(&field_key, field, replacer, scope)?;
+                    replaced.insert(field_key, field);
+                }
+                Ok(Value::Object(replaced))
+            },
+            Value::Array(items) => {
+                let mut replaced = Vec::new();
+                for (index, item) in items.into_iter().enumerate() {
+                    replaced.push(self.apply_replacer(&index.to_string(), item, replacer, scope)?);
+                }
+                Ok(Value::Array(replaced))
+            },
+            other => Ok(other),
+        }
+    }
+    
+    // Run `value`, found under `key`, through a `JSON.parse` reviver after
+    // reviving everything nested in it. Object properties revived to
+    // undefined are removed.
+    fn apply_reviver(&mut self, key: &str, value: Value, reviver: &Value, scope: &Scope) -> Result<Value, String> {
+        let value = match value {
+            Value::Object(fields) => {
+                let mut revived = HashMap::new();
+                for (field_key, field) in fields {
+                    match self.apply_reviver(&field_key, field, reviver, scope)? {
+                        Value::Undefined => {},
+                        field => {
+                            revived.insert(field_key, field);
+                        },
+                    }
+                }
+                Value::Object(revived)
+            },
+            Value::Array(items) => {
+                let mut revived = Vec::new();
+                for (index, item) in items.into_iter().enumerate() {
+                    revived.push(self.apply_reviver(&index.to_string(), item, reviver, scope)?);
+                }
+                Value::Array(revived)
+            },
+            other => other,
+        };
+        self.call_value(reviver, &[Value::String(key.to_string()), value], scope)
+    }
+    
+    // `new Promise(executor)`: call the executor with functions that resolve
+    // and reject the new promise. An exception thrown by the executor
+    // rejects the promise.
+    fn promise_with_executor(&mut self, executor: &Value, scope: &Scope) -> Value {
+        let promise = PromiseCell::new();
+        let resolve = Value::PromiseResolver(promise.clone(), false);
+        let reject = Value::PromiseResolver(promise.clone(), true);
+        
+        if let Err(message) = self.call_value(executor, &[resolve, reject], scope) {
+            let exception = self.take_exception(message);
+            self.settle(&promise, Err(exception));
+        }
+        Value::Promise(promise)
+    }
+    
+    // A promise settled with `outcome`. Resolving with a promise gives that
+    // promise back, like `Promise.resolve`.
+    fn settled_promise(&mut self, outcome: Result<Value, Value>) -> Value {
+        if let Ok(promise @ Value::Promise(_)) = outcome {
+            return promise;
+        }
+        let promise = PromiseCell::new();
+        self.settle(&promise, outcome);
+        Value::Promise(promise)
+    }
+    
+    // `promise.then(on_fulfilled, on_rejected)`. Handlers that are not
+    // functions are ignored, as in JavaScript.
+    fn promise_then(&mut self, promise: &Rc<RefCell<PromiseCell>>, on_fulfilled: Option<Value>, on_rejected: Option<Value>, scope: &Scope) -> Value {
+        let result = PromiseCell::new();
+        let callable = |handler: Option<Value>| handler.filter(|handler| {
+            matches!(handler, Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _))
+        });
+        self.subscribe(promise, Reaction::Then {
+            on_fulfilled: callable(on_fulfilled),
+            on_rejected: callable(on_rejected),
+            result: result.clone(),
+            scope: scope.clone(),
+        });
+        Value::Promise(result)
+    }
+    
+    // `promise.finally(callback)`
+    fn promise_finally(&mut self, promise: &Rc<RefCell<PromiseCell>>, callback: Value, scope: &Scope) -> Value {
+        let result = PromiseCell::new();
+        self.subscribe(promise, Reaction::Finally {
+            callback,
+            result: result.clone(),
+            scope: scope.clone(),
+        });
+        Value::Promise(result)
+    }
+    
+    // `Promise.all(values)`: fulfilled with every value once all promises
+    // among them fulfill, or rejected by the first one that rejects
+    fn promise_all(&mut self, values: Value) -> Result<Value, String> {
+        let values = spread_elements(values).map_err(|_| "Promise.all requires an iterable".to_string())?;
+        let result = PromiseCell::new();
+        let remaining = Rc::new(Cell::new(values.len()));
+        let settled = Rc::new(RefCell::new(vec![Value::Undefined; values.len()]));
+        
+        if values.is_empty() {
+            self.settle(&result, Ok(Value::Array(Vec::new())));
+        }
+        for (index, value) in values.into_iter().enumerate() {
+            let Value::Promise(promise) = self.settled_promise(Ok(value)) else {
+                unreachable!("settled_promise always gives a promise");
+            };
+            self.subscribe(&promise, Reaction::AllElement {
+                index,
+                values: settled.clone(),
+                remaining: remaining.clone(),
+                result: result.clone(),
+            });
+        }
+        Ok(Value::Promise(result))
+    }
+    
+    // Evaluate the receiver of `.then`/`.catch`/`.finally`
+    fn evaluate_promise(&mut self, expr: &AstNode, scope: &mut Scope) -> Result<Rc<RefCell<PromiseCell>>, String> {
+        match self.evaluate_ast_with_scope(expr, scope)? {
+            Value::Promise(promise) => Ok(promise),
+            other => Err(format!("Expected a promise, got {:?}", other)),
+        }
+    }
+    
+    // `await value`: run microtasks until the awaited promise settles, then
+    // give its value or throw its rejection reason. Other values are
+    // returned as they are.
+    fn await_value(&mut self, value: Value) -> Result<Value, String> {
+        let Value::Promise(promise) = value else {
+            return Ok(value);
+        };
+        
+        loop {
+            let outcome = promise.borrow().outcome();
+            match outcome {
+                Some(Ok(value)) => return Ok(value),
+                Some(Err(reason)) => return self.throw(reason),
+                None => {
+                    if !self.run_next_microtask() {
+                        return Err("await on a promise that never settles".to_string());
+                    }
+                },
+            }
+        }
+    }
+    
+    // Call an async function. The body runs to completion now, with each
+    // `await` inside it waiting for its promise, and the returned promise
+    // settles with the body's result or the exception it threw.
+    fn call_async_function(&mut self, param_names: &[String], body: &AstNode, args: &[Value], scope: &Scope) -> Value {
+        let mut function_scope = Scope::with_parent(Box::new(scope.clone()));
+        for (i, param) in param_names.iter().enumerate() {
+            function_scope.set(param, args.get(i).cloned().unwrap_or(Value::Undefined));
+        }
+        
+        let outcome = match self.call_function_body(None, body, &mut function_scope) {
+            Ok(value) => Ok(value),
+            Err(message) => Err(self.take_exception(message)),
+        };
+        
+        let promise = PromiseCell::new();
+        self.settle(&promise, outcome);
+        Value::Promise(promise)
+    }
+    
+    // Resolve a module specifier. Relative paths resolve against the
+    // directory of the importing module, or `base_dir` at the prompt; other
+    // specifiers are looked up in each of `module_paths`
+    fn resolve_module(&self, specifier: &str) -> Result<PathBuf, String> {
+        let bases = if specifier.starts_with("./") || specifier.starts_with("../") {
+            vec![match self.module_stack.last() {
+                Some(importer) => importer.parent().map(Path::to_path_buf).unwrap_or_default(),
+                None => self.options.base_dir.clone(),
+            }]
+        } else {
+            self.options.module_paths.clone()
+        };
+        
+        let candidates: Vec<PathBuf> = bases.iter().map(|base| {
+            // Drop `.` components so errors show a tidy path
+            let path: PathBuf = base.join(specifier).components()
+                .filter(|component| *component != std::path::Component::CurDir)
+                .collect();
+            if path.extension().is_none() { path.with_extension("smash") } else { path }
+        }).collect();
+        
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => path.canonicalize().map_err(|e| format!("Cannot load module '{}': {}", specifier, e)),
+            None => {
+                let tried: Vec<String> = candidates.iter().map(|path| path.display().to_string()).collect();
+                Err(format!("Cannot find module '{}' (tried: {})", specifier, tried.join(", ")))
+            },
+        }
+    }
+    
+    // Evaluate the module `specifier` refers to unless it is already loaded
+    // or being loaded, and return its canonical path
+    fn load_module(&mut self, specifier: &str) -> Result<PathBuf, String> {
+        let path = self.resolve_module(specifier)?;
+        if self.modules.contains_key(&path) {
+            return Ok(path);
+        }
+        
+        let source = fs::read_to_string(&path)
+            .map_err(|e| format!("Cannot load module '{}': {}", specifier, e))?;
+        let program = parse_program(&source)?;
+        
+        // Register the module before running it so circular imports find it
+        self.modules.insert(path.clone(), Module { exports: HashMap::new(), commonjs: None, evaluated: false });
+        self.module_stack.push(path.clone());
+        
+        // CommonJS modules export by assigning to `module.exports` or `exports`
+        let mut scope = Scope::new();
+        let mut module_object = HashMap::new();
+        module_object.insert("exports".to_string(), Value::Object(HashMap::new()));
+        scope.set("module", Value::Object(module_object));
+        scope.set("exports", Value::Object(HashMap::new()));
+        
+        let result = program.iter().try_for_each(|statement| {
+            self.evaluate_ast_with_scope(statement, &mut scope).map(|_| ())
+        });
+        
+        self.module_stack.pop();
+        match result {
+            Ok(()) => {
+                let is_es_module = program.iter().any(|statement| matches!(statement,
+                    AstNode::Export { .. } | AstNode::ExportDefault { .. } | AstNode::ExportNamed { .. } | AstNode::ExportAll { .. }));
+                let commonjs = if is_es_module { None } else { Some(commonjs_exports(&scope)) };
+                
+                if let Some(module) = self.modules.get_mut(&path) {
+                    // Imports of a CommonJS module see `module.exports` as the
+                    // default export and its properties as named exports
+                    if let Some(exports) = &commonjs {
+                        if let Value::Object(fields) = exports {
+                            module.exports = fields.clone();
+                        }
+                        module.exports.insert("default".to_string(), exports.clone());
+                    }
+                    module.commonjs = commonjs;
+                    module.evaluated = true;
+                }
+                Ok(path)
+            },
+            Err(err) => {
+                // A module that failed is loaded again by the next import
+                self.modules.remove(&path);
+                Err(err)
+            },
+        }
+    }
+    
+    // `require(specifier)`: the `module.exports` of a CommonJS module, or the
+    // exports object of an ES module
+    fn require(&mut self, specifier: &str) -> Result<Value, String> {
+        let path = self.load_module(specifier)?;
+        let module = &self.modules[&path];
+        Ok(module.commonjs.clone().unwrap_or_else(|| Value::Object(module.exports.clone())))
+    }
+    
+    // Store `value` in the variable or object property `target` names
+    fn assign(&mut self, target: &AstNode, value: Value, scope: &mut Scope) -> Result<(), String> {
+        match target {
+            AstNode::Identifier(name) => {
+                if scope.get(name).is_none() {
+                    return Err(format!("Variable \"{}\" not found ", name));
+                }
+                scope.update(name, value);
+                Ok(())
+            },
+            AstNode::PropertyAccess { object, property } => {
+                match self.evaluate_ast_with_scope(object, scope)? {
+                    Value::Object(mut fields) => {
+                        fields.insert(property.clone(), value);
+                        self.assign(object, Value::Object(fields), scope)
+                    },
+                    other => Err(format!("Cannot set property '{}' on {:?}", property, other)),
+                }
+            },
+            AstNode::ComputedPropertyAccess { object, property } => {
+                let key = self.evaluate_ast_with_scope(property, scope)?;
+                match (self.evaluate_ast_with_scope(object, scope)?, key) {
+                    (Value::Array(mut items), Value::Number(index)) if index >= 0 => {
+                        let index = index as usize;
+                        if index >= items.len() {
+                            items.resize(index + 1, Value::Undefined);
+                        }
+                        items[index] = value;
+                        self.assign(object, Value::Array(items), scope)
+                    },
+                    (Value::Object(mut fields), key) => {
+                        fields.insert(property_key(&key), value);
+                        self.assign(object, Value::Object(fields), scope)
+                    },
+                    (other, key) => Err(format!("Cannot set property '{}' on {:?}", property_key(&key), other)),
+                }
+            },
+            _ => Err("Left side of assignment must be a variable or property ".to_string()),
+        }
+    }
+    
+    // The export `name` of a loaded module
+    fn module_export(&self, path: &Path, specifier: &str, name: &str) -> Result<Value, String> {
+        let module = &self.modules[path];
+        match module.exports.get(name) {
+            Some(value) => Ok(value.clone()),
+            // Imported through a cycle before the module got to export it
+            None if !module.evaluated => Ok(Value::Undefined),
+            None => Err(format!("Module '{}' has no export named '{}'", specifier, name)),
+        }
+    }
+    
+    // Export `value` from the module being evaluated. Exports typed at the
+    // prompt have no module to go to and are dropped
+    fn export(&mut self, name: &str, value: Value) {
+        if let Some(module) = self.module_stack.last().and_then(|path| self.modules.get_mut(path)) {
+            module.exports.insert(name.to_string(), value);
+        }
+    }
+    
+    fn evaluate_ast_with_scope(&mut self, ast: &AstNode, scope: &mut Scope) -> Result<Value, String> {
+        if let Some(max_steps) = self.options.max_steps.filter(|max| *max > 0) {
+            self.steps += 1;
+            if self.steps > max_steps {
+                return Err("script exceeded step limit".to_string());
+            }
+        }
+        
+        match ast {
+            AstNode::Number(n) => Ok(Value::Number(*n)),
+            AstNode::Float(f) => Ok(Value::Float(*f)),
+            AstNode::String(s) => Ok(Value::String(s.clone())),
+            AstNode::Boolean(b) => Ok(Value::Boolean(*b)),
+            AstNode::Null => Ok(Value::Null),
+            AstNode::Undefined => Ok(Value::Undefined),
+            AstNode::Regex(literal) => {
+                // The literal keeps its slashes and flags; only the pattern is used
+                let pattern = literal.strip_prefix('/').and_then(|rest| rest.rsplit_once('/')).map_or(literal.as_str(), |(pattern, _)| pattern);
+                Ok(Value::Regex(pattern.to_string()))
+            },
+            
+            AstNode::TemplateLiteral(parts) => {
+                // Evaluate each part of the template literal
+                let mut result = String::new();
+                
+                for part in parts {
+                    match part {
+                        AstNode::String(s) => {
+                            // String parts are added directly
+                            result.push_str(s);
+                        },
+                        _ => {
+                            // Evaluate expressions and convert to string
+                            let value = self.evaluate_ast_with_scope(part, scope)?;
+                            match value {
+                                Value::String(s) => result.push_str(&s),
+                                Value::Number(n) => result.push_str(&n.to_string()),
+                                Value::Float(f) => result.push_str(&f.to_string()),
+                                Value::Boolean(b) => result.push_str(&b.to_string()),
+                                Value::Null => result.push_str("null"),
+                                Value::Regex(r) => result.push_str(&format!("/{}/", r)),
+                                Value::Undefined => result.push_str("undefined"),
+                                _ => result.push_str(&format!("{:?}", value))
+                            }
                         }
                     }
                 }
@@ -317,6 +1716,20 @@ impl Repl {
                 Ok(Value::String(result))
             },
             
+            // Logical operators short-circuit and yield an operand, as in JavaScript
+            AstNode::BinaryOp { left, op, right } if op == "&&" || op == "||" => {
+                let left_val = self.evaluate_ast_with_scope(left, scope)?;
+                
+                // && stops at a falsy left operand, || at a truthy one
+                let short_circuits = if op == "&&" { !left_val.is_truthy() } else { left_val.is_truthy() };
+                
+                if short_circuits {
+                    Ok(left_val)
+                } else {
+                    self.evaluate_ast_with_scope(right, scope)
+                }
+            },
+            
             // Binary operations
             AstNode::BinaryOp { left, op, right } => {
                 let left_val = self.evaluate_ast_with_scope(left, scope)?;
@@ -328,43 +1741,43 @@ impl Repl {
                 
                 match (left_val, op.as_str(), right_val) {
                     // Arithmetic operators
-                    (Value::Number(l), "+", Value::Number(r)) => Ok(Value::Number(l + r)),
-                    (Value::Number(l), "-", Value::Number(r)) => Ok(Value::Number(l - r)),
-                    (Value::Number(l), "*", Value::Number(r)) => Ok(Value::Number(l * r)),
+                    (Value::Number(l), "+", Value::Number(r)) => Ok(integer_arithmetic(l, "+", r)),
+                    (Value::Number(l), "-", Value::Number(r)) => Ok(integer_arithmetic(l, "-", r)),
+                    (Value::Number(l), "*", Value::Number(r)) => Ok(integer_arithmetic(l, "*", r)),
                     (Value::Number(l), "/", Value::Number(r)) => {
                         if r == 0 {
                             return Err("Division by zero ".to_string());
                         }
-                        Ok(Value::Number(l / r))
+                        Ok(integer_arithmetic(l, "/", r))
                     },
                     (Value::Number(l), "%", Value::Number(r)) => {
                         if r == 0 {
                             return Err("Modulo by zero ".to_string());
                         }
-                        Ok(Value::Number(l % r))
+                        Ok(integer_arithmetic(l, "%", r))
                     },
                     
+                    // Exponentiation
+                    (Value::Number(l), "**", Value::Number(r)) => Ok(integer_power(l, r)),
+                    (Value::Float(l), "**", Value::Float(r)) => Ok(Value::Float(l.powf(r))),
+                    (Value::Number(l), "**", Value::Float(r)) => Ok(Value::Float((l as f64).powf(r))),
+                    (Value::Float(l), "**", Value::Number(r)) => Ok(Value::Float(l.powf(r as f64))),
+                    
                     // String concatenation
                     (Value::String(l), "+", Value::String(r)) => Ok(Value::String(l + &r)),
                     (Value::String(l), "+", Value::Number(r)) => Ok(Value::String(l + &r.to_string())),
                     (Value::Number(l), "+", Value::String(r)) => Ok(Value::String(l.to_string() + &r)),
                     
                     // Comparison operators
-                    (Value::Number(l), "==", Value::Number(r)) => Ok(Value::Boolean(l == r)),
-                    (Value::String(l), "==", Value::String(r)) => Ok(Value::Boolean(l == r)),
-                    (Value::Boolean(l), "==", Value::Boolean(r)) => Ok(Value::Boolean(l == r)),
-                    (Value::Number(l), "!=", Value::Number(r)) => Ok(Value::Boolean(l != r)),
-                    (Value::String(l), "!=", Value::String(r)) => Ok(Value::Boolean(l != r)),
-                    (Value::Boolean(l), "!=", Value::Boolean(r)) => Ok(Value::Boolean(l != r)),
+                    (l, "===", r) => Ok(Value::Boolean(strict_equals(&l, &r))),
+                    (l, "!==", r) => Ok(Value::Boolean(!strict_equals(&l, &r))),
+                    (l, "==", r) => Ok(Value::Boolean(loose_equals(&l, &r))),
+                    (l, "!=", r) => Ok(Value::Boolean(!loose_equals(&l, &r))),
                     (Value::Number(l), "<", Value::Number(r)) => Ok(Value::Boolean(l < r)),
                     (Value::Number(l), ">", Value::Number(r)) => Ok(Value::Boolean(l > r)),
                     (Value::Number(l), "<=", Value::Number(r)) => Ok(Value::Boolean(l <= r)),
                     (Value::Number(l), ">=", Value::Number(r)) => Ok(Value::Boolean(l >= r)),
                     
-                    // Logical operators
-                    (Value::Boolean(l), "&&", Value::Boolean(r)) => Ok(Value::Boolean(l && r)),
-                    (Value::Boolean(l), "||", Value::Boolean(r)) => Ok(Value::Boolean(l || r)),
-                    
                     // Bitwise operators
                     (Value::Number(l), "&", Value::Number(r)) => Ok(Value::Number(l & r)),
                     (Value::Number(l), "|", Value::Number(r)) => Ok(Value::Number(l | r)),
@@ -376,6 +1789,53 @@ impl Repl {
                 }
             },
             
+            AstNode::ArrayLiteral(elements) => {
+                let mut items = Vec::new();
+                for element in elements {
+                    match element {
+                        AstNode::SpreadElement(expr) => {
+                            let value = self.evaluate_ast_with_scope(expr, scope)?;
+                            items.extend(spread_elements(value)?);
+                        },
+                        _ => items.push(self.evaluate_ast_with_scope(element, scope)?),
+                    }
+                }
+                Ok(Value::Array(items))
+            },
+            
+            AstNode::ObjectLiteral(properties) => {
+                // Properties are applied in source order, so later keys win
+                let mut object = HashMap::new();
+                for property in properties {
+                    match property {
+                        ObjectProperty::KeyValue { key, value } => {
+                            let value = self.evaluate_ast_with_scope(value, scope)?;
+                            object.insert(key.clone(), value);
+                        },
+                        ObjectProperty::Computed { key, value } => {
+                            // The key is evaluated before the value, as in JavaScript
+                            let key = property_key(&self.evaluate_ast_with_scope(key, scope)?);
+                            let value = self.evaluate_ast_with_scope(value, scope)?;
+                            object.insert(key, value);
+                        },
+                        ObjectProperty::Shorthand(name) => {
+                            let value = scope.get(name).ok_or_else(|| format!("Variable \"{}\" not found ", name))?;
+                            object.insert(name.clone(), value);
+                        },
+                        ObjectProperty::Method { name, params, body, .. } => {
+                            let params = params.iter().map(|param| param.name.clone()).collect();
+                            let method = Value::Function(name.clone(), params, Box::new(AstNode::Block(body.clone())));
+                            object.insert(name.clone(), method);
+                        },
+                        ObjectProperty::Spread(expr) => {
+                            let value = self.evaluate_ast_with_scope(expr, scope)?;
+                            object.extend(spread_properties(value));
+                        },
+                    }
+                }
+                Ok(Value::Object(object))
+            },
+            
             AstNode::Identifier(name) => {
                 if let Some(value) = scope.get(name) {
                     Ok(value)
@@ -385,31 +1845,19 @@ impl Repl {
             },
             
             // Handle block-level scoping
-            AstNode::Block(statements) => {
-                // Create a new scope with the current scope as parent
-                let mut block_scope = Scope::with_parent(Box::new(scope.clone()));
-                let mut result = Value::Null;
-                
-                // Execute each statement in the block with the new scope
-                for stmt in statements {
-                    result = self.evaluate_ast_with_scope(stmt, &mut block_scope)?;
-                }
-                
-                // Return the result of the last statement
-                Ok(result)
-            },
+            AstNode::Block(statements) => self.evaluate_block(statements, scope),
             
             AstNode::LetDecl { name, value } => {
                 // Special handling for arrow functions to associate them with a name
                 if let AstNode::ArrowFunction { params, body, expression, is_async } = &**value {
                     // Create a named function value
-                    let function_value = Value::Function(name.clone(), params.clone(), Box::new(if *expression && body.len() == 1 {
+                    let function_value = make_function(name.clone(), parameter_names(params), if *expression && body.len() == 1 {
                         // For expression bodies, wrap in a return statement
                         AstNode::Block(vec![AstNode::Return(Box::new(body[0].clone()))])
                     } else {
                         // For block bodies, use the block as is
                         AstNode::Block(body.clone())
-                    }));
+                    }, *is_async);
                     
                     // Store in scope
                     scope.set(name, function_value.clone());
@@ -426,13 +1874,13 @@ impl Repl {
                 // Special handling for arrow functions to associate them with a name
                 if let AstNode::ArrowFunction { params, body, expression, is_async } = &**value {
                     // Create a named function value
-                    let function_value = Value::Function(name.clone(), params.clone(), Box::new(if *expression && body.len() == 1 {
+                    let function_value = make_function(name.clone(), parameter_names(params), if *expression && body.len() == 1 {
                         // For expression bodies, wrap in a return statement
                         AstNode::Block(vec![AstNode::Return(Box::new(body[0].clone()))])
                     } else {
                         // For block bodies, use the block as is
                         AstNode::Block(body.clone())
-                    }));
+                    }, *is_async);
                     
                     // Store in scope
                     scope.set(name, function_value.clone());
@@ -449,9 +1897,9 @@ impl Repl {
             AstNode::PreIncrement(expr) => {
                 if let AstNode::Identifier(name) = &**expr {
                     if let Some(Value::Number(n)) = scope.get(name) {
-                        let new_value = n + 1;
-                        scope.set(name, Value::Number(new_value));
-                        Ok(Value::Number(new_value))
+                        let new_value = integer_arithmetic(n, "+", 1);
+                        scope.update(name, new_value.clone());
+                        Ok(new_value)
                     } else {
                         Err("Can only increment numeric variables ".to_string())
                     }
@@ -464,7 +1912,7 @@ impl Repl {
                 if let AstNode::Identifier(name) = &**expr {
                     if let Some(Value::Number(n)) = scope.get(name) {
                         let old_value = n;
-                        scope.set(name, Value::Number(old_value + 1));
+                        scope.update(name, integer_arithmetic(old_value, "+", 1));
                         Ok(Value::Number(old_value)) // Return the original value for post-increment
                     } else {
                         Err("Can only increment numeric variables ".to_string())
@@ -477,9 +1925,9 @@ impl Repl {
             AstNode::PreDecrement(expr) => {
                 if let AstNode::Identifier(name) = &**expr {
                     if let Some(Value::Number(n)) = scope.get(name) {
-                        let new_value = n - 1;
-                        scope.set(name, Value::Number(new_value));
-                        Ok(Value::Number(new_value))
+                        let new_value = integer_arithmetic(n, "-", 1);
+                        scope.update(name, new_value.clone());
+                        Ok(new_value)
                     } else {
                         Err("Can only decrement numeric variables ".to_string())
                     }
@@ -492,7 +1940,7 @@ impl Repl {
                 if let AstNode::Identifier(name) = &**expr {
                     if let Some(Value::Number(n)) = scope.get(name) {
                         let old_value = n;
-                        scope.set(name, Value::Number(old_value - 1));
+                        scope.update(name, integer_arithmetic(old_value, "-", 1));
                         Ok(Value::Number(old_value)) // Return the original value for post-decrement
                     } else {
                         Err("Can only decrement numeric variables ".to_string())
@@ -502,6 +1950,12 @@ impl Repl {
                 }
             },
             
+            AstNode::Assignment { target, value } => {
+                let value = self.evaluate_ast_with_scope(value, scope)?;
+                self.assign(target, value.clone(), scope)?;
+                Ok(value)
+            },
+            
             // Handle compound assignments
             AstNode::CompoundAssignment { target, op, value } => {
                 if let AstNode::Identifier(name) = &**target {
@@ -511,27 +1965,31 @@ impl Repl {
                         let new_value = match (current_value.clone(), &right_value, op.as_str()) {
                             // Handle both + and += for addition
                             (Value::Number(left), Value::Number(right), op) if op == "+" || op == "+=" => {
-                                Value::Number(left + *right)
+                                integer_arithmetic(left, "+", *right)
                             },
                             // Handle both - and -= for subtraction
                             (Value::Number(left), Value::Number(right), op) if op == "-" || op == "-=" => {
-                                Value::Number(left - *right)
+                                integer_arithmetic(left, "-", *right)
                             },
                             // Handle both * and *= for multiplication
                             (Value::Number(left), Value::Number(right), op) if op == "*" || op == "*=" => {
-                                Value::Number(left * *right)
+                                integer_arithmetic(left, "*", *right)
                             },
                             // Handle both / and /= for division
                             (Value::Number(left), Value::Number(right), op) if op == "/" || op == "/=" => {
                                 if *right == 0 {
                                     return Err("Division by zero ".to_string());
                                 }
-                                Value::Number(left / *right)
+                                integer_arithmetic(left, "/", *right)
+                            },
+                            // Handle both ** and **= for exponentiation
+                            (Value::Number(left), Value::Number(right), op) if op == "**" || op == "**=" => {
+                                integer_power(left, *right)
                             },
                             _ => return Err(format!("Invalid operation: {:?} {} {:?}", current_value.clone(), op, right_value))
                         };
                         
-                        scope.set(name, new_value.clone());
+                        scope.update(name, new_value.clone());
                         Ok(new_value)
                     } else {
                         Err(format!("Variable \"{}\" not found ", name))
@@ -542,7 +2000,20 @@ impl Repl {
             },
             
             // Handle function calls
-            AstNode::FunctionCall { name, args } => {
+            AstNode::FunctionCall { callee, args } => {
+                let name = match &**callee {
+                    AstNode::Identifier(name) => name,
+                    // Computed callees such as `makeAdder(1)(2)`
+                    callee => {
+                        let callee = self.evaluate_ast_with_scope(callee, scope)?;
+                        let mut evaluated_args = Vec::new();
+                        for arg in args {
+                            evaluated_args.push(self.evaluate_ast_with_scope(arg, scope)?);
+                        }
+                        return self.call_value(&callee, &evaluated_args, scope);
+                    }
+                };
+                
                 // Evaluate all arguments first
                 let mut evaluated_args = Vec::new();
                 for arg in args {
@@ -566,7 +2037,8 @@ impl Repl {
                                 Value::Null => print!("null"),
                                 Value::Array(_) => print!("[Array]"),
                                 Value::Object(_) => print!("{{}}"),
-                                Value::Function(_, _, _) => print!("[Function]"),
+                                Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _) => print!("[Function]"),
+                                Value::Promise(_) => print!("[Promise]"),
                                 Value::Regex(r) => print!("/{}/", r),
                                 Value::Undefined => print!("undefined"),
                             }
@@ -580,6 +2052,52 @@ impl Repl {
                             Ok(Value::Null)
                         }
                     },
+                    "require" => {
+                        match evaluated_args.first() {
+                            Some(Value::String(specifier)) => self.require(specifier),
+                            _ => Err("require expects a module path string".to_string()),
+                        }
+                    },
+                    "eval" => {
+                        if !self.options.allow_eval {
+                            return Err(not_permitted("eval"));
+                        }
+                        // Like JavaScript, anything but a string is returned unchanged
+                        let code = match evaluated_args.into_iter().next() {
+                            Some(Value::String(code)) => code,
+                            other => return Ok(other.unwrap_or(Value::Undefined)),
+                        };
+                        
+                        // The code runs in the calling scope, so it sees and
+                        // can declare its variables
+                        let mut result = Value::Undefined;
+                        for statement in parse_input(&code)? {
+                            result = self.evaluate_ast_with_scope(&statement, scope)?;
+                            if self.pending_control.is_some() {
+                                break;
+                            }
+                        }
+                        Ok(result)
+                    },
+                    "queueMicrotask" => {
+                        // Queued behind promise reactions already waiting, so
+                        // it runs after the current synchronous code
+                        match evaluated_args.into_iter().next() {
+                            Some(callback @ (Value::Function(..) | Value::AsyncFunction(..) | Value::PromiseResolver(..))) => {
+                                let reaction = Reaction::Task { callback, scope: scope.clone() };
+                                self.microtasks.push_back(Microtask { reaction, outcome: Ok(Value::Undefined) });
+                                Ok(Value::Undefined)
+                            },
+                            _ => Err("queueMicrotask requires a function as first argument".to_string()),
+                        }
+                    },
+                    "structuredClone" => {
+                        // Deep copy with explicit copy semantics
+                        match evaluated_args.first() {
+                            Some(value) => structured_clone(value),
+                            None => Err("structuredClone requires a value".to_string()),
+                        }
+                    },
                     "map" => {
                         // Handle array.map(callback)
                         // First argument should be the array (this)
@@ -617,7 +2135,7 @@ impl Repl {
                                     }
                                     
                                     // Call the callback function
-                                    let mapped_value = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
+                                    let mapped_value = self.call_function_body(None, body, &mut callback_scope)?;
                                     result.push(mapped_value);
                                 }
                                 
@@ -663,24 +2181,10 @@ impl Repl {
                                     }
                                     
                                     // Call the callback function
-                                    let result_value = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
-                                    
-                                    // Check if the result is truthy
-                                    let is_truthy = match result_value {
-                                        Value::Boolean(b) => b,
-                                        Value::Number(n) => n != 0,
-                                        Value::Float(f) => f != 0.0,
-                                        Value::String(s) => !s.is_empty(),
-                                        Value::Array(arr) => !arr.is_empty(),
-                                        Value::Object(obj) => !obj.is_empty(),
-                                        Value::Function(_, _, _) => true,
-                                        Value::Regex(_) => true,
-                                        Value::Null => false,
-                                        Value::Undefined => false,
-                                    };
+                                    let result_value = self.call_function_body(None, body, &mut callback_scope)?;
                                     
                                     // If the result is truthy, include the item in the result
-                                    if is_truthy {
+                                    if result_value.is_truthy() {
                                         result.push(item.clone());
                                     }
                                 }
@@ -693,21 +2197,35 @@ impl Repl {
                     _ => {
                         // Check if it's a user-defined function
                         if let Some(Value::Function(_, param_names, body)) = scope.get(name) {
-                            // Create a new scope with the function's parameters
-                            let mut function_scope = Scope::with_parent(Box::new(scope.clone()));
-                            
-                            // Bind arguments to parameters
-                            for (i, param) in param_names.iter().enumerate() {
-                                let arg_value = if i < evaluated_args.len() {
-                                    evaluated_args[i].clone()
-                                } else {
-                                    Value::Undefined // Default value if argument is missing
-                                };
-                                function_scope.set(param, arg_value);
+                            // Trampoline: self tail calls re-run the body here
+                            // instead of growing the stack
+                            let mut evaluated_args = evaluated_args;
+                            loop {
+                                // Create a new scope with the function's parameters
+                                let mut function_scope = Scope::with_parent(Box::new(scope.clone()));
+                                
+                                // Bind arguments to parameters
+                                for (i, param) in param_names.iter().enumerate() {
+                                    let arg_value = if i < evaluated_args.len() {
+                                        evaluated_args[i].clone()
+                                    } else {
+                                        Value::Undefined // Default value if argument is missing
+                                    };
+                                    function_scope.set(param, arg_value);
+                                }
+                                
+                                // Execute the function body
+                                let result = self.call_function_body(Some(name), &body, &mut function_scope)?;
+                                match self.pending_control.take() {
+                                    Some(ControlFlow::TailCall(args)) => evaluated_args = args,
+                                    other => {
+                                        self.pending_control = other;
+                                        break Ok(result);
+                                    }
+                                }
                             }
-                            
-                            // Execute the function body
-                            self.evaluate_ast_with_scope(&body, &mut function_scope)
+                        } else if let Some(callee @ (Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _))) = scope.get(name) {
+                            self.call_value(&callee, &evaluated_args, scope)
                         } else {
                             Err(format!("Function '{}' not found", name))
                         }
@@ -721,13 +2239,191 @@ impl Repl {
             AstNode::ArrowFunction { params, body, expression, is_async } => {
                 // Create a function value
                 // We'll use an empty name for anonymous functions
-                Ok(Value::Function(String::new(), params.clone(), Box::new(if *expression && body.len() == 1 {
+                Ok(make_function(String::new(), parameter_names(params), if *expression && body.len() == 1 {
                     // For expression bodies, we wrap the expression in a return statement
                     AstNode::Block(vec![AstNode::Return(Box::new(body[0].clone()))])
                 } else {
                     // For block bodies, we use the block as is
                     AstNode::Block(body.clone())
-                })))
+                }, *is_async))
+            },
+            
+            // Function declarations bind their name in the current scope
+            AstNode::Function { name, params, body, is_async } => {
+                let function = make_function(name.clone(), parameter_names(params), AstNode::Block(body.clone()), *is_async);
+                if !name.is_empty() {
+                    scope.set(name, function.clone());
+                }
+                Ok(function)
+            },
+            
+            AstNode::Import { source, default_import, named_imports, namespace_import, .. } => {
+                let path = self.load_module(source)?;
+                if let Some(local) = default_import {
+                    scope.set(local, self.module_export(&path, source, "default")?);
+                }
+                for specifier in named_imports {
+                    let value = self.module_export(&path, source, &specifier.name)?;
+                    scope.set(specifier.alias.as_ref().unwrap_or(&specifier.name), value);
+                }
+                if let Some(local) = namespace_import {
+                    scope.set(local, Value::Object(self.modules[&path].exports.clone()));
+                }
+                Ok(Value::Undefined)
+            },
+            
+            AstNode::Export { declaration } => {
+                let value = self.evaluate_ast_with_scope(declaration, scope)?;
+                if let AstNode::LetDecl { name, .. }
+                | AstNode::ConstDecl { name, .. }
+                | AstNode::Function { name, .. }
+                | AstNode::ClassDeclaration { name, .. } = &**declaration {
+                    self.export(name, scope.get(name).unwrap_or_else(|| value.clone()));
+                }
+                Ok(value)
+            },
+            
+            AstNode::ExportDefault { expression } => {
+                let value = self.evaluate_ast_with_scope(expression, scope)?;
+                self.export("default", value.clone());
+                Ok(value)
+            },
+            
+            AstNode::ExportNamed { specifiers, source } => {
+                let from = match source {
+                    Some(source) => Some((self.load_module(source)?, source)),
+                    None => None,
+                };
+                for specifier in specifiers {
+                    let value = match &from {
+                        Some((path, source)) => self.module_export(path, source, &specifier.name)?,
+                        None => scope.get(&specifier.name)
+                            .ok_or_else(|| format!("Variable \"{}\" not found ", specifier.name))?,
+                    };
+                    self.export(specifier.exported_name.as_ref().unwrap_or(&specifier.name), value);
+                }
+                Ok(Value::Undefined)
+            },
+            
+            AstNode::ExportAll { source, exported_name } => {
+                let path = self.load_module(source)?;
+                let exports = self.modules[&path].exports.clone();
+                match exported_name {
+                    Some(name) => self.export(name, Value::Object(exports)),
+                    // `export *` leaves out the default export
+                    None => exports.into_iter()
+                        .filter(|(name, _)| name != "default")
+                        .for_each(|(name, value)| self.export(&name, value)),
+                }
+                Ok(Value::Undefined)
+            },
+            
+            AstNode::NewPromise { executor } => {
+                let executor = self.evaluate_ast_with_scope(executor, scope)?;
+                Ok(self.promise_with_executor(&executor, scope))
+            },
+            
+            AstNode::PromiseResolve { value } => {
+                let value = self.evaluate_ast_with_scope(value, scope)?;
+                Ok(self.settled_promise(Ok(value)))
+            },
+            
+            AstNode::PromiseReject { reason } => {
+                let reason = self.evaluate_ast_with_scope(reason, scope)?;
+                Ok(self.settled_promise(Err(reason)))
+            },
+            
+            AstNode::PromiseAll { iterable } => {
+                let values = self.evaluate_ast_with_scope(iterable, scope)?;
+                self.promise_all(values)
+            },
+            
+            AstNode::PromiseThen { promise, on_fulfilled, on_rejected } => {
+                let promise = self.evaluate_promise(promise, scope)?;
+                let on_fulfilled = on_fulfilled.as_deref().map(|handler| self.evaluate_ast_with_scope(handler, scope)).transpose()?;
+                let on_rejected = on_rejected.as_deref().map(|handler| self.evaluate_ast_with_scope(handler, scope)).transpose()?;
+                Ok(self.promise_then(&promise, on_fulfilled, on_rejected, scope))
+            },
+            
+            AstNode::PromiseCatch { promise, on_rejected } => {
+                let promise = self.evaluate_promise(promise, scope)?;
+                let on_rejected = self.evaluate_ast_with_scope(on_rejected, scope)?;
+                Ok(self.promise_then(&promise, None, Some(on_rejected), scope))
+            },
+            
+            AstNode::PromiseFinally { promise, on_finally } => {
+                let promise = self.evaluate_promise(promise, scope)?;
+                let callback = self.evaluate_ast_with_scope(on_finally, scope)?;
+                Ok(self.promise_finally(&promise, callback, scope))
+            },
+            
+            AstNode::AwaitExpr { expr } => {
+                let value = self.evaluate_ast_with_scope(expr, scope)?;
+                self.await_value(value)
+            },
+            
+            AstNode::Throw(value) => {
+                let value = self.evaluate_ast_with_scope(value, scope)?;
+                self.throw(value)
+            },
+            
+            AstNode::Try { body, catch_param, catch_body, finally_body } => {
+                let mut result = self.evaluate_block(body, scope);
+                
+                if let (Err(message), Some(param)) = (&result, catch_param) {
+                    let exception = self.take_exception(message.clone());
+                    let mut catch_scope = Scope::with_parent(Box::new(scope.clone()));
+                    catch_scope.set(param, exception);
+                    result = self.evaluate_block(catch_body, &mut catch_scope);
+                    if let Some(parent) = catch_scope.parent {
+                        *scope = *parent;
+                    }
+                }
+                
+                if let Some(finally_body) = finally_body {
+                    let pending = self.pending_control.take();
+                    let exception = self.thrown.take();
+                    let value = self.evaluate_block(finally_body, scope)?;
+                    // A break, continue or return in `finally` replaces the outcome
+                    if self.pending_control.is_some() {
+                        return Ok(value);
+                    }
+                    self.pending_control = pending;
+                    self.thrown = exception;
+                }
+                
+                result
+            },
+            
+            // Comma operator: evaluate left to right, keep the last value
+            AstNode::Sequence(expressions) => {
+                let mut result = Value::Undefined;
+                for expr in expressions {
+                    result = self.evaluate_ast_with_scope(expr, scope)?;
+                }
+                Ok(result)
+            },
+            
+            AstNode::If { condition, then_branch, else_branch } => {
+                if self.evaluate_ast_with_scope(condition, scope)?.is_truthy() {
+                    self.evaluate_ast_with_scope(then_branch, scope)
+                } else if let Some(else_branch) = else_branch {
+                    self.evaluate_ast_with_scope(else_branch, scope)
+                } else {
+                    Ok(Value::Undefined)
+                }
+            },
+            
+            AstNode::UnaryOp { op, expr } => {
+                match (op.as_str(), self.evaluate_ast_with_scope(expr, scope)?) {
+                    ("!", value) => Ok(Value::Boolean(!value.is_truthy())),
+                    ("-", Value::Number(n)) => Ok(n.checked_neg().map_or(Value::Float(-(n as f64)), Value::Number)),
+                    ("-", Value::Float(f)) => Ok(Value::Float(-f)),
+                    ("+", value @ (Value::Number(_) | Value::Float(_))) => Ok(value),
+                    ("+", Value::String(s)) => Ok(Value::Float(string_to_number(&s))),
+                    ("~", Value::Number(n)) => Ok(Value::Number(!(n as i32) as i64)),
+                    (op, value) => Err(format!("Cannot apply unary '{}' to {}", op, value.type_name())),
+                }
             },
             
             // Handle ternary operator
@@ -736,35 +2432,35 @@ impl Repl {
                 let cond_value = self.evaluate_ast_with_scope(condition, scope)?;
                 
                 // Determine which branch to evaluate based on the condition
-                match cond_value {
-                    Value::Boolean(true) => self.evaluate_ast_with_scope(true_expr, scope),
-                    Value::Boolean(false) => self.evaluate_ast_with_scope(false_expr, scope),
-                    _ => {
-                        // For non-boolean conditions, do a truthy/falsy check
-                        let is_truthy = match cond_value {
-                            Value::Number(n) => n != 0,
-                            Value::Float(f) => f != 0.0,
-                            Value::String(s) => !s.is_empty(),
-                            Value::Boolean(b) => b,
-                            Value::Null => false,
-                            Value::Undefined => false,
-                            Value::Array(arr) => !arr.is_empty(),
-                            Value::Object(obj) => !obj.is_empty(),
-                            Value::Function(_, _, _) => true,
-                            Value::Regex(_) => true,
-                        };
-                        
-                        if is_truthy {
-                            self.evaluate_ast_with_scope(true_expr, scope)
-                        } else {
-                            self.evaluate_ast_with_scope(false_expr, scope)
-                        }
-                    }
+                if cond_value.is_truthy() {
+                    self.evaluate_ast_with_scope(true_expr, scope)
+                } else {
+                    self.evaluate_ast_with_scope(false_expr, scope)
                 }
             },
             
             // Handle method calls (obj.method())
             AstNode::MethodCall { object, method, args } => {
+                // Static methods on the String constructor, JSON, console,
+                // process and fs, unless shadowed
+                if let AstNode::Identifier(name) = &**object {
+                    if matches!(name.as_str(), "String" | "JSON" | "console" | "process" | "fs") && scope.get(name).is_none() {
+                        let mut evaluated_args = Vec::new();
+                        for arg in args {
+                            evaluated_args.push(self.evaluate_ast_with_scope(arg, scope)?);
+                        }
+                        return match name.as_str() {
+                            "JSON" => self.json_method(method, &evaluated_args, scope),
+                            "console" => console_method(method, &evaluated_args),
+                            "process" if !self.options.allow_process => Err(not_permitted(&format!("process.{}", method))),
+                            "process" => process::call(method, &evaluated_args),
+                            "fs" if !self.options.allow_fs => Err(not_permitted(&format!("fs.{}", method))),
+                            "fs" => runtime::fs::call(method, &evaluated_args),
+                            _ => string_from_codes(method, &evaluated_args),
+                        };
+                    }
+                }
+                
                 // Evaluate the object first
                 let obj_value = self.evaluate_ast_with_scope(object, scope)?;
                 
@@ -791,13 +2487,8 @@ impl Repl {
                                     return Err("charAt requires an index argument".to_string());
                                 }
                                 if let Value::Number(idx) = &evaluated_args[0] {
-                                    let idx = *idx as usize;
-                                    if idx < s.len() {
-                                        let ch = s.chars().nth(idx).unwrap();
-                                        Ok(Value::String(ch.to_string()))
-                                    } else {
-                                        Ok(Value::String("".to_string()))
-                                    }
+                                    let ch = usize::try_from(*idx).ok().and_then(|idx| s.chars().nth(idx));
+                                    Ok(Value::String(ch.map(String::from).unwrap_or_default()))
                                 } else {
                                     Err("charAt requires a number index".to_string())
                                 }
@@ -833,7 +2524,7 @@ impl Repl {
                                 }
                                 if let Value::String(search) = &evaluated_args[0] {
                                     if let Some(idx) = s.find(search) {
-                                        Ok(Value::Number(idx as i64))
+                                        Ok(Value::Number(char_index(&s, idx)))
                                     } else {
                                         Ok(Value::Number(-1))
                                     }
@@ -859,26 +2550,42 @@ impl Repl {
                                     return Err("slice requires a start index argument".to_string());
                                 }
                                 if let Value::Number(start) = &evaluated_args[0] {
-                                    let start = *start as usize;
+                                    let len = char_len(&s);
+                                    let start = relative_index(*start, len);
                                     let end = if evaluated_args.len() > 1 {
                                         if let Value::Number(end) = &evaluated_args[1] {
-                                            *end as usize
+                                            relative_index(*end, len)
                                         } else {
                                             return Err("slice end index must be a number".to_string());
                                         }
                                     } else {
-                                        s.len()
+                                        len
                                     };
                                     
-                                    if start <= s.len() && end <= s.len() && start <= end {
-                                        Ok(Value::String(s[start..end].to_string()))
-                                    } else {
-                                        Ok(Value::String("".to_string()))
-                                    }
+                                    Ok(Value::String(char_slice(&s, start, end)))
                                 } else {
                                     Err("slice requires number indices".to_string())
                                 }
                             },
+                            "substring" => {
+                                // Like slice, but negative indices clamp to 0 and
+                                // the bounds are swapped if start > end
+                                if evaluated_args.is_empty() {
+                                    return Err("substring requires a start index argument".to_string());
+                                }
+                                let len = char_len(&s);
+                                let clamp = |value: &Value| match value {
+                                    Value::Number(n) => Ok((*n).clamp(0, len as i64) as usize),
+                                    _ => Err("substring requires number indices".to_string()),
+                                };
+                                let start = clamp(&evaluated_args[0])?;
+                                let end = match evaluated_args.get(1) {
+                                    Some(end) => clamp(end)?,
+                                    None => len,
+                                };
+                                
+                                Ok(Value::String(char_slice(&s, start.min(end), start.max(end))))
+                            },
                             "split" => {
                                 // Split string into array
                                 if evaluated_args.len() > 0 {
@@ -1043,7 +2750,7 @@ impl Repl {
                                         };
                                         
                                         if let Some(idx) = index {
-                                            Ok(Value::Number(idx as i64))
+                                            Ok(Value::Number(char_index(&s, idx)))
                                         } else {
                                             Ok(Value::Number(-1))
                                         }
@@ -1051,7 +2758,7 @@ impl Repl {
                                     Value::String(pattern) => {
                                         // Find index of string
                                         if let Some(idx) = s.find(pattern) {
-                                            Ok(Value::Number(idx as i64))
+                                            Ok(Value::Number(char_index(&s, idx)))
                                         } else {
                                             Ok(Value::Number(-1))
                                         }
@@ -1059,6 +2766,47 @@ impl Repl {
                                     _ => Err("search requires a regex or string pattern".to_string())
                                 }
                             },
+                            "codePointAt" => {
+                                // Full code point of the character at an index
+                                match evaluated_args.first() {
+                                    Some(Value::Number(idx)) => Ok(usize::try_from(*idx)
+                                        .ok()
+                                        .and_then(|idx| s.chars().nth(idx))
+                                        .map(|ch| Value::Number(ch as i64))
+                                        .unwrap_or(Value::Undefined)),
+                                    _ => Err("codePointAt requires a number index".to_string()),
+                                }
+                            },
+                            "normalize" => {
+                                // Unicode normalization, NFC by default
+                                let form = match evaluated_args.first() {
+                                    None | Some(Value::Undefined) => "NFC".to_string(),
+                                    Some(Value::String(form)) => form.clone(),
+                                    Some(other) => return Err(format!("normalize form must be a string, got {:?}", other)),
+                                };
+                                let normalized: String = match form.as_str() {
+                                    "NFC" => s.nfc().collect(),
+                                    "NFD" => s.nfd().collect(),
+                                    "NFKC" => s.nfkc().collect(),
+                                    "NFKD" => s.nfkd().collect(),
+                                    _ => return Err(format!(
+                                        "Invalid normalization form '{}' (expected one of: NFC, NFD, NFKC, NFKD)",
+                                        form
+                                    )),
+                                };
+                                Ok(Value::String(normalized))
+                            },
+                            "localeCompare" => {
+                                // Locale-agnostic for now: compares by code point
+                                match evaluated_args.first() {
+                                    Some(Value::String(other)) => Ok(Value::Number(match s.as_str().cmp(other.as_str()) {
+                                        std::cmp::Ordering::Less => -1,
+                                        std::cmp::Ordering::Equal => 0,
+                                        std::cmp::Ordering::Greater => 1,
+                                    })),
+                                    _ => Err("localeCompare requires a string argument".to_string()),
+                                }
+                            },
                             "toString" => {
                                 // Return the string itself
                                 Ok(Value::String(s.clone()))
@@ -1135,6 +2883,10 @@ impl Repl {
                                 // Format in exponential notation
                                 Ok(Value::String(format!("{:.1$e}", n as f64, frac_digits)))
                             },
+                            "toLocaleString" => {
+                                // Locale-agnostic grouping with thousands separators
+                                Ok(Value::String(group_thousands(&n.to_string())))
+                            },
                             "valueOf" => {
                                 // Return the number itself
                                 Ok(Value::Number(n.clone()))
@@ -1142,6 +2894,15 @@ impl Repl {
                             _ => Err(format!("Method '{}' not found on number", method))
                         }
                     },
+                    Value::Float(f) => {
+                        // Handle float methods
+                        match method.as_str() {
+                            "toString" => Ok(Value::String(f.to_string())),
+                            "toLocaleString" => Ok(Value::String(group_thousands(&f.to_string()))),
+                            "valueOf" => Ok(Value::Float(f)),
+                            _ => Err(format!("Method '{}' not found on number", method))
+                        }
+                    },
                     Value::Array(arr) => {
                         // Handle array methods
                         match method.as_str() {
@@ -1194,7 +2955,7 @@ impl Repl {
                                             }
                                             
                                             // Call the callback function
-                                            let mapped_value = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
+                                            let mapped_value = self.call_function_body(None, body, &mut callback_scope)?;
                                             result.push(mapped_value);
                                         }
                                         
@@ -1237,24 +2998,10 @@ impl Repl {
                                             }
                                             
                                             // Call the callback function
-                                            let result_value = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
-                                            
-                                            // Check if the result is truthy
-                                            let is_truthy = match result_value {
-                                                Value::Boolean(b) => b,
-                                                Value::Number(n) => n != 0,
-                                                Value::Float(f) => f != 0.0,
-                                                Value::String(s) => !s.is_empty(),
-                                                Value::Array(arr) => !arr.is_empty(),
-                                                Value::Object(obj) => !obj.is_empty(),
-                                                Value::Function(_, _, _) => true,
-                                                Value::Regex(_) => true,
-                                                Value::Null => false,
-                                                Value::Undefined => false,
-                                            };
+                                            let result_value = self.call_function_body(None, body, &mut callback_scope)?;
                                             
                                             // If the result is truthy, include the item in the result
-                                            if is_truthy {
+                                            if result_value.is_truthy() {
                                                 result.push(item.clone());
                                             }
                                         }
@@ -1295,7 +3042,7 @@ impl Repl {
                                             }
                                             
                                             // Call the callback function and ignore the result
-                                            let _ = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
+                                            let _ = self.call_function_body(None, body, &mut callback_scope)?;
                                         }
                                         
                                         // forEach returns undefined
@@ -1335,24 +3082,10 @@ impl Repl {
                                             }
                                             
                                             // Call the callback function
-                                            let result_value = self.evaluate_ast_with_scope(body, &mut callback_scope)?;
-                                            
-                                            // Check if the result is truthy
-                                            let is_truthy = match result_value {
-                                                Value::Boolean(b) => b,
-                                                Value::Number(n) => n != 0,
-                                                Value::Float(f) => f != 0.0,
-                                                Value::String(s) => !s.is_empty(),
-                                                Value::Array(a) => !a.is_empty(),
-                                                Value::Object(_) => true,
-                                                Value::Function(_, _, _) => true,
-                                                Value::Regex(_) => true,
-                                                Value::Null => false,
-                                                Value::Undefined => false,
-                                            };
+                                            let result_value = self.call_function_body(None, body, &mut callback_scope)?;
                                             
                                             // If truthy, return this item
-                                            if is_truthy {
+                                            if result_value.is_truthy() {
                                                 return Ok(item.clone());
                                             }
                                         }
@@ -1549,7 +3282,8 @@ impl Repl {
                                         Value::Undefined => "undefined".to_string(),
                                         Value::Array(_) => "[Array]".to_string(),
                                         Value::Object(_) => "[Object]".to_string(),
-                                        Value::Function(_, _, _) => "[Function]".to_string(),
+                                        Value::Function(_, _, _) | Value::AsyncFunction(_, _, _) | Value::PromiseResolver(_, _) => "[Function]".to_string(),
+                                        Value::Promise(_) => "[Promise]".to_string(),
                                         Value::Regex(r) => format!("/{}/", r)
                                     };
                                     parts.push(format!("\"{}\":{}", key, value_str));
@@ -1574,19 +3308,59 @@ impl Repl {
                                     }
                                     
                                     // Execute the function body
-                                    self.evaluate_ast_with_scope(body, &mut function_scope)
+                                    self.call_function_body(None, body, &mut function_scope)
+                                } else if let Some(Value::AsyncFunction(_, param_names, body)) = obj.get(method) {
+                                    Ok(self.call_async_function(param_names, body, &evaluated_args, scope))
                                 } else {
                                     Err(format!("Method '{}' not found on object", method))
                                 }
                             }
                         }
                     },
+                    Value::Promise(promise) => {
+                        let mut evaluated_args = evaluated_args.into_iter();
+                        match method.as_str() {
+                            "then" => {
+                                let on_fulfilled = evaluated_args.next();
+                                Ok(self.promise_then(&promise, on_fulfilled, evaluated_args.next(), scope))
+                            },
+                            "catch" => Ok(self.promise_then(&promise, None, evaluated_args.next(), scope)),
+                            "finally" => {
+                                let callback = evaluated_args.next().unwrap_or(Value::Undefined);
+                                Ok(self.promise_finally(&promise, callback, scope))
+                            },
+                            _ => Err(format!("Method '{}' not found on promise", method))
+                        }
+                    },
                     _ => Err(format!("Cannot call method '{}' on this value type", method))
                 }
             },
             
             // Handle property access (obj.property)
+            AstNode::ComputedPropertyAccess { object, property } => {
+                let object = self.evaluate_ast_with_scope(object, scope)?;
+                match (object, self.evaluate_ast_with_scope(property, scope)?) {
+                    (Value::Array(items), Value::Number(index)) => {
+                        Ok(usize::try_from(index).ok().and_then(|index| items.get(index).cloned()).unwrap_or(Value::Undefined))
+                    },
+                    (Value::String(s), Value::Number(index)) => {
+                        let c = usize::try_from(index).ok().and_then(|index| s.chars().nth(index));
+                        Ok(c.map_or(Value::Undefined, |c| Value::String(c.to_string())))
+                    },
+                    (Value::Object(fields), key) => Ok(fields.get(&property_key(&key)).cloned().unwrap_or(Value::Undefined)),
+                    (object, key) => Err(format!("Cannot read property '{}' of {}", property_key(&key), object.type_name())),
+                }
+            },
+            
             AstNode::PropertyAccess { object, property } => {
+                // `process.argv` and `process.env`, unless shadowed
+                if matches!(&**object, AstNode::Identifier(name) if name == "process") && scope.get("process").is_none() {
+                    if !self.options.allow_process {
+                        return Err(not_permitted(&format!("process.{}", property)));
+                    }
+                    return process::property(property, &self.options.args);
+                }
+                
                 // Evaluate the object first
                 let obj_value = self.evaluate_ast_with_scope(object, scope)?;
                 
@@ -1595,7 +3369,7 @@ impl Repl {
                     Value::String(s) => {
                         // Handle string properties
                         match property.as_str() {
-                            "length" => Ok(Value::Number(s.len() as i64)),
+                            "length" => Ok(Value::Number(char_len(&s) as i64)),
                             "toUpperCase" => Ok(Value::Function("toUpperCase".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             "toLowerCase" => Ok(Value::Function("toLowerCase".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             "trim" => Ok(Value::Function("trim".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
@@ -1616,6 +3390,9 @@ impl Repl {
                             "startsWith" => Ok(Value::Function("startsWith".to_string(), vec!["searchString".to_string(), "position".to_string()], Box::new(AstNode::Block(vec![])))),
                             "endsWith" => Ok(Value::Function("endsWith".to_string(), vec!["searchString".to_string(), "length".to_string()], Box::new(AstNode::Block(vec![])))),
                             "substring" => Ok(Value::Function("substring".to_string(), vec!["start".to_string(), "end".to_string()], Box::new(AstNode::Block(vec![])))),
+                            "normalize" => Ok(Value::Function("normalize".to_string(), vec!["form".to_string()], Box::new(AstNode::Block(vec![])))),
+                            "codePointAt" => Ok(Value::Function("codePointAt".to_string(), vec!["index".to_string()], Box::new(AstNode::Block(vec![])))),
+                            "localeCompare" => Ok(Value::Function("localeCompare".to_string(), vec!["compareString".to_string()], Box::new(AstNode::Block(vec![])))),
                             "toString" => Ok(Value::Function("toString".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             "valueOf" => Ok(Value::Function("valueOf".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             _ => Err(format!("Property '{}' not found on string", property))
@@ -1800,6 +3577,7 @@ impl Repl {
                             "toFixed" => Ok(Value::Function("toFixed".to_string(), vec!["digits".to_string()], Box::new(AstNode::Block(vec![])))),
                             "toPrecision" => Ok(Value::Function("toPrecision".to_string(), vec!["precision".to_string()], Box::new(AstNode::Block(vec![])))),
                             "toExponential" => Ok(Value::Function("toExponential".to_string(), vec!["fractionDigits".to_string()], Box::new(AstNode::Block(vec![])))),
+                            "toLocaleString" => Ok(Value::Function("toLocaleString".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             "valueOf" => Ok(Value::Function("valueOf".to_string(), vec![], Box::new(AstNode::Block(vec![])))),
                             _ => Err(format!("Property '{}' not found on number", property))
                         }
@@ -1825,8 +3603,1073 @@ impl Repl {
                 }
             },
             
+            AstNode::While { .. } | AstNode::DoWhile { .. } | AstNode::For { .. } => {
+                self.evaluate_loop(ast, None, scope)
+            },
+            
+            AstNode::Labeled { label, body } => {
+                match &**body {
+                    AstNode::While { .. } | AstNode::DoWhile { .. } | AstNode::For { .. } => {
+                        self.evaluate_loop(body, Some(label), scope)
+                    },
+                    _ => {
+                        // A labeled non-loop statement can only be exited with `break label`
+                        let result = self.evaluate_ast_with_scope(body, scope)?;
+                        if matches!(&self.pending_control, Some(ControlFlow::Break(Some(target))) if target == label) {
+                            self.pending_control = None;
+                        }
+                        Ok(result)
+                    }
+                }
+            },
+            
+            AstNode::Break(label) => {
+                self.pending_control = Some(ControlFlow::Break(label.clone()));
+                Ok(Value::Undefined)
+            },
+            
+            AstNode::Continue(label) => {
+                self.pending_control = Some(ControlFlow::Continue(label.clone()));
+                Ok(Value::Undefined)
+            },
+            
+            AstNode::Return(value) => {
+                let value = self.evaluate_tail_expression(value, scope)?;
+                // A self tail call is already pending for the caller to run
+                if !matches!(self.pending_control, Some(ControlFlow::TailCall(_))) {
+                    self.pending_control = Some(ControlFlow::Return(value.clone()));
+                }
+                Ok(value)
+            },
+            
             // For simplicity, we'll just return a placeholder for other node types
             _ => Err(format!("Evaluation not implemented for this AST node: {:?}", ast))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn num(n: i64) -> AstNode {
+        AstNode::Number(n)
+    }
+    
+    fn binop(left: AstNode, op: &str, right: AstNode) -> AstNode {
+        AstNode::BinaryOp {
+            left: Box::new(left),
+            op: op.to_string(),
+            right: Box::new(right),
+        }
+    }
+    
+    fn float(f: f64) -> AstNode {
+        AstNode::Float(f)
+    }
+    
+    fn eval(ast: &AstNode) -> Result<Value, String> {
+        Repl::new().evaluate_ast(ast)
+    }
+    
+    #[test]
+    fn test_overflowing_multiplication_promotes_to_float() {
+        let result = eval(&binop(num(i64::MAX), "*", num(2))).unwrap();
+        match result {
+            Value::Float(f) => assert_eq!(f, i64::MAX as f64 * 2.0),
+            other => panic!("expected float promotion, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_overflowing_addition_promotes_to_float() {
+        let result = eval(&binop(num(i64::MAX), "+", num(1))).unwrap();
+        assert!(matches!(result, Value::Float(f) if f > 0.0));
+        
+        // Results that fit stay integers
+        assert!(matches!(eval(&binop(num(6), "*", num(7))), Ok(Value::Number(42))));
+    }
+    
+    #[test]
+    fn test_integer_exponentiation() {
+        assert!(matches!(eval(&binop(num(2), "**", num(10))), Ok(Value::Number(1024))));
+        assert!(matches!(eval(&binop(num(2), "**", num(-1))), Ok(Value::Float(f)) if f == 0.5));
+    }
+    
+    #[test]
+    fn test_exponentiation_from_source() {
+        let mut repl = Repl::new();
+        assert!(matches!(repl.evaluate("2 ** 10"), Ok(Value::Number(1024))));
+        // Right-associative and binding tighter than `*`
+        assert!(matches!(repl.evaluate("2 ** 3 ** 2"), Ok(Value::Number(512))));
+        assert!(matches!(repl.evaluate("2 * 3 ** 2"), Ok(Value::Number(18))));
+        assert!(matches!(repl.evaluate("let x = 3; x **= 2; x"), Ok(Value::Number(9))));
+    }
+    
+    #[test]
+    fn test_float_exponentiation() {
+        match eval(&binop(float(2.0), "**", float(0.5))).unwrap() {
+            Value::Float(f) => assert!((f - std::f64::consts::SQRT_2).abs() < 1e-12),
+            other => panic!("expected a float, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_or_returns_first_truthy_operand() {
+        let result = eval(&binop(num(0), "||", AstNode::String("x".to_string()))).unwrap();
+        assert!(matches!(result, Value::String(s) if s == "x"));
+        
+        let result = eval(&binop(num(5), "||", AstNode::String("x".to_string()))).unwrap();
+        assert!(matches!(result, Value::Number(5)));
+    }
+    
+    #[test]
+    fn test_and_short_circuits() {
+        // sideEffect is not defined, so evaluating it would be an error
+        let side_effect = AstNode::FunctionCall {
+            callee: Box::new(AstNode::Identifier("sideEffect".to_string())),
+            args: vec![],
+        };
+        let result = eval(&binop(AstNode::Boolean(false), "&&", side_effect.clone())).unwrap();
+        assert!(matches!(result, Value::Boolean(false)));
+        
+        assert!(eval(&binop(AstNode::Boolean(true), "&&", side_effect)).is_err());
+    }
+    
+    #[test]
+    fn test_strict_equality_never_coerces() {
+        let one_string = || AstNode::String("1".to_string());
+        
+        assert!(matches!(eval(&binop(num(1), "===", one_string())), Ok(Value::Boolean(false))));
+        assert!(matches!(eval(&binop(num(1), "!==", one_string())), Ok(Value::Boolean(true))));
+        assert!(matches!(eval(&binop(AstNode::Null, "===", AstNode::Undefined)), Ok(Value::Boolean(false))));
+        assert!(matches!(eval(&binop(num(1), "===", float(1.0))), Ok(Value::Boolean(true))));
+    }
+    
+    #[test]
+    fn test_equality_operators_from_source() {
+        let mut repl = Repl::new();
+        assert!(matches!(repl.evaluate("1 === \"1\""), Ok(Value::Boolean(false))));
+        assert!(matches!(repl.evaluate("1 !== \"1\""), Ok(Value::Boolean(true))));
+        assert!(matches!(repl.evaluate("1 == \"1\""), Ok(Value::Boolean(true))));
+        assert!(matches!(repl.evaluate("1 != 1"), Ok(Value::Boolean(false))));
+    }
+    
+    #[test]
+    fn test_loose_equality_coerces() {
+        assert!(matches!(eval(&binop(num(1), "==", AstNode::String("1".to_string()))), Ok(Value::Boolean(true))));
+        assert!(matches!(eval(&binop(AstNode::Null, "==", AstNode::Undefined)), Ok(Value::Boolean(true))));
+        assert!(matches!(eval(&binop(AstNode::Boolean(true), "==", num(1))), Ok(Value::Boolean(true))));
+        assert!(matches!(eval(&binop(AstNode::Null, "==", num(0))), Ok(Value::Boolean(false))));
+        
+        // Mismatched types compare unequal instead of erroring
+        assert!(matches!(eval(&binop(AstNode::String("a".to_string()), "!=", num(1))), Ok(Value::Boolean(true))));
+    }
+    
+    #[test]
+    fn test_truthiness_of_each_variant() {
+        let mut object = HashMap::new();
+        object.insert("key".to_string(), Value::Null);
+        
+        let truthy = vec![
+            Value::Boolean(true),
+            Value::Number(-1),
+            Value::Float(0.5),
+            Value::String("0".to_string()),
+            Value::Array(vec![Value::Null]),
+            Value::Object(object),
+            Value::Function(String::new(), vec![], Box::new(AstNode::Block(vec![]))),
+            Value::Regex("a+".to_string()),
+        ];
+        let falsy = vec![
+            Value::Boolean(false),
+            Value::Number(0),
+            Value::Float(0.0),
+            Value::Float(f64::NAN),
+            Value::String(String::new()),
+            Value::Array(vec![]),
+            Value::Object(HashMap::new()),
+            Value::Null,
+            Value::Undefined,
+        ];
+        
+        for value in &truthy {
+            assert!(value.is_truthy(), "{:?} should be truthy", value);
+        }
+        for value in &falsy {
+            assert!(!value.is_truthy(), "{:?} should be falsy", value);
+        }
+    }
+    
+    // for (let <var> = 0; <var> < <limit>; <var>++) <body>
+    fn counted_for(var: &str, limit: i64, body: Vec<AstNode>) -> AstNode {
+        AstNode::For {
+            init: Some(Box::new(AstNode::LetDecl {
+                name: var.to_string(),
+                value: Box::new(num(0)),
+            })),
+            condition: Some(Box::new(binop(AstNode::Identifier(var.to_string()), "<", num(limit)))),
+            update: Some(Box::new(AstNode::PostIncrement(Box::new(AstNode::Identifier(var.to_string()))))),
+            body: Box::new(AstNode::Block(body)),
+        }
+    }
+    
+    // outer: for (i...) { for (j...) { i * 10 + j; <jump>; } }
+    fn nested_loops_with(jump: AstNode) -> AstNode {
+        let record = binop(
+            binop(AstNode::Identifier("i".to_string()), "*", num(10)),
+            "+",
+            AstNode::Identifier("j".to_string()),
+        );
+        AstNode::Labeled {
+            label: "outer".to_string(),
+            body: Box::new(counted_for("i", 3, vec![counted_for("j", 3, vec![record, jump])])),
+        }
+    }
+    
+    #[test]
+    fn test_labeled_break_exits_both_loops() {
+        // Only the first inner iteration (i = 0, j = 0) runs
+        let result = eval(&nested_loops_with(AstNode::Break(Some("outer".to_string())))).unwrap();
+        assert!(matches!(result, Value::Number(0)), "got {:?}", result);
+    }
+    
+    #[test]
+    fn test_labeled_continue_resumes_outer_loop() {
+        // Each outer iteration runs one inner iteration; the last is i = 2, j = 0
+        let result = eval(&nested_loops_with(AstNode::Continue(Some("outer".to_string())))).unwrap();
+        assert!(matches!(result, Value::Number(20)), "got {:?}", result);
+    }
+    
+    #[test]
+    fn test_unlabeled_break_exits_inner_loop_only() {
+        // The outer loop keeps going; the last inner iteration is i = 2, j = 0
+        let result = eval(&nested_loops_with(AstNode::Break(None))).unwrap();
+        assert!(matches!(result, Value::Number(20)), "got {:?}", result);
+    }
+    
+    #[test]
+    fn test_sequence_runs_side_effects_and_yields_last_value() {
+        // sideEffect is not defined, so an error proves it was evaluated
+        let side_effect = AstNode::FunctionCall {
+            callee: Box::new(AstNode::Identifier("sideEffect".to_string())),
+            args: vec![],
+        };
+        assert!(eval(&AstNode::Sequence(vec![side_effect, num(42)])).is_err());
+        
+        let mut repl = Repl::new();
+        let increment = AstNode::PostIncrement(Box::new(AstNode::Identifier("counter".to_string())));
+        let result = repl.evaluate_ast(&AstNode::Sequence(vec![increment, num(42)])).unwrap();
+        assert!(matches!(result, Value::Number(42)));
+        assert!(matches!(repl.global_scope.get("counter"), Some(Value::Number(1))));
+    }
+    
+    fn string_method(s: &str, method: &str, args: Vec<AstNode>) -> Result<Value, String> {
+        eval(&AstNode::MethodCall {
+            object: Box::new(AstNode::String(s.to_string())),
+            method: method.to_string(),
+            args,
+        })
+    }
+    
+    fn assert_string(result: Result<Value, String>, expected: &str) {
+        match result {
+            Ok(Value::String(s)) => assert_eq!(s, expected),
+            other => panic!("expected string {:?}, got {:?}", expected, other),
+        }
+    }
+    
+    #[test]
+    fn test_string_length_counts_characters() {
+        let length = |s: &str| {
+            eval(&AstNode::PropertyAccess {
+                object: Box::new(AstNode::String(s.to_string())),
+                property: "length".to_string(),
+            })
+        };
+        assert!(matches!(length("héllo"), Ok(Value::Number(5))));
+        assert!(matches!(length("a😀b"), Ok(Value::Number(3))));
+    }
+    
+    #[test]
+    fn test_string_indexing_with_multibyte_characters() {
+        assert_string(string_method("a😀b", "charAt", vec![num(1)]), "😀");
+        assert_string(string_method("a😀b", "charAt", vec![num(2)]), "b");
+        assert_string(string_method("a😀b", "charAt", vec![num(3)]), "");
+        assert_string(string_method("café!", "charAt", vec![num(3)]), "é");
+        
+        assert_string(string_method("café au lait", "slice", vec![num(2), num(4)]), "fé");
+        assert_string(string_method("café", "slice", vec![num(-2)]), "fé");
+        assert_string(string_method("😀café", "substring", vec![num(5), num(1)]), "café");
+        assert!(matches!(
+            string_method("😀café", "indexOf", vec![AstNode::String("é".to_string())]),
+            Ok(Value::Number(4))
+        ));
+    }
+    
+    #[test]
+    fn test_normalize_composes_decomposed_text() {
+        // "e" followed by a combining acute accent
+        let decomposed = "caf\u{0065}\u{0301}";
+        assert_string(string_method(decomposed, "normalize", vec![AstNode::String("NFC".to_string())]), "caf\u{00e9}");
+        assert_string(string_method(decomposed, "normalize", vec![]), "caf\u{00e9}");
+        assert_string(string_method("caf\u{00e9}", "normalize", vec![AstNode::String("NFD".to_string())]), decomposed);
+        
+        let err = string_method("x", "normalize", vec![AstNode::String("NFX".to_string())]).unwrap_err();
+        assert!(err.contains("NFC, NFD, NFKC, NFKD"), "{}", err);
+    }
+    
+    #[test]
+    fn test_locale_compare_orders_by_code_point() {
+        let compare = |a: &str, b: &str| string_method(a, "localeCompare", vec![AstNode::String(b.to_string())]);
+        assert!(matches!(compare("apple", "banana"), Ok(Value::Number(-1))));
+        assert!(matches!(compare("pear", "pear"), Ok(Value::Number(0))));
+        assert!(matches!(compare("pear", "peach"), Ok(Value::Number(1))));
+    }
+    
+    #[test]
+    fn test_code_points_round_trip_through_strings() {
+        let string_static = |method: &str, codes: &[i64]| {
+            eval(&AstNode::MethodCall {
+                object: Box::new(AstNode::Identifier("String".to_string())),
+                method: method.to_string(),
+                args: codes.iter().map(|&code| num(code)).collect(),
+            })
+        };
+        
+        // U+1F600 is outside the Basic Multilingual Plane
+        assert_string(string_static("fromCodePoint", &[72, 105, 0x1F600]), "Hi😀");
+        assert_string(string_static("fromCharCode", &[72, 105, 0xD83D, 0xDE00]), "Hi😀");
+        assert!(string_static("fromCodePoint", &[0x110000]).is_err());
+        
+        assert!(matches!(string_method("Hi😀", "codePointAt", vec![num(2)]), Ok(Value::Number(0x1F600))));
+        assert!(matches!(string_method("Hi😀", "codePointAt", vec![num(0)]), Ok(Value::Number(72))));
+        assert!(matches!(string_method("Hi😀", "codePointAt", vec![num(3)]), Ok(Value::Undefined)));
+    }
+    
+    #[test]
+    fn test_to_locale_string_groups_thousands() {
+        let to_locale_string = |value: AstNode| {
+            eval(&AstNode::MethodCall {
+                object: Box::new(value),
+                method: "toLocaleString".to_string(),
+                args: vec![],
+            })
+        };
+        
+        assert_string(to_locale_string(num(1234567)), "1,234,567");
+        assert_string(to_locale_string(num(-1234567)), "-1,234,567");
+        assert_string(to_locale_string(num(999)), "999");
+        assert_string(to_locale_string(float(1234.5)), "1,234.5");
+    }
+    
+    #[test]
+    fn test_structured_clone_is_independent_of_original() {
+        let mut inner = HashMap::new();
+        inner.insert("tags".to_string(), Value::Array(vec![Value::String("a".to_string())]));
+        let original = Value::Array(vec![Value::Object(inner), Value::Array(vec![Value::Number(1)])]);
+        
+        let mut clone = structured_clone(&original).unwrap();
+        if let Value::Array(items) = &mut clone {
+            if let Value::Object(fields) = &mut items[0] {
+                if let Some(Value::Array(tags)) = fields.get_mut("tags") {
+                    tags.push(Value::String("b".to_string()));
+                }
+            }
+            if let Value::Array(numbers) = &mut items[1] {
+                numbers[0] = Value::Number(2);
+            }
+        }
+        
+        assert_ne!(format!("{:?}", original), format!("{:?}", clone));
+        match &original {
+            Value::Array(items) => {
+                assert!(matches!(&items[1], Value::Array(numbers) if matches!(numbers[..], [Value::Number(1)])));
+                match &items[0] {
+                    Value::Object(fields) => assert!(matches!(fields.get("tags"), Some(Value::Array(tags)) if tags.len() == 1)),
+                    other => panic!("expected object, got {:?}", other),
+                }
+            },
+            other => panic!("expected array, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_structured_clone_rejects_functions() {
+        let clone = |value: AstNode| {
+            eval(&AstNode::FunctionCall {
+                callee: Box::new(AstNode::Identifier("structuredClone".to_string())),
+                args: vec![value],
+            })
+        };
+        
+        assert!(matches!(clone(num(5)), Ok(Value::Number(5))));
+        assert!(matches!(clone(AstNode::ArrayLiteral(vec![num(1), num(2)])), Ok(Value::Array(items)) if items.len() == 2));
+        
+        let function = Value::Function("helper".to_string(), vec![], Box::new(AstNode::Block(vec![])));
+        let err = structured_clone(&Value::Array(vec![function])).unwrap_err();
+        assert!(err.contains("helper"));
+    }
+    
+    #[test]
+    fn test_infinite_recursion_hits_call_depth_limit() {
+        // const recurse = () => 1 + recurse(); recurse();
+        let call = || AstNode::FunctionCall {
+            callee: Box::new(AstNode::Identifier("recurse".to_string())),
+            args: vec![],
+        };
+        let program = AstNode::Block(vec![
+            AstNode::ConstDecl {
+                name: "recurse".to_string(),
+                value: Box::new(AstNode::ArrowFunction {
+                    params: vec![],
+                    body: vec![binop(num(1), "+", call())],
+                    expression: true,
+                    is_async: false,
+                }),
+            },
+            call(),
+        ]);
+        
+        // Unoptimized builds use far more stack per call than the default
+        // test thread has
+        let recursion = std::thread::Builder::new().stack_size(128 << 20).spawn(move || {
+            let mut repl = Repl::with_options(ReplOptions { max_call_depth: 100, ..ReplOptions::default() });
+            assert_eq!(repl.evaluate_ast(&program).unwrap_err(), "Maximum call stack size exceeded");
+            
+            // The depth counter unwinds, so later calls still work
+            assert!(matches!(repl.evaluate_ast(&num(1)), Ok(Value::Number(1))));
+            assert_eq!(repl.call_depth, 0);
+        });
+        recursion.unwrap().join().unwrap();
+    }
+    
+    #[test]
+    fn test_default_options_stop_recursion_before_the_stack_overflows() {
+        // The default test thread has far less stack than 1000 calls need in
+        // an unoptimized build; running low fails the call instead of aborting
+        let mut repl = Repl::new();
+        let result = repl.evaluate("fn g(n) { if (n == 1000) { return 0; } return g(n + 1) + 1; } g(0)");
+        match result {
+            Ok(Value::Number(1000)) => {},
+            Err(err) => assert_eq!(err, "Maximum call stack size exceeded"),
+            other => panic!("expected 1000 or a call stack error, got {:?}", other),
+        }
+        
+        let runaway = repl.evaluate("fn f(n) { return f(n + 1) + 1; } f(0)");
+        assert_eq!(runaway.unwrap_err(), "Maximum call stack size exceeded");
+        assert!(matches!(repl.evaluate("1 + 1"), Ok(Value::Number(2))));
+    }
+    
+    #[test]
+    fn test_json_keeps_integers_and_floats_apart() {
+        let mut repl = Repl::new();
+        let stringify = |repl: &mut Repl, source: &str| match repl.evaluate(source) {
+            Ok(Value::String(json)) => json,
+            other => panic!("expected JSON text from {}, got {:?}", source, other),
+        };
+        assert_eq!(stringify(&mut repl, "JSON.stringify([5, 5.0, 2.5])"), "[5,5.0,2.5]");
+        
+        assert!(matches!(repl.evaluate("JSON.parse(\"5\")"), Ok(Value::Number(5))));
+        assert!(matches!(repl.evaluate("JSON.parse(\"5.0\")"), Ok(Value::Float(f)) if f == 5.0));
+        assert!(matches!(repl.evaluate("JSON.parse(\"5e2\")"), Ok(Value::Float(f)) if f == 500.0));
+        
+        let round_trip = repl.evaluate("JSON.parse(JSON.stringify({ int: 5, float: 5.0 }))").unwrap();
+        match round_trip {
+            Value::Object(fields) => {
+                assert!(matches!(fields.get("int"), Some(Value::Number(5))));
+                assert!(matches!(fields.get("float"), Some(Value::Float(f)) if *f == 5.0));
+            },
+            other => panic!("expected an object, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_json_stringify_with_space() {
+        let mut repl = Repl::new();
+        let mut stringify = |source: &str| match repl.evaluate(source) {
+            Ok(Value::String(json)) => json,
+            other => panic!("expected JSON text from {}, got {:?}", source, other),
+        };
+        
+        // Nested values are indented; string contents are left alone
+        assert_eq!(stringify("JSON.stringify({ tags: [\"a  b\"] }, null, 2)"), "{\n  \"tags\": [\n    \"a  b\"\n  ]\n}");
+        assert_eq!(stringify("JSON.stringify({ tags: [\"a  b\"] }, null, \"\\t\")"), "{\n\t\"tags\": [\n\t\t\"a  b\"\n\t]\n}");
+        assert_eq!(stringify("JSON.stringify({ tags: [\"a  b\"] }, [\"tags\"], 0)"), "{\"tags\":[\"a  b\"]}");
+        assert_eq!(stringify("JSON.stringify({ a: [], b: {} }, null, 2)"), "{\n  \"a\": [],\n  \"b\": {}\n}");
+    }
+    
+    #[test]
+    fn test_json_stringify_with_replacer() {
+        let mut repl = Repl::new();
+        repl.evaluate("let user = { name: \"Ann\", password: \"secret\", address: { city: \"Oslo\", zip: \"0150\" } }").unwrap();
+        
+        let hidden = repl.evaluate("JSON.stringify(user, (key, value) => key === \"password\" ? undefined : value)");
+        assert!(matches!(hidden, Ok(Value::String(json)) if json == r#"{"address":{"city":"Oslo","zip":"0150"},"name":"Ann"}"#));
+        
+        // An array keeps only the listed keys, at every level
+        let picked = repl.evaluate("JSON.stringify(user, [\"name\", \"address\", \"city\"])");
+        assert!(matches!(picked, Ok(Value::String(json)) if json == r#"{"address":{"city":"Oslo"},"name":"Ann"}"#));
+    }
+    
+    #[test]
+    fn test_json_parse_with_reviver() {
+        let mut repl = Repl::new();
+        let revived = repl.evaluate(r#"JSON.parse("{\"a\": 1, \"b\": [2, 3], \"drop\": 4}", (key, value) => key === "drop" ? undefined : key === "1" || key === "a" ? value * 10 : value)"#);
+        match revived {
+            Ok(Value::Object(fields)) => {
+                assert!(matches!(fields.get("a"), Some(Value::Number(10))));
+                assert!(matches!(fields.get("b"), Some(Value::Array(items)) if matches!(items[..], [Value::Number(2), Value::Number(30)])));
+                assert!(!fields.contains_key("drop"));
+            },
+            other => panic!("expected an object, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_infinite_loop_hits_step_limit() {
+        let mut repl = Repl::with_options(ReplOptions { max_steps: Some(10_000), ..ReplOptions::default() });
+        assert_eq!(repl.evaluate("let n = 0; while (true) { n += 1; }").unwrap_err(), "script exceeded step limit");
+        assert!(matches!(repl.variable("n"), Some(Value::Number(n)) if n > 0));
+        
+        // Catching the error doesn't buy more steps
+        let err = repl.evaluate("while (true) { try { while (true) {} } catch (e) {} }").unwrap_err();
+        assert_eq!(err, "script exceeded step limit");
+        
+        // Each statement gets a fresh budget
+        assert!(matches!(repl.evaluate("n = 1; n + 1"), Ok(Value::Number(2))));
+    }
+    
+    #[test]
+    fn test_self_tail_calls_do_not_grow_the_stack() {
+        // const countdown = (n, acc) => n === 0 ? acc : countdown(n - 1, acc + 1);
+        let ident = |name: &str| AstNode::Identifier(name.to_string());
+        let countdown = |args: Vec<AstNode>| AstNode::FunctionCall {
+            callee: Box::new(AstNode::Identifier("countdown".to_string())),
+            args,
+        };
+        let program = AstNode::Block(vec![
+            AstNode::ConstDecl {
+                name: "countdown".to_string(),
+                value: Box::new(AstNode::ArrowFunction {
+                    params: vec![Parameter::new("n".to_string()), Parameter::new("acc".to_string())],
+                    body: vec![AstNode::TernaryOp {
+                        condition: Box::new(binop(ident("n"), "===", num(0))),
+                        true_expr: Box::new(ident("acc")),
+                        false_expr: Box::new(countdown(vec![
+                            binop(ident("n"), "-", num(1)),
+                            binop(ident("acc"), "+", num(1)),
+                        ])),
+                    }],
+                    expression: true,
+                    is_async: false,
+                }),
+            },
+            countdown(vec![num(10_000), num(0)]),
+        ]);
+        
+        // Far deeper than the call-depth limit
+        let mut repl = Repl::with_options(ReplOptions { max_call_depth: 100, ..ReplOptions::default() });
+        assert!(matches!(repl.evaluate_ast(&program), Ok(Value::Number(10_000))));
+        assert_eq!(repl.call_depth, 0);
+    }
+    
+    fn spread(expr: AstNode) -> AstNode {
+        AstNode::SpreadElement(Box::new(expr))
+    }
+    
+    fn key_value(key: &str, value: AstNode) -> ObjectProperty {
+        ObjectProperty::KeyValue {
+            key: key.to_string(),
+            value,
+        }
+    }
+    
+    #[test]
+    fn test_array_spread_preserves_order() {
+        // const a = [2, 3]; [1, ...a, 4, ..."ab"]
+        let program = AstNode::Block(vec![
+            AstNode::ConstDecl {
+                name: "a".to_string(),
+                value: Box::new(AstNode::ArrayLiteral(vec![num(2), num(3)])),
+            },
+            AstNode::ArrayLiteral(vec![
+                num(1),
+                spread(AstNode::Identifier("a".to_string())),
+                num(4),
+                spread(AstNode::String("ab".to_string())),
+            ]),
+        ]);
+        
+        match eval(&program) {
+            Ok(Value::Array(items)) => assert!(matches!(
+                &items[..],
+                [Value::Number(1), Value::Number(2), Value::Number(3), Value::Number(4), Value::String(a), Value::String(b)]
+                    if a == "a" && b == "b"
+            )),
+            other => panic!("expected array, got {:?}", other),
+        }
+        
+        // Only iterables can be spread into an array
+        let err = eval(&AstNode::ArrayLiteral(vec![spread(num(5))])).unwrap_err();
+        assert!(err.contains("iterable"));
+    }
+    
+    #[test]
+    fn test_object_spread_overrides_earlier_keys() {
+        // const base = { x: 1, y: 2 }; ({ x: 0, ...base, y: 3, ...null })
+        let program = AstNode::Block(vec![
+            AstNode::ConstDecl {
+                name: "base".to_string(),
+                value: Box::new(AstNode::ObjectLiteral(vec![key_value("x", num(1)), key_value("y", num(2))])),
+            },
+            AstNode::ObjectLiteral(vec![
+                key_value("x", num(0)),
+                ObjectProperty::Spread(AstNode::Identifier("base".to_string())),
+                key_value("y", num(3)),
+                ObjectProperty::Spread(AstNode::Null),
+            ]),
+        ]);
+        
+        match eval(&program) {
+            Ok(Value::Object(fields)) => {
+                assert_eq!(fields.len(), 2);
+                assert!(matches!(fields.get("x"), Some(Value::Number(1))));
+                assert!(matches!(fields.get("y"), Some(Value::Number(3))));
+            },
+            other => panic!("expected object, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_computed_keys_are_evaluated() {
+        let mut repl = Repl::new();
+        match repl.evaluate("const a = \"x\"; ({ [a + \"b\"]: 1, [1 + 1]: 2, [[1, 2]]: 3 })") {
+            Ok(Value::Object(fields)) => {
+                let mut keys: Vec<&String> = fields.keys().collect();
+                keys.sort();
+                assert_eq!(keys, ["1,2", "2", "xb"]);
+                assert!(matches!(fields.get("xb"), Some(Value::Number(1))));
+            },
+            other => panic!("expected object, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_import_named_export_from_sibling_file() {
+        let dir = std::env::temp_dir().join(format!("smash_repl_modules_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("util.smash"), "export const answer = 42;\n").unwrap();
+        
+        let mut repl = Repl::with_options(ReplOptions { base_dir: dir.clone(), ..ReplOptions::default() });
+        let import = |local: Option<&str>| AstNode::Import {
+            source: "./util.smash".to_string(),
+            default_import: None,
+            named_imports: vec![crate::parser::ImportSpecifier {
+                name: "answer".to_string(),
+                alias: local.map(str::to_string),
+            }],
+            namespace_import: None,
+            side_effect_only: false,
+        };
+        
+        repl.evaluate_ast(&import(None)).unwrap();
+        repl.evaluate_ast(&import(Some("again"))).unwrap();
+        let result = repl.evaluate_ast(&binop(
+            AstNode::Identifier("answer".to_string()),
+            "+",
+            AstNode::Identifier("again".to_string()),
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        assert!(matches!(result, Ok(Value::Number(84))), "got {:?}", result);
+        // The second import reused the cached module
+        assert_eq!(repl.modules.len(), 1);
+    }
+    
+    #[test]
+    fn test_require_returns_cached_exports() {
+        let dir = std::env::temp_dir().join(format!("smash_repl_require_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("counter.smash"), "exports.count = 1;\n").unwrap();
+        
+        let mut repl = Repl::with_options(ReplOptions { base_dir: dir.clone(), ..ReplOptions::default() });
+        let require = || call("require", vec![AstNode::String("./counter.smash".to_string())]);
+        let first = repl.evaluate_ast(&require());
+        
+        // A cached module isn't read or evaluated again
+        std::fs::write(dir.join("counter.smash"), "exports.count = 2;\n").unwrap();
+        let second = repl.evaluate_ast(&require());
+        let missing = repl.evaluate_ast(&call("require", vec![AstNode::String("./missing.smash".to_string())]));
+        std::fs::remove_dir_all(&dir).unwrap();
+        
+        for exports in [first, second] {
+            match exports {
+                Ok(Value::Object(fields)) => assert!(matches!(fields.get("count"), Some(Value::Number(1)))),
+                other => panic!("expected exports object, got {:?}", other),
+            }
+        }
+        assert_eq!(repl.modules.len(), 1);
+        assert!(missing.unwrap_err().contains(&dir.join("missing.smash").display().to_string()));
+    }
+    
+    #[test]
+    fn test_type_command_reports_array() {
+        let mut repl = Repl::new();
+        
+        assert_eq!(repl.type_of(" [1,2,3]"), Ok("array"));
+        assert!(repl.type_of("").is_err());
+    }
+
+    #[test]
+    fn test_time_command_reports_value_and_duration() {
+        let mut repl = Repl::new();
+        
+        let report = repl.time(" 1 + 2 * 3").unwrap();
+        let duration = report.strip_prefix("7 (").and_then(|rest| rest.strip_suffix(')')).unwrap();
+        assert!(duration.ends_with('s'), "no duration in {:?}", report);
+        assert!(repl.time("").is_err());
+    }
+    
+    #[test]
+    fn test_eval_runs_in_the_calling_scope() {
+        let mut repl = Repl::new();
+        
+        assert!(matches!(repl.evaluate("eval(\"1 + 2\")"), Ok(Value::Number(3))));
+        assert!(matches!(
+            repl.evaluate("fn scaled(x) { let factor = 10; return eval(\"x * factor\"); } scaled(4)"),
+            Ok(Value::Number(40))
+        ));
+        
+        // Syntax errors inside eval can be caught
+        assert!(matches!(
+            repl.evaluate("let caught = false; try { eval(\"let = ;\"); } catch (e) { caught = true; } caught"),
+            Ok(Value::Boolean(true))
+        ));
+        
+        let mut untrusted = Repl::with_options(ReplOptions { allow_eval: false, ..ReplOptions::default() });
+        assert_eq!(untrusted.evaluate("eval(\"1 + 2\")").unwrap_err(), "eval is not permitted in sandbox");
+    }
+    
+    #[test]
+    fn test_session_keeps_history_and_variables() {
+        let mut repl = Repl::new();
+        
+        assert!(matches!(repl.evaluate("let total = 1 + 2 * 3;"), Ok(Value::Number(7))));
+        assert!(repl.evaluate("for (let i = 0; i < 3; i++) { total += i; }").is_ok());
+        // The trailing semicolon may be left off
+        assert!(matches!(repl.evaluate("total"), Ok(Value::Number(10))));
+        assert!(matches!(repl.evaluate("fn twice(x) { return x * 2; }"), Ok(Value::Function(..))));
+        assert!(matches!(repl.evaluate("twice(total)"), Ok(Value::Number(20))));
+        assert!(repl.evaluate("let = ;").is_err());
+        
+        assert_eq!(repl.history().len(), 6);
+        assert_eq!(repl.history()[2], "total");
+        assert!(matches!(repl.variable("total"), Some(Value::Number(10))));
+        
+        repl.clear_context();
+        assert!(repl.variable("total").is_none());
+    }
+
+    #[test]
+    fn test_help_examples_from_source() {
+        // Each operator and example listed by .help, in the same session
+        let mut repl = Repl::new();
+        let cases = [
+            ("let counter = 1; counter++; ++counter", "3"),
+            ("counter--; --counter", "1"),
+            ("counter += 5; counter -= 2; counter *= 3; counter /= 2; counter", "6"),
+            ("[1 == 1, 1 === 1, 1 != 2, 1 !== \"1\", 1 < 2, 2 > 1, 2 <= 2, 3 >= 4]", "true,true,true,true,true,true,true,false"),
+            ("[true && false, false || true, !true]", "false,true,false"),
+            ("[6 & 3, 6 | 3, 6 ^ 3, ~6, 1 << 4, -16 >> 2]", "2,7,5,-7,16,-4"),
+            ("counter > 5 ? \"big\" : \"small\"", "big"),
+            ("let x = 10; { let x = 20; console.log(x); } x", "10"),
+            ("let z = 1; { z++; let z = 100; z++; } z", "2"),
+            ("let name = 'Smash'; [\"Hello\", 'world', `Hello, ${name}!`]", "Hello,world,Hello, Smash!"),
+            ("let arr = [1, 2, 3, 4]; let obj = { name: 'John', age: 30 }; [arr[0], obj.name]", "1,John"),
+        ];
+        for (source, expected) in cases {
+            match repl.evaluate(source) {
+                Ok(value) => assert_eq!(property_key(&value), expected, "{}", source),
+                Err(err) => panic!("{}: {}", source, err),
+            }
+        }
+
+        // Block-scoped names are gone once the block ends
+        assert!(repl.evaluate("{ let y = 5; } console.log(y);").is_err());
+    }
+        
+    #[test]
+    fn test_shorthand_properties_and_methods() {
+        let mut repl = Repl::new();
+        match repl.evaluate("let x = 5; let o = { x, twice(n) { return n * 2; } }; [o.x === 5, o.twice(o.x)]") {
+            Ok(Value::Array(items)) => assert!(matches!(&items[..], [Value::Boolean(true), Value::Number(10)])),
+            other => panic!("expected array, got {:?}", other),
+        }
+        
+        // Shorthand for an undeclared variable is an error
+        let err = eval(&AstNode::ObjectLiteral(vec![ObjectProperty::Shorthand("missing".to_string())])).unwrap_err();
+        assert!(err.contains("missing"));
+    }
+    
+    fn async_function(name: &str, body: Vec<AstNode>) -> AstNode {
+        AstNode::Function {
+            name: name.to_string(),
+            params: Vec::new(),
+            body,
+            is_async: true,
+        }
+    }
+    
+    fn await_call(name: &str) -> AstNode {
+        AstNode::AwaitExpr {
+            expr: Box::new(AstNode::FunctionCall {
+                callee: Box::new(AstNode::Identifier(name.to_string())),
+                args: Vec::new(),
+            }),
+        }
+    }
+    
+    #[test]
+    fn test_await_resolved_promise() {
+        // async function answer() { return 42; } await answer()
+        let mut repl = Repl::new();
+        let answer = async_function("answer", vec![AstNode::Return(Box::new(num(42)))]);
+        repl.evaluate_ast(&answer).unwrap();
+        
+        // Calling an async function gives a promise that is already settled
+        let call = AstNode::FunctionCall { callee: Box::new(AstNode::Identifier("answer".to_string())), args: Vec::new() };
+        match repl.evaluate_ast(&call) {
+            Ok(Value::Promise(promise)) => {
+                assert!(matches!(promise.borrow().state(), PromiseState::Fulfilled(Value::Number(42))));
+            },
+            other => panic!("expected promise, got {:?}", other),
+        }
+        
+        assert!(matches!(repl.evaluate_ast(&await_call("answer")), Ok(Value::Number(42))));
+    }
+    
+    #[test]
+    fn test_await_rejected_promise_in_try_catch() {
+        // async function fail() { throw { message: "boom" }; }
+        // try { await fail(); "unreachable" } catch (e) { e.message }
+        let mut repl = Repl::new();
+        let error = AstNode::ObjectLiteral(vec![key_value("message", AstNode::String("boom".to_string()))]);
+        repl.evaluate_ast(&async_function("fail", vec![AstNode::Throw(Box::new(error))])).unwrap();
+        
+        let caught = AstNode::Try {
+            body: vec![await_call("fail"), AstNode::String("unreachable".to_string())],
+            catch_param: Some("e".to_string()),
+            catch_body: vec![AstNode::PropertyAccess {
+                object: Box::new(AstNode::Identifier("e".to_string())),
+                property: "message".to_string(),
+            }],
+            finally_body: None,
+        };
+        assert!(matches!(repl.evaluate_ast(&caught), Ok(Value::String(s)) if s == "boom"));
+        
+        // Without a catch the rejection is reported as an uncaught error
+        let err = repl.evaluate_ast(&await_call("fail")).unwrap_err();
+        assert!(err.contains("boom"));
+    }
+    
+    fn arrow(params: &[&str], body: AstNode) -> AstNode {
+        AstNode::ArrowFunction {
+            params: params.iter().map(|param| Parameter::new(param.to_string())).collect(),
+            body: vec![body],
+            expression: true,
+            is_async: false,
+        }
+    }
+    
+    fn call(name: &str, args: Vec<AstNode>) -> AstNode {
+        AstNode::FunctionCall { callee: Box::new(AstNode::Identifier(name.to_string())), args }
+    }
+    
+    fn promise_state(repl: &Repl, name: &str) -> PromiseState {
+        match repl.global_scope.get(name) {
+            Some(Value::Promise(promise)) => promise.borrow().state().clone(),
+            other => panic!("expected promise, got {:?}", other),
+        }
+    }
+    
+    #[test]
+    fn test_promise_constructor_resolves_asynchronously() {
+        let mut repl = Repl::new();
+        let source = "const p = new Promise((resolve) => Promise.resolve(20).then((v) => resolve(v + 1)));\
+                      const q = p.then((v) => v * 2);";
+        // Statements are run one at a time, since evaluate drains the
+        // microtask queue before returning
+        for node in parse_input(source).unwrap() {
+            repl.evaluate_ast(&node).unwrap();
+        }
+        
+        // Nothing settles until the microtasks run
+        assert!(matches!(promise_state(&repl, "p"), PromiseState::Pending));
+        assert!(matches!(promise_state(&repl, "q"), PromiseState::Pending));
+        
+        repl.run_microtasks();
+        assert!(matches!(promise_state(&repl, "p"), PromiseState::Fulfilled(Value::Number(21))));
+        assert!(matches!(promise_state(&repl, "q"), PromiseState::Fulfilled(Value::Number(42))));
+    }
+    
+    #[test]
+    fn test_promise_all_and_catch() {
+        // Promise.all([1, Promise.resolve(2)]) fulfills in order;
+        // Promise.all([Promise.reject("no")]).catch((e) => e) recovers
+        let mut repl = Repl::new();
+        let all = AstNode::PromiseAll {
+            iterable: Box::new(AstNode::ArrayLiteral(vec![num(1), AstNode::PromiseResolve { value: Box::new(num(2)) }])),
+        };
+        let awaited = repl.evaluate_ast(&AstNode::AwaitExpr { expr: Box::new(all) });
+        assert!(matches!(&awaited, Ok(Value::Array(items)) if matches!(&items[..], [Value::Number(1), Value::Number(2)])));
+        
+        let recovered = AstNode::PromiseCatch {
+            promise: Box::new(AstNode::PromiseAll {
+                iterable: Box::new(AstNode::ArrayLiteral(vec![AstNode::PromiseReject {
+                    reason: Box::new(AstNode::String("no".to_string())),
+                }])),
+            }),
+            on_rejected: Box::new(arrow(&["e"], AstNode::Identifier("e".to_string()))),
+        };
+        let awaited = repl.evaluate_ast(&AstNode::AwaitExpr { expr: Box::new(recovered) });
+        assert!(matches!(awaited, Ok(Value::String(s)) if s == "no"));
+    }
+    
+    #[test]
+    fn test_finally_keeps_the_settled_value() {
+        let mut repl = Repl::new();
+        // What the finally callback returns is ignored
+        let kept = repl.evaluate("await Promise.resolve(5).finally(() => 1)");
+        assert!(matches!(kept, Ok(Value::Number(5))));
+        
+        // A rejection passes through as the original object, not a string
+        let reason = repl.evaluate("await Promise.reject({ code: 7 }).finally(() => 1).catch((e) => e.code)");
+        assert!(matches!(reason, Ok(Value::Number(7))));
+    }
+    
+    #[test]
+    fn test_then_on_a_settled_promise_runs_after_synchronous_code() {
+        let mut repl = Repl::new();
+        let source = "const p = Promise.resolve(1);\
+                      const q = p.then((v) => v + 1);\
+                      const after = 3;";
+        for node in parse_input(source).unwrap() {
+            repl.evaluate_ast(&node).unwrap();
+        }
+        
+        // p is already fulfilled, but its callback still waits for the
+        // statements after it
+        assert!(matches!(promise_state(&repl, "p"), PromiseState::Fulfilled(Value::Number(1))));
+        assert!(matches!(promise_state(&repl, "q"), PromiseState::Pending));
+        
+        repl.run_microtasks();
+        assert!(matches!(promise_state(&repl, "q"), PromiseState::Fulfilled(Value::Number(2))));
+    }
+    
+    #[test]
+    fn test_then_callback_runs_once() {
+        let mut repl = Repl::new();
+        let source = "const p = new Promise((resolve, reject) => { resolve(1); resolve(2); reject(3); });\
+                      const q = p.then((v) => v + 10);";
+        for node in parse_input(source).unwrap() {
+            repl.evaluate_ast(&node).unwrap();
+        }
+        
+        // Only the first resolve counts, so exactly one reaction is queued
+        let mut ran = 0;
+        while repl.run_next_microtask() {
+            ran += 1;
+        }
+        assert_eq!(ran, 1);
+        assert!(matches!(promise_state(&repl, "q"), PromiseState::Fulfilled(Value::Number(11))));
+    }
+    
+    #[test]
+    fn test_queue_microtask_shares_the_promise_queue() {
+        let mut repl = Repl::new();
+        let ran = repl.evaluate("await new Promise((resolve) => queueMicrotask(() => resolve(\"ran\")))");
+        assert!(matches!(ran, Ok(Value::String(s)) if s == "ran"));
+        
+        // The callback and a promise reaction run in the order they were queued
+        let source = "const first = new Promise((resolve) => queueMicrotask(() => resolve(1)));\
+                      const second = Promise.resolve(2).then((v) => v);";
+        for node in parse_input(source).unwrap() {
+            repl.evaluate_ast(&node).unwrap();
+        }
+        assert!(matches!(promise_state(&repl, "first"), PromiseState::Pending));
+        
+        assert!(repl.run_next_microtask());
+        assert!(matches!(promise_state(&repl, "first"), PromiseState::Fulfilled(Value::Number(1))));
+        assert!(matches!(promise_state(&repl, "second"), PromiseState::Pending));
+        
+        repl.run_microtasks();
+        assert!(matches!(promise_state(&repl, "second"), PromiseState::Fulfilled(Value::Number(2))));
+        
+        assert!(repl.evaluate("queueMicrotask(1)").is_err());
+    }
+    
+    #[test]
+    fn test_process_global() {
+        let args = vec!["input.txt".to_string(), "--verbose".to_string()];
+        let mut repl = Repl::with_options(ReplOptions { args, ..ReplOptions::default() });
+        
+        let argv = repl.evaluate("process.argv").unwrap();
+        assert_eq!(property_key(&argv), "input.txt,--verbose");
+        let cwd = repl.evaluate("process.cwd()").unwrap();
+        assert_eq!(property_key(&cwd), std::env::current_dir().unwrap().display().to_string());
+        assert!(matches!(repl.evaluate("process.env"), Ok(Value::Object(_))));
+        assert!(repl.evaluate("process.chdir(\"/\")").is_err());
+        
+        // A variable named process hides the global
+        let shadowed = repl.evaluate("let process = { argv: 1 }; process.argv");
+        assert!(matches!(shadowed, Ok(Value::Number(1))));
+    }
+    
+    #[test]
+    fn test_fs_global() {
+        let path = std::env::temp_dir().join(format!("smash_repl_fs_{}.txt", std::process::id()));
+        let mut repl = Repl::new();
+        repl.evaluate(&format!("const path = \"{}\"", path.display())).unwrap();
+        
+        repl.evaluate("fs.writeFileSync(path, \"héllo\\n\")").unwrap();
+        let exists = repl.evaluate("fs.existsSync(path)");
+        let contents = repl.evaluate("fs.readFileSync(path, \"utf8\")");
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(exists, Ok(Value::Boolean(true))));
+        assert!(matches!(contents, Ok(Value::String(s)) if s == "héllo\n"));
+        assert!(matches!(repl.evaluate("fs.existsSync(path)"), Ok(Value::Boolean(false))));
+        
+        // A missing file is an error scripts can catch
+        let caught = repl.evaluate("let message = \"\"; try { fs.readFileSync(path); } catch (e) { message = e; } message").unwrap();
+        let message = property_key(&caught);
+        assert!(message.starts_with("ENOENT:"), "unexpected error: {}", message);
+        assert!(message.contains(&path.display().to_string()));
+    }
+    
+    #[test]
+    fn test_sandbox_options_disable_fs_and_process() {
+        let path = std::env::temp_dir().join(format!("smash_repl_sandbox_{}.txt", std::process::id()));
+        std::fs::write(&path, "secret").unwrap();
+        
+        let options = ReplOptions { allow_fs: false, allow_process: false, ..ReplOptions::default() };
+        let mut untrusted = Repl::with_options(options);
+        untrusted.evaluate(&format!("const path = \"{}\"", path.display())).unwrap();
+        
+        let read = untrusted.evaluate("fs.readFileSync(path)");
+        let write = untrusted.evaluate("fs.writeFileSync(path, \"overwritten\")");
+        // The file was never touched
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap_err(), "fs.readFileSync is not permitted in sandbox");
+        assert_eq!(write.unwrap_err(), "fs.writeFileSync is not permitted in sandbox");
+        assert_eq!(contents, "secret");
+        
+        assert_eq!(untrusted.evaluate("process.env").unwrap_err(), "process.env is not permitted in sandbox");
+        assert_eq!(untrusted.evaluate("process.exit(1)").unwrap_err(), "process.exit is not permitted in sandbox");
+        
+        // Scripts can catch the error like any other
+        let caught = untrusted.evaluate("let message = \"\"; try { fs.existsSync(path); } catch (e) { message = e; } message");
+        assert!(matches!(caught, Ok(Value::String(s)) if s == "fs.existsSync is not permitted in sandbox"));
+    }
+}
@@ -1,5 +1,11 @@
 // Simplified runtime module for SmashLang
 
+pub mod fs;
+pub mod process;
+pub mod timers;
+
+use std::time::Instant;
+
 /// Placeholder for runtime functionality
 pub struct Runtime;
 
@@ -8,4 +14,21 @@ impl Runtime {
     pub fn new() -> Self {
         Self
     }
-}
\ No newline at end of file
+}
+
+/// Run pending timers until there are none left
+///
+/// The timers that are due fire, sleeping until the next one is due if
+/// necessary. This repeats until no timer is left, so a still-running
+/// interval keeps the loop going.
+pub fn run_event_loop() {
+    let timers = timers::timer_manager();
+    
+    while let Some(next) = timers.next_execution() {
+        let wait = next.saturating_duration_since(Instant::now());
+        if !wait.is_zero() {
+            std::thread::sleep(wait);
+        }
+        timers.process_timers();
+    }
+}
@@ -0,0 +1,67 @@
+//! The `fs` global: synchronous file system access
+//!
+//! Failures are reported as errors naming the operation and path, prefixed
+//! with a Node-style code such as `ENOENT`, so scripts can catch them.
+
+use std::io;
+use crate::repl::Value;
+
+/// Call the method `method` of `fs`
+pub fn call(method: &str, args: &[Value]) -> Result<Value, String> {
+    match method {
+        "readFileSync" => {
+            let path = path_argument(method, args)?;
+            match args.get(1) {
+                None | Some(Value::Undefined) => {},
+                Some(Value::String(encoding)) if is_utf8(encoding) => {},
+                Some(Value::String(encoding)) => return Err(format!("readFileSync: unsupported encoding {}", encoding)),
+                Some(other) => return Err(format!("readFileSync: unsupported encoding of type {}", other.type_name())),
+            }
+            
+            std::fs::read_to_string(&path)
+                .map(Value::String)
+                .map_err(|e| io_error(&e, "open", &path))
+        },
+        "writeFileSync" => {
+            let path = path_argument(method, args)?;
+            let data = match args.get(1) {
+                Some(Value::String(data)) => data,
+                Some(other) => return Err(format!("writeFileSync: data must be a string, got {}", other.type_name())),
+                None => return Err("writeFileSync requires data to write".to_string()),
+            };
+            
+            std::fs::write(&path, data)
+                .map(|_| Value::Undefined)
+                .map_err(|e| io_error(&e, "open", &path))
+        },
+        "existsSync" => {
+            let path = path_argument(method, args)?;
+            Ok(Value::Boolean(std::path::Path::new(&path).exists()))
+        },
+        _ => Err(format!("fs.{} is not a function", method)),
+    }
+}
+
+fn path_argument(function: &str, args: &[Value]) -> Result<String, String> {
+    match args.first() {
+        Some(Value::String(path)) => Ok(path.clone()),
+        _ => Err(format!("{} requires a path string as first argument", function)),
+    }
+}
+
+fn is_utf8(encoding: &str) -> bool {
+    encoding.eq_ignore_ascii_case("utf8") || encoding.eq_ignore_ascii_case("utf-8")
+}
+
+/// Describe a failed file operation, e.g.
+/// `ENOENT: No such file or directory (os error 2), open 'missing.txt'`
+fn io_error(err: &io::Error, operation: &str, path: &str) -> String {
+    let code = match err.kind() {
+        io::ErrorKind::NotFound => "ENOENT",
+        io::ErrorKind::PermissionDenied => "EACCES",
+        io::ErrorKind::AlreadyExists => "EEXIST",
+        io::ErrorKind::InvalidData => "EINVAL",
+        _ => "EIO",
+    };
+    format!("{}: {}, {} '{}'", code, err, operation, path)
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use crate::interpreter::{Value, Function, Environment};
+use serde::Serialize;
 use serde_json::{self, json, Value as JsonValue};
 
 /// Convert a SmashLang Value to a serde_json Value
@@ -101,36 +102,30 @@ pub fn create_json_object() -> Value {
                 return Ok(Value::String("undefined".to_string()));
             }
             
-            let value = &args[0];
-            let replacer = args.get(1);
-            let space = args.get(2);
+            let mut value = args[0].clone();
             
-            // Convert value to JSON
-            let mut json_value = value_to_json(value)?;
-            
-            // Apply replacer if provided
-            if let Some(replacer) = replacer {
-                json_value = apply_replacer(json_value, "", replacer.clone(), env)?;
+            // Apply replacer if provided, starting from the root key ""
+            if let Some(replacer) = args.get(1).and_then(Replacer::from_value) {
+                let mut root = HashMap::new();
+                root.insert(String::new(), value.clone());
+                value = apply_replacer(&Value::Object(root), "", value, &replacer, env)?;
+                
+                if matches!(value, Value::Undefined) {
+                    return Ok(Value::Undefined);
+                }
             }
             
-            // Apply space if provided
-            let formatted = match space {
-                Some(Value::Number(n)) if *n > 0.0 => {
-                    let spaces = n.min(10.0) as usize;
-                    serde_json::to_string_pretty(&json_value)
-                        .unwrap_or_else(|_| "null".to_string())
-                        .replace("  ", &" ".repeat(spaces))
-                },
-                Some(Value::String(s)) if !s.is_empty() => {
-                    let indent = s.chars().take(10).collect::<String>();
-                    serde_json::to_string_pretty(&json_value)
-                        .unwrap_or_else(|_| "null".to_string())
-                        .replace("  ", &indent)
-                },
-                _ => serde_json::to_string(&json_value).unwrap_or_else(|_| "null".to_string()),
+            // Convert value to JSON
+            let json_value = value_to_json(&value)?;
+            
+            // Indent with up to 10 spaces or the first 10 characters of a string
+            let indent = match args.get(2) {
+                Some(Value::Number(n)) if *n >= 1.0 => Some(" ".repeat(n.min(10.0) as usize)),
+                Some(Value::String(s)) if !s.is_empty() => Some(s.chars().take(10).collect::<String>()),
+                _ => None,
             };
             
-            Ok(Value::String(formatted))
+            Ok(Value::String(format_json(&json_value, indent.as_deref())))
         },
     )));
     
@@ -148,159 +143,156 @@ pub fn create_json_object() -> Value {
                 _ => return Err("JSON.parse requires a string argument".to_string()),
             };
             
-            let reviver = args.get(1);
-            
             // Parse JSON
             let json_value = match serde_json::from_str::<JsonValue>(text) {
                 Ok(v) => v,
                 Err(e) => return Err(format!("Invalid JSON: {}", e)),
             };
+            let value = json_to_value(&json_value);
             
-            // Apply reviver if provided
-            let value = if let Some(reviver) = reviver {
-                if let Value::Function(reviver_fn) = reviver {
-                    apply_reviver(json_value, "", reviver_fn.clone(), env)?
-                } else {
-                    json_to_value(&json_value)
-                }
-            } else {
-                json_to_value(&json_value)
-            };
-            
-            Ok(value)
+            // Apply reviver if provided, starting from the root key ""
+            match args.get(1) {
+                Some(Value::Function(reviver)) => {
+                    let mut root = HashMap::new();
+                    root.insert(String::new(), value.clone());
+                    apply_reviver(&Value::Object(root), "", value, reviver, env)
+                },
+                _ => Ok(value),
+            }
         },
     )));
     
     Value::Object(json_obj)
 }
 
-/// Enum to represent the type of replacer
-enum ReplacerType {
+/// Serialize a JSON value, pretty-printed when an indent is given
+fn format_json(value: &JsonValue, indent: Option<&str>) -> String {
+    match indent {
+        Some(indent) => {
+            let mut output = Vec::new();
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+            let mut serializer = serde_json::Serializer::with_formatter(&mut output, formatter);
+            match value.serialize(&mut serializer) {
+                Ok(()) => String::from_utf8(output).unwrap_or_else(|_| "null".to_string()),
+                Err(_) => "null".to_string(),
+            }
+        },
+        None => serde_json::to_string(value).unwrap_or_else(|_| "null".to_string()),
+    }
+}
+
+/// The second argument of JSON.stringify
+enum Replacer {
+    /// Called as `replacer(key, value)` for every value, root first
     Function(Function),
-    Array(Vec<String>),
+    /// Object keys to keep, at every nesting level
+    Keys(Vec<String>),
 }
 
-/// Apply a replacer to a JSON value
-fn apply_replacer(value: JsonValue, key: &str, replacer: Value, env: &Environment) -> Result<JsonValue, String> {
-    let replacer_type = match &replacer {
-        Value::Function(f) => ReplacerType::Function(f.clone()),
-        Value::Array(ref arr) => {
-            let mut keys = Vec::new();
-            for item in arr {
-                if let Value::String(s) = item {
-                    keys.push(s.clone());
+impl Replacer {
+    /// Interpret a replacer argument; anything but a function or array is ignored
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Function(f) => Some(Replacer::Function(f.clone())),
+            Value::Array(arr) => {
+                let mut keys = Vec::new();
+                for item in arr {
+                    let key = match item {
+                        Value::String(s) => s.clone(),
+                        Value::Number(n) => n.to_string(),
+                        _ => continue,
+                    };
+                    if !keys.contains(&key) {
+                        keys.push(key);
+                    }
                 }
-            }
-            ReplacerType::Array(keys)
-        },
-        _ => return Ok(value),
+                Some(Replacer::Keys(keys))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Apply a replacer to `holder[key]` and then to everything nested inside it
+///
+/// Object properties replaced with undefined are omitted; array elements
+/// replaced with undefined become null.
+fn apply_replacer(holder: &Value, key: &str, value: Value, replacer: &Replacer, env: &Environment) -> Result<Value, String> {
+    let value = match replacer {
+        Replacer::Function(replacer_fn) => replacer_fn.call(holder.clone(), &[Value::String(key.to_string()), value], env)?,
+        Replacer::Keys(_) => value,
     };
     
-    match replacer_type {
-        ReplacerType::Function(replacer_fn) => {
-            // Convert JSON value to SmashLang value
-            let smash_value = json_to_value(&value);
+    match &value {
+        Value::Object(obj) => {
+            let mut result = HashMap::new();
             
-            // Call replacer function
-            let result = replacer_fn.call(
-                Value::Object(HashMap::new()),
-                &[Value::String(key.to_string()), smash_value],
-                env,
-            )?;
+            for (k, v) in obj {
+                if let Replacer::Keys(keys) = replacer {
+                    if !keys.contains(k) {
+                        continue;
+                    }
+                }
+                
+                let replaced = apply_replacer(&value, k, v.clone(), replacer, env)?;
+                if !matches!(replaced, Value::Undefined) {
+                    result.insert(k.clone(), replaced);
+                }
+            }
             
-            // Convert result back to JSON value
-            value_to_json(&result)
+            Ok(Value::Object(result))
         },
-        ReplacerType::Array(ref keys) => {
-            match value {
-                JsonValue::Object(obj) => {
-                    let mut result = serde_json::Map::new();
-                    
-                    for (k, v) in obj {
-                        if keys.contains(&k) {
-                            // Apply replacer to each property recursively
-                            let processed = apply_replacer(v, &k, replacer.clone(), env)?;
-                            result.insert(k, processed);
-                        }
-                    }
-                    
-                    Ok(JsonValue::Object(result))
-                },
-                _ => Ok(value),
+        Value::Array(arr) => {
+            let mut result = Vec::new();
+            
+            for (i, v) in arr.iter().enumerate() {
+                match apply_replacer(&value, &i.to_string(), v.clone(), replacer, env)? {
+                    Value::Undefined => result.push(Value::Null),
+                    replaced => result.push(replaced),
+                }
             }
+            
+            Ok(Value::Array(result))
         },
+        _ => Ok(value),
     }
 }
 
-/// Apply a reviver to a JSON value
-fn apply_reviver(value: JsonValue, key: &str, reviver: Function, env: &Environment) -> Result<Value, String> {
-    match value {
-        JsonValue::Object(obj) => {
+/// Apply a reviver to `holder[key]`, innermost values first
+///
+/// Each value is passed to the reviver exactly once, after its own
+/// properties have been revived. Object properties revived to undefined
+/// are removed; array elements revived to undefined become null.
+fn apply_reviver(holder: &Value, key: &str, value: Value, reviver: &Function, env: &Environment) -> Result<Value, String> {
+    let value = match value {
+        Value::Object(obj) => {
+            let parent = Value::Object(obj.clone());
             let mut result = HashMap::new();
             
             for (k, v) in obj {
-                // Apply reviver to each property recursively
-                let processed = apply_reviver(v, &k, reviver.clone(), env)?;
-                
-                // Call reviver function
-                let reviver_result = reviver.call(
-                    Value::Object(result.clone()),
-                    &[Value::String(k.clone()), processed],
-                    env,
-                )?;
-                
-                // If reviver returns undefined, remove the property
-                if !matches!(reviver_result, Value::Undefined) {
-                    result.insert(k, reviver_result);
+                let revived = apply_reviver(&parent, &k, v, reviver, env)?;
+                if !matches!(revived, Value::Undefined) {
+                    result.insert(k, revived);
                 }
             }
             
-            // Call reviver on the object itself
-            reviver.call(
-                Value::Object(HashMap::new()),
-                &[Value::String(key.to_string()), Value::Object(result)],
-                env,
-            )
+            Value::Object(result)
         },
-        JsonValue::Array(arr) => {
+        Value::Array(arr) => {
+            let parent = Value::Array(arr.clone());
             let mut result = Vec::new();
             
-            for (i, v) in arr.iter().enumerate() {
-                // Apply reviver to each element recursively
-                let processed = apply_reviver(v.clone(), &i.to_string(), reviver.clone(), env)?;
-                
-                // Call reviver function
-                let reviver_result = reviver.call(
-                    Value::Array(result.clone()),
-                    &[Value::String(i.to_string()), processed],
-                    env,
-                )?;
-                
-                // If reviver returns undefined, set element to null
-                if matches!(reviver_result, Value::Undefined) {
-                    result.push(Value::Null);
-                } else {
-                    result.push(reviver_result);
+            for (i, v) in arr.into_iter().enumerate() {
+                match apply_reviver(&parent, &i.to_string(), v, reviver, env)? {
+                    Value::Undefined => result.push(Value::Null),
+                    revived => result.push(revived),
                 }
             }
             
-            // Call reviver on the array itself
-            reviver.call(
-                Value::Object(HashMap::new()),
-                &[Value::String(key.to_string()), Value::Array(result)],
-                env,
-            )
-        },
-        _ => {
-            // Convert JSON value to SmashLang value
-            let smash_value = json_to_value(&value);
-            
-            // Call reviver function
-            reviver.call(
-                Value::Object(HashMap::new()),
-                &[Value::String(key.to_string()), smash_value],
-                env,
-            )
+            Value::Array(result)
         },
-    }
+        other => other,
+    };
+    
+    reviver.call(holder.clone(), &[Value::String(key.to_string()), value], env)
 }
\ No newline at end of file
@@ -0,0 +1,35 @@
+//! The `process` global: command-line arguments, environment variables,
+//! the working directory and exiting
+
+use crate::repl::Value;
+
+/// Read the property `name` of `process`
+///
+/// `argv` holds the arguments given after the script path. `process.env` is
+/// a snapshot of the environment variables, so assigning to it never changes
+/// the environment of the interpreter itself.
+pub fn property(name: &str, argv: &[String]) -> Result<Value, String> {
+    match name {
+        "argv" => Ok(Value::Array(argv.iter().cloned().map(Value::String).collect())),
+        "env" => Ok(Value::Object(std::env::vars().map(|(name, value)| (name, Value::String(value))).collect())),
+        _ => Err(format!("Property '{}' not found on process", name)),
+    }
+}
+
+/// Call the method `method` of `process`
+pub fn call(method: &str, args: &[Value]) -> Result<Value, String> {
+    match method {
+        "exit" => {
+            let code = match args.first() {
+                Some(Value::Number(code)) => *code as i32,
+                None | Some(Value::Undefined) => 0,
+                Some(other) => return Err(format!("process.exit expects a number, got {}", other.type_name())),
+            };
+            std::process::exit(code)
+        },
+        "cwd" => std::env::current_dir()
+            .map(|dir| Value::String(dir.display().to_string()))
+            .map_err(|e| format!("process.cwd failed: {}", e)),
+        _ => Err(format!("process.{} is not a function", method)),
+    }
+}
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::{Duration, Instant};
 use crate::interpreter::{Value, Function, Environment};
 
@@ -75,20 +74,22 @@ pub struct TimerManager {
 impl TimerManager {
     /// Create a new timer manager
     pub fn new(environment: Environment) -> Self {
-        let manager = Self {
+        // Timers are polled by the event loop rather than run on a separate
+        // thread, which avoids the need to send Values between threads
+        Self {
             timers: Arc::new(Mutex::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(1)),
             environment,
-        };
-        
-        // Start a thread to check for ready timers
-        let timers_clone = manager.timers.clone();
-        let env_clone = manager.environment.clone();
-        
-        // We'll use a simple polling approach instead of a separate thread
-        // This avoids the need to send Values between threads
-        
-        manager
+        }
+    }
+    
+    /// When the next live timer is due, or `None` if there are none
+    pub fn next_execution(&self) -> Option<Instant> {
+        let timers = self.timers.lock().unwrap();
+        timers.values()
+            .filter(|timer| !timer.cancelled)
+            .map(|timer| timer.next_execution)
+            .min()
     }
     
     /// Create a timeout
@@ -148,13 +149,15 @@ impl TimerManager {
             let mut to_remove = Vec::new();
             
             for (id, timer) in timers.iter_mut() {
-                if timer.is_ready(now) {
+                if timer.cancelled {
+                    to_remove.push(*id);
+                } else if timer.is_ready(now) {
                     // Clone the timer for execution
                     let callback = timer.callback.clone();
                     let args = timer.args.clone();
                     
                     // Add to ready timers
-                    ready_timers.push((callback, args));
+                    ready_timers.push((timer.next_execution, *id, callback, args));
                     
                     // Update or remove the timer
                     if timer.is_interval {
@@ -171,14 +174,28 @@ impl TimerManager {
             }
         }
         
-        // Execute ready timers
-        for (callback, args) in ready_timers {
+        // Execute ready timers in the order they were due, then created
+        ready_timers.sort_by_key(|(due, id, _, _)| (*due, *id));
+        for (_, _, callback, args) in ready_timers {
             // Execute the callback
             let _ = callback.call(Value::Undefined, &args, &self.environment);
         }
     }
 }
 
+thread_local! {
+    /// Timers run by `runtime::run_event_loop`
+    static TIMER_MANAGER: TimerManager = TimerManager::new(Environment::new());
+}
+
+/// The timer manager driven by the event loop on this thread
+///
+/// Clones share their timers, so timers set through the returned manager
+/// are run by `runtime::run_event_loop`.
+pub fn timer_manager() -> TimerManager {
+    TIMER_MANAGER.with(|manager| manager.clone())
+}
+
 /// Create a setTimeout function
 pub fn create_set_timeout_function(timer_manager: TimerManager) -> Function {
     Function::new_native(
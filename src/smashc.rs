@@ -7,7 +7,7 @@ use std::io;
 
 use smashlang::lexer::Lexer;
 use smashlang::parser::{SmashParser as Parser, AstNode};
-use smashlang::compiler::Compiler;
+use smashlang::compiler::{Compiler, CompilerOptions};
 
 fn main() -> io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -21,6 +21,9 @@ fn main() -> io::Result<()> {
         println!("  smashc hello.smash --target linux-arm64  Compile for Linux ARM64 (e.g., Raspberry Pi 4)");
         println!("  smashc hello.smash --target windows  Cross-compile for Windows");
         println!("  smashc hello.smash --wasm            Compile to WebAssembly");
+        println!("  smashc hello.smash -O0               Disable optimization passes (not implemented yet)");
+        println!("  smashc hello.smash -g                Emit debug information (not implemented yet)");
+        println!("  smashc hello.smash --keep-c          Keep the intermediate C source (not implemented yet)");
         return Ok(());
     }
     
@@ -28,6 +31,7 @@ fn main() -> io::Result<()> {
     let input_file = &args[1];
     let mut output_file = "a.out";
     let mut target = None;
+    let mut options = CompilerOptions::default();
     
     // Process command line options
     let mut i = 2;
@@ -67,6 +71,22 @@ fn main() -> io::Result<()> {
                 target = Some("wasm32-unknown-unknown");
                 i += 1;
             },
+            // Recorded for the native code generator, which doesn't exist yet
+            "-O0" | "--no-optimize" => {
+                eprintln!("{}: {} has no effect yet; no code is generated from the optimized AST", "Warning".yellow(), args[i]);
+                options.optimize = false;
+                i += 1;
+            },
+            "-g" | "--debug" => {
+                eprintln!("{}: {} has no effect yet; debug information is not generated", "Warning".yellow(), args[i]);
+                options.emit_debug_info = true;
+                i += 1;
+            },
+            "--keep-c" => {
+                eprintln!("{}: --keep-c has no effect yet; no C source is generated", "Warning".yellow());
+                options.keep_c_source = true;
+                i += 1;
+            },
             _ => {
                 eprintln!("{}: Unknown option '{}'", "Error".red(), args[i]);
                 process::exit(1);
@@ -113,7 +133,7 @@ fn main() -> io::Result<()> {
     };
     
     // Compile the AST
-    let mut compiler = Compiler::new();
+    let mut compiler = Compiler::with_options(options);
     
     // Set target if specified
     if let Some(target_triple) = target {
@@ -0,0 +1,163 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+/// Write a SmashLang source file to a per-test temporary path
+fn write_source(name: &str, source: &str) -> PathBuf {
+    let path = env::temp_dir().join(format!("smash_cli_{}_{}.smash", name, std::process::id()));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+/// Run the `smash` binary with the given arguments
+fn smash(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_smash"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Run the `smash` REPL with `NO_COLOR` set, typing `input` at the prompt
+///
+/// `CLICOLOR_FORCE` would otherwise color output even though it's piped.
+fn smash_repl_without_color(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_smash"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .env("CLICOLOR_FORCE", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Commands that don't read stdin may exit before it's written
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_check_valid_file() {
+    let path = write_source("check_valid", "let x = 1;\nprint(x);\n");
+    
+    let output = smash(&["check", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    
+    assert!(output.status.success());
+    
+    // Nothing is executed, so print produces no output
+    assert!(output.stdout.is_empty());
+    assert!(output.stderr.is_empty());
+}
+
+#[test]
+fn test_check_reports_syntax_error() {
+    let path = write_source("check_broken", "print(\"ran\");\nlet = ;\n");
+    
+    let output = smash(&["check", path.to_str().unwrap()]);
+    fs::remove_file(&path).unwrap();
+    
+    assert!(!output.status.success());
+    
+    // The error names the file and the line of the broken statement
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("{}:2:", path.display())), "unexpected error: {}", stderr);
+    assert!(stderr.contains("Syntax error"));
+    
+    // The valid statement before the error must not have run
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("ran"));
+}
+
+#[test]
+fn test_check_json_diagnostics() {
+    let valid = write_source("check_json_valid", "let x = 1;\n");
+    let broken = write_source("check_json_broken", "print(\"ran\");\nlet x = ;\n");
+    
+    let success = smash(&["check", "--format=json", valid.to_str().unwrap()]);
+    let failure = smash(&["check", broken.to_str().unwrap(), "--format=json"]);
+    fs::remove_file(&valid).unwrap();
+    fs::remove_file(&broken).unwrap();
+    
+    assert!(success.status.success());
+    assert_eq!(String::from_utf8_lossy(&success.stdout).trim(), "[]");
+    
+    assert!(!failure.status.success());
+    let diagnostics: serde_json::Value = serde_json::from_slice(&failure.stdout).unwrap();
+    let diagnostics = diagnostics.as_array().unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0]["severity"], "error");
+    assert_eq!(diagnostics[0]["line"], 2);
+    assert_eq!(diagnostics[0]["column"], 9);
+    assert!(diagnostics[0]["message"].as_str().unwrap().starts_with("Syntax error"));
+}
+
+#[test]
+fn test_run_time_reports_phases_on_stderr() {
+    let path = write_source("run_time", "let x = 1;\nprint(x);\n");
+    
+    let plain = smash(&["run", path.to_str().unwrap()]);
+    let timed = smash(&["run", path.to_str().unwrap(), "--time"]);
+    fs::remove_file(&path).unwrap();
+    
+    assert!(plain.status.success());
+    assert!(timed.status.success());
+    
+    // Each phase is reported on stderr
+    let stderr = String::from_utf8_lossy(&timed.stderr);
+    for phase in ["lex:", "parse:", "eval:"] {
+        assert!(stderr.contains(phase), "missing {} in {}", phase, stderr);
+    }
+    assert!(!String::from_utf8_lossy(&plain.stderr).contains("lex:"));
+    
+    // Program output is unchanged
+    assert_eq!(plain.stdout, timed.stdout);
+}
+#[test]
+fn test_no_color_disables_escape_codes() {
+    for args in [&["version"][..], &["help"], &[]] {
+        let output = smash_repl_without_color(args, "1 + 2\n");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(!stdout.contains('\x1b'), "escape codes in output of {:?}: {:?}", args, stdout);
+    }
+    
+    // The REPL still printed its result
+    let output = smash_repl_without_color(&[], "1 + 2\n");
+    assert!(String::from_utf8_lossy(&output.stdout).lines().any(|line| line == "3"));
+    
+    // An explicit flag wins over the environment
+    let output = smash_repl_without_color(&["--color=always"], "1 + 2\n");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("\x1b["));
+}
+
+#[test]
+fn test_version_lists_enabled_features() {
+    let output = smash(&["--version"]);
+    assert!(output.status.success());
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let features = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Features: "))
+        .unwrap_or_else(|| panic!("no features line in {:?}", stdout));
+    
+    // Building without default features drops `jit` from the list
+    assert_eq!(features.split(", ").any(|feature| feature == "jit"), cfg!(feature = "jit"));
+    assert_eq!(features.split(", ").any(|feature| feature == "wasm"), cfg!(feature = "wasm"));
+}
+
+#[test]
+fn test_run_exposes_process() {
+    let path = write_source("run_process", "print(process.argv.length);\nprint(process.argv[1]);\nprint(process.cwd());\nprocess.exit(3);\nprint(\"ran\");\n");
+    
+    let output = smash(&["run", path.to_str().unwrap(), "input.txt", "--verbose"]);
+    fs::remove_file(&path).unwrap();
+    
+    // The arguments after the script path, then the working directory
+    let cwd = env::current_dir().unwrap();
+    let expected = format!("2\n--verbose\n{}\n", cwd.display());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+    
+    // process.exit stops the script with its status
+    assert_eq!(output.status.code(), Some(3));
+}
@@ -1,4 +1,8 @@
-use smashlang::{execute, Value};
+use smashlang::{execute, Interpreter, Value};
+use smashlang::interpreter::Function;
+use smashlang::runtime::{self, timers::{self, TimerManager}};
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::thread;
 use std::time::Duration;
 
@@ -130,6 +134,31 @@ fn test_clearTimeout() {
     assert_eq!(result.to_string(), "Initial");
 }
 
+// A timeout callback that logs `step` and sets the next timeout until `last`
+fn timeout_chain(manager: TimerManager, log: Rc<RefCell<Vec<usize>>>, step: usize, last: usize) -> Function {
+    Function::new_native(None, Vec::new(), move |_this, _args, _env| {
+        log.borrow_mut().push(step);
+        if step < last {
+            let next = timeout_chain(manager.clone(), log.clone(), step + 1, last);
+            manager.set_timeout(next, Vec::new(), 0);
+        }
+        Ok(Value::Undefined)
+    })
+}
+
+#[test]
+fn test_run_event_loop_runs_timeout_chain() {
+    let manager = timers::timer_manager();
+    let log = Rc::new(RefCell::new(Vec::new()));
+    
+    manager.set_timeout(timeout_chain(manager.clone(), log.clone(), 1, 5), Vec::new(), 0);
+    runtime::run_event_loop();
+    
+    // Every timeout set along the chain has run and nothing is left pending
+    assert_eq!(*log.borrow(), vec![1, 2, 3, 4, 5]);
+    assert!(manager.next_execution().is_none());
+}
+
 #[test]
 fn test_json_parse() {
     let code = r#"
@@ -454,4 +483,43 @@ fn test_document() {
     } else {
         panic!("Expected array result");
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_variables_snapshot_lists_defined_variables() {
+    let mut interpreter = Interpreter::new();
+    interpreter.define("answer", Value::Number(42.0));
+    interpreter.define("greeting", Value::String("hi".to_string()));
+    
+    let variables = interpreter.variables();
+    let find = |name: &str| variables.iter().find(|(n, _)| n == name).map(|(_, value)| value.to_string());
+    assert_eq!(find("answer").as_deref(), Some("42"));
+    assert_eq!(find("greeting").as_deref(), Some("hi"));
+    
+    // The snapshot is a copy that later changes don't affect
+    interpreter.define("answer", Value::Number(0.0));
+    assert_eq!(find("answer").as_deref(), Some("42"));
+    
+    let names: Vec<&str> = variables.iter().map(|(name, _)| name.as_str()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+}
+
+#[test]
+fn test_statement_hook_runs_before_each_statement() {
+    let program = smashlang::parse("let a = 1;\nlet b = 2;\nprint(a + b);\n").unwrap();
+    
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut interpreter = Interpreter::new();
+    interpreter.set_on_statement({
+        let seen = seen.clone();
+        move |statement, env| {
+            assert!(env.get("console").is_some(), "hook should see the global environment");
+            seen.borrow_mut().push(statement.to_source().trim().to_string());
+        }
+    });
+    interpreter.evaluate(&program).unwrap();
+    
+    assert_eq!(*seen.borrow(), vec!["let a = 1;", "let b = 2;", "print(a + b);"]);
+}